@@ -23,6 +23,7 @@
 use hkdf::hashes::Sha512;
 use hkdf::{BitcoinHash, Hkdf};
 use ring::aead;
+use zeroize::ZeroizeOnDrop;
 
 const CHILD_TAG: &[u8; 8] = b"childkey";
 const SECP256K1_TAG: &[u8; 8] = b"secp256k";
@@ -41,11 +42,12 @@ pub struct DerivableSecret {
     level: usize,
     /// An instance of the HKDF (Hash-based Key Derivation
     ///   Function) with SHA-512 as the underlying hash function. It is used to
-    ///   derive child keys.
-    // TODO: wrap in some secret protecting wrappers maybe?
+    ///   derive child keys. Wiped from memory on drop.
     kdf: Hkdf<Sha512>,
 }
 
+impl ZeroizeOnDrop for DerivableSecret {}
+
 impl DerivableSecret {
     /// Derive root secret key from a secret material and salt.
     ///
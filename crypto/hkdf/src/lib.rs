@@ -5,10 +5,12 @@
 //! [`bitcoin_hashes`]: https://docs.rs/bitcoin_hashes/latest/bitcoin_hashes/
 
 use std::cmp::min;
+use std::marker::PhantomData;
 
 pub use bitcoin_hashes;
 pub use bitcoin_hashes::Hash as BitcoinHash;
 use bitcoin_hashes::{HashEngine, Hmac, HmacEngine};
+use zeroize::{ZeroizeOnDrop, Zeroizing};
 
 pub mod hashes {
     pub use bitcoin_hashes::hash160::Hash as Hash160;
@@ -26,7 +28,10 @@ pub mod hashes {
 /// [RFC5869]: https://www.rfc-editor.org/rfc/rfc5869
 #[derive(Clone)]
 pub struct Hkdf<H: BitcoinHash> {
-    prk: Hmac<H>,
+    /// Bytes of the pseudo random key `Hmac<H>`, wiped from memory when
+    /// dropped
+    prk: Zeroizing<Vec<u8>>,
+    _hash: PhantomData<H>,
 }
 
 impl<H: BitcoinHash> Hkdf<H> {
@@ -42,9 +47,7 @@ pub fn new(ikm: &[u8], salt: Option<&[u8]>) -> Self {
         let mut engine = HmacEngine::new(salt.unwrap_or(&vec![0x00; H::LEN]));
         engine.input(ikm);
 
-        Hkdf {
-            prk: Hmac::from_engine(engine),
-        }
+        Self::from_prk(Hmac::from_engine(engine))
     }
 
     /// Construct the HKDF from a pseudo random key that has the correct
@@ -54,19 +57,25 @@ pub fn new(ikm: &[u8], salt: Option<&[u8]>) -> Self {
     ///
     /// See also [`Hkdf::derive_hmac`].
     pub fn from_prk(prk: Hmac<H>) -> Self {
-        Hkdf { prk }
+        Hkdf {
+            prk: Zeroizing::new(prk[..].to_vec()),
+            _hash: PhantomData,
+        }
     }
 
     /// Construct the HKDF from serialized PRK bytes.
     pub fn from_prk_bytes(prk: H::Bytes) -> Self {
-        Hkdf {
-            prk: Hmac::from_byte_array(prk),
-        }
+        Self::from_prk(Hmac::from_byte_array(prk))
     }
 
     /// Serialize the PRK bytes backing this HKDF instance.
     pub fn to_prk_bytes(&self) -> H::Bytes {
-        self.prk.to_byte_array()
+        self.prk().to_byte_array()
+    }
+
+    /// Rebuilds the pseudo random key from its bytes
+    fn prk(&self) -> Hmac<H> {
+        Hmac::from_slice(&self.prk).expect("PRK has the length of the hash")
     }
 
     /// Run HKDF-expand to generate new key material
@@ -103,7 +112,7 @@ pub fn to_prk_bytes(&self) -> H::Bytes {
             };
 
             // TODO: re-use midstate
-            let mut engine = HmacEngine::<H>::new(&self.prk[..]);
+            let mut engine = HmacEngine::<H>::new(&self.prk()[..]);
             engine.input(&output[last_slice]);
             engine.input(info);
             engine.input(&[(iteration + 1) as u8]);
@@ -120,12 +129,15 @@ pub fn to_prk_bytes(&self) -> H::Bytes {
     ///
     /// See [`Hkdf::derive`] for more information.
     pub fn derive_hmac(&self, info: &[u8]) -> Hmac<H> {
-        let mut engine = HmacEngine::<H>::new(&self.prk[..]);
+        let mut engine = HmacEngine::<H>::new(&self.prk()[..]);
         engine.input(info);
         engine.input(&[1u8]);
         Hmac::from_engine(engine)
     }
 }
 
+/// The pseudo random key is wiped from memory when dropped
+impl<H: BitcoinHash> ZeroizeOnDrop for Hkdf<H> {}
+
 #[cfg(test)]
 mod tests;
@@ -266,3 +266,13 @@ fn rfc5896_test_vector_7() {
         ]
     );
 }
+
+#[test]
+fn prk_bytes_roundtrip() {
+    let hkdf = Hkdf::<crate::hashes::Sha512>::new("foo".as_bytes(), None);
+    let restored = Hkdf::<crate::hashes::Sha512>::from_prk_bytes(hkdf.to_prk_bytes());
+
+    assert_eq!(hkdf.to_prk_bytes(), restored.to_prk_bytes());
+    assert_eq!(hkdf.derive::<100>(b"bar"), restored.derive::<100>(b"bar"));
+    assert_eq!(hkdf.derive_hmac(b"bar"), hkdf.clone().derive_hmac(b"bar"));
+}
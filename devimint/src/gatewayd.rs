@@ -18,7 +18,7 @@
 use fedimint_gateway_common::{
     ChannelInfo, CreateOfferResponse, GatewayBalances, GatewayFedConfig, GetInvoiceResponse,
     ListTransactionsResponse, MnemonicResponse, PaymentDetails, PaymentStatus,
-    PaymentSummaryResponse, V1_API_ENDPOINT, WithdrawResponse,
+    PaymentSummaryResponse, RebalanceResponse, V1_API_ENDPOINT, WithdrawResponse,
 };
 use fedimint_ln_server::common::lightning_invoice::Bolt11Invoice;
 use fedimint_lnv2_common::gateway_api::PaymentFee;
@@ -37,7 +37,7 @@
 use crate::federation::Federation;
 use crate::util::{Command, ProcessHandle, ProcessManager, poll, poll_with_timeout};
 use crate::vars::utf8;
-use crate::version_constants::{VERSION_0_10_0_ALPHA, VERSION_0_11_0_ALPHA};
+use crate::version_constants::{VERSION_0_10_0_ALPHA, VERSION_0_11_0_ALPHA, VERSION_0_12_0_ALPHA};
 
 #[derive(Debug, Clone)]
 pub struct GatewayClient {
@@ -221,6 +221,32 @@ pub async fn get_mnemonic(&self) -> Result<MnemonicResponse> {
     }
 
     pub async fn leave_federation(&self, federation_id: FederationId) -> Result<serde_json::Value> {
+        let gateway_cli_version = crate::util::GatewayCli::version_or_default().await;
+        let fed_info = if gateway_cli_version >= *VERSION_0_12_0_ALPHA {
+            cmd!(
+                self,
+                "leave-fed",
+                "--federation-id",
+                federation_id,
+                "--force",
+                "--yes"
+            )
+            .out_json()
+            .await?
+        } else {
+            cmd!(self, "leave-fed", "--federation-id", federation_id)
+                .out_json()
+                .await?
+        };
+        Ok(fed_info)
+    }
+
+    /// Leaves the federation without `--force`, which gateways since v0.12
+    /// refuse while they still hold ecash in it
+    pub async fn leave_federation_unforced(
+        &self,
+        federation_id: FederationId,
+    ) -> Result<serde_json::Value> {
         let fed_info = cmd!(self, "leave-fed", "--federation-id", federation_id)
             .out_json()
             .await?;
@@ -761,6 +787,28 @@ pub async fn pegout(
         .await?;
         Ok(serde_json::from_value(value)?)
     }
+
+    pub async fn rebalance(
+        &self,
+        from_fed_id: String,
+        to_fed_id: String,
+        amount_msat: u64,
+    ) -> Result<RebalanceResponse> {
+        let value = cmd!(
+            self,
+            "ecash",
+            "rebalance",
+            "--from-federation",
+            from_fed_id,
+            "--to-federation",
+            to_fed_id,
+            "--amount-msat",
+            amount_msat
+        )
+        .out_json()
+        .await?;
+        Ok(serde_json::from_value(value)?)
+    }
 }
 
 #[derive(Clone)]
@@ -1,2 +1,3 @@
 pub mod with_cache;
 pub mod with_request_hook;
+pub mod with_response_cache;
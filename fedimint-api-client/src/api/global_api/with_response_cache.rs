@@ -0,0 +1,311 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use fedimint_connectors::{DynGuaridianConnection, PeerStatus, ServerResult};
+use fedimint_core::core::ModuleInstanceId;
+use fedimint_core::module::ApiRequestErased;
+use fedimint_core::task::{MaybeSend, MaybeSync};
+use fedimint_core::{PeerId, apply, async_trait_maybe_send, maybe_add_send_sync};
+use fedimint_logging::LOG_CLIENT_NET_API;
+use futures::stream::BoxStream;
+use serde_json::Value;
+use tracing::trace;
+
+use super::super::{DynModuleApi, IRawFederationApi};
+use super::with_request_hook::DynIRawFederationApi;
+
+/// Key identifying a cacheable api request
+///
+/// `params` is the JSON encoding of the request parameters, so requests with
+/// equal parameters map to the same key.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ApiResponseCacheKey {
+    pub peer_id: PeerId,
+    pub method: String,
+    pub params: String,
+}
+
+impl ApiResponseCacheKey {
+    fn new(peer_id: PeerId, method: &str, params: &ApiRequestErased) -> Self {
+        Self {
+            peer_id,
+            method: method.to_owned(),
+            params: params.params.to_string(),
+        }
+    }
+}
+
+/// Local store of api responses
+///
+/// Implemented by downstream applications that want to answer (some) api
+/// requests locally, e.g. when the OS reports there's no connectivity.
+/// The cache decides on its own what to return: it can answer only while
+/// offline, only for certain methods, or always.
+///
+/// Requests carrying authentication are never looked up nor stored.
+#[apply(async_trait_maybe_send!)]
+pub trait IApiResponseCache: Debug + MaybeSend + MaybeSync {
+    /// Return a response to answer the request with, without hitting the
+    /// network
+    async fn get(&self, key: &ApiResponseCacheKey) -> Option<Value>;
+
+    /// Called with every successful response received from the network
+    async fn insert(&self, key: ApiResponseCacheKey, response: &Value);
+}
+
+pub type DynApiResponseCache = Arc<maybe_add_send_sync!(dyn IApiResponseCache + 'static)>;
+
+/// [`IRawFederationApi`] consulting a [`IApiResponseCache`] before forwarding
+/// requests to the wrapped api
+///
+/// Typically installed via `ClientBuilder::with_response_cache`, which
+/// composes it with the api request hook.
+#[derive(Debug)]
+pub struct RawFederationApiWithResponseCache {
+    inner: DynIRawFederationApi,
+    cache: DynApiResponseCache,
+}
+
+impl RawFederationApiWithResponseCache {
+    pub fn new(inner: DynIRawFederationApi, cache: DynApiResponseCache) -> Self {
+        Self { inner, cache }
+    }
+}
+
+#[apply(async_trait_maybe_send!)]
+impl IRawFederationApi for RawFederationApiWithResponseCache {
+    fn all_peers(&self) -> &BTreeSet<PeerId> {
+        self.inner.all_peers()
+    }
+
+    fn self_peer(&self) -> Option<PeerId> {
+        self.inner.self_peer()
+    }
+
+    fn with_module(&self, id: ModuleInstanceId) -> DynModuleApi {
+        self.inner.with_module(id)
+    }
+
+    async fn request_raw(
+        &self,
+        peer_id: PeerId,
+        method: &str,
+        params: &ApiRequestErased,
+    ) -> ServerResult<Value> {
+        if params.auth.is_some() {
+            return self.inner.request_raw(peer_id, method, params).await;
+        }
+
+        let key = ApiResponseCacheKey::new(peer_id, method, params);
+
+        if let Some(response) = self.cache.get(&key).await {
+            trace!(target: LOG_CLIENT_NET_API, %peer_id, %method, "Api response served from cache");
+            return Ok(response);
+        }
+
+        let response = self.inner.request_raw(peer_id, method, params).await?;
+        self.cache.insert(key, &response).await;
+
+        Ok(response)
+    }
+
+    fn connection_status_stream(&self) -> BoxStream<'static, BTreeMap<PeerId, PeerStatus>> {
+        self.inner.connection_status_stream()
+    }
+
+    async fn wait_for_initialized_connections(&self) {
+        self.inner.wait_for_initialized_connections().await;
+    }
+
+    async fn get_peer_connection(&self, peer_id: PeerId) -> ServerResult<DynGuaridianConnection> {
+        self.inner.get_peer_connection(peer_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use fedimint_core::module::ApiAuth;
+    use futures::StreamExt as _;
+    use serde_json::json;
+
+    use super::*;
+
+    /// Answers every request with its method name and counts the requests
+    #[derive(Debug, Default)]
+    struct CountingApi {
+        peers: BTreeSet<PeerId>,
+        requests: Arc<AtomicUsize>,
+    }
+
+    #[apply(async_trait_maybe_send!)]
+    impl IRawFederationApi for CountingApi {
+        fn all_peers(&self) -> &BTreeSet<PeerId> {
+            &self.peers
+        }
+
+        fn self_peer(&self) -> Option<PeerId> {
+            None
+        }
+
+        fn with_module(&self, _id: ModuleInstanceId) -> DynModuleApi {
+            unimplemented!()
+        }
+
+        async fn request_raw(
+            &self,
+            _peer_id: PeerId,
+            method: &str,
+            _params: &ApiRequestErased,
+        ) -> ServerResult<Value> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            Ok(json!(method))
+        }
+
+        fn connection_status_stream(&self) -> BoxStream<'static, BTreeMap<PeerId, PeerStatus>> {
+            futures::stream::pending().boxed()
+        }
+
+        async fn wait_for_initialized_connections(&self) {}
+
+        async fn get_peer_connection(
+            &self,
+            _peer_id: PeerId,
+        ) -> ServerResult<DynGuaridianConnection> {
+            unimplemented!()
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct MemoryCache {
+        entries: Mutex<BTreeMap<ApiResponseCacheKey, Value>>,
+    }
+
+    #[apply(async_trait_maybe_send!)]
+    impl IApiResponseCache for MemoryCache {
+        async fn get(&self, key: &ApiResponseCacheKey) -> Option<Value> {
+            self.entries
+                .lock()
+                .expect("Locking failed")
+                .get(key)
+                .cloned()
+        }
+
+        async fn insert(&self, key: ApiResponseCacheKey, response: &Value) {
+            self.entries
+                .lock()
+                .expect("Locking failed")
+                .insert(key, response.clone());
+        }
+    }
+
+    fn api_with_cache() -> (
+        RawFederationApiWithResponseCache,
+        Arc<AtomicUsize>,
+        Arc<MemoryCache>,
+    ) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let cache = Arc::new(MemoryCache::default());
+        let api = RawFederationApiWithResponseCache::new(
+            Box::new(CountingApi {
+                peers: BTreeSet::from([PeerId::from(0)]),
+                requests: requests.clone(),
+            }),
+            cache.clone(),
+        );
+
+        (api, requests, cache)
+    }
+
+    #[tokio::test]
+    async fn miss_is_forwarded_and_stored() {
+        let (api, requests, cache) = api_with_cache();
+        let params = ApiRequestErased::new(42);
+
+        let response = api
+            .request_raw(PeerId::from(0), "method", &params)
+            .await
+            .expect("Request failed");
+
+        assert_eq!(response, json!("method"));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            cache
+                .get(&ApiResponseCacheKey::new(
+                    PeerId::from(0),
+                    "method",
+                    &params
+                ))
+                .await,
+            Some(json!("method"))
+        );
+    }
+
+    #[tokio::test]
+    async fn hit_is_served_from_cache() {
+        let (api, requests, cache) = api_with_cache();
+        let params = ApiRequestErased::new(42);
+        cache
+            .insert(
+                ApiResponseCacheKey::new(PeerId::from(0), "method", &params),
+                &json!("cached"),
+            )
+            .await;
+
+        let response = api
+            .request_raw(PeerId::from(0), "method", &params)
+            .await
+            .expect("Request failed");
+
+        assert_eq!(response, json!("cached"));
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+        // Different params or peers are not served from the cached response
+        api.request_raw(PeerId::from(0), "method", &ApiRequestErased::new(43))
+            .await
+            .expect("Request failed");
+        api.request_raw(PeerId::from(1), "method", &params)
+            .await
+            .expect("Request failed");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn authenticated_requests_bypass_cache() {
+        let (api, requests, cache) = api_with_cache();
+        let params = ApiRequestErased::new(42);
+        cache
+            .insert(
+                ApiResponseCacheKey::new(PeerId::from(0), "method", &params),
+                &json!("cached"),
+            )
+            .await;
+
+        let authenticated = params.with_auth(ApiAuth::new("password".to_string()));
+        let response = api
+            .request_raw(PeerId::from(0), "method", &authenticated)
+            .await
+            .expect("Request failed");
+
+        assert_eq!(response, json!("method"));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        let authenticated = ApiRequestErased::new(7).with_auth(ApiAuth::new("password".into()));
+        api.request_raw(PeerId::from(0), "method", &authenticated)
+            .await
+            .expect("Request failed");
+        assert!(
+            cache
+                .get(&ApiResponseCacheKey::new(
+                    PeerId::from(0),
+                    "method",
+                    &authenticated
+                ))
+                .await
+                .is_none()
+        );
+    }
+}
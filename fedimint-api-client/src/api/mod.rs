@@ -17,7 +17,9 @@
     ConnectionPool, Connectivity, ConnectorRegistry, DynGuaridianConnection, IGuardianConnection,
     PeerStatus,
 };
-use fedimint_core::admin_client::{GuardianConfigBackup, ServerStatusLegacy, SetupStatus};
+use fedimint_core::admin_client::{
+    ApiConnectionStatus, GuardianConfigBackup, ServerStatusLegacy, SetupStatus,
+};
 use fedimint_core::backup::{BackupStatistics, ClientBackupSnapshot};
 use fedimint_core::core::backup::SignedBackupRequest;
 use fedimint_core::core::{Decoder, DynOutputOutcome, ModuleInstanceId, ModuleKind, OutputOutcome};
@@ -609,6 +611,28 @@ async fn sign_guardian_metadata(
     /// Returns the chain ID (bitcoin block hash at height 1) from the
     /// federation
     async fn chain_id(&self) -> FederationResult<ChainId>;
+
+    /// Change the number of database checkpoints the guardian retains without
+    /// restarting it. Takes effect on the next checkpoint cleanup and is reset
+    /// to the configured value on restart.
+    async fn set_db_checkpoint_retention(
+        &self,
+        retention: u64,
+        auth: ApiAuth,
+    ) -> FederationResult<()>;
+
+    /// Returns the guardian's API connection limit and the number of active
+    /// connections
+    async fn api_connections(&self, auth: ApiAuth) -> FederationResult<ApiConnectionStatus>;
+
+    /// Change the guardian's API connection limit without restarting it. New
+    /// connections above the limit are rejected while existing ones are left
+    /// to drain.
+    async fn set_max_api_connections(
+        &self,
+        max_connections: u64,
+        auth: ApiAuth,
+    ) -> FederationResult<()>;
 }
 
 pub fn deserialize_outcome<R>(
@@ -632,6 +656,49 @@ pub fn deserialize_outcome<R>(
     })
 }
 
+/// Shared map of the URLs used to reach each federation peer
+///
+/// Cloning it yields a handle to the same map, so an update is visible to
+/// every [`FederationApi`] holding it. Connections are pooled by URL, so
+/// established connections (and requests in flight on them) are unaffected by
+/// an update; only new requests are routed to the new URLs.
+#[derive(Clone, Debug)]
+pub struct PeerUrls(Arc<std::sync::RwLock<BTreeMap<PeerId, SafeUrl>>>);
+
+impl PeerUrls {
+    fn new(peers: BTreeMap<PeerId, SafeUrl>) -> Self {
+        Self(Arc::new(std::sync::RwLock::new(peers)))
+    }
+
+    /// Current URL of `peer`, if it is part of the federation
+    pub fn get(&self, peer: PeerId) -> Option<SafeUrl> {
+        self.0.read().expect("Lock poisoned").get(&peer).cloned()
+    }
+
+    /// Snapshot of the URLs of all peers
+    pub fn to_map(&self) -> BTreeMap<PeerId, SafeUrl> {
+        self.0.read().expect("Lock poisoned").clone()
+    }
+
+    /// Replace the URLs of known peers with the ones in `urls`
+    ///
+    /// The set of peers is fixed for the lifetime of the api, so entries for
+    /// unknown peers are ignored. Returns `true` if any URL changed.
+    pub fn update(&self, urls: BTreeMap<PeerId, SafeUrl>) -> bool {
+        let mut peers = self.0.write().expect("Lock poisoned");
+        let mut changed = false;
+        for (peer_id, url) in urls {
+            if let Some(current) = peers.get_mut(&peer_id)
+                && *current != url
+            {
+                *current = url;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
 /// Federation API client
 ///
 /// The core underlying object used to make API requests to a federation.
@@ -645,7 +712,7 @@ pub fn deserialize_outcome<R>(
 #[derive(Clone, Debug)]
 pub struct FederationApi {
     /// Map of known URLs to use to connect to peers
-    peers: BTreeMap<PeerId, SafeUrl>,
+    peers: PeerUrls,
     /// List of peer ids, redundant to avoid collecting all the time
     peers_keys: BTreeSet<PeerId>,
     /// Our own [`PeerId`] to use when making admin apis
@@ -667,7 +734,7 @@ pub fn new(
     ) -> Self {
         Self {
             peers_keys: peers.keys().copied().collect(),
-            peers,
+            peers: PeerUrls::new(peers),
             admin_id: admin_peer_id,
             module_id: None,
             api_secret: api_secret.map(ToOwned::to_owned),
@@ -675,6 +742,15 @@ pub fn new(
         }
     }
 
+    /// Handle to the URLs used to connect to peers
+    ///
+    /// Shared by every api derived from this one (e.g. via
+    /// [`IRawFederationApi::with_module`]), so it can be used to re-target all
+    /// of them after the federation announced new URLs.
+    pub fn peer_urls(&self) -> PeerUrls {
+        self.peers.clone()
+    }
+
     async fn get_or_create_connection(
         &self,
         url: &SafeUrl,
@@ -699,10 +775,10 @@ async fn request(
         trace!(target: LOG_CLIENT_NET_API, %peer, %method, "Api request");
         let url = self
             .peers
-            .get(&peer)
+            .get(peer)
             .ok_or_else(|| ServerError::InvalidPeerId { peer_id: peer })?;
         let conn = self
-            .get_or_create_connection(url, self.api_secret.as_deref())
+            .get_or_create_connection(&url, self.api_secret.as_deref())
             .await
             .context("Failed to connect to peer")
             .map_err(ServerError::Connection)?;
@@ -801,6 +877,7 @@ fn connection_status_stream(&self) -> BoxStream<'static, BTreeMap<PeerId, PeerSt
             .map(move |()| {
                 let active_urls = active_rx.borrow().clone();
                 peers
+                    .to_map()
                     .iter()
                     .map(|(peer_id, url)| {
                         let status = if active_urls.contains(url) {
@@ -834,9 +911,9 @@ async fn wait_for_initialized_connections(&self) {
     async fn get_peer_connection(&self, peer_id: PeerId) -> ServerResult<DynGuaridianConnection> {
         let url = self
             .peers
-            .get(&peer_id)
+            .get(peer_id)
             .ok_or_else(|| ServerError::InvalidPeerId { peer_id })?;
-        self.get_or_create_connection(url, self.api_secret.as_deref())
+        self.get_or_create_connection(&url, self.api_secret.as_deref())
             .await
     }
 }
@@ -852,6 +929,10 @@ pub struct LegacyFederationStatus {
     /// should generate an alert if this is not the case.
     pub peers_flagged: u64,
     pub scheduled_shutdown: Option<u64>,
+    /// Number of database checkpoints the guardian retains, `None` if the
+    /// guardian does not report it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_checkpoint_retention: Option<u64>,
 }
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
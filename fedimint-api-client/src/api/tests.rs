@@ -44,3 +44,28 @@ fn creates_essential_guardians_invite_code() {
         peer_to_url_map.into_iter().take(max_size).collect();
     assert_eq!(expected_map, code.peers());
 }
+
+#[test]
+fn peer_urls_update_only_known_peers() {
+    let peer_urls = super::PeerUrls::new(BTreeMap::from([
+        (PeerId::from(0), "ws://test1".parse().expect("URL fail")),
+        (PeerId::from(1), "ws://test2".parse().expect("URL fail")),
+    ]));
+    let shared = peer_urls.clone();
+
+    assert!(!peer_urls.update(BTreeMap::from([(
+        PeerId::from(0),
+        "ws://test1".parse().expect("URL fail"),
+    )])));
+
+    assert!(peer_urls.update(BTreeMap::from([
+        (PeerId::from(1), "ws://moved".parse().expect("URL fail")),
+        (PeerId::from(2), "ws://unknown".parse().expect("URL fail")),
+    ])));
+
+    assert_eq!(
+        shared.get(PeerId::from(1)),
+        Some("ws://moved".parse().expect("URL fail"))
+    );
+    assert_eq!(shared.get(PeerId::from(2)), None);
+}
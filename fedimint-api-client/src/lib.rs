@@ -5,6 +5,8 @@
 #![allow(clippy::must_use_candidate)]
 #![allow(clippy::return_self_not_must_use)]
 
+use std::collections::BTreeMap;
+
 use anyhow::{Context as _, bail};
 use api::{DynGlobalApi, FederationApiExt as _};
 use fedimint_connectors::ConnectorRegistry;
@@ -13,9 +15,10 @@
 use fedimint_core::endpoint_constants::CLIENT_CONFIG_ENDPOINT;
 use fedimint_core::invite_code::InviteCode;
 use fedimint_core::module::ApiRequestErased;
-use fedimint_core::util::backoff_util;
+use fedimint_core::util::{SafeUrl, backoff_util};
+use fedimint_core::{NumPeersExt as _, PeerId};
 use fedimint_logging::LOG_CLIENT_NET;
-use query::FilterMap;
+use query::{FilterMap, ThresholdConsensus};
 use tracing::debug;
 
 pub mod api;
@@ -28,6 +31,21 @@
 pub async fn download_from_invite_code(
     endpoints: &ConnectorRegistry,
     invite: &InviteCode,
+) -> anyhow::Result<(ClientConfig, DynGlobalApi)> {
+    download_from_invite_code_with_quorum(endpoints, invite, None).await
+}
+
+/// Like [`download_from_invite_code`], but accepts the [`ClientConfig`] once
+/// `quorum` guardians returned an identical one instead of a threshold of them.
+///
+/// The quorum must be at least [`fedimint_core::NumPeers::one_honest`] of the
+/// federation, so that at least one honest guardian vouches for the config;
+/// smaller values are rejected. If `None`, a consensus threshold of guardians
+/// has to agree.
+pub async fn download_from_invite_code_with_quorum(
+    endpoints: &ConnectorRegistry,
+    invite: &InviteCode,
+    quorum: Option<usize>,
 ) -> anyhow::Result<(ClientConfig, DynGlobalApi)> {
     debug!(
         target: LOG_CLIENT_NET,
@@ -48,11 +66,12 @@ pub async fn download_from_invite_code(
         "Downloading client config",
         backoff_util::aggressive_backoff(),
         || {
-            try_download_client_config(
+            try_download_client_config_with_quorum(
                 endpoints,
                 &api_from_invite,
                 federation_id,
                 api_secret.clone(),
+                quorum,
             )
         },
     )
@@ -66,6 +85,25 @@ pub async fn try_download_client_config(
     api_from_invite: &DynGlobalApi,
     federation_id: FederationId,
     api_secret: Option<String>,
+) -> anyhow::Result<(ClientConfig, DynGlobalApi)> {
+    try_download_client_config_with_quorum(
+        endpoints,
+        api_from_invite,
+        federation_id,
+        api_secret,
+        None,
+    )
+    .await
+}
+
+/// Tries to download the [`ClientConfig`] only once, see
+/// [`download_from_invite_code_with_quorum`] for the meaning of `quorum`.
+pub async fn try_download_client_config_with_quorum(
+    endpoints: &ConnectorRegistry,
+    api_from_invite: &DynGlobalApi,
+    federation_id: FederationId,
+    api_secret: Option<String>,
+    quorum: Option<usize>,
 ) -> anyhow::Result<(ClientConfig, DynGlobalApi)> {
     debug!(target: LOG_CLIENT_NET, "Downloading client config from peer");
     // TODO: use new download approach based on guardian PKs
@@ -88,16 +126,28 @@ pub async fn try_download_client_config(
         .await?;
 
     // now we can build an api for all guardians and download the client config
-    let api_endpoints = api_endpoints
+    let api_endpoints: BTreeMap<PeerId, SafeUrl> = api_endpoints
         .into_iter()
         .map(|(peer, url)| (peer, url.url))
         .collect();
 
-    debug!(target: LOG_CLIENT_NET, "Verifying client config with all peers");
+    let num_peers = api_endpoints.to_num_peers();
+    let threshold = match quorum {
+        Some(quorum) if quorum < num_peers.one_honest() => bail!(
+            "Quorum of {quorum} is below the safe minimum of {} out of {} guardians",
+            num_peers.one_honest(),
+            num_peers.total()
+        ),
+        Some(quorum) => quorum.min(num_peers.total()),
+        None => num_peers.threshold(),
+    };
+
+    debug!(target: LOG_CLIENT_NET, %threshold, "Verifying client config with peers");
 
     let api_full = DynGlobalApi::new(endpoints.clone(), api_endpoints, api_secret.as_deref())?;
     let client_config = api_full
-        .request_current_consensus::<ClientConfig>(
+        .request_with_strategy(
+            ThresholdConsensus::<ClientConfig>::with_threshold(threshold),
             CLIENT_CONFIG_ENDPOINT.to_owned(),
             ApiRequestErased::default(),
         )
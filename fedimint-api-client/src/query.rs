@@ -115,6 +115,16 @@ pub fn new(num_peers: NumPeers) -> Self {
             threshold: num_peers.threshold(),
         }
     }
+
+    /// Like [`Self::new`], but succeeds once `threshold` peers returned the
+    /// same response instead of the federation's consensus threshold
+    pub fn with_threshold(threshold: usize) -> Self {
+        Self {
+            responses: BTreeMap::new(),
+            retry: BTreeSet::new(),
+            threshold,
+        }
+    }
 }
 
 impl<R: Eq + Clone> QueryStrategy<R> for ThresholdConsensus<R> {
@@ -149,3 +159,13 @@ fn test_threshold_consensus() {
     assert_matches!(consensus.process(PeerId::from(1), 1), QueryStep::Continue);
     assert_matches!(consensus.process(PeerId::from(2), 1), QueryStep::Success(1));
 }
+
+#[test]
+fn test_threshold_consensus_with_threshold() {
+    use assert_matches::assert_matches;
+
+    let mut consensus = ThresholdConsensus::<u64>::with_threshold(2);
+
+    assert_matches!(consensus.process(PeerId::from(0), 1), QueryStep::Continue);
+    assert_matches!(consensus.process(PeerId::from(1), 1), QueryStep::Success(1));
+}
@@ -5,7 +5,7 @@
 use std::io::{Read, Write};
 
 pub use bip39::{Language, Mnemonic};
-use fedimint_client::secret::RootSecretStrategy;
+use fedimint_client::secret::{FEDIMINT_CLIENT_NONCE, RootSecretStrategy};
 use fedimint_core::encoding::{Decodable, DecodeError, Encodable};
 use fedimint_core::module::registry::ModuleRegistry;
 use fedimint_derive_secret::DerivableSecret;
@@ -19,7 +19,6 @@ impl<const WORD_COUNT: usize> RootSecretStrategy for Bip39RootSecretStrategy<WOR
     type Encoding = Mnemonic;
 
     fn to_root_secret(secret: &Self::Encoding) -> DerivableSecret {
-        const FEDIMINT_CLIENT_NONCE: &[u8] = b"Fedimint Client Salt";
         const EMPTY_PASSPHRASE: &str = "";
 
         DerivableSecret::new_root(
@@ -47,3 +46,37 @@ fn random<R>(rng: &mut R) -> Self::Encoding
             .expect("Failed to generate mnemonic, bad word count")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use fedimint_client::RootSecret;
+    use fedimint_client::secret::RootSecretStrategy;
+
+    use super::{Bip39RootSecretStrategy, Language, Mnemonic};
+
+    #[test]
+    fn from_bip39_matches_strategy_with_empty_passphrase() {
+        let mnemonic = Mnemonic::generate_in(Language::English, 12).expect("valid word count");
+
+        let RootSecret::StandardDoubleDerive(from_bip39) = RootSecret::from_bip39(&mnemonic, "")
+        else {
+            panic!("from_bip39 must return a standard root secret");
+        };
+        let from_strategy = Bip39RootSecretStrategy::<12>::to_root_secret(&mnemonic);
+
+        assert_eq!(
+            from_bip39.to_random_bytes::<32>(),
+            from_strategy.to_random_bytes::<32>()
+        );
+
+        let RootSecret::StandardDoubleDerive(with_passphrase) =
+            RootSecret::from_bip39(&mnemonic, "passphrase")
+        else {
+            panic!("from_bip39 must return a standard root secret");
+        };
+        assert_ne!(
+            with_passphrase.to_random_bytes::<32>(),
+            from_strategy.to_random_bytes::<32>()
+        );
+    }
+}
@@ -165,6 +165,22 @@ pub(crate) enum AdminCmd {
     },
     /// Show statistics about client backups stored by the federation
     BackupStatistics,
+    /// Change the number of database checkpoints the guardian retains until
+    /// its next restart. Takes effect on the next checkpoint cleanup.
+    SetDbCheckpointRetention {
+        /// Number of checkpoints from the current session to retain, 0
+        /// disables checkpointing
+        retention: u64,
+    },
+    /// Show the guardian's iroh API connection limit and the number of active
+    /// connections
+    ApiConnections,
+    /// Change the guardian's iroh API connection limit until its next restart.
+    /// Existing connections above the new limit are kept until they close.
+    SetMaxApiConnections {
+        /// Maximum number of concurrent connections
+        max_connections: u64,
+    },
     /// Change guardian password, will shut down fedimintd and require manual
     /// restart
     ChangePassword {
@@ -905,6 +905,48 @@ async fn handle_command(&mut self, cli: Opts) -> CliOutputResult {
                     serde_json::to_value(backup_statistics).expect("Can be encoded"),
                 ))
             }
+            Command::Admin(AdminCmd::SetDbCheckpointRetention { retention }) => {
+                let client = self.client_open(&cli).await?;
+
+                cli.admin_client(
+                    &client.get_peer_urls().await,
+                    client.api_secret().as_deref(),
+                )
+                .await?
+                .set_db_checkpoint_retention(retention, cli.auth()?)
+                .await?;
+
+                Ok(CliOutput::Raw(json!(null)))
+            }
+            Command::Admin(AdminCmd::ApiConnections) => {
+                let client = self.client_open(&cli).await?;
+
+                let api_connections = cli
+                    .admin_client(
+                        &client.get_peer_urls().await,
+                        client.api_secret().as_deref(),
+                    )
+                    .await?
+                    .api_connections(cli.auth()?)
+                    .await?;
+
+                Ok(CliOutput::Raw(
+                    serde_json::to_value(api_connections).expect("Can be encoded"),
+                ))
+            }
+            Command::Admin(AdminCmd::SetMaxApiConnections { max_connections }) => {
+                let client = self.client_open(&cli).await?;
+
+                cli.admin_client(
+                    &client.get_peer_urls().await,
+                    client.api_secret().as_deref(),
+                )
+                .await?
+                .set_max_api_connections(max_connections, cli.auth()?)
+                .await?;
+
+                Ok(CliOutput::Raw(json!(null)))
+            }
             Command::Admin(AdminCmd::ChangePassword { new_password }) => {
                 let client = self.client_open(&cli).await?;
 
@@ -215,6 +215,10 @@ async fn log_event_json(
 
     /// Returns the core API version that the federation supports
     async fn core_api_version(&self) -> ApiVersion;
+
+    /// Resolves once the operation the state machine belongs to was cancelled
+    /// by the user. Triggers can select on it to move to a terminal state.
+    async fn await_operation_cancelled(&self);
 }
 
 #[apply(async_trait_maybe_send!)]
@@ -277,6 +281,10 @@ async fn transaction_update_stream(&self) -> BoxStream<TxSubmissionStatesSM> {
     async fn core_api_version(&self) -> ApiVersion {
         unimplemented!("fake implementation, only for tests");
     }
+
+    async fn await_operation_cancelled(&self) {
+        unimplemented!("fake implementation, only for tests");
+    }
 }
 
 dyn_newtype_define! {
@@ -107,6 +107,8 @@ async fn await_primary_module_outputs(
 
     fn get_internal_payment_markers(&self) -> anyhow::Result<(PublicKey, u64)>;
 
+    fn is_watch_only(&self) -> bool;
+
     #[allow(clippy::too_many_arguments)]
     async fn log_event_json(
         &self,
@@ -514,6 +516,12 @@ pub fn get_internal_payment_markers(&self) -> anyhow::Result<(PublicKey, u64)> {
         self.client.get().get_internal_payment_markers()
     }
 
+    /// Returns `true` if the client must not spend, modules have to refuse
+    /// handing out funds without submitting a transaction themselves
+    pub fn is_watch_only(&self) -> bool {
+        self.client.get().is_watch_only()
+    }
+
     /// This method starts n state machines with given operation id without a
     /// corresponding transaction
     pub async fn manual_operation_start(
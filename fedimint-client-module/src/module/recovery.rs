@@ -1,5 +1,6 @@
 use std::any::Any;
 use std::fmt::{self, Debug};
+use std::time::Duration;
 
 use fedimint_core::core::{IntoDynInstance, ModuleInstanceId, ModuleKind};
 use fedimint_core::encoding::{Decodable, DynEncodable, Encodable};
@@ -142,6 +143,24 @@ pub fn is_none(self) -> bool {
         self.total == 0
     }
 
+    /// Estimated time until the recovery is done, extrapolating the
+    /// throughput since `started` progress was reported `elapsed` ago
+    ///
+    /// Returns `None` if no progress was made since `started`.
+    pub fn eta(self, started: RecoveryProgress, elapsed: Duration) -> Option<Duration> {
+        if self.is_done() {
+            return Some(Duration::ZERO);
+        }
+
+        let processed = self
+            .complete
+            .checked_sub(started.complete)
+            .filter(|processed| *processed != 0)?;
+        let remaining = self.total.saturating_sub(self.complete);
+
+        Some(elapsed.mul_f64(f64::from(remaining) / f64::from(processed)))
+    }
+
     pub fn to_complete(self) -> RecoveryProgress {
         if self.is_none() {
             // Since we don't have a valid "total", we make up a 1 out of 1
@@ -163,3 +182,43 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_fmt(format_args!("{}/{}", self.complete, self.total))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::RecoveryProgress;
+
+    fn progress(complete: u32, total: u32) -> RecoveryProgress {
+        RecoveryProgress { complete, total }
+    }
+
+    #[test]
+    fn eta_is_zero_when_done() {
+        assert_eq!(
+            progress(10, 10).eta(progress(10, 10), Duration::from_secs(5)),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn eta_is_unknown_without_progress() {
+        assert_eq!(
+            progress(3, 10).eta(progress(3, 10), Duration::from_secs(5)),
+            None
+        );
+        assert_eq!(
+            RecoveryProgress::none().eta(RecoveryProgress::none(), Duration::from_secs(5)),
+            None
+        );
+    }
+
+    #[test]
+    fn eta_extrapolates_partial_progress() {
+        // 2 were processed in 10s, so the remaining 6 take 30s
+        assert_eq!(
+            progress(4, 10).eta(progress(2, 10), Duration::from_secs(10)),
+            Some(Duration::from_secs(30))
+        );
+    }
+}
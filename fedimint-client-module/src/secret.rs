@@ -8,6 +8,10 @@
 use fedimint_derive_secret::{ChildId, DerivableSecret};
 use rand::{CryptoRng, Rng, RngCore};
 
+/// Salt used to derive the client's pre-root secret from the raw secret
+/// material (random bytes or a BIP39 seed)
+pub const FEDIMINT_CLIENT_NONCE: &[u8] = b"Fedimint Client Salt";
+
 // Derived from pre-root-secret (pre-federation-derived)
 const TYPE_PRE_ROOT_SECRET_HASH: ChildId = ChildId(0);
 
@@ -83,7 +87,6 @@ impl RootSecretStrategy for PlainRootSecretStrategy {
     type Encoding = [u8; 64];
 
     fn to_root_secret(secret: &Self::Encoding) -> DerivableSecret {
-        const FEDIMINT_CLIENT_NONCE: &[u8] = b"Fedimint Client Salt";
         DerivableSecret::new_root(secret.as_ref(), FEDIMINT_CLIENT_NONCE)
     }
 
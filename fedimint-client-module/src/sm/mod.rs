@@ -10,7 +10,7 @@
 pub use executor::{ActiveStateMeta, InactiveStateMeta};
 pub use state::{
     Context, DynContext, DynState, IState, OperationState, State, StateTransition,
-    StateTransitionFunction,
+    StateTransitionFunction, TriggerTimeoutOutcome,
 };
 
 pub use self::notifier::ModuleNotifier;
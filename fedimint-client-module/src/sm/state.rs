@@ -5,13 +5,20 @@
 use std::io::{Error, Read, Write};
 use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
 use fedimint_core::core::{IntoDynInstance, ModuleInstanceId, ModuleKind, OperationId};
 use fedimint_core::encoding::{Decodable, DecodeError, DynEncodable, Encodable};
 use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::task::{MaybeSend, MaybeSync};
-use fedimint_core::util::BoxFuture;
-use fedimint_core::{maybe_add_send, maybe_add_send_sync, module_plugin_dyn_newtype_define};
+use fedimint_core::util::backoff_util::Backoff;
+use fedimint_core::util::{BoxFuture, retry};
+use fedimint_core::{
+    maybe_add_send, maybe_add_send_sync, module_plugin_dyn_newtype_define, runtime,
+};
+use fedimint_logging::LOG_CLIENT_REACTOR;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
 
 use crate::DynGlobalClientContext;
 use crate::sm::ClientSMDatabaseTransaction;
@@ -45,6 +52,31 @@ fn transitions(
     /// details.
     fn operation_id(&self) -> OperationId;
 
+    /// Stable name of the current state, used in logs and debugging tools.
+    ///
+    /// Defaults to the type name. State enums can name their variants using
+    /// [`sm_enum_state_name`](crate::sm_enum_state_name).
+    fn state_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Called by the executor once, when this state is first persisted, as
+    /// part of the database transaction persisting it (this includes terminal
+    /// states).
+    ///
+    /// Meant for one-time effects like recording timing or emitting
+    /// notifications, which would otherwise have to be smuggled into trigger
+    /// futures. As the transaction might be retried, effects outside of the
+    /// database should be deferred using `on_commit`. Defaults to a no-op.
+    fn on_enter<'a>(
+        &'a self,
+        _context: &'a Self::ModuleContext,
+        _global_context: &'a DynGlobalClientContext,
+        _dbtx: &'a mut ClientSMDatabaseTransaction<'_, '_>,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(async {})
+    }
+
     /// Human-readable visualization of this state machine for debugging.
     ///
     /// Defaults to `Debug` output. Override to show only the relevant
@@ -69,6 +101,17 @@ fn transitions(
     /// details.
     fn operation_id(&self) -> OperationId;
 
+    /// See [`State::state_name`]
+    fn state_name(&self) -> &'static str;
+
+    /// See [`State::on_enter`]
+    fn on_enter<'a>(
+        &'a self,
+        context: &'a DynContext,
+        global_context: &'a DynGlobalClientContext,
+        dbtx: &'a mut ClientSMDatabaseTransaction<'_, '_>,
+    ) -> BoxFuture<'a, ()>;
+
     /// Clone state
     fn clone(&self, module_instance_id: ModuleInstanceId) -> DynState;
 
@@ -190,6 +233,144 @@ pub fn new<V, Trigger, TransitionFn>(
             }),
         }
     }
+
+    /// Like [`Self::new`], but gives up waiting on `trigger` after `timeout`
+    /// and runs `on_timeout_transition` instead of `transition`.
+    ///
+    /// **The timeout is measured per attempt**: since triggers are re-run from
+    /// scratch when the client restarts, the deadline starts over every time
+    /// the state machine is (re)started, not when the state was entered. States
+    /// that need an absolute deadline must persist it in the state itself.
+    pub fn new_with_timeout<V, Trigger, TransitionFn, TimeoutFn>(
+        trigger: Trigger,
+        timeout: Duration,
+        transition: TransitionFn,
+        on_timeout_transition: TimeoutFn,
+    ) -> StateTransition<S>
+    where
+        S: MaybeSend + MaybeSync + Clone + 'static,
+        V: serde::Serialize + serde::de::DeserializeOwned + Send,
+        Trigger: Future<Output = V> + MaybeSend + 'static,
+        TransitionFn: for<'a> Fn(&'a mut ClientSMDatabaseTransaction<'_, '_>, V, S) -> BoxFuture<'a, S>
+            + MaybeSend
+            + MaybeSync
+            + Clone
+            + 'static,
+        TimeoutFn: for<'a> Fn(&'a mut ClientSMDatabaseTransaction<'_, '_>, S) -> BoxFuture<'a, S>
+            + MaybeSend
+            + MaybeSync
+            + Clone
+            + 'static,
+    {
+        StateTransition::new(
+            async move {
+                match runtime::timeout(timeout, trigger).await {
+                    Ok(val) => TriggerTimeoutOutcome::Triggered(val),
+                    Err(_) => TriggerTimeoutOutcome::TimedOut,
+                }
+            },
+            move |dbtx, outcome, state| match outcome {
+                TriggerTimeoutOutcome::Triggered(val) => transition(dbtx, val, state),
+                TriggerTimeoutOutcome::TimedOut => on_timeout_transition(dbtx, state),
+            },
+        )
+    }
+
+    /// Creates a new `StateTransition` whose trigger runs the fallible
+    /// `operation_fn` until it succeeds, sleeping according to `backoff`
+    /// between attempts, and hands the value to `transition`.
+    ///
+    /// Like any trigger, `operation_fn` is re-run from scratch when the client
+    /// restarts, so it must be idempotent. `backoff` should not give up (e.g.
+    /// [`fedimint_core::util::backoff_util::background_backoff`]); if it does,
+    /// the trigger stays pending until the state machine is restarted.
+    pub fn retrying<V, OpFn, OpFut, TransitionFn>(
+        operation_fn: OpFn,
+        backoff: impl Backoff + MaybeSend + 'static,
+        transition: TransitionFn,
+    ) -> StateTransition<S>
+    where
+        S: MaybeSend + MaybeSync + Clone + 'static,
+        V: serde::Serialize + serde::de::DeserializeOwned + Send,
+        OpFn: Fn() -> OpFut + MaybeSend + 'static,
+        OpFut: Future<Output = anyhow::Result<V>> + MaybeSend + 'static,
+        TransitionFn: for<'a> Fn(&'a mut ClientSMDatabaseTransaction<'_, '_>, V, S) -> BoxFuture<'a, S>
+            + MaybeSend
+            + MaybeSync
+            + Clone
+            + 'static,
+    {
+        StateTransition::new(
+            async move {
+                let op_name = format!("{} trigger", std::any::type_name::<S>());
+                match retry(op_name, backoff, operation_fn).await {
+                    Ok(val) => val,
+                    Err(_) => {
+                        warn!(
+                            target: LOG_CLIENT_REACTOR,
+                            state = %std::any::type_name::<S>(),
+                            "Retrying trigger gave up, waiting for restart"
+                        );
+                        std::future::pending().await
+                    }
+                }
+            },
+            transition,
+        )
+    }
+
+    /// Combines multiple state transitions into one whose trigger resolves as
+    /// soon as the first of the given triggers does, and then runs the
+    /// transition belonging to that trigger.
+    ///
+    /// The remaining triggers are dropped. Like any trigger they are re-run
+    /// from scratch when the client restarts, so each of them must be
+    /// idempotent on its own.
+    ///
+    /// # Panics
+    /// If `transitions` is empty.
+    pub fn race(transitions: Vec<StateTransition<S>>) -> StateTransition<S>
+    where
+        S: MaybeSend + MaybeSync + Clone + 'static,
+    {
+        assert!(
+            !transitions.is_empty(),
+            "Can not race an empty list of state transitions"
+        );
+
+        let (triggers, transition_fns): (Vec<_>, Vec<_>) = transitions
+            .into_iter()
+            .map(|transition| (transition.trigger, transition.transition))
+            .unzip();
+
+        StateTransition::new(
+            async move {
+                let (value, index, _) = futures::future::select_all(triggers).await;
+                RaceOutcome { index, value }
+            },
+            move |dbtx, RaceOutcome { index, value }, state| {
+                (transition_fns[index])(dbtx, value, state)
+            },
+        )
+    }
+}
+
+/// Outcome of a trigger created with [`StateTransition::race`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RaceOutcome {
+    /// Position of the trigger that resolved first
+    index: usize,
+    /// Value the trigger resolved to
+    value: serde_json::Value,
+}
+
+/// Outcome of a trigger created with [`StateTransition::new_with_timeout`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TriggerTimeoutOutcome<V> {
+    /// The trigger completed in time with the given value
+    Triggered(V),
+    /// The timeout elapsed before the trigger completed
+    TimedOut,
 }
 
 impl<T> IState for T
@@ -239,6 +420,24 @@ fn operation_id(&self) -> OperationId {
         <T as State>::operation_id(self)
     }
 
+    fn state_name(&self) -> &'static str {
+        <T as State>::state_name(self)
+    }
+
+    fn on_enter<'a>(
+        &'a self,
+        context: &'a DynContext,
+        global_context: &'a DynGlobalClientContext,
+        dbtx: &'a mut ClientSMDatabaseTransaction<'_, '_>,
+    ) -> BoxFuture<'a, ()> {
+        <T as State>::on_enter(
+            self,
+            context.as_any().downcast_ref().expect("Wrong module"),
+            global_context,
+            dbtx,
+        )
+    }
+
     fn clone(&self, module_instance_id: ModuleInstanceId) -> DynState {
         DynState::from_typed(module_instance_id, <T as Clone>::clone(self))
     }
@@ -285,6 +484,19 @@ fn operation_id(&self) -> OperationId {
         (**self).operation_id()
     }
 
+    fn state_name(&self) -> &'static str {
+        (**self).state_name()
+    }
+
+    fn on_enter<'a>(
+        &'a self,
+        context: &'a DynContext,
+        global_context: &'a DynGlobalClientContext,
+        dbtx: &'a mut ClientSMDatabaseTransaction<'_, '_>,
+    ) -> BoxFuture<'a, ()> {
+        (**self).on_enter(context, global_context, dbtx)
+    }
+
     fn clone(&self, module_instance_id: ModuleInstanceId) -> DynState {
         (**self).clone(module_instance_id)
     }
@@ -331,6 +543,14 @@ pub fn module_instance_id(&self) -> ModuleInstanceId {
         self.1
     }
 
+    /// Kind of the module this state belongs to, looked up by its module
+    /// instance id in `decoders`
+    pub fn module_kind(&self, decoders: &ModuleDecoderRegistry) -> Option<ModuleKind> {
+        decoders
+            .get_with_kind(self.module_instance_id())
+            .map(|(kind, _)| kind.clone())
+    }
+
     pub fn from_typed<I>(module_instance_id: ModuleInstanceId, typed: I) -> Self
     where
         I: IState + 'static,
@@ -418,6 +638,28 @@ pub struct OperationState<S> {
     pub state: S,
 }
 
+impl<S> OperationState<S> {
+    /// Replaces the inner state using `f`, keeping the operation id, e.g. when
+    /// migrating to a new state type
+    pub fn map_inner<T>(self, f: impl FnOnce(S) -> T) -> OperationState<T> {
+        OperationState {
+            operation_id: self.operation_id,
+            state: f(self.state),
+        }
+    }
+
+    /// Fallible version of [`Self::map_inner`]
+    pub fn try_map_inner<T, E>(
+        self,
+        f: impl FnOnce(S) -> Result<T, E>,
+    ) -> Result<OperationState<T>, E> {
+        Ok(OperationState {
+            operation_id: self.operation_id,
+            state: f(self.state)?,
+        })
+    }
+}
+
 /// Wrapper for states that don't want to carry around their operation id. `S`
 /// is allowed to panic when `operation_id` is called.
 impl<S> State for OperationState<S>
@@ -466,6 +708,19 @@ fn operation_id(&self) -> OperationId {
         self.operation_id
     }
 
+    fn state_name(&self) -> &'static str {
+        self.state.state_name()
+    }
+
+    fn on_enter<'a>(
+        &'a self,
+        context: &'a Self::ModuleContext,
+        global_context: &'a DynGlobalClientContext,
+        dbtx: &'a mut ClientSMDatabaseTransaction<'_, '_>,
+    ) -> BoxFuture<'a, ()> {
+        self.state.on_enter(context, global_context, dbtx)
+    }
+
     fn fmt_visualization(&self, f: &mut dyn std::fmt::Write, indent: &str) -> std::fmt::Result {
         self.state.fmt_visualization(f, indent)
     }
@@ -546,3 +801,225 @@ fn clone(&self) -> Self {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+
+    use anyhow::bail;
+    use fedimint_core::core::Decoder;
+    use fedimint_core::db::Database;
+    use fedimint_core::db::mem_impl::MemDatabase;
+    use fedimint_core::module::registry::ModuleRegistry;
+    use fedimint_core::util::backoff_util::custom_backoff;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct TestContext;
+
+    impl Context for TestContext {
+        const KIND: Option<ModuleKind> = Some(ModuleKind::from_static_str("test"));
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash, Encodable, Decodable)]
+    enum TestState {
+        Created,
+        Funded { amount: u64 },
+    }
+
+    impl State for TestState {
+        type ModuleContext = TestContext;
+
+        fn transitions(
+            &self,
+            _context: &Self::ModuleContext,
+            _global_context: &DynGlobalClientContext,
+        ) -> Vec<StateTransition<Self>> {
+            vec![]
+        }
+
+        fn operation_id(&self) -> OperationId {
+            OperationId([0; 32])
+        }
+
+        fn state_name(&self) -> &'static str {
+            crate::sm_enum_state_name!(self, TestState { Created, Funded })
+        }
+    }
+
+    /// Waits for the trigger of `transition` and runs its transition function
+    /// on `state`
+    async fn run_transition(transition: StateTransition<u64>, state: u64) -> u64 {
+        let db = Database::new(MemDatabase::new(), ModuleRegistry::default());
+        let mut dbtx = db.begin_transaction_nc().await;
+
+        let value = transition.trigger.await;
+        (transition.transition)(
+            &mut ClientSMDatabaseTransaction::new(&mut dbtx, 0),
+            value,
+            state,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn new_with_timeout_runs_transition_if_triggered_in_time() {
+        let transition = StateTransition::new_with_timeout(
+            async { 5u64 },
+            Duration::from_secs(60),
+            |_dbtx, value, state| Box::pin(async move { state + value }),
+            |_dbtx, _state| Box::pin(async { 0 }),
+        );
+
+        assert_eq!(run_transition(transition, 1).await, 6);
+    }
+
+    #[tokio::test]
+    async fn new_with_timeout_runs_timeout_transition_after_timeout() {
+        let transition = StateTransition::new_with_timeout(
+            std::future::pending::<u64>(),
+            Duration::from_millis(10),
+            |_dbtx, value, state| Box::pin(async move { state + value }),
+            |_dbtx, _state| Box::pin(async { 0 }),
+        );
+
+        assert_eq!(run_transition(transition, 1).await, 0);
+    }
+
+    /// Returns an operation that fails `failures` times before returning the
+    /// number of the successful attempt, and the counter of its attempts
+    fn flaky_operation(
+        failures: u64,
+    ) -> (
+        impl Fn() -> BoxFuture<'static, anyhow::Result<u64>>,
+        Arc<AtomicU64>,
+    ) {
+        let attempts = Arc::new(AtomicU64::new(0));
+        let operation_attempts = attempts.clone();
+        let operation = move || -> BoxFuture<'static, anyhow::Result<u64>> {
+            let attempt = operation_attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            Box::pin(async move {
+                if attempt <= failures {
+                    bail!("Attempt {attempt} failed");
+                }
+                Ok(attempt)
+            })
+        };
+
+        (operation, attempts)
+    }
+
+    #[tokio::test]
+    async fn retrying_runs_transition_once_operation_succeeds() {
+        let (operation, attempts) = flaky_operation(2);
+        let transition = StateTransition::retrying(
+            operation,
+            custom_backoff(Duration::ZERO, Duration::ZERO, None),
+            |_dbtx, value, state| Box::pin(async move { state + value }),
+        );
+
+        assert_eq!(run_transition(transition, 10).await, 13);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn retrying_stays_pending_when_backoff_gives_up() {
+        let (operation, attempts) = flaky_operation(u64::MAX);
+        let transition = StateTransition::<u64>::retrying(
+            operation,
+            custom_backoff(Duration::ZERO, Duration::ZERO, Some(2)),
+            |_dbtx, value, state| Box::pin(async move { state + value }),
+        );
+
+        assert!(
+            runtime::timeout(Duration::from_millis(100), transition.trigger)
+                .await
+                .is_err()
+        );
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn race_runs_transition_of_first_ready_trigger() {
+        let (loser_tx, loser_rx) = tokio::sync::oneshot::channel::<()>();
+        let transition = StateTransition::race(vec![
+            StateTransition::new(
+                async move {
+                    let _loser_tx = loser_tx;
+                    std::future::pending::<u64>().await
+                },
+                |_dbtx, value, state| Box::pin(async move { state + value }),
+            ),
+            StateTransition::new(async { 3u64 }, |_dbtx, value, state| {
+                Box::pin(async move { state * value })
+            }),
+            StateTransition::new(async { 5u64 }, |_dbtx, value, state| {
+                Box::pin(async move { state - value })
+            }),
+        ]);
+
+        assert_eq!(run_transition(transition, 10).await, 30);
+        assert!(
+            loser_rx.await.is_err(),
+            "Triggers that lost the race must be dropped"
+        );
+    }
+
+    #[test]
+    fn trigger_timeout_outcome_roundtrips_through_json() {
+        for outcome in [
+            TriggerTimeoutOutcome::Triggered(7u64),
+            TriggerTimeoutOutcome::TimedOut,
+        ] {
+            let json = serde_json::to_value(&outcome).expect("Serialization failed");
+            let decoded: TriggerTimeoutOutcome<u64> =
+                serde_json::from_value(json).expect("Deserialization failed");
+
+            match (outcome, decoded) {
+                (TriggerTimeoutOutcome::Triggered(a), TriggerTimeoutOutcome::Triggered(b)) => {
+                    assert_eq!(a, b);
+                }
+                (TriggerTimeoutOutcome::TimedOut, TriggerTimeoutOutcome::TimedOut) => {}
+                (outcome, decoded) => panic!("Expected {outcome:?}, got {decoded:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn state_name_is_forwarded_by_wrappers() {
+        assert_eq!(State::state_name(&TestState::Created), "TestState::Created");
+        assert_eq!(
+            State::state_name(&TestState::Funded { amount: 1 }),
+            "TestState::Funded"
+        );
+
+        let dyn_state = DynState::from_typed(0, TestState::Funded { amount: 1 });
+        assert_eq!(dyn_state.state_name(), "TestState::Funded");
+
+        let operation_state = OperationState {
+            operation_id: OperationId([1; 32]),
+            state: TestState::Created,
+        };
+        assert_eq!(State::state_name(&operation_state), "TestState::Created");
+    }
+
+    #[test]
+    fn module_kind_comes_from_module_instance() {
+        let decoders = ModuleDecoderRegistry::from_iter([(
+            0,
+            ModuleKind::from_static_str("test"),
+            Decoder::builder().build(),
+        )]);
+
+        assert_eq!(
+            DynState::from_typed(0, TestState::Created).module_kind(&decoders),
+            Some(ModuleKind::from_static_str("test"))
+        );
+        assert_eq!(
+            DynState::from_typed(1, TestState::Created).module_kind(&decoders),
+            None
+        );
+    }
+}
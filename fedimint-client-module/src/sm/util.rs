@@ -64,3 +64,15 @@ macro_rules! sm_enum_variant_translation {
         )
     }};
 }
+
+/// Implements [`State::state_name`](crate::sm::State::state_name) for a state
+/// enum by naming its variants, e.g.
+/// `sm_enum_state_name!(self, MyStates { Created, Funded, Refunded })`.
+#[macro_export]
+macro_rules! sm_enum_state_name {
+    ($state:expr_2021, $enum:ident { $($variant:ident),* $(,)? }) => {
+        match $state {
+            $($enum::$variant { .. } => concat!(stringify!($enum), "::", stringify!($variant)),)*
+        }
+    };
+}
@@ -15,8 +15,10 @@
 use fedimint_core::util::backoff_util::custom_backoff;
 use fedimint_core::util::{FmtCompactAnyhow as _, SafeUrl};
 use fedimint_core::{NumPeersExt as _, PeerId, impl_db_lookup, impl_db_record};
+use fedimint_eventlog::{Event, EventKind, EventPersistence};
 use fedimint_logging::LOG_CLIENT;
 use futures::stream::{FuturesUnordered, StreamExt as _};
+use serde::{Deserialize, Serialize};
 use tracing::debug;
 
 use crate::Client;
@@ -40,6 +42,21 @@
     query_prefix = ApiAnnouncementPrefix
 );
 
+/// The guardian API URLs the client talks to changed, e.g. because a guardian
+/// announced a new URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiEndpointsChanged {
+    pub old: BTreeMap<PeerId, SafeUrl>,
+    pub new: BTreeMap<PeerId, SafeUrl>,
+}
+
+impl Event for ApiEndpointsChanged {
+    const MODULE: Option<fedimint_core::core::ModuleKind> = None;
+
+    const KIND: EventKind = EventKind::from_static("api-endpoints-changed");
+    const PERSISTENCE: EventPersistence = EventPersistence::Persistent;
+}
+
 /// Fetches API URL announcements from guardians, validates them and updates the
 /// DB if any new more upt to date ones are found.
 pub(crate) async fn run_api_announcement_refresh_task(client_inner: Arc<Client>) {
@@ -64,6 +81,8 @@ pub(crate) async fn run_api_announcement_refresh_task(client_inner: Arc<Client>)
             debug!(target: LOG_CLIENT, err = %err.fmt_compact_anyhow(), "Refreshing api announcements failed");
         }
 
+        client_inner.apply_api_urls().await;
+
         let duration = if is_running_in_test_env() {
             Duration::from_secs(1)
         } else {
@@ -74,6 +93,17 @@ pub(crate) async fn run_api_announcement_refresh_task(client_inner: Arc<Client>)
     }
 }
 
+/// Refreshes API announcements every `interval` and re-targets the client's
+/// api at the resulting guardian URLs.
+pub(crate) async fn run_api_url_refresh_task(client_inner: Arc<Client>, interval: Duration) {
+    loop {
+        sleep(interval).await;
+        if let Err(err) = client_inner.refresh_api_urls().await {
+            debug!(target: LOG_CLIENT, err = %err.fmt_compact_anyhow(), "Refreshing api urls failed");
+        }
+    }
+}
+
 pub(crate) async fn store_api_announcements_updates_from_peers(
     db: &Database,
     updates: &[BTreeMap<PeerId, SignedApiAnnouncement>],
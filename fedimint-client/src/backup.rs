@@ -307,12 +307,39 @@ async fn store_last_backup(&self, backup: &ClientBackup) {
         dbtx.commit_tx().await;
     }
 
+    /// Prepare the backup that [`Self::backup_to_federation`] would upload for
+    /// `metadata`, without storing or uploading it, so it can be inspected or
+    /// stored elsewhere
+    pub async fn prepare_backup(&self, metadata: Metadata) -> Result<ClientBackup> {
+        let (backup, _encrypted) = self.build_backup(metadata).await?;
+        Ok(backup)
+    }
+
     /// Prepare an encrypted backup and send it to federation for storing
     #[deprecated(
         note = "Recovery is now efficient enough that backups are no longer necessary. Backups will be removed in v0.13.0 due to backups being inherently complicated and brittle."
     )]
     #[allow(deprecated)]
     pub async fn backup_to_federation(&self, metadata: Metadata) -> Result<()> {
+        let (new_backup, encrypted) = self.build_backup(metadata).await?;
+
+        self.store_last_backup(&new_backup).await;
+
+        self.upload_backup(&encrypted).await?;
+
+        self.log_event(None, EventBackupDone).await;
+
+        Ok(())
+    }
+
+    /// Build a backup including `metadata`, falling back to the last backup for
+    /// modules that fail to produce one, and encrypt it, checking the size
+    /// limit
+    #[allow(deprecated)]
+    async fn build_backup(
+        &self,
+        metadata: Metadata,
+    ) -> Result<(ClientBackup, EncryptedClientBackup)> {
         ensure!(
             !self.has_pending_recoveries(),
             "Cannot backup while there are pending recoveries"
@@ -327,13 +354,7 @@ pub async fn backup_to_federation(&self, metadata: Metadata) -> Result<()> {
 
         self.validate_backup(&encrypted)?;
 
-        self.store_last_backup(&new_backup).await;
-
-        self.upload_backup(&encrypted).await?;
-
-        self.log_event(None, EventBackupDone).await;
-
-        Ok(())
+        Ok((new_backup, encrypted))
     }
 
     /// Validate backup before sending it to federation
@@ -1,8 +1,8 @@
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::fmt::{self, Formatter};
 use std::future::{Future, pending};
 use std::ops::Range;
-use std::pin::Pin;
+use std::pin::{Pin, pin};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -14,8 +14,11 @@
 use fedimint_api_client::api::global_api::with_request_hook::ApiRequestHook;
 use fedimint_api_client::api::{
     ApiVersionSet, DynGlobalApi, FederationApiExt as _, FederationResult, IGlobalFederationApi,
+    PeerUrls,
 };
 use fedimint_bitcoind::DynBitcoindRpc;
+use fedimint_client_module::api_version_discovery::discover_common_api_versions_set;
+use fedimint_client_module::meta::MetaValues;
 use fedimint_client_module::module::recovery::RecoveryProgress;
 use fedimint_client_module::module::{
     ClientContextIface, ClientModule, ClientModuleRegistry, DynClientModule, FinalClientIface,
@@ -79,8 +82,10 @@
 use tokio_stream::wrappers::WatchStream;
 use tracing::{Span, debug, info, warn};
 
-use crate::ClientBuilder;
-use crate::api_announcements::{ApiAnnouncementPrefix, get_api_urls};
+use crate::api_announcements::{
+    ApiAnnouncementPrefix, ApiEndpointsChanged, fetch_api_announcements_from_at_least_num_of_peers,
+    get_api_urls, store_api_announcements_updates_from_peers,
+};
 use crate::backup::Metadata;
 use crate::client::event_log::DefaultApplicationEventLogKey;
 use crate::db::{
@@ -92,16 +97,21 @@
 };
 use crate::meta::MetaService;
 use crate::module_init::{ClientModuleInitRegistry, DynClientModuleInit, IClientModuleInit};
-use crate::oplog::OperationLog;
+use crate::oplog::{OperationFilter, OperationLog, OperationSummary};
 use crate::sm::executor::{
     ActiveModuleOperationStateKeyPrefix, ActiveOperationStateKeyPrefix, Executor,
-    InactiveModuleOperationStateKeyPrefix, InactiveOperationStateKeyPrefix,
+    InactiveModuleOperationStateKeyPrefix, InactiveOperationStateKeyPrefix, StateTransitionHook,
+};
+use crate::{
+    ClientBuilder, ModuleCompat, ModuleCompatReport, ModuleCompatStatus, RootSecretDeriver,
 };
 
 pub(crate) mod builder;
 pub(crate) mod event_log;
 pub(crate) mod global_ctx;
 pub(crate) mod handle;
+#[cfg(test)]
+mod tests;
 
 /// List of core api versions supported by the implementation.
 /// Notably `major` version is the one being supported, and corresponding
@@ -145,6 +155,10 @@ pub struct Client {
     module_inits: ClientModuleInitRegistry,
     executor: Executor,
     pub(crate) api: DynGlobalApi,
+    /// URLs [`Self::api`] uses to reach the guardians, updatable in place
+    peer_urls: PeerUrls,
+    /// API versions negotiated with the federation when the client was built
+    common_api_versions: ApiVersionSet,
     root_secret: DerivableSecret,
     operation_log: OperationLog,
     secp_ctx: Secp256k1<secp256k1::All>,
@@ -160,6 +174,11 @@ pub struct Client {
     /// Updates about client recovery progress
     client_recovery_progress_receiver:
         watch::Receiver<BTreeMap<ModuleInstanceId, RecoveryProgress>>,
+    /// Recovery progress of each module when the client was started, used to
+    /// estimate the remaining recovery time
+    client_recovery_started: BTreeMap<ModuleInstanceId, (RecoveryProgress, SystemTime)>,
+    client_recovery_result_receiver:
+        watch::Receiver<BTreeMap<ModuleInstanceId, ModuleRecoveryResult>>,
 
     /// Internal client sender to wake up log ordering task every time a
     /// (unuordered) log event is added.
@@ -167,9 +186,25 @@ pub struct Client {
     /// Receiver for events fired every time (ordered) log event is added.
     log_event_added_rx: watch::Receiver<()>,
     log_event_added_transient_tx: broadcast::Sender<EventLogEntry>,
+    /// Operations cancelled via [`Client::cancel_operation`] since the client
+    /// was started
+    cancelled_operations: watch::Sender<BTreeSet<OperationId>>,
     request_hook: ApiRequestHook,
+    /// Set via [`ClientBuilder::with_state_transition_hook`]
+    state_transition_hook: Option<StateTransitionHook>,
+    /// Set via [`ClientBuilder::with_root_secret_deriver`]
+    root_secret_deriver: Option<Arc<dyn RootSecretDeriver>>,
     iroh_enable_dht: bool,
     iroh_enable_next: bool,
+    api_url_refresh_interval: Option<Duration>,
+    /// Api versions set via [`ClientBuilder::with_pinned_api_versions`]
+    pinned_api_versions: Option<ApiVersionSet>,
+    /// Set via [`ClientBuilder::with_event_log_retention`]
+    event_log_retention: Duration,
+    /// Set via [`ClientBuilder::with_db_integrity_check`]
+    db_integrity_check: bool,
+    /// Set via [`ClientBuilder::open_watch_only`]
+    watch_only: bool,
     /// User-provided Bitcoin RPC client for modules to use
     ///
     /// Stored here for potential future access; currently passed to modules
@@ -210,6 +245,25 @@ pub struct GetBalanceChangesRequest {
     unit: AmountUnit,
 }
 
+/// Terminal status of a module recovery, see [`Client::recovery_results`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModuleRecoveryResult {
+    Success,
+    /// The recovery failed with the given error and will not complete
+    Failure(String),
+}
+
+/// An active state machine, see [`Client::active_states_summary`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActiveStateSummary {
+    pub operation_id: OperationId,
+    pub module_instance_id: ModuleInstanceId,
+    /// See [`fedimint_client_module::sm::State::state_name`]
+    pub state_name: String,
+    /// Time since the state was entered
+    pub age: Duration,
+}
+
 impl Client {
     /// Initialize a client builder that can be configured to create a new
     /// client.
@@ -502,6 +556,15 @@ pub async fn core_api_version(&self) -> ApiVersion {
             .unwrap_or(ApiVersion { major: 0, minor: 0 })
     }
 
+    /// Returns the common API version set negotiated with the federation
+    ///
+    /// This is the set the client was built with: the core API version and the
+    /// API version of every module that was initialized. Modules that failed
+    /// negotiation are absent from [`ApiVersionSet::modules`].
+    pub fn common_api_versions(&self) -> &ApiVersionSet {
+        &self.common_api_versions
+    }
+
     /// Returns the chain ID (bitcoin block hash at height 1) from the
     /// federation
     ///
@@ -548,6 +611,12 @@ fn try_get_module(
         Some(self.modules.get(instance)?.as_ref())
     }
 
+    /// Returns `true` if the client was opened with
+    /// [`ClientBuilder::open_watch_only`] and refuses to submit transactions
+    pub fn is_watch_only(&self) -> bool {
+        self.watch_only
+    }
+
     pub fn has_module(&self, instance: ModuleInstanceId) -> bool {
         self.modules.get(instance).is_some()
     }
@@ -628,6 +697,25 @@ pub async fn get_active_operations(&self) -> HashSet<OperationId> {
         active_operations
     }
 
+    /// Lists up to `limit` operations matching `filter`, newest first
+    ///
+    /// Operations with active state machines are listed as
+    /// [`OperationStatus::Active`](crate::oplog::OperationStatus::Active).
+    /// Pass the returned page token to continue listing after the last
+    /// returned operation, `None` is returned once no further operations match.
+    /// See [`OperationLog::paginate_operations_rev_filtered`].
+    pub async fn list_operations(
+        &self,
+        filter: &OperationFilter,
+        limit: usize,
+        page: Option<ChronologicalOperationLogKey>,
+    ) -> (Vec<OperationSummary>, Option<ChronologicalOperationLogKey>) {
+        let active_operations = self.get_active_operations().await;
+        self.operation_log
+            .paginate_operations_rev_filtered(filter, &active_operations, limit, page)
+            .await
+    }
+
     pub fn operation_log(&self) -> &OperationLog {
         &self.operation_log
     }
@@ -637,6 +725,22 @@ pub fn meta_service(&self) -> &Arc<MetaService> {
         &self.meta_service
     }
 
+    /// Returns a stream that yields all meta fields every time the meta source
+    /// produces an update, starting with the cached ones if there are any
+    ///
+    /// See [`MetaService::subscribe_to_values`].
+    pub fn subscribe_meta(&self) -> BoxStream<'static, MetaValues> {
+        let meta_service = self.meta_service.clone();
+        let db = self.db.clone();
+
+        Box::pin(async_stream::stream! {
+            let mut values = pin!(meta_service.subscribe_to_values(&db));
+            while let Some(values) = values.next().await {
+                yield values;
+            }
+        })
+    }
+
     /// Get the meta manager to read meta fields.
     pub async fn get_meta_expiration_timestamp(&self) -> Option<SystemTime> {
         let meta_service = self.meta_service();
@@ -827,6 +931,10 @@ async fn finalize_and_submit_transaction_inner(
         operation_id: OperationId,
         tx_builder: TransactionBuilder,
     ) -> anyhow::Result<OutPointRange> {
+        if self.watch_only {
+            bail!("Client was opened in watch-only mode and can not submit transactions");
+        }
+
         let (transaction, mut states, change_range) = self
             .finalize_transaction(&mut dbtx.to_ref_nc(), operation_id, tx_builder)
             .await?;
@@ -1051,6 +1159,35 @@ pub async fn get_balance_for_unit(&self, unit: AmountUnit) -> anyhow::Result<Amo
             .await)
     }
 
+    /// Returns all non-zero balances held by primary modules, by module and
+    /// unit
+    pub async fn get_outstanding_balances(
+        &self,
+    ) -> BTreeMap<ModuleInstanceId, BTreeMap<AmountUnit, Amount>> {
+        let units = std::iter::once(AmountUnit::BITCOIN)
+            .chain(
+                self.primary_modules
+                    .values()
+                    .flat_map(|candidates| candidates.specific.keys().copied()),
+            )
+            .collect::<BTreeSet<_>>();
+
+        let mut dbtx = self.db().begin_transaction_nc().await;
+        let mut balances = BTreeMap::<ModuleInstanceId, BTreeMap<AmountUnit, Amount>>::new();
+
+        for unit in units {
+            for (id, module) in self.primary_modules_for_unit(unit) {
+                let balance = module.get_balance(id, &mut dbtx, unit).await;
+
+                if balance != Amount::ZERO {
+                    balances.entry(id).or_default().insert(unit, balance);
+                }
+            }
+        }
+
+        balances
+    }
+
     /// Returns a stream that yields the current client balance every time it
     /// changes.
     pub async fn subscribe_balance_changes(&self, unit: AmountUnit) -> BoxStream<'static, Amount> {
@@ -1371,7 +1508,84 @@ pub fn supported_api_versions_summary_static(
         }
     }
 
+    /// Checks which modules of the federation described by `config` the
+    /// client would be able to use, given the API versions the peers support
+    /// (see [`Self::fetch_common_api_versions`]).
+    ///
+    /// Nothing is built or written, so this can be used to check support of a
+    /// federation before a client secret exists.
+    pub fn check_module_compatibility(
+        config: &ClientConfig,
+        module_inits: &ClientModuleInitRegistry,
+        peer_api_versions: &BTreeMap<PeerId, SupportedApiVersionsSummary>,
+    ) -> ModuleCompatReport {
+        Self::module_compat_report(
+            config,
+            &Self::supported_api_versions_summary_static(config, module_inits),
+            peer_api_versions,
+        )
+    }
+
+    /// Maps the API versions discovered like when building the client to the
+    /// compatibility of each module of the federation
+    ///
+    /// Without a common core API version the client can't use the federation
+    /// at all, so all registered modules are reported as incompatible.
+    fn module_compat_report(
+        config: &ClientConfig,
+        client_versions: &SupportedApiVersionsSummary,
+        peer_api_versions: &BTreeMap<PeerId, SupportedApiVersionsSummary>,
+    ) -> ModuleCompatReport {
+        let common_api_versions =
+            discover_common_api_versions_set(client_versions, peer_api_versions).ok();
+
+        let modules = config
+            .modules
+            .iter()
+            .map(|(module_instance_id, module_config)| {
+                let common_api_version = common_api_versions
+                    .as_ref()
+                    .and_then(|versions| versions.modules.get(module_instance_id));
+
+                let status = match (
+                    client_versions.modules.get(module_instance_id),
+                    common_api_version,
+                ) {
+                    (None, _) => ModuleCompatStatus::Unregistered,
+                    (Some(_), Some(api_version)) => ModuleCompatStatus::Compatible {
+                        api_version: *api_version,
+                    },
+                    (Some(client_module_versions), None) => ModuleCompatStatus::Incompatible {
+                        client_api_versions: client_module_versions.api.into_iter().collect(),
+                        federation_api_versions: peer_api_versions
+                            .values()
+                            .filter_map(|versions| versions.modules.get(module_instance_id))
+                            .flat_map(|versions| &versions.api)
+                            .collect(),
+                    },
+                };
+
+                (
+                    *module_instance_id,
+                    ModuleCompat {
+                        kind: module_config.kind().clone(),
+                        status,
+                    },
+                )
+            })
+            .collect();
+
+        ModuleCompatReport {
+            core_api_version: common_api_versions.map(|versions| versions.core),
+            modules,
+        }
+    }
+
     pub async fn load_and_refresh_common_api_version(&self) -> anyhow::Result<ApiVersionSet> {
+        if let Some(pinned_api_versions) = &self.pinned_api_versions {
+            return Ok(pinned_api_versions.clone());
+        }
+
         Self::load_and_refresh_common_api_version_static(
             &self.config().await,
             &self.module_inits,
@@ -1613,6 +1827,39 @@ pub fn subscribe_to_recovery_progress(
             .flat_map(futures::stream::iter)
     }
 
+    /// Estimated time remaining until the recovery of `module_instance_id` is
+    /// done
+    ///
+    /// Based on the throughput of the recovery since the client was started.
+    /// Returns `None` if the module is not recovering or no progress was made
+    /// yet to base an estimate on.
+    pub fn recovery_eta(&self, module_instance_id: ModuleInstanceId) -> Option<Duration> {
+        let (started, started_at) = self.client_recovery_started.get(&module_instance_id)?;
+        let progress = *self
+            .client_recovery_progress_receiver
+            .borrow()
+            .get(&module_instance_id)?;
+
+        progress.eta(
+            *started,
+            fedimint_core::time::now()
+                .duration_since(*started_at)
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Subscribe to the terminal status of module recoveries
+    ///
+    /// A module is only present once its recovery finished, either
+    /// successfully or with an error. Failed recoveries never show up as done
+    /// in [`Self::subscribe_to_recovery_progress`], so this is the way to
+    /// learn about them.
+    pub fn recovery_results(
+        &self,
+    ) -> watch::Receiver<BTreeMap<ModuleInstanceId, ModuleRecoveryResult>> {
+        self.client_recovery_result_receiver.clone()
+    }
+
     pub async fn wait_for_module_kind_recovery(
         &self,
         module_kind: ModuleKind,
@@ -1634,6 +1881,127 @@ pub async fn wait_for_module_kind_recovery(
         Ok(())
     }
 
+    /// Returns `true` if the client's state machines are caught up
+    ///
+    /// That is no state machine is waiting to be processed or in the middle of
+    /// a transition. State machines waiting on external events (e.g. an
+    /// incoming payment) don't count as pending work.
+    pub fn is_idle(&self) -> bool {
+        self.executor.is_idle()
+    }
+
+    /// Wait until the client is idle, see [`Self::is_idle`]
+    pub async fn wait_idle(&self) {
+        self.executor.wait_idle().await;
+    }
+
+    /// Asks the state machines of an operation to cancel it
+    ///
+    /// Only has an effect on modules whose triggers select on
+    /// [`fedimint_client_module::IGlobalClientContext::await_operation_cancelled`].
+    /// The cancellation is not persisted, so it has to be repeated after a
+    /// restart if the operation is still active.
+    pub fn cancel_operation(&self, operation_id: OperationId) {
+        self.cancelled_operations.send_modify(|operations| {
+            operations.insert(operation_id);
+        });
+    }
+
+    /// Resolves once [`Self::cancel_operation`] was called for the operation
+    pub async fn await_operation_cancelled(&self, operation_id: OperationId) {
+        self.cancelled_operations
+            .subscribe()
+            .wait_for(|operations| operations.contains(&operation_id))
+            .await
+            .expect("Sender is owned by the client");
+    }
+
+    /// Lists all active state machines with how long they have been in their
+    /// current state, oldest first, to help diagnose stuck operations
+    pub async fn active_states_summary(&self) -> Vec<ActiveStateSummary> {
+        let now = fedimint_core::time::now();
+        let mut summary = self
+            .executor
+            .get_active_states()
+            .await
+            .into_iter()
+            .map(|(state, meta)| ActiveStateSummary {
+                operation_id: state.operation_id(),
+                module_instance_id: state.module_instance_id(),
+                state_name: state.state_name().to_string(),
+                age: now.duration_since(meta.created_at).unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        summary.sort_by(|a, b| b.age.cmp(&a.age));
+        summary
+    }
+
+    /// Consensus-encodes the active state machines of an operation to hex, so
+    /// they can be attached to bug reports and reproduced with
+    /// [`Self::load_state_hex`]
+    ///
+    /// **Attention**: the dump is not redacted. State machines can hold secret
+    /// material, e.g. the spend keys of ecash notes, payment preimages or
+    /// refund keys, so anyone with the dump may be able to steal the funds of
+    /// the operation. Only share it with people you would trust with them.
+    pub async fn dump_states_hex(&self, operation_id: OperationId) -> Vec<String> {
+        self.executor
+            .get_operation_states(operation_id)
+            .await
+            .0
+            .into_iter()
+            .map(|(state, _)| state.consensus_encode_to_hex())
+            .collect()
+    }
+
+    /// Decodes a state machine dumped by [`Self::dump_states_hex`] using the
+    /// decoders of this client's modules
+    ///
+    /// Only meant for debugging, the state is not added to the executor.
+    #[doc(hidden)]
+    pub fn load_state_hex(&self, hex: &str) -> anyhow::Result<DynState> {
+        Ok(DynState::consensus_decode_hex(hex, self.decoders())?)
+    }
+
+    /// Removes data a long-running client doesn't need anymore to limit the
+    /// growth of its database
+    ///
+    /// Trims event log entries older than the retention configured with
+    /// [`ClientBuilder::with_event_log_retention`] from the trimable event log
+    /// and removes the state machines of operations that finished before it,
+    /// as long as their outcome is cached in the operation log. The persisted
+    /// (untrimable) event log is kept.
+    pub async fn compact_db(&self) {
+        let trimmed_events =
+            fedimint_eventlog::trim_trimable_log_older_than(&self.db, self.event_log_retention)
+                .await;
+
+        let exited_before = fedimint_core::time::now()
+            .checked_sub(self.event_log_retention)
+            .unwrap_or(UNIX_EPOCH);
+        let mut pruned_states = 0;
+        for operation_id in self.executor.operations_inactive_since(exited_before).await {
+            let outcome_cached = self
+                .operation_log
+                .get_operation(operation_id)
+                .await
+                .is_some_and(|operation| operation.outcome_time().is_some());
+            if outcome_cached {
+                pruned_states += self
+                    .executor
+                    .remove_inactive_operation_states(operation_id)
+                    .await;
+            }
+        }
+
+        debug!(
+            target: LOG_CLIENT,
+            trimmed_events,
+            pruned_states,
+            "Compacted client database"
+        );
+    }
+
     pub async fn wait_for_all_active_state_machines(&self) -> anyhow::Result<()> {
         loop {
             if self.executor.get_active_states().await.is_empty() {
@@ -1652,6 +2020,7 @@ pub async fn set_metadata_dbtx(dbtx: &mut DatabaseTransaction<'_>, metadata: &Me
     fn spawn_module_recoveries_task(
         &self,
         recovery_sender: watch::Sender<BTreeMap<ModuleInstanceId, RecoveryProgress>>,
+        recovery_result_sender: watch::Sender<BTreeMap<ModuleInstanceId, ModuleRecoveryResult>>,
         module_recoveries: BTreeMap<
             ModuleInstanceId,
             Pin<Box<maybe_add_send!(dyn Future<Output = anyhow::Result<()>>)>>,
@@ -1673,6 +2042,7 @@ fn spawn_module_recoveries_task(
                 db,
                 log_ordering_wakeup_tx,
                 recovery_sender,
+                recovery_result_sender,
                 module_recoveries,
                 module_recovery_progress_receivers,
                 module_kinds,
@@ -1685,6 +2055,7 @@ async fn run_module_recoveries_task(
         db: Database,
         log_ordering_wakeup_tx: watch::Sender<()>,
         recovery_sender: watch::Sender<BTreeMap<ModuleInstanceId, RecoveryProgress>>,
+        recovery_result_sender: watch::Sender<BTreeMap<ModuleInstanceId, ModuleRecoveryResult>>,
         module_recoveries: BTreeMap<
             ModuleInstanceId,
             Pin<Box<maybe_add_send!(dyn Future<Output = anyhow::Result<()>>)>>,
@@ -1700,14 +2071,26 @@ async fn run_module_recoveries_task(
         let progress_stream = futures::stream::FuturesUnordered::new();
 
         for (module_instance_id, f) in module_recoveries {
+            let recovery_result_sender = recovery_result_sender.clone();
             completed_stream.push(futures::stream::once(Box::pin(async move {
                 match f.await {
-                    Ok(()) => (module_instance_id, None),
+                    Ok(()) => {
+                        recovery_result_sender.send_modify(|v| {
+                            v.insert(module_instance_id, ModuleRecoveryResult::Success);
+                        });
+                        (module_instance_id, None)
+                    }
                     Err(err) => {
                         warn!(
                             target: LOG_CLIENT,
                             err = %err.fmt_compact_anyhow(), module_instance_id, "Module recovery failed"
                         );
+                        recovery_result_sender.send_modify(|v| {
+                            v.insert(
+                                module_instance_id,
+                                ModuleRecoveryResult::Failure(err.fmt_compact_anyhow().to_string()),
+                            );
+                        });
                         // a module recovery that failed reports and error and
                         // just never finishes, so we don't need a separate state
                         // for it
@@ -1838,6 +2221,44 @@ pub async fn get_peer_urls(&self) -> BTreeMap<PeerId, SafeUrl> {
         get_api_urls(&self.db, &self.config().await).await
     }
 
+    /// Fetch the latest API announcements from the guardians and re-target the
+    /// federation api at the resulting URLs
+    ///
+    /// Established connections are kept, so requests already in flight are not
+    /// affected; only new requests go to the updated URLs.
+    pub async fn refresh_api_urls(&self) -> anyhow::Result<()> {
+        let guardian_pub_keys = self.get_guardian_public_keys_blocking().await;
+        let announcements = fetch_api_announcements_from_at_least_num_of_peers(
+            1,
+            &self.api,
+            &guardian_pub_keys,
+            Duration::from_secs(1),
+        )
+        .await;
+        store_api_announcements_updates_from_peers(&self.db, &announcements).await?;
+
+        self.apply_api_urls().await;
+
+        Ok(())
+    }
+
+    /// Point the federation api at the URLs currently stored in the database
+    ///
+    /// Logs an [`ApiEndpointsChanged`] event if any URL changed.
+    pub(crate) async fn apply_api_urls(&self) {
+        let peer_urls = get_api_urls(&self.db, &self.config().await).await;
+        let old = self.peer_urls.to_map();
+        if self.peer_urls.update(peer_urls) {
+            let new = self.peer_urls.to_map();
+            debug!(
+                target: LOG_CLIENT_NET_API,
+                peer_urls = ?new,
+                "Updated guardian API URLs"
+            );
+            self.log_event(None, ApiEndpointsChanged { old, new }).await;
+        }
+    }
+
     /// Create an invite code with the api endpoint of the given peer which can
     /// be used to download this client config
     pub async fn invite_code(&self, peer: PeerId) -> Option<InviteCode> {
@@ -1855,6 +2276,33 @@ pub async fn invite_code(&self, peer: PeerId) -> Option<InviteCode> {
             })
     }
 
+    /// Create an invite code for sharing the federation, listing as many
+    /// guardians as needed to reach a working federation
+    ///
+    /// If `peers` is given, only those guardians are considered. Returns `None`
+    /// if none of them has a known API endpoint.
+    pub async fn federation_invite_code(
+        &self,
+        peers: Option<&BTreeSet<PeerId>>,
+    ) -> Option<InviteCode> {
+        let peer_urls = self
+            .get_peer_urls()
+            .await
+            .into_iter()
+            .filter(|(peer, _)| peers.is_none_or(|peers| peers.contains(peer)))
+            .collect::<BTreeMap<_, _>>();
+
+        if peer_urls.is_empty() {
+            return None;
+        }
+
+        Some(InviteCode::from_map(
+            &peer_urls,
+            self.federation_id(),
+            self.api_secret.clone(),
+        ))
+    }
+
     /// Blocks till the client has synced the guardian public key set
     /// (introduced in version 0.4) and returns it. Once it has been fetched
     /// once this function is guaranteed to return immediately.
@@ -2189,6 +2637,46 @@ pub async fn get_event_log(
             .await
     }
 
+    /// Query the persisted event log
+    ///
+    /// Returns up to `limit` entries starting at `start` (inclusive, or the
+    /// beginning of the log if `None`). If `kinds` is non-empty, only events of
+    /// these kinds are returned, so ids of the returned entries might not be
+    /// contiguous. To get the next page, pass the id following the last
+    /// returned entry as `start`.
+    pub async fn query_event_log(
+        &self,
+        start: Option<EventLogId>,
+        limit: usize,
+        kinds: &[EventKind],
+    ) -> Vec<PersistedLogEntry> {
+        const BATCH_SIZE: u64 = 1_000;
+
+        let mut dbtx = self.db.begin_transaction_nc().await;
+        let end = dbtx.get_next_event_log_id().await;
+
+        let mut position = start.unwrap_or_default();
+        let mut entries = Vec::new();
+
+        while entries.len() < limit && position < end {
+            let batch = self
+                .get_event_log_dbtx(&mut dbtx, Some(position), BATCH_SIZE)
+                .await;
+
+            position = position.saturating_add(BATCH_SIZE);
+
+            entries.extend(
+                batch
+                    .into_iter()
+                    .filter(|entry| kinds.is_empty() || kinds.contains(&entry.kind)),
+            );
+        }
+
+        entries.truncate(limit);
+
+        entries
+    }
+
     pub async fn get_event_log_trimable(
         &self,
         pos: Option<EventLogTrimableId>,
@@ -2227,6 +2715,68 @@ pub fn get_event_log_transient_receiver(&self) -> broadcast::Receiver<EventLogEn
         self.log_event_added_transient_tx.subscribe()
     }
 
+    /// Subscribe to all events belonging to `operation_id`
+    ///
+    /// First replays the persisted (trimable) event log entries of the
+    /// operation, then follows newly persisted as well as transient events.
+    /// Events are matched by the `operation_id` field of their payload, see
+    /// [`EventLogEntry::operation_id`].
+    pub fn subscribe_events_for_operation(
+        &self,
+        operation_id: OperationId,
+    ) -> BoxStream<'static, EventLogEntry> {
+        let db = self.db.clone();
+        let mut log_event_added_rx = self.log_event_added_rx();
+        let mut transient_rx = self.get_event_log_transient_receiver();
+
+        Box::pin(async_stream::stream! {
+            let mut next_pos = EventLogTrimableId::default();
+
+            loop {
+                log_event_added_rx.borrow_and_update();
+
+                let entries = db
+                    .begin_transaction_nc()
+                    .await
+                    .find_by_range(next_pos..EventLogTrimableId::from(u64::MAX))
+                    .await
+                    .collect::<Vec<_>>()
+                    .await;
+
+                for (pos, entry) in entries {
+                    next_pos = pos.saturating_add(1);
+
+                    if entry.operation_id() == Some(operation_id) {
+                        yield entry;
+                    }
+                }
+
+                let transient = tokio::select! {
+                    res = log_event_added_rx.changed() => {
+                        if res.is_err() {
+                            break;
+                        }
+                        None
+                    }
+                    res = transient_rx.recv() => match res {
+                        Ok(entry) => Some(entry),
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            warn!(target: LOG_CLIENT, skipped, "Missed transient events of operation");
+                            None
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                };
+
+                if let Some(entry) = transient
+                    && entry.operation_id() == Some(operation_id)
+                {
+                    yield entry;
+                }
+            }
+        })
+    }
+
     /// Get a receiver that signals when new events are added to the event log
     pub fn log_event_added_rx(&self) -> watch::Receiver<()> {
         self.log_event_added_rx.clone()
@@ -2236,6 +2786,10 @@ pub fn iroh_enable_dht(&self) -> bool {
         self.iroh_enable_dht
     }
 
+    pub fn api_url_refresh_interval(&self) -> Option<Duration> {
+        self.api_url_refresh_interval
+    }
+
     pub(crate) async fn run_core_migrations(
         db_no_decoders: &Database,
     ) -> Result<(), anyhow::Error> {
@@ -2390,6 +2944,10 @@ fn get_internal_payment_markers(&self) -> anyhow::Result<(PublicKey, u64)> {
         Client::get_internal_payment_markers(self)
     }
 
+    fn is_watch_only(&self) -> bool {
+        Client::is_watch_only(self)
+    }
+
     async fn log_event_json(
         &self,
         dbtx: &mut DatabaseTransaction<'_, NonCommittable>,
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
@@ -10,8 +10,11 @@
 use fedimint_api_client::api::global_api::with_request_hook::{
     ApiRequestHook, RawFederationApiWithRequestHookExt as _,
 };
+use fedimint_api_client::api::global_api::with_response_cache::{
+    DynApiResponseCache, RawFederationApiWithResponseCache,
+};
 use fedimint_api_client::api::{ApiVersionSet, DynGlobalApi, FederationApi, FederationApiExt as _};
-use fedimint_api_client::download_from_invite_code;
+use fedimint_api_client::download_from_invite_code_with_quorum;
 use fedimint_bitcoind::DynBitcoindRpc;
 use fedimint_client_module::api::ClientRawFederationApiExt as _;
 use fedimint_client_module::meta::LegacyMetaSource;
@@ -22,7 +25,9 @@
 use fedimint_client_module::module::{
     ClientModuleRegistry, FinalClientIface, PrimaryModulePriority, PrimaryModuleSupport,
 };
-use fedimint_client_module::secret::{DeriveableSecretClientExt as _, get_default_client_secret};
+use fedimint_client_module::secret::{
+    DeriveableSecretClientExt as _, FEDIMINT_CLIENT_NONCE, get_default_client_secret,
+};
 use fedimint_client_module::transaction::{
     TRANSACTION_SUBMISSION_MODULE_INSTANCE, TxSubmissionContext, tx_submission_sm_decoder,
 };
@@ -31,30 +36,39 @@
 use fedimint_core::config::{ClientConfig, FederationId, ModuleInitRegistry};
 use fedimint_core::core::{ModuleInstanceId, ModuleKind};
 use fedimint_core::db::{
-    Database, IDatabaseTransactionOpsCoreTyped as _, verify_module_db_integrity_dbtx,
+    Database, IDatabaseTransactionOpsCoreTyped as _, try_verify_module_db_integrity_dbtx,
+    verify_module_db_integrity_dbtx,
 };
 use fedimint_core::endpoint_constants::CLIENT_CONFIG_ENDPOINT;
 use fedimint_core::envs::is_running_in_test_env;
 use fedimint_core::invite_code::InviteCode;
 use fedimint_core::module::registry::ModuleDecoderRegistry;
-use fedimint_core::module::{ApiRequestErased, ApiVersion, SupportedApiVersionsSummary};
-use fedimint_core::task::TaskGroup;
+use fedimint_core::module::{
+    AmountUnit, ApiRequestErased, ApiVersion, CORE_CONSENSUS_VERSION, CoreConsensusVersion,
+    SupportedApiVersionsSummary,
+};
 use fedimint_core::task::jit::{Jit, JitTry, JitTryAnyhow};
+use fedimint_core::task::{MaybeSend, MaybeSync, TaskGroup};
 use fedimint_core::util::{FmtCompact as _, FmtCompactAnyhow as _, SafeUrl};
-use fedimint_core::{ChainId, NumPeers, PeerId, fedimint_build_code_version_env, maybe_add_send};
+use fedimint_core::{
+    ChainId, NumPeers, PeerId, fedimint_build_code_version_env, maybe_add_send, runtime,
+};
 use fedimint_derive_secret::DerivableSecret;
 use fedimint_eventlog::{
     DBTransactionEventLogExt as _, EventLogEntry, run_event_log_ordering_task,
 };
 use fedimint_logging::LOG_CLIENT;
+use serde::Serialize;
 use tokio::sync::{broadcast, watch};
 use tracing::{Span, debug, trace, warn};
+use zeroize::ZeroizeOnDrop;
 
 use super::handle::ClientHandle;
 use super::{Client, client_decoders};
 use crate::api_announcements::{
     PeersSignedApiAnnouncements, fetch_api_announcements_from_at_least_num_of_peers, get_api_urls,
-    run_api_announcement_refresh_task, store_api_announcements_updates_from_peers,
+    run_api_announcement_refresh_task, run_api_url_refresh_task,
+    store_api_announcements_updates_from_peers,
 };
 use crate::backup::{ClientBackup, Metadata};
 use crate::client::PrimaryModuleCandidates;
@@ -65,11 +79,45 @@
 };
 use crate::guardian_metadata::run_guardian_metadata_refresh_task;
 use crate::meta::MetaService;
-use crate::module_init::ClientModuleInitRegistry;
-use crate::oplog::OperationLog;
-use crate::sm::executor::Executor;
+use crate::module_init::{ClientModuleInitFactory, ClientModuleInitRegistry, IClientModuleInit};
+use crate::oplog::{OperationLog, OperationObserver};
+use crate::sm::executor::{Executor, StateTransitionHook};
 use crate::sm::notifier::Notifier;
 
+/// How long joining waits for the api announcements prefetched during the
+/// preview unless [`ClientBuilder::with_strict_announcements`] is set
+const PREFETCH_API_ANNOUNCEMENTS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default for [`ClientBuilder::with_existing_state_check_timeout`]
+const DEFAULT_EXISTING_STATE_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Default for [`ClientBuilder::with_event_log_retention`]
+const DEFAULT_EVENT_LOG_RETENTION: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// Errors building a [`Client`] that applications may want to handle
+/// specifically
+///
+/// Returned wrapped in an [`anyhow::Error`], use
+/// [`anyhow::Error::downcast_ref`] to detect them.
+#[derive(Debug, thiserror::Error)]
+pub enum BuildError {
+    #[error(
+        "Federation uses core consensus version {federation_version}, but this client only supports {client_supports}. Please update the application."
+    )]
+    ConsensusVersionUnsupported {
+        federation_version: CoreConsensusVersion,
+        client_supports: CoreConsensusVersion,
+    },
+    #[error(
+        "The federation has a backup created with this secret, joining would lose its funds. Use recover instead."
+    )]
+    ExistingStateDetected,
+    #[error(
+        "Could not check whether the federation has a backup created with this secret. Retry joining, or use recover if the secret was used before."
+    )]
+    ExistingStateUnknown,
+}
+
 /// The type of root secret hashing
 ///
 /// *Please read this documentation carefully if, especially if you're upgrading
@@ -106,6 +154,20 @@ pub enum RootSecret {
 }
 
 impl RootSecret {
+    /// Derives the standard root secret from a BIP39 `mnemonic` and
+    /// `passphrase`
+    ///
+    /// With an empty `passphrase` this is the same secret
+    /// `fedimint_bip39::Bip39RootSecretStrategy` derives, so seed phrases stay
+    /// interoperable between applications.
+    #[cfg(feature = "bip39")]
+    pub fn from_bip39(mnemonic: &bip39::Mnemonic, passphrase: &str) -> Self {
+        RootSecret::StandardDoubleDerive(DerivableSecret::new_root(
+            mnemonic.to_seed_normalized(passphrase).as_ref(),
+            FEDIMINT_CLIENT_NONCE,
+        ))
+    }
+
     fn to_inner(&self, federation_id: FederationId) -> DerivableSecret {
         match self {
             RootSecret::StandardDoubleDerive(derivable_secret) => {
@@ -116,16 +178,47 @@ fn to_inner(&self, federation_id: FederationId) -> DerivableSecret {
     }
 }
 
+/// The wrapped [`DerivableSecret`] (like all copies of it) is wiped from
+/// memory when dropped
+impl ZeroizeOnDrop for RootSecret {}
+
+/// Derives the root secret a client uses for a single federation
+///
+/// By default the client derives it from the pre-root secret it was opened
+/// with. Hardware wallets that can't expose the raw secret can instead do the
+/// derivation on-device, see [`ClientBuilder::with_root_secret_deriver`].
+pub trait RootSecretDeriver: MaybeSend + MaybeSync + 'static {
+    fn federation_key(&self, federation_id: &FederationId) -> DerivableSecret;
+}
+
+impl RootSecretDeriver for DerivableSecret {
+    fn federation_key(&self, federation_id: &FederationId) -> DerivableSecret {
+        DerivableSecret::federation_key(self, federation_id)
+    }
+}
+
 /// Used to configure, assemble and build [`Client`]
 pub struct ClientBuilder {
     module_inits: ClientModuleInitRegistry,
+    module_init_factories: BTreeMap<ModuleKind, ClientModuleInitFactory>,
     admin_creds: Option<AdminCreds>,
     meta_service: Arc<crate::meta::MetaService>,
     stopped: bool,
     log_event_added_transient_tx: broadcast::Sender<EventLogEntry>,
     request_hook: ApiRequestHook,
+    state_transition_hook: Option<StateTransitionHook>,
+    operation_observer: Option<Arc<dyn OperationObserver>>,
+    root_secret_deriver: Option<Arc<dyn RootSecretDeriver>>,
     iroh_enable_dht: bool,
     iroh_enable_next: bool,
+    api_url_refresh_interval: Option<Duration>,
+    pinned_api_versions: Option<ApiVersionSet>,
+    strict_announcements: bool,
+    preview_quorum: Option<usize>,
+    existing_state_check_timeout: Option<Duration>,
+    event_log_retention: Duration,
+    db_integrity_check: bool,
+    watch_only: bool,
     bitcoind_rpc_factory: Option<BitcoindRpcFactory>,
     bitcoind_rpc_no_chain_id_factory: Option<BitcoindRpcNoChainIdFactory>,
 }
@@ -143,13 +236,25 @@ pub(crate) fn new() -> Self {
 
         ClientBuilder {
             module_inits: ModuleInitRegistry::new(),
+            module_init_factories: BTreeMap::new(),
             admin_creds: None,
             stopped: false,
             meta_service,
             log_event_added_transient_tx,
             request_hook: Arc::new(|api| api),
+            state_transition_hook: None,
+            operation_observer: None,
+            root_secret_deriver: None,
             iroh_enable_dht: true,
             iroh_enable_next: true,
+            api_url_refresh_interval: None,
+            pinned_api_versions: None,
+            strict_announcements: false,
+            preview_quorum: None,
+            existing_state_check_timeout: Some(DEFAULT_EXISTING_STATE_CHECK_TIMEOUT),
+            event_log_retention: DEFAULT_EVENT_LOG_RETENTION,
+            db_integrity_check: false,
+            watch_only: false,
             bitcoind_rpc_factory: None,
             bitcoind_rpc_no_chain_id_factory: None,
         }
@@ -158,14 +263,28 @@ pub(crate) fn new() -> Self {
     pub(crate) fn from_existing(client: &Client) -> Self {
         ClientBuilder {
             module_inits: client.module_inits.clone(),
+            // factories of kinds used by the federation were already resolved
+            module_init_factories: BTreeMap::new(),
             admin_creds: None,
             stopped: false,
             // non unique
             meta_service: client.meta_service.clone(),
             log_event_added_transient_tx: client.log_event_added_transient_tx.clone(),
             request_hook: client.request_hook.clone(),
+            state_transition_hook: client.state_transition_hook.clone(),
+            operation_observer: client.operation_log.observer(),
+            root_secret_deriver: client.root_secret_deriver.clone(),
             iroh_enable_dht: client.iroh_enable_dht,
             iroh_enable_next: client.iroh_enable_next,
+            api_url_refresh_interval: client.api_url_refresh_interval,
+            pinned_api_versions: client.pinned_api_versions.clone(),
+            // announcements are only prefetched when joining
+            strict_announcements: false,
+            preview_quorum: None,
+            existing_state_check_timeout: Some(DEFAULT_EXISTING_STATE_CHECK_TIMEOUT),
+            event_log_retention: client.event_log_retention,
+            db_integrity_check: client.db_integrity_check,
+            watch_only: client.watch_only,
             // Note: bitcoind_rpc_factory is not cloned from existing client
             // since it's a one-time factory that's consumed during build
             bitcoind_rpc_factory: None,
@@ -184,6 +303,46 @@ pub fn with_module<M: ClientModuleInit>(&mut self, module_init: M) {
         self.module_inits.attach(module_init);
     }
 
+    /// Make module generator of `kind` available when reading the config,
+    /// constructing it only if the federation actually uses `kind`
+    ///
+    /// Ignored if a module generator of the same kind is already registered
+    /// via [`Self::with_module`] or [`Self::with_module_inits`].
+    pub fn with_module_factory(&mut self, kind: ModuleKind, factory: ClientModuleInitFactory) {
+        self.module_init_factories.insert(kind, factory);
+    }
+
+    /// Construct module generators of kinds used in `config` from the
+    /// factories registered via [`Self::with_module_factory`]
+    fn resolve_module_factories(&mut self, config: &ClientConfig) {
+        for module_config in config.modules.values() {
+            let kind = module_config.kind();
+
+            if self.module_inits.get(kind).is_some() {
+                continue;
+            }
+
+            let Some(factory) = self.module_init_factories.remove(kind) else {
+                continue;
+            };
+
+            let module_init = factory();
+            let actual_kind = AsRef::<dyn IClientModuleInit>::as_ref(&module_init).module_kind();
+
+            if actual_kind != *kind {
+                warn!(
+                    target: LOG_CLIENT,
+                    %kind,
+                    %actual_kind,
+                    "Module factory returned module of wrong kind, ignoring"
+                );
+                continue;
+            }
+
+            self.module_inits.attach(module_init);
+        }
+    }
+
     pub fn stopped(&mut self) {
         self.stopped = true;
     }
@@ -200,6 +359,62 @@ pub fn with_api_request_hook(mut self, hook: ApiRequestHook) -> Self {
         self
     }
 
+    /// Call `hook` with the operation id, the old and the new state after
+    /// every state machine transition the executor committed
+    ///
+    /// Meant for building operation timelines and analytics. The hook is called
+    /// from a background task and never holds up the state machines, but
+    /// transitions are dropped while it falls too far behind, see
+    /// [`StateTransitionHook`].
+    pub fn with_state_transition_hook(mut self, hook: StateTransitionHook) -> Self {
+        self.state_transition_hook = Some(hook);
+        self
+    }
+
+    /// Mirror every operation log write to `observer`, e.g. to keep an
+    /// external analytics store up to date
+    ///
+    /// The observer is notified in order after each write was committed and
+    /// never delays or fails the client, but writes are dropped while it falls
+    /// too far behind, see [`OperationObserver`].
+    pub fn with_operation_observer(mut self, observer: Arc<dyn OperationObserver>) -> Self {
+        self.operation_observer = Some(observer);
+        self
+    }
+
+    /// Delegate the derivation of the federation's root secret to `deriver`
+    /// instead of deriving it from the pre-root secret
+    ///
+    /// Meant for hardware wallets that have to keep the secret on-device. The
+    /// pre-root secret passed when joining, recovering or opening the client
+    /// is then not used at all, so any placeholder works. Instead the client's
+    /// database remembers the secret derived by `deriver`, so the client has
+    /// to keep being opened with a deriver deriving the same secret.
+    pub fn with_root_secret_deriver(mut self, deriver: Arc<dyn RootSecretDeriver>) -> Self {
+        self.root_secret_deriver = Some(deriver);
+        self
+    }
+
+    /// Answer api requests from a local cache when it has a response
+    ///
+    /// Every unauthenticated request is first looked up in `cache` (keyed by
+    /// peer, method and params) and only sent over the network on a miss.
+    /// Successful network responses are handed back to the cache to store.
+    /// This allows implementing the offline and battery saving use cases of
+    /// [`Self::with_api_request_hook`] for reads.
+    ///
+    /// The cache is applied on top of any request hook already set.
+    pub fn with_response_cache(mut self, cache: DynApiResponseCache) -> Self {
+        let request_hook = self.request_hook.clone();
+        self.request_hook = Arc::new(move |api| {
+            Box::new(RawFederationApiWithResponseCache::new(
+                request_hook(api),
+                cache.clone(),
+            ))
+        });
+        self
+    }
+
     pub fn with_meta_service(&mut self, meta_service: Arc<MetaService>) {
         self.meta_service = meta_service;
     }
@@ -218,6 +433,81 @@ pub fn with_iroh_enable_next(mut self, iroh_enable_next: bool) -> Self {
         self
     }
 
+    /// Periodically refresh guardian API URLs while the client is running
+    ///
+    /// Every `interval` the client fetches the guardians' API announcements
+    /// and re-targets its api at the announced URLs, see
+    /// [`Client::refresh_api_urls`]. Disabled by default, in which case
+    /// URL changes are only picked up with the (hourly) announcement refresh.
+    pub fn with_api_url_refresh_interval(mut self, interval: Duration) -> Self {
+        self.api_url_refresh_interval = Some(interval);
+        self
+    }
+
+    /// Verify that every module only wrote to its declared
+    /// [`IClientModuleInit::used_db_prefixes`] when migrating the module
+    /// databases, failing to build the client otherwise
+    ///
+    /// Always done (and a panic on failure) when running in a test environment.
+    pub fn with_db_integrity_check(mut self, enabled: bool) -> Self {
+        self.db_integrity_check = enabled;
+        self
+    }
+
+    /// Use `api_versions` instead of negotiating them with the federation
+    ///
+    /// Skips loading, refreshing and caching the common api versions when
+    /// building the [`Client`], avoiding the network round-trip on startup.
+    /// Intended for tests and embedded environments with a known-good set of
+    /// versions; using versions the federation does not support will make
+    /// requests fail.
+    pub fn with_pinned_api_versions(mut self, api_versions: ApiVersionSet) -> Self {
+        self.pinned_api_versions = Some(api_versions);
+        self
+    }
+
+    /// Fail joining if the api announcements prefetched during the preview can
+    /// not be obtained or stored
+    ///
+    /// By default joining gives up waiting for them after a while and
+    /// continues, leaving it to the background refresh task to fetch them
+    /// later.
+    pub fn with_strict_announcements(mut self, strict: bool) -> Self {
+        self.strict_announcements = strict;
+        self
+    }
+
+    /// Accept the config downloaded in [`Self::preview`] once `quorum`
+    /// guardians returned an identical one, instead of waiting for a consensus
+    /// threshold of them
+    ///
+    /// Speeds up joining federations with slow or distant guardians. The
+    /// quorum must be at least [`fedimint_core::NumPeers::one_honest`] of the
+    /// federation (e.g. 2 out of 4 guardians) for at least one honest guardian
+    /// to vouch for the config, otherwise the preview fails.
+    pub fn with_preview_quorum(mut self, quorum: usize) -> Self {
+        self.preview_quorum = Some(quorum);
+        self
+    }
+
+    /// How long [`ClientPreview::join`] waits for the federation to return a
+    /// backup created with the secret, 3 seconds by default
+    ///
+    /// Joining fails with [`BuildError::ExistingStateUnknown`] if the check
+    /// fails or times out. `None` skips the check and its round-trip, for
+    /// applications that know the secret was never used before.
+    pub fn with_existing_state_check_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.existing_state_check_timeout = timeout;
+        self
+    }
+
+    /// How long [`Client::compact_db`] keeps trimable event log entries and
+    /// the state machines of finished operations, 14 days by default
+    pub fn with_event_log_retention(mut self, retention: Duration) -> Self {
+        self.event_log_retention = retention;
+        self
+    }
+
     /// Set a factory function for creating a Bitcoin RPC client
     ///
     /// This allows applications to provide their own Bitcoin RPC client
@@ -305,16 +595,24 @@ async fn migrate_module_dbs(
                 *module_id,
             )
             .await?;
-            if let Some(used_db_prefixes) = init.used_db_prefixes()
-                && is_running_in_test_env()
-            {
-                verify_module_db_integrity_dbtx(
-                    &mut dbtx.to_ref_nc(),
-                    *module_id,
-                    kind,
-                    &used_db_prefixes,
-                )
-                .await;
+            if let Some(used_db_prefixes) = init.used_db_prefixes() {
+                if is_running_in_test_env() {
+                    verify_module_db_integrity_dbtx(
+                        &mut dbtx.to_ref_nc(),
+                        *module_id,
+                        kind,
+                        &used_db_prefixes,
+                    )
+                    .await;
+                } else if self.db_integrity_check {
+                    try_verify_module_db_integrity_dbtx(
+                        &mut dbtx.to_ref_nc(),
+                        *module_id,
+                        kind,
+                        &used_db_prefixes,
+                    )
+                    .await?;
+                }
             }
             dbtx.commit_tx_result().await?;
         }
@@ -365,7 +663,7 @@ async fn init(
                 .await;
             dbtx.insert_entry(
                 &ClientPreRootSecretHashKey,
-                &pre_root_secret.derive_pre_root_secret_hash(),
+                &self.secret_hash(&pre_root_secret, &config),
             )
             .await;
 
@@ -406,7 +704,9 @@ pub async fn preview(
         connectors: ConnectorRegistry,
         invite_code: &InviteCode,
     ) -> anyhow::Result<ClientPreview> {
-        let (config, api) = download_from_invite_code(&connectors, invite_code).await?;
+        let (config, api) =
+            download_from_invite_code_with_quorum(&connectors, invite_code, self.preview_quorum)
+                .await?;
 
         let prefetch_api_announcements =
             config
@@ -458,13 +758,16 @@ pub async fn preview_with_existing_config(
     }
 
     async fn preview_inner(
-        self,
+        mut self,
         connectors: ConnectorRegistry,
         config: ClientConfig,
         api_secret: Option<String>,
         prefetch_api: Option<DynGlobalApi>,
         prefetch_api_announcements: Option<Jit<Vec<PeersSignedApiAnnouncements>>>,
     ) -> anyhow::Result<ClientPreview> {
+        Self::check_consensus_version(&config)?;
+        self.resolve_module_factories(&config);
+
         let preview_prefetch_api_version_set = prefetch_api.as_ref().map(|api| {
             JitTry::new_try({
                 let config = config.clone();
@@ -473,7 +776,7 @@ async fn preview_inner(
             })
         });
 
-        let prefetch_chain_id = prefetch_api.map(|api| {
+        let prefetch_chain_id = prefetch_api.clone().map(|api| {
             JitTry::new_try(|| async move { api.chain_id().await.map_err(anyhow::Error::from) })
         });
 
@@ -482,12 +785,46 @@ async fn preview_inner(
             inner: self,
             config,
             api_secret,
+            api: prefetch_api,
             prefetch_api_announcements,
             preview_prefetch_api_version_set,
             prefetch_chain_id,
         })
     }
 
+    /// Checks whether `pre_root_secret` is the secret the client in
+    /// `db_no_decoders` was created with, without opening the client
+    ///
+    /// Only compares the secret hash stored in the database, so it is cheap
+    /// enough to validate e.g. a user's seed in a login flow before calling
+    /// [`Self::open`]. Clients created before the hash was stored accept any
+    /// secret in [`Self::open`], so `Ok(true)` is returned for them.
+    ///
+    /// With a [`RootSecretDeriver`] set, the deriver is checked instead of
+    /// `pre_root_secret`, see [`Self::with_root_secret_deriver`].
+    pub async fn verify_root_secret(
+        &self,
+        db_no_decoders: &Database,
+        pre_root_secret: &RootSecret,
+    ) -> anyhow::Result<bool> {
+        let Some(config) = Client::get_config_from_db(db_no_decoders).await else {
+            bail!("Client database not initialized")
+        };
+
+        let Some(secret_hash) = db_no_decoders
+            .begin_transaction_nc()
+            .await
+            .get_value(&ClientPreRootSecretHashKey)
+            .await
+        else {
+            return Ok(true);
+        };
+
+        let pre_root_secret = pre_root_secret.to_inner(config.calculate_federation_id());
+
+        Ok(self.secret_hash(&pre_root_secret, &config) == secret_hash)
+    }
+
     pub async fn open(
         self,
         connectors: ConnectorRegistry,
@@ -513,7 +850,7 @@ pub async fn open(
         {
             Some(secret_hash) => {
                 ensure!(
-                    pre_root_secret.derive_pre_root_secret_hash() == secret_hash,
+                    self.secret_hash(&pre_root_secret, &config) == secret_hash,
                     "Secret hash does not match. Incorrect secret"
                 );
             }
@@ -523,7 +860,7 @@ pub async fn open(
                 let mut dbtx = db_no_decoders.begin_transaction().await;
                 dbtx.insert_entry(
                     &ClientPreRootSecretHashKey,
-                    &pre_root_secret.derive_pre_root_secret_hash(),
+                    &self.secret_hash(&pre_root_secret, &config),
                 )
                 .await;
                 dbtx.commit_tx().await;
@@ -555,6 +892,24 @@ pub async fn open(
         Ok(client)
     }
 
+    /// Open an existing client like [`Self::open`], but without the ability to
+    /// spend
+    ///
+    /// The executor still drives existing state machines forward, so balances
+    /// and operations keep getting updated, but parks the ones that need to
+    /// submit a transaction until the client is opened normally again.
+    /// Starting new spends fails, see [`Client::is_watch_only`]. The root
+    /// secret is still required to initialize the client modules.
+    pub async fn open_watch_only(
+        mut self,
+        connectors: ConnectorRegistry,
+        db_no_decoders: Database,
+        pre_root_secret: RootSecret,
+    ) -> anyhow::Result<ClientHandle> {
+        self.watch_only = true;
+        self.open(connectors, db_no_decoders, pre_root_secret).await
+    }
+
     /// Build a [`Client`] and start the executor
     #[allow(clippy::too_many_arguments)]
     pub(crate) async fn build(
@@ -617,6 +972,8 @@ async fn build_stopped(
             version = %fedimint_build_code_version_env!(),
             "Building fedimint client",
         );
+        Self::check_consensus_version(config)?;
+        self.resolve_module_factories(config);
         for (kind, module) in self.module_inits.iter() {
             debug!(
                 target: LOG_CLIENT,
@@ -633,23 +990,21 @@ async fn build_stopped(
         let fed_id = config.calculate_federation_id();
         let db = db_no_decoders.with_decoders(decoders.clone());
         let peer_urls = get_api_urls(&db, &config).await;
-        let api = match self.admin_creds.as_ref() {
+        let federation_api = match self.admin_creds.as_ref() {
             Some(admin_creds) => FederationApi::new(
                 connectors.clone(),
                 peer_urls,
                 Some(admin_creds.peer_id),
                 Some(admin_creds.auth.as_str()),
-            )
+            ),
+            None => FederationApi::new(connectors.clone(), peer_urls, None, api_secret.as_deref()),
+        };
+        let peer_urls = federation_api.peer_urls();
+        let api: DynGlobalApi = federation_api
             .with_client_ext(db.clone(), log_ordering_wakeup_tx.clone())
             .with_request_hook(&request_hook)
             .with_cache()
-            .into(),
-            None => FederationApi::new(connectors.clone(), peer_urls, None, api_secret.as_deref())
-                .with_client_ext(db.clone(), log_ordering_wakeup_tx.clone())
-                .with_request_hook(&request_hook)
-                .with_cache()
-                .into(),
-        };
+            .into();
 
         let task_group = TaskGroup::new();
         let client_span = Client::make_client_span(fed_id);
@@ -663,12 +1018,27 @@ async fn build_stopped(
         let notifier = Notifier::new();
 
         if let Some(p) = preview_prefetch_api_announcements {
-            // We want to fail if we were unable to figure out
-            // current addresses of peers in the federation, as it will potentially never
-            // fix itself, so it's better to fail the join explicitly.
-            let announcements = p.get().await;
+            if self.strict_announcements {
+                // We want to fail if we were unable to figure out
+                // current addresses of peers in the federation, as it will potentially never
+                // fix itself, so it's better to fail the join explicitly.
+                let announcements = p.get().await;
 
-            store_api_announcements_updates_from_peers(&db, announcements).await?
+                store_api_announcements_updates_from_peers(&db, announcements).await?;
+            } else {
+                match runtime::timeout(PREFETCH_API_ANNOUNCEMENTS_TIMEOUT, p.get()).await {
+                    Ok(announcements) => {
+                        if let Err(err) =
+                            store_api_announcements_updates_from_peers(&db, announcements).await
+                        {
+                            warn!(target: LOG_CLIENT, err = %err.fmt_compact_anyhow(), "Storing prefetched api announcements failed, continuing");
+                        }
+                    }
+                    Err(_) => {
+                        warn!(target: LOG_CLIENT, "Timed out waiting for prefetched api announcements, continuing");
+                    }
+                }
+            }
         }
 
         if let Some(preview_prefetch_api_version_set) = preview_prefetch_api_version_set {
@@ -688,24 +1058,28 @@ async fn build_stopped(
             }
         }
 
-        let common_api_versions = Client::load_and_refresh_common_api_version_static(
-            &config,
-            &self.module_inits,
-            connectors.clone(),
-            &api,
-            &db,
-            &task_group,
-            &client_span,
-        )
-        .await
-        .inspect_err(|err| {
-            warn!(target: LOG_CLIENT, err = %err.fmt_compact_anyhow(), "Failed to discover API version to use.");
-        })
-        .unwrap_or(ApiVersionSet {
-            core: ApiVersion::new(0, 0),
-            // This will cause all modules to skip initialization
-            modules: BTreeMap::new(),
-        });
+        let common_api_versions = if let Some(pinned_api_versions) = &self.pinned_api_versions {
+            pinned_api_versions.clone()
+        } else {
+            Client::load_and_refresh_common_api_version_static(
+                &config,
+                &self.module_inits,
+                connectors.clone(),
+                &api,
+                &db,
+                &task_group,
+                &client_span,
+            )
+            .await
+            .inspect_err(|err| {
+                warn!(target: LOG_CLIENT, err = %err.fmt_compact_anyhow(), "Failed to discover API version to use.");
+            })
+            .unwrap_or(ApiVersionSet {
+                core: ApiVersion::new(0, 0),
+                // This will cause all modules to skip initialization
+                modules: BTreeMap::new(),
+            })
+        };
 
         client_span.in_scope(|| {
             debug!(
@@ -779,7 +1153,7 @@ async fn build_stopped(
 
         let final_client = FinalClientIface::default();
 
-        let root_secret = Self::federation_root_secret(&pre_root_secret, &config);
+        let root_secret = self.federation_root_secret(&pre_root_secret, &config);
 
         let modules = {
             let mut modules = ClientModuleRegistry::default();
@@ -1009,6 +1383,14 @@ async fn build_stopped(
                 executor_builder.with_valid_module_id(*module_instance_id);
             }
 
+            if let Some(hook) = self.state_transition_hook.clone() {
+                executor_builder.with_state_transition_hook(hook);
+            }
+
+            if self.watch_only {
+                executor_builder.with_watch_only();
+            }
+
             executor_builder.build(
                 db.clone(),
                 notifier,
@@ -1021,8 +1403,20 @@ async fn build_stopped(
             .iter()
             .map(|(module_instance_id, rx)| (*module_instance_id, *rx.borrow()))
             .collect::<BTreeMap<_, _>>();
+        let recovery_started_at = fedimint_core::time::now();
+        let client_recovery_started = recovery_receiver_init_val
+            .iter()
+            .map(|(module_instance_id, progress)| {
+                (*module_instance_id, (*progress, recovery_started_at))
+            })
+            .collect();
         let (client_recovery_progress_sender, client_recovery_progress_receiver) =
             watch::channel(recovery_receiver_init_val);
+        let (client_recovery_result_sender, client_recovery_result_receiver) =
+            watch::channel(BTreeMap::new());
+
+        let operation_log =
+            OperationLog::new(db.clone()).with_observer(self.operation_observer, &task_group);
 
         let client_inner = Arc::new(Client {
             final_client: final_client.clone(),
@@ -1039,18 +1433,30 @@ async fn build_stopped(
             log_ordering_wakeup_tx,
             log_event_added_rx,
             log_event_added_transient_tx: log_event_added_transient_tx.clone(),
+            cancelled_operations: watch::channel(BTreeSet::new()).0,
             request_hook,
+            state_transition_hook: self.state_transition_hook,
+            root_secret_deriver: self.root_secret_deriver.clone(),
             executor,
             api,
+            peer_urls,
+            common_api_versions,
             secp_ctx: Secp256k1::new(),
             root_secret,
             task_group,
             client_span,
-            operation_log: OperationLog::new(db.clone()),
+            operation_log,
             client_recovery_progress_receiver,
+            client_recovery_started,
+            client_recovery_result_receiver,
             meta_service: self.meta_service,
             iroh_enable_dht: self.iroh_enable_dht,
             iroh_enable_next: self.iroh_enable_next,
+            api_url_refresh_interval: self.api_url_refresh_interval,
+            pinned_api_versions: self.pinned_api_versions,
+            event_log_retention: self.event_log_retention,
+            db_integrity_check: self.db_integrity_check,
+            watch_only: self.watch_only,
             user_bitcoind_rpc,
             user_bitcoind_rpc_no_chain_id: self.bitcoind_rpc_no_chain_id_factory,
         });
@@ -1075,6 +1481,19 @@ async fn build_stopped(
             }
         });
 
+        if let Some(interval) = client_inner.api_url_refresh_interval {
+            client_inner.spawn_cancellable("api url refresh task", {
+                let client_inner = client_inner.clone();
+                async move {
+                    client_inner
+                        .connectors
+                        .wait_for_initialized_connections()
+                        .await;
+                    run_api_url_refresh_task(client_inner.clone(), interval).await
+                }
+            });
+        }
+
         client_inner.spawn_cancellable("guardian metadata refresh task", {
             let client_inner = client_inner.clone();
             async move {
@@ -1147,6 +1566,7 @@ async fn build_stopped(
         if !module_recoveries.is_empty() {
             client_arc.spawn_module_recoveries_task(
                 client_recovery_progress_sender,
+                client_recovery_result_sender,
                 module_recoveries,
                 module_recovery_progress_receivers,
             );
@@ -1188,6 +1608,22 @@ fn decoders(&self, config: &ClientConfig) -> ModuleDecoderRegistry {
         decoders
     }
 
+    /// Fails with [`BuildError::ConsensusVersionUnsupported`] if the federation
+    /// runs a core consensus version with a newer major version than this
+    /// client, since its config and consensus items can not be decoded
+    /// reliably
+    fn check_consensus_version(config: &ClientConfig) -> Result<(), BuildError> {
+        let federation_version = config.global.consensus_version;
+        if CORE_CONSENSUS_VERSION.major < federation_version.major {
+            return Err(BuildError::ConsensusVersionUnsupported {
+                federation_version,
+                client_supports: CORE_CONSENSUS_VERSION,
+            });
+        }
+
+        Ok(())
+    }
+
     fn config_decoded(
         config: &ClientConfig,
         decoders: &ModuleDecoderRegistry,
@@ -1198,11 +1634,33 @@ fn config_decoded(
     /// Re-derive client's `root_secret` using the federation ID. This
     /// eliminates the possibility of having the same client `root_secret`
     /// across multiple federations.
+    ///
+    /// Uses the [`RootSecretDeriver`] set via
+    /// [`Self::with_root_secret_deriver`] if any.
     fn federation_root_secret(
+        &self,
         pre_root_secret: &DerivableSecret,
         config: &ClientConfig,
     ) -> DerivableSecret {
-        pre_root_secret.federation_key(&config.global.calculate_federation_id())
+        let deriver = self
+            .root_secret_deriver
+            .as_deref()
+            .unwrap_or(pre_root_secret as &dyn RootSecretDeriver);
+
+        deriver.federation_key(&config.global.calculate_federation_id())
+    }
+
+    /// Hash identifying the secret the client's database was created with
+    ///
+    /// With a [`RootSecretDeriver`] the pre-root secret is only a placeholder,
+    /// so the hash is taken from the federation root secret it derives.
+    fn secret_hash(&self, pre_root_secret: &DerivableSecret, config: &ClientConfig) -> [u8; 8] {
+        match self.root_secret_deriver.as_deref() {
+            Some(deriver) => deriver
+                .federation_key(&config.global.calculate_federation_id())
+                .derive_pre_root_secret_hash(),
+            None => pre_root_secret.derive_pre_root_secret_hash(),
+        }
     }
 
     /// Register to receiver all new transient (unpersisted) events
@@ -1336,6 +1794,152 @@ async fn refresh_client_config_static_try(
     }
 }
 
+/// Summary of what happened while joining a federation, see
+/// [`ClientPreview::join_with_report`]
+#[derive(Debug, Clone, Serialize)]
+pub struct JoinReport {
+    /// Module instances that were initialized, with their kinds
+    pub initialized_modules: BTreeMap<ModuleInstanceId, ModuleKind>,
+    /// Module instances of the federation the client does not use
+    pub skipped_modules: BTreeMap<ModuleInstanceId, SkippedModule>,
+    /// API versions negotiated with the federation
+    pub api_versions: ApiVersionSet,
+    /// Module used as the primary module for Bitcoin, if any
+    pub primary_module: Option<PrimaryModuleReport>,
+}
+
+/// A module instance that was not initialized during join
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedModule {
+    pub kind: ModuleKind,
+    pub reason: SkippedModuleReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkippedModuleReason {
+    /// No client module for the kind was registered with the [`ClientBuilder`]
+    UnsupportedKind,
+    /// No API version supported by both client and federation was found
+    IncompatibleApiVersion,
+}
+
+/// Result of [`Client::check_module_compatibility`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleCompatReport {
+    /// Core API version the client would use, `None` if the client can't talk
+    /// to the federation at all
+    pub core_api_version: Option<ApiVersion>,
+    /// Compatibility of each module instance of the federation
+    pub modules: BTreeMap<ModuleInstanceId, ModuleCompat>,
+}
+
+impl ModuleCompatReport {
+    /// Returns `true` if the client can use the federation and every module of
+    /// it
+    pub fn is_fully_supported(&self) -> bool {
+        self.core_api_version.is_some()
+            && self
+                .modules
+                .values()
+                .all(|module| matches!(module.status, ModuleCompatStatus::Compatible { .. }))
+    }
+}
+
+/// Compatibility of a single module instance
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleCompat {
+    pub kind: ModuleKind,
+    pub status: ModuleCompatStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModuleCompatStatus {
+    /// Client and federation share the given API version
+    Compatible { api_version: ApiVersion },
+    /// No API version supported by both client and federation was found
+    Incompatible {
+        /// API versions supported by the client module
+        client_api_versions: Vec<ApiVersion>,
+        /// API versions offered by any of the peers
+        federation_api_versions: BTreeSet<ApiVersion>,
+    },
+    /// No client module for the kind was registered
+    Unregistered,
+}
+
+/// The primary module chosen for Bitcoin
+#[derive(Debug, Clone, Serialize)]
+pub struct PrimaryModuleReport {
+    pub instance_id: ModuleInstanceId,
+    pub kind: ModuleKind,
+    pub selection: PrimaryModuleSelection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrimaryModuleSelection {
+    /// The module explicitly listed Bitcoin among the units it handles
+    Explicit,
+    /// The module handles any unit and was picked by its priority
+    Automatic,
+}
+
+impl JoinReport {
+    fn new(client: &Client, config: &ClientConfig) -> Self {
+        let mut initialized_modules = BTreeMap::new();
+        let mut skipped_modules = BTreeMap::new();
+        for (module_instance_id, module_config) in &config.modules {
+            let kind = module_config.kind().clone();
+            if client.has_module(*module_instance_id) {
+                initialized_modules.insert(*module_instance_id, kind);
+                continue;
+            }
+
+            let reason = if client.module_inits.get(&kind).is_none() {
+                SkippedModuleReason::UnsupportedKind
+            } else {
+                SkippedModuleReason::IncompatibleApiVersion
+            };
+            skipped_modules.insert(*module_instance_id, SkippedModule { kind, reason });
+        }
+
+        // Mirrors `Client::primary_module_for_unit`: priorities in order, and
+        // within the same priority specific matches come before wildcard ones
+        let primary_module = client
+            .primary_modules
+            .values()
+            .find_map(|candidates| {
+                candidates
+                    .specific
+                    .get(&AmountUnit::BITCOIN)
+                    .and_then(|ids| ids.first())
+                    .map(|id| (*id, PrimaryModuleSelection::Explicit))
+                    .or_else(|| {
+                        candidates
+                            .wildcard
+                            .first()
+                            .map(|id| (*id, PrimaryModuleSelection::Automatic))
+                    })
+            })
+            .and_then(|(instance_id, selection)| {
+                Some(PrimaryModuleReport {
+                    instance_id,
+                    kind: initialized_modules.get(&instance_id)?.clone(),
+                    selection,
+                })
+            });
+
+        Self {
+            initialized_modules,
+            skipped_modules,
+            api_versions: client.common_api_versions.clone(),
+            primary_module,
+        }
+    }
+}
+
 /// An intermediate step before Client joining or recovering
 ///
 /// Meant to support showing user some initial information about the Federation
@@ -1345,6 +1949,8 @@ pub struct ClientPreview {
     config: ClientConfig,
     connectors: ConnectorRegistry,
     api_secret: Option<String>,
+    /// Api the config was downloaded with, if any
+    api: Option<DynGlobalApi>,
     prefetch_api_announcements: Option<Jit<Vec<PeersSignedApiAnnouncements>>>,
     preview_prefetch_api_version_set:
         Option<JitTryAnyhow<BTreeMap<PeerId, SupportedApiVersionsSummary>>>,
@@ -1435,6 +2041,12 @@ pub fn config(&self) -> &ClientConfig {
     /// # Ok(())
     /// # }
     /// ```
+    ///
+    /// Fails with [`BuildError::ExistingStateDetected`] if the federation has a
+    /// backup created with `pre_root_secret`, as the secret was used before
+    /// and [`Self::recover`] has to be used instead, or with
+    /// [`BuildError::ExistingStateUnknown`] if that could not be checked, see
+    /// [`ClientBuilder::with_existing_state_check_timeout`].
     pub async fn join(
         self,
         db_no_decoders: Database,
@@ -1442,6 +2054,21 @@ pub async fn join(
     ) -> anyhow::Result<ClientHandle> {
         let pre_root_secret = pre_root_secret.to_inner(self.config.calculate_federation_id());
 
+        if let Some(timeout) = self.inner.existing_state_check_timeout {
+            match runtime::timeout(timeout, self.download_backup(&pre_root_secret)).await {
+                Ok(Ok(Some(_))) => bail!(BuildError::ExistingStateDetected),
+                Ok(Ok(None)) => {}
+                Ok(Err(err)) => {
+                    warn!(target: LOG_CLIENT, err = %err.fmt_compact_anyhow(), "Checking for existing backups failed");
+                    bail!(BuildError::ExistingStateUnknown)
+                }
+                Err(_) => {
+                    warn!(target: LOG_CLIENT, ?timeout, "Checking for existing backups timed out");
+                    bail!(BuildError::ExistingStateUnknown)
+                }
+            }
+        }
+
         let client = self
             .inner
             .init(
@@ -1460,6 +2087,22 @@ pub async fn join(
         Ok(client)
     }
 
+    /// Like [`Self::join`], but also returns a [`JoinReport`] describing which
+    /// modules were initialized or skipped and the negotiated API versions
+    ///
+    /// Useful for onboarding UIs explaining what the federation supports.
+    pub async fn join_with_report(
+        self,
+        db_no_decoders: Database,
+        pre_root_secret: RootSecret,
+    ) -> anyhow::Result<(ClientHandle, JoinReport)> {
+        let config = self.config.clone();
+        let client = self.join(db_no_decoders, pre_root_secret).await?;
+        let report = JoinReport::new(client.as_inner(), &config);
+
+        Ok((client, report))
+    }
+
     /// Join a (possibly) previous joined Federation
     ///
     /// Unlike [`Self::join`], `recover` will run client module
@@ -1509,21 +2152,34 @@ pub async fn download_backup_from_federation(
         pre_root_secret: RootSecret,
     ) -> anyhow::Result<Option<ClientBackup>> {
         let pre_root_secret = pre_root_secret.to_inner(self.config.calculate_federation_id());
-        let api = DynGlobalApi::new(
-            self.connectors.clone(),
-            // TODO: change join logic to use FederationId v2
-            self.config
-                .global
-                .api_endpoints
-                .iter()
-                .map(|(peer_id, peer_url)| (*peer_id, peer_url.url.clone()))
-                .collect(),
-            self.api_secret.as_deref(),
-        )?;
+        self.download_backup(&pre_root_secret).await
+    }
+
+    #[allow(deprecated)]
+    async fn download_backup(
+        &self,
+        pre_root_secret: &DerivableSecret,
+    ) -> anyhow::Result<Option<ClientBackup>> {
+        let api = match &self.api {
+            Some(api) => api.clone(),
+            None => DynGlobalApi::new(
+                self.connectors.clone(),
+                // TODO: change join logic to use FederationId v2
+                self.config
+                    .global
+                    .api_endpoints
+                    .iter()
+                    .map(|(peer_id, peer_url)| (*peer_id, peer_url.url.clone()))
+                    .collect(),
+                self.api_secret.as_deref(),
+            )?,
+        };
 
         Client::download_backup_from_federation_static(
             &api,
-            &ClientBuilder::federation_root_secret(&pre_root_secret, &self.config),
+            &self
+                .inner
+                .federation_root_secret(pre_root_secret, &self.config),
             &self.inner.decoders(&self.config),
         )
         .await
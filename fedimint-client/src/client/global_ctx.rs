@@ -118,4 +118,8 @@ async fn log_event_json(
     async fn core_api_version(&self) -> fedimint_core::module::ApiVersion {
         self.client.core_api_version().await
     }
+
+    async fn await_operation_cancelled(&self) {
+        self.client.await_operation_cancelled(self.operation).await;
+    }
 }
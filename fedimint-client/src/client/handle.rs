@@ -1,17 +1,24 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::ops;
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::format_err;
-use fedimint_core::runtime;
+use anyhow::{bail, format_err};
+use fedimint_connectors::ConnectorRegistry;
+use fedimint_core::core::{ModuleInstanceId, OperationId};
+use fedimint_core::db::IDatabaseTransactionOpsCore as _;
+use fedimint_core::module::AmountUnit;
 use fedimint_core::util::FmtCompactAnyhow as _;
+use fedimint_core::{Amount, runtime};
 use fedimint_logging::LOG_CLIENT;
+use serde::Serialize;
 #[cfg(not(target_family = "wasm"))]
 use tokio::runtime::{Handle as RuntimeHandle, RuntimeFlavor};
 use tracing::{Instrument as _, debug, error, trace, warn};
 
 use super::Client;
 use crate::ClientBuilder;
+use crate::meta::MetaService;
 
 /// User handle to the [`Client`] instance
 ///
@@ -30,6 +37,16 @@ pub struct ClientHandle {
 /// An alias for a reference counted [`ClientHandle`]
 pub type ClientHandleArc = Arc<ClientHandle>;
 
+/// Summary of a client that left its federation, see [`ClientHandle::leave`]
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaveReport {
+    /// Balances that were abandoned, only non-empty if the leave was forced
+    pub abandoned_balances: BTreeMap<ModuleInstanceId, BTreeMap<AmountUnit, Amount>>,
+    /// Operations that still had active state machines, only non-empty if the
+    /// leave was forced
+    pub abandoned_operations: BTreeSet<OperationId>,
+}
+
 impl ClientHandle {
     /// Create
     pub(crate) fn new(inner: Arc<Client>) -> Self {
@@ -98,6 +115,61 @@ async fn shutdown_inner(&mut self) {
         });
     }
 
+    /// Leave the federation, deleting all of the client's local data
+    ///
+    /// Refuses if any module still holds funds or any state machine is still
+    /// active (e.g. a payment in flight that could still refund), listing the
+    /// outstanding balances and operations, unless `force` is set. On success
+    /// the client is shut down and everything stored in its database
+    /// (config, init state, operation log and module data) is removed in a
+    /// single transaction. When multiple clients share a database via
+    /// prefixes, only this client's prefix is affected.
+    pub async fn leave(self, force: bool) -> anyhow::Result<LeaveReport> {
+        let (db, abandoned_balances, abandoned_operations) = {
+            let client = self
+                .inner
+                .as_ref()
+                .ok_or_else(|| format_err!("Already stopped"))?;
+
+            let active_operations = client
+                .executor
+                .get_active_states()
+                .await
+                .into_iter()
+                .map(|(state, _)| state.operation_id())
+                .collect::<BTreeSet<_>>();
+
+            (
+                client.db().clone(),
+                client.get_outstanding_balances().await,
+                active_operations,
+            )
+        };
+
+        if !abandoned_balances.is_empty() && !force {
+            bail!(
+                "Refusing to leave the federation with outstanding balances: {abandoned_balances:?}"
+            );
+        }
+
+        if !abandoned_operations.is_empty() && !force {
+            bail!(
+                "Refusing to leave the federation with active operations: {abandoned_operations:?}"
+            );
+        }
+
+        self.shutdown().await;
+
+        let mut dbtx = db.begin_transaction().await;
+        dbtx.raw_remove_by_prefix(&[]).await?;
+        dbtx.commit_tx_result().await?;
+
+        Ok(LeaveReport {
+            abandoned_balances,
+            abandoned_operations,
+        })
+    }
+
     /// Restart the client
     ///
     /// Returns false if there are other clones of [`ClientHandle`], or starting
@@ -106,17 +178,53 @@ async fn shutdown_inner(&mut self) {
     /// Notably it will re-use the original [`fedimint_core::db::Database`]
     /// handle, and not attempt to open it again.
     pub async fn restart(self) -> anyhow::Result<ClientHandle> {
+        self.restart_inner(None, None).await
+    }
+
+    /// Restart the client, connecting to the federation through `connectors`
+    /// instead of the ones the client was opened with
+    ///
+    /// Allows e.g. switching a client from clearnet to Tor without
+    /// reconstructing the [`ClientBuilder`].
+    pub async fn restart_with_connectors(
+        self,
+        connectors: ConnectorRegistry,
+    ) -> anyhow::Result<ClientHandle> {
+        self.restart_inner(Some(connectors), None).await
+    }
+
+    /// Restart the client, reading the federation meta fields through
+    /// `meta_service` instead of the one the client was opened with
+    ///
+    /// Allows e.g. switching to an override or cached meta source without
+    /// reconstructing the [`ClientBuilder`]. The meta update task of the
+    /// restarted client runs on the new service.
+    pub async fn restart_with_meta_service(
+        self,
+        meta_service: Arc<MetaService>,
+    ) -> anyhow::Result<ClientHandle> {
+        self.restart_inner(None, Some(meta_service)).await
+    }
+
+    async fn restart_inner(
+        self,
+        connectors: Option<ConnectorRegistry>,
+        meta_service: Option<Arc<MetaService>>,
+    ) -> anyhow::Result<ClientHandle> {
         let (builder, config, api_secret, root_secret, db, endpoints) = {
             let client = self
                 .inner
                 .as_ref()
                 .ok_or_else(|| format_err!("Already stopped"))?;
-            let builder = ClientBuilder::from_existing(client);
+            let mut builder = ClientBuilder::from_existing(client);
+            if let Some(meta_service) = meta_service {
+                builder.with_meta_service(meta_service);
+            }
             let config = client.config().await;
             let api_secret = client.api_secret.clone();
             let root_secret = client.root_secret.clone();
             let db = client.db().clone();
-            let endpoints = client.endpoints().clone();
+            let endpoints = connectors.unwrap_or_else(|| client.endpoints().clone());
 
             (builder, config, api_secret, root_secret, db, endpoints)
         };
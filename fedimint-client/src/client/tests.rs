@@ -0,0 +1,157 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use fedimint_core::PeerId;
+use fedimint_core::config::{ClientConfig, ClientModuleConfig, GlobalClientConfig};
+use fedimint_core::core::{ModuleInstanceId, ModuleKind};
+use fedimint_core::encoding::DynRawFallback;
+use fedimint_core::module::{
+    ApiVersion, CoreConsensusVersion, ModuleConsensusVersion, MultiApiVersion,
+    SupportedApiVersionsSummary, SupportedCoreApiVersions, SupportedModuleApiVersions,
+};
+
+use crate::module_init::ClientModuleInitRegistry;
+use crate::{Client, ModuleCompatStatus};
+
+const CORE_CONSENSUS: CoreConsensusVersion = CoreConsensusVersion::new(2, 0);
+const MODULE_CONSENSUS: ModuleConsensusVersion = ModuleConsensusVersion::new(1, 0);
+
+/// Federation with a compatible, an incompatible and an unregistered module
+fn config() -> ClientConfig {
+    ClientConfig {
+        global: GlobalClientConfig {
+            api_endpoints: BTreeMap::new(),
+            broadcast_public_keys: None,
+            consensus_version: CORE_CONSENSUS,
+            meta: BTreeMap::new(),
+        },
+        modules: [(0, "mint"), (1, "ln"), (2, "unknown")]
+            .into_iter()
+            .map(|(module_instance_id, kind)| {
+                (
+                    module_instance_id,
+                    ClientModuleConfig {
+                        kind: ModuleKind::from_static_str(kind),
+                        version: MODULE_CONSENSUS,
+                        config: DynRawFallback::Raw {
+                            module_instance_id,
+                            raw: vec![],
+                        },
+                    },
+                )
+            })
+            .collect(),
+    }
+}
+
+fn versions(
+    core: &[ApiVersion],
+    modules: &[(ModuleInstanceId, &[ApiVersion])],
+) -> SupportedApiVersionsSummary {
+    SupportedApiVersionsSummary {
+        core: SupportedCoreApiVersions {
+            core_consensus: CORE_CONSENSUS,
+            api: MultiApiVersion::try_from_iter(core.to_owned()).unwrap(),
+        },
+        modules: modules
+            .iter()
+            .map(|(module_instance_id, api)| {
+                (
+                    *module_instance_id,
+                    SupportedModuleApiVersions {
+                        core_consensus: CORE_CONSENSUS,
+                        module_consensus: MODULE_CONSENSUS,
+                        api: MultiApiVersion::try_from_iter(api.to_vec()).unwrap(),
+                    },
+                )
+            })
+            .collect(),
+    }
+}
+
+fn client_versions() -> SupportedApiVersionsSummary {
+    versions(
+        &[ApiVersion::new(0, 0)],
+        &[(0, &[ApiVersion::new(0, 1)]), (1, &[ApiVersion::new(1, 0)])],
+    )
+}
+
+fn peer_versions(core: ApiVersion) -> BTreeMap<PeerId, SupportedApiVersionsSummary> {
+    (0..4)
+        .map(|peer| {
+            (
+                PeerId::from(peer),
+                versions(
+                    &[core],
+                    &[
+                        (0, &[ApiVersion::new(0, u32::from(peer) + 1)]),
+                        (1, &[ApiVersion::new(2, 0), ApiVersion::new(3, 0)]),
+                    ],
+                ),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn module_compat_report_maps_common_api_versions() {
+    let report = Client::module_compat_report(
+        &config(),
+        &client_versions(),
+        &peer_versions(ApiVersion::new(0, 0)),
+    );
+
+    assert_eq!(report.core_api_version, Some(ApiVersion::new(0, 0)));
+    assert_eq!(
+        report.modules[&0].status,
+        ModuleCompatStatus::Compatible {
+            api_version: ApiVersion::new(0, 1)
+        }
+    );
+    // Every peer offers the same versions, they are only reported once
+    assert_eq!(
+        report.modules[&1].status,
+        ModuleCompatStatus::Incompatible {
+            client_api_versions: vec![ApiVersion::new(1, 0)],
+            federation_api_versions: BTreeSet::from([ApiVersion::new(2, 0), ApiVersion::new(3, 0)]),
+        }
+    );
+    assert_eq!(report.modules[&2].status, ModuleCompatStatus::Unregistered);
+    assert_eq!(
+        report.modules[&2].kind,
+        ModuleKind::from_static_str("unknown")
+    );
+    assert!(!report.is_fully_supported());
+}
+
+#[test]
+fn module_compat_report_without_common_core_api_version() {
+    let report = Client::module_compat_report(
+        &config(),
+        &client_versions(),
+        &peer_versions(ApiVersion::new(1, 0)),
+    );
+
+    assert_eq!(report.core_api_version, None);
+    assert!(matches!(
+        report.modules[&0].status,
+        ModuleCompatStatus::Incompatible { .. }
+    ));
+    assert!(!report.is_fully_supported());
+}
+
+#[test]
+fn check_module_compatibility_without_module_inits() {
+    let report = Client::check_module_compatibility(
+        &config(),
+        &ClientModuleInitRegistry::default(),
+        &peer_versions(ApiVersion::new(0, 0)),
+    );
+
+    assert_eq!(report.core_api_version, Some(ApiVersion::new(0, 0)));
+    assert!(
+        report
+            .modules
+            .values()
+            .all(|module| module.status == ModuleCompatStatus::Unregistered)
+    );
+}
@@ -67,6 +67,7 @@ pub enum DbKeyPrefix {
 
     ActiveStates = ExecutorDbPrefixes::ActiveStates as u8,
     InactiveStates = ExecutorDbPrefixes::InactiveStates as u8,
+    CompactedOperations = ExecutorDbPrefixes::CompactedOperations as u8,
 
     /// Arbitrary data of the applications integrating Fedimint client and
     /// wanting to store some Federation-specific data in Fedimint client
@@ -0,0 +1,172 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use fedimint_api_client::api::{DynGlobalApi, FederationApiExt as _};
+use fedimint_connectors::ConnectorRegistry;
+use fedimint_core::config::ClientConfig;
+use fedimint_core::endpoint_constants::CLIENT_CONFIG_ENDPOINT;
+use fedimint_core::invite_code::InviteCode;
+use fedimint_core::module::ApiRequestErased;
+use fedimint_core::{PeerId, runtime};
+
+/// URL schemes guardians can be reached at
+const GUARDIAN_API_SCHEMES: [&str; 3] = ["ws", "wss", "iroh"];
+
+/// Outcome of [`validate_invite_code`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InviteCodeValidation {
+    /// The invite code is well formed
+    Valid,
+    /// The invite code can not be used to reach the federation
+    Malformed(String),
+}
+
+impl InviteCodeValidation {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid)
+    }
+}
+
+/// Outcome of [`probe_invite_code`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InviteCodeProbe {
+    /// The invite code is malformed, see [`validate_invite_code`]
+    Malformed(String),
+    /// No guardian in the invite code returned the config of the federation
+    Unreachable(String),
+    /// The listed guardians returned the config of the federation
+    Reachable { responding_peers: BTreeSet<PeerId> },
+}
+
+/// Checks the structure of an invite code without accessing the network
+pub fn validate_invite_code(code: &InviteCode) -> InviteCodeValidation {
+    let peers = code.peers();
+    if peers.is_empty() {
+        return InviteCodeValidation::Malformed("Invite code lists no guardian".to_string());
+    }
+
+    for (peer, url) in &peers {
+        if !GUARDIAN_API_SCHEMES.contains(&url.scheme()) {
+            return InviteCodeValidation::Malformed(format!(
+                "Guardian {peer} has an API URL with unsupported scheme: {url}"
+            ));
+        }
+
+        if url.host_str().is_none_or(str::is_empty) {
+            return InviteCodeValidation::Malformed(format!(
+                "Guardian {peer} has an API URL without host: {url}"
+            ));
+        }
+    }
+
+    if let Some(api_secret) = code.api_secret()
+        && (api_secret.is_empty() || api_secret.chars().any(char::is_whitespace))
+    {
+        return InviteCodeValidation::Malformed(
+            "API secret must be non-empty and must not contain whitespace".to_string(),
+        );
+    }
+
+    InviteCodeValidation::Valid
+}
+
+/// Validates an invite code like [`validate_invite_code`] and additionally
+/// asks every guardian in it for the config of the federation, waiting at most
+/// `timeout` for each of them
+pub async fn probe_invite_code(
+    connectors: &ConnectorRegistry,
+    code: &InviteCode,
+    timeout: Duration,
+) -> InviteCodeProbe {
+    if let InviteCodeValidation::Malformed(reason) = validate_invite_code(code) {
+        return InviteCodeProbe::Malformed(reason);
+    }
+
+    let api = match DynGlobalApi::new(
+        connectors.clone(),
+        code.peers(),
+        code.api_secret().as_deref(),
+    ) {
+        Ok(api) => api,
+        Err(err) => return InviteCodeProbe::Malformed(err.to_string()),
+    };
+
+    let federation_id = code.federation_id();
+    let responses = futures::future::join_all(code.peers().into_keys().map(|peer| {
+        let api = &api;
+        async move {
+            let response = runtime::timeout(
+                timeout,
+                api.request_single_peer::<ClientConfig>(
+                    CLIENT_CONFIG_ENDPOINT.to_owned(),
+                    ApiRequestErased::default(),
+                    peer,
+                ),
+            )
+            .await;
+            (peer, response)
+        }
+    }))
+    .await;
+
+    let mut responding_peers = BTreeSet::new();
+    let mut errors = Vec::new();
+    for (peer, response) in responses {
+        match response {
+            Ok(Ok(config)) if config.calculate_federation_id() == federation_id => {
+                responding_peers.insert(peer);
+            }
+            Ok(Ok(_)) => errors.push(format!("guardian {peer} belongs to a different federation")),
+            Ok(Err(err)) => errors.push(format!("guardian {peer}: {err}")),
+            Err(_) => errors.push(format!(
+                "guardian {peer} did not respond within {}s",
+                timeout.as_secs()
+            )),
+        }
+    }
+
+    if responding_peers.is_empty() {
+        InviteCodeProbe::Unreachable(errors.join(", "))
+    } else {
+        InviteCodeProbe::Reachable { responding_peers }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fedimint_core::config::FederationId;
+    use fedimint_core::invite_code::InviteCode;
+    use fedimint_core::util::SafeUrl;
+
+    use super::{InviteCodeValidation, validate_invite_code};
+
+    fn invite_code(url: &str, api_secret: Option<&str>) -> InviteCode {
+        InviteCode::new(
+            SafeUrl::parse(url).expect("valid url"),
+            PeerId::from(0),
+            FederationId::dummy(),
+            api_secret.map(ToString::to_string),
+        )
+    }
+
+    #[test]
+    fn validates_invite_code_structure() {
+        assert!(validate_invite_code(&invite_code("wss://example.com", None)).is_valid());
+        assert!(
+            validate_invite_code(&invite_code("ws://127.0.0.1:8174", Some("secret"))).is_valid()
+        );
+
+        assert!(matches!(
+            validate_invite_code(&invite_code("http://example.com", None)),
+            InviteCodeValidation::Malformed(_)
+        ));
+        assert!(matches!(
+            validate_invite_code(&invite_code("wss://example.com", Some(""))),
+            InviteCodeValidation::Malformed(_)
+        ));
+        assert!(matches!(
+            validate_invite_code(&invite_code("wss://example.com", Some("my secret"))),
+            InviteCodeValidation::Malformed(_)
+        ));
+    }
+}
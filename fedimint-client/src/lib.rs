@@ -90,6 +90,9 @@
 /// Database keys used by the client
 pub mod db;
 
+/// Checking invite codes before joining a federation
+pub mod invite_code;
+
 /// Management of meta fields
 pub mod meta;
 
@@ -99,12 +102,20 @@
 
 pub mod sm;
 pub mod visualize;
-pub use client::Client;
-pub use client::builder::{ClientBuilder, ClientPreview, RootSecret};
-pub use client::handle::{ClientHandle, ClientHandleArc};
+pub use api_announcements::ApiEndpointsChanged;
+pub use client::builder::{
+    BuildError, ClientBuilder, ClientPreview, JoinReport, ModuleCompat, ModuleCompatReport,
+    ModuleCompatStatus, PrimaryModuleReport, PrimaryModuleSelection, RootSecret, RootSecretDeriver,
+    SkippedModule, SkippedModuleReason,
+};
+pub use client::handle::{ClientHandle, ClientHandleArc, LeaveReport};
+pub use client::{ActiveStateSummary, Client, ModuleRecoveryResult};
 pub use fedimint_client_module as module;
 /// Re-exporting of everything from `fedimint_client_module`
 ///
 /// This should be removed when the splitting of [`fedimint_client_module`] is
 /// complete.
 pub use fedimint_client_module::*;
+pub use invite_code::{
+    InviteCodeProbe, InviteCodeValidation, probe_invite_code, validate_invite_code,
+};
@@ -126,6 +126,21 @@ async fn entries_from_db(&self, db: &Database) -> Option<MetaEntries> {
         Some(entries)
     }
 
+    async fn values_from_db(&self, db: &Database) -> Option<MetaValues> {
+        let dbtx = &mut db.begin_transaction_nc().await;
+        let info = dbtx.get_value(&MetaServiceInfoKey).await?;
+        let values = dbtx
+            .find_by_prefix(&MetaFieldPrefix)
+            .await
+            .map(|(k, v)| (k.0, v.0))
+            .collect()
+            .await;
+        Some(MetaValues {
+            values,
+            revision: info.revision,
+        })
+    }
+
     async fn current_revision(&self, dbtx: &mut DatabaseTransaction<'_>) -> Option<u64> {
         dbtx.get_value(&MetaServiceInfoKey)
             .await
@@ -179,6 +194,28 @@ pub fn subscribe_to_field<'a, V: DeserializeOwned + 'static>(
         }
     }
 
+    /// NOTE: this subscription never ends even after update task is shutdown.
+    /// You should consume this stream in a spawn_cancellable.
+    ///
+    /// Stream will yield the cached meta values immediately if there are any,
+    /// and then all meta values every time they are updated.
+    pub fn subscribe_to_values<'a>(
+        &'a self,
+        db: &'a Database,
+    ) -> impl Stream<Item = MetaValues> + 'a {
+        stream! {
+            let mut update_stream = pin!(self.subscribe_to_updates());
+            loop {
+                if let Some(values) = self.values_from_db(db).await {
+                    yield values;
+                }
+                if update_stream.next().await.is_none() {
+                    break;
+                }
+            }
+        }
+    }
+
     /// Update all source in background.
     ///
     /// Caller should run this method in a task.
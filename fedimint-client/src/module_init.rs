@@ -28,6 +28,10 @@
 
 pub type ClientModuleInitRegistry = ModuleInitRegistry<DynClientModuleInit>;
 
+/// Constructs a [`DynClientModuleInit`] on demand, see
+/// [`crate::ClientBuilder::with_module_factory`]
+pub type ClientModuleInitFactory = Box<dyn Fn() -> DynClientModuleInit + Send + Sync>;
+
 #[apply(async_trait_maybe_send!)]
 pub trait IClientModuleInit: IDynCommonModuleInit + fmt::Debug + MaybeSend + MaybeSync {
     fn decoder(&self) -> Decoder;
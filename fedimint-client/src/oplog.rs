@@ -1,22 +1,23 @@
 use std::collections::HashSet;
 use std::fmt::Debug;
 use std::ops::Range;
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use fedimint_client_module::oplog::{
     IOperationLog, JsonStringed, OperationLogEntry, OperationOutcome, UpdateStreamOrOutcome,
 };
 use fedimint_core::core::OperationId;
 use fedimint_core::db::{Database, DatabaseTransaction, IDatabaseTransactionOpsCoreTyped as _};
-use fedimint_core::task::{MaybeSend, MaybeSync};
+use fedimint_core::task::{MaybeSend, MaybeSync, TaskGroup};
 use fedimint_core::time::now;
-use fedimint_core::util::BoxStream;
+use fedimint_core::util::{BoxStream, FmtCompactAnyhow as _};
 use fedimint_core::{apply, async_trait_maybe_send};
 use fedimint_logging::LOG_CLIENT;
 use futures::StreamExt as _;
-use serde::Serialize;
 use serde::de::DeserializeOwned;
-use tokio::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OnceCell, mpsc};
 use tracing::{error, instrument, warn};
 
 use crate::db::{ChronologicalOperationLogKey, OperationLogKey};
@@ -24,10 +25,196 @@
 #[cfg(test)]
 mod tests;
 
-#[derive(Debug, Clone)]
+/// How many operation log writes can wait for the [`OperationObserver`]
+/// before further ones are dropped
+const OPERATION_OBSERVER_QUEUE_LEN: usize = 1024;
+
+#[derive(Clone)]
 pub struct OperationLog {
     db: Database,
     oldest_entry: tokio::sync::OnceCell<ChronologicalOperationLogKey>,
+    observer: Option<ObserverQueue>,
+}
+
+impl Debug for OperationLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OperationLog")
+            .field("db", &self.db)
+            .field("oldest_entry", &self.oldest_entry)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Receives every write to the [`OperationLog`] after it was committed, e.g.
+/// to mirror operations to an external store for analytics
+///
+/// Observers are called on a dedicated task, one write at a time and in the
+/// order the writes were committed, so a slow observer does not delay the
+/// client. Writes are dropped with a warning while the observer falls too far
+/// behind. Errors are only logged, the operation log itself stays the source
+/// of truth.
+#[apply(async_trait_maybe_send!)]
+pub trait OperationObserver: MaybeSend + MaybeSync + 'static {
+    /// Called after a new operation was added to the log
+    async fn on_operation_created(
+        &self,
+        operation_id: OperationId,
+        entry: &OperationLogEntry,
+    ) -> anyhow::Result<()>;
+
+    /// Called after the outcome of an operation was set or updated
+    async fn on_operation_updated(
+        &self,
+        operation_id: OperationId,
+        entry: &OperationLogEntry,
+    ) -> anyhow::Result<()>;
+}
+
+/// Kind of write to the operation log an [`OperationObserver`] is notified of
+#[derive(Debug, Clone, Copy)]
+enum OperationWrite {
+    Created,
+    Updated,
+}
+
+/// An [`OperationObserver`] together with the queue of the task calling it
+#[derive(Clone)]
+struct ObserverQueue {
+    observer: Arc<dyn OperationObserver>,
+    tx: mpsc::Sender<(OperationWrite, OperationId, OperationLogEntry)>,
+}
+
+impl ObserverQueue {
+    /// Spawns the task calling `observer` in `task_group`
+    fn spawn(observer: Arc<dyn OperationObserver>, task_group: &TaskGroup) -> Self {
+        let (tx, mut rx) = mpsc::channel::<(OperationWrite, OperationId, OperationLogEntry)>(
+            OPERATION_OBSERVER_QUEUE_LEN,
+        );
+
+        task_group.spawn_cancellable("operation-observer", {
+            let observer = observer.clone();
+            async move {
+                while let Some((write, operation_id, entry)) = rx.recv().await {
+                    let result = match write {
+                        OperationWrite::Created => {
+                            observer.on_operation_created(operation_id, &entry).await
+                        }
+                        OperationWrite::Updated => {
+                            observer.on_operation_updated(operation_id, &entry).await
+                        }
+                    };
+
+                    if let Err(err) = result {
+                        warn!(
+                            target: LOG_CLIENT,
+                            operation_id = %operation_id.fmt_short(),
+                            ?write,
+                            err = %err.fmt_compact_anyhow(),
+                            "Operation observer failed"
+                        );
+                    }
+                }
+            }
+        });
+
+        Self { observer, tx }
+    }
+
+    /// Queues a committed operation log write without waiting for the
+    /// observer
+    fn notify(&self, write: OperationWrite, operation_id: OperationId, entry: OperationLogEntry) {
+        if self.tx.try_send((write, operation_id, entry)).is_err() {
+            warn!(
+                target: LOG_CLIENT,
+                operation_id = %operation_id.fmt_short(),
+                ?write,
+                "Operation observer is falling behind, dropping write"
+            );
+        }
+    }
+}
+
+/// Status of an operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationStatus {
+    /// Some state machines of the operation are still running
+    Active,
+    /// None of the state machines of the operation are running anymore
+    ///
+    /// Outcomes are module specific, so whether the operation succeeded has to
+    /// be determined from [`OperationLogEntry::outcome`].
+    Completed,
+}
+
+/// Criteria operations have to match to be listed by
+/// [`OperationLog::paginate_operations_rev_filtered`]
+#[derive(Debug, Clone, Default)]
+pub struct OperationFilter {
+    /// Only list operations created by this module kind
+    pub module_kind: Option<String>,
+    pub status: Option<OperationStatus>,
+    /// Only list operations created at or after this time
+    pub created_after: Option<SystemTime>,
+    /// Only list operations created before this time
+    pub created_before: Option<SystemTime>,
+}
+
+impl OperationFilter {
+    fn matches(&self, key: &ChronologicalOperationLogKey, summary: &OperationSummary) -> bool {
+        self.module_kind
+            .as_ref()
+            .is_none_or(|module_kind| *module_kind == summary.module_kind)
+            && self.status.is_none_or(|status| status == summary.status)
+            && self
+                .created_after
+                .is_none_or(|created_after| created_after <= key.creation_time)
+            && self
+                .created_before
+                .is_none_or(|created_before| key.creation_time < created_before)
+    }
+}
+
+/// Overview of an operation for history screens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperationSummary {
+    pub operation_id: OperationId,
+    pub creation_time: SystemTime,
+    pub module_kind: String,
+    pub status: OperationStatus,
+    /// Module kind and, if the meta data of the operation contains one, the
+    /// name of its variant, e.g. `ln pay`
+    pub description: String,
+}
+
+impl OperationSummary {
+    fn new(key: ChronologicalOperationLogKey, entry: &OperationLogEntry, active: bool) -> Self {
+        let module_kind = entry.operation_module_kind().to_string();
+        let variant = entry.try_meta::<serde_json::Value>().ok().and_then(|meta| {
+            match meta.get("variant")? {
+                serde_json::Value::String(variant) => Some(variant.clone()),
+                serde_json::Value::Object(variant) if variant.len() == 1 => {
+                    variant.keys().next().cloned()
+                }
+                _ => None,
+            }
+        });
+
+        Self {
+            operation_id: key.operation_id,
+            creation_time: key.creation_time,
+            description: match variant {
+                Some(variant) => format!("{module_kind} {variant}"),
+                None => format!("{module_kind} operation"),
+            },
+            module_kind,
+            status: if active {
+                OperationStatus::Active
+            } else {
+                OperationStatus::Completed
+            },
+        }
+    }
 }
 
 impl OperationLog {
@@ -35,9 +222,25 @@ pub fn new(db: Database) -> Self {
         Self {
             db,
             oldest_entry: OnceCell::new(),
+            observer: None,
         }
     }
 
+    /// Notify `observer` of every operation created or updated through this
+    /// log from a task spawned in `task_group`
+    pub fn with_observer(
+        mut self,
+        observer: Option<Arc<dyn OperationObserver>>,
+        task_group: &TaskGroup,
+    ) -> Self {
+        self.observer = observer.map(|observer| ObserverQueue::spawn(observer, task_group));
+        self
+    }
+
+    pub(crate) fn observer(&self) -> Option<Arc<dyn OperationObserver>> {
+        self.observer.as_ref().map(|queue| queue.observer.clone())
+    }
+
     /// Will return the oldest operation log key in the database and cache the
     /// result. If no entry exists yet the DB will be queried on each call till
     /// an entry is present.
@@ -64,16 +267,14 @@ pub async fn add_operation_log_entry_dbtx(
         operation_type: &str,
         operation_meta: impl serde::Serialize,
     ) {
+        let operation_meta = JsonStringed(
+            serde_json::to_value(operation_meta)
+                .expect("Can only fail if meta is not serializable"),
+        );
+
         dbtx.insert_new_entry(
             &OperationLogKey { operation_id },
-            &OperationLogEntry::new(
-                operation_type.to_string(),
-                JsonStringed(
-                    serde_json::to_value(operation_meta)
-                        .expect("Can only fail if meta is not serializable"),
-                ),
-                None,
-            ),
+            &OperationLogEntry::new(operation_type.to_string(), operation_meta.clone(), None),
         )
         .await;
         dbtx.insert_new_entry(
@@ -84,6 +285,14 @@ pub async fn add_operation_log_entry_dbtx(
             &(),
         )
         .await;
+
+        if let Some(observer) = self.observer.clone() {
+            let operation_type = operation_type.to_string();
+            dbtx.on_commit(move || {
+                let entry = OperationLogEntry::new(operation_type, operation_meta, None);
+                observer.notify(OperationWrite::Created, operation_id, entry);
+            });
+        }
     }
 
     #[deprecated(since = "0.6.0", note = "Use `paginate_operations_rev` instead")]
@@ -160,6 +369,69 @@ pub async fn paginate_operations_rev(
         operation_log_entries
     }
 
+    /// Returns up to `limit` summaries of the operations matching `filter`,
+    /// newest first, starting after `last_seen`
+    ///
+    /// Operations in `active_operations` are reported as
+    /// [`OperationStatus::Active`], all others as completed. Also returns the
+    /// key to pass as `last_seen` to fetch the next page, or `None` if there
+    /// are no further matching operations. A `limit` of 0 returns `last_seen`
+    /// unchanged without reading the log.
+    pub async fn paginate_operations_rev_filtered(
+        &self,
+        filter: &OperationFilter,
+        active_operations: &HashSet<OperationId>,
+        limit: usize,
+        last_seen: Option<ChronologicalOperationLogKey>,
+    ) -> (Vec<OperationSummary>, Option<ChronologicalOperationLogKey>) {
+        const BATCH_SIZE: usize = 100;
+
+        if limit == 0 {
+            return (Vec::new(), last_seen);
+        }
+
+        let mut last_seen = last_seen.or_else(|| {
+            filter
+                .created_before
+                .map(|created_before| ChronologicalOperationLogKey {
+                    creation_time: created_before,
+                    operation_id: OperationId([0; 32]),
+                })
+        });
+        let mut summaries = Vec::new();
+        loop {
+            let batch = self.paginate_operations_rev(BATCH_SIZE, last_seen).await;
+            let exhausted = batch.len() < BATCH_SIZE;
+            for (key, entry) in batch {
+                // Operations are returned newest first, so none of the
+                // remaining ones can match
+                if filter
+                    .created_after
+                    .is_some_and(|created_after| key.creation_time < created_after)
+                {
+                    return (summaries, None);
+                }
+
+                last_seen = Some(key);
+                let summary = OperationSummary::new(
+                    key,
+                    &entry,
+                    active_operations.contains(&key.operation_id),
+                );
+                if filter.matches(&key, &summary) {
+                    summaries.push(summary);
+                    if limit <= summaries.len() {
+                        return (summaries, last_seen);
+                    }
+                }
+            }
+
+            if exhausted {
+                return (summaries, None);
+            }
+        }
+    }
+
     pub async fn get_operation(&self, operation_id: OperationId) -> Option<OperationLogEntry> {
         Self::get_operation_dbtx(
             &mut self.db.begin_transaction_nc().await.into_nc(),
@@ -181,6 +453,15 @@ pub async fn set_operation_outcome(
         db: &Database,
         operation_id: OperationId,
         outcome: &(impl Serialize + Debug),
+    ) -> anyhow::Result<()> {
+        Self::set_operation_outcome_observed(db, None, operation_id, outcome).await
+    }
+
+    async fn set_operation_outcome_observed(
+        db: &Database,
+        observer: Option<ObserverQueue>,
+        operation_id: OperationId,
+        outcome: &(impl Serialize + Debug),
     ) -> anyhow::Result<()> {
         let outcome_json =
             JsonStringed(serde_json::to_value(outcome).expect("Outcome is not serializable"));
@@ -197,6 +478,10 @@ pub async fn set_operation_outcome(
             .await;
         dbtx.commit_tx_result().await?;
 
+        if let Some(observer) = observer {
+            observer.notify(OperationWrite::Updated, operation_id, operation);
+        }
+
         Ok(())
     }
 
@@ -233,7 +518,18 @@ pub async fn optimistically_set_operation_outcome(
         operation_id: OperationId,
         outcome: &(impl Serialize + Debug),
     ) {
-        if let Err(e) = Self::set_operation_outcome(db, operation_id, outcome).await {
+        Self::optimistically_set_operation_outcome_observed(db, None, operation_id, outcome).await;
+    }
+
+    async fn optimistically_set_operation_outcome_observed(
+        db: &Database,
+        observer: Option<ObserverQueue>,
+        operation_id: OperationId,
+        outcome: &(impl Serialize + Debug),
+    ) {
+        if let Err(e) =
+            Self::set_operation_outcome_observed(db, observer, operation_id, outcome).await
+        {
             warn!(
                 target: LOG_CLIENT,
                 "Error setting operation outcome: {e}"
@@ -280,11 +576,14 @@ fn outcome_or_updates(
         operation: OperationLogEntry,
         stream_gen: Box<dyn FnOnce() -> BoxStream<'static, serde_json::Value>>,
     ) -> UpdateStreamOrOutcome<serde_json::Value> {
-        match OperationLog::outcome_or_updates(db, operation_id, operation, stream_gen) {
-            UpdateStreamOrOutcome::UpdateStream(pin) => UpdateStreamOrOutcome::UpdateStream(pin),
-            UpdateStreamOrOutcome::Outcome(o) => {
-                UpdateStreamOrOutcome::Outcome(serde_json::from_value(o).expect("Can't fail"))
-            }
+        match operation.outcome::<serde_json::Value>() {
+            Some(outcome) => UpdateStreamOrOutcome::Outcome(outcome),
+            None => UpdateStreamOrOutcome::UpdateStream(observed_caching_operation_update_stream(
+                db.clone(),
+                self.observer.clone(),
+                operation_id,
+                stream_gen(),
+            )),
         }
     }
 }
@@ -353,6 +652,19 @@ pub fn caching_operation_update_stream<'a, U, S>(
     operation_id: OperationId,
     stream: S,
 ) -> BoxStream<'a, U>
+where
+    U: Clone + Serialize + Debug + MaybeSend + MaybeSync + 'static,
+    S: futures::Stream<Item = U> + MaybeSend + 'a,
+{
+    observed_caching_operation_update_stream(db, None, operation_id, stream)
+}
+
+fn observed_caching_operation_update_stream<'a, U, S>(
+    db: Database,
+    observer: Option<ObserverQueue>,
+    operation_id: OperationId,
+    stream: S,
+) -> BoxStream<'a, U>
 where
     U: Clone + Serialize + Debug + MaybeSend + MaybeSync + 'static,
     S: futures::Stream<Item = U> + MaybeSend + 'a,
@@ -373,6 +685,12 @@ pub fn caching_operation_update_stream<'a, U, S>(
             return;
         };
 
-        OperationLog::optimistically_set_operation_outcome(&db, operation_id, &last_update).await;
+        OperationLog::optimistically_set_operation_outcome_observed(
+            &db,
+            observer,
+            operation_id,
+            &last_update,
+        )
+        .await;
     })
 }
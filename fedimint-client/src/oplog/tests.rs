@@ -1,18 +1,27 @@
+use std::collections::HashSet;
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
 use assert_matches::assert_matches;
-use fedimint_client_module::oplog::{JsonStringed, OperationOutcome, UpdateStreamOrOutcome};
+use fedimint_client_module::oplog::{
+    IOperationLog, JsonStringed, OperationOutcome, UpdateStreamOrOutcome,
+};
 use fedimint_core::core::OperationId;
 use fedimint_core::db::mem_impl::MemDatabase;
 use fedimint_core::db::{
     Database, DatabaseTransaction, IDatabaseTransactionOpsCoreTyped, IRawDatabaseExt,
 };
 use fedimint_core::module::registry::ModuleRegistry;
+use fedimint_core::task::TaskGroup;
+use fedimint_core::{apply, async_trait_maybe_send};
 use futures::stream::StreamExt;
 use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
 
 use crate::db::{ChronologicalOperationLogKey, OperationLogKey};
-use crate::oplog::{OperationLog, OperationLogEntry};
+use crate::oplog::{
+    OperationFilter, OperationLog, OperationLogEntry, OperationObserver, OperationStatus,
+};
 
 #[test]
 fn test_operation_log_entry_serde() {
@@ -121,6 +130,97 @@ async fn test_operation_log_update_from_stream() {
     );
 }
 
+/// Forwards every notification to a channel and then fails, the operation log
+/// must not be affected by the failure
+struct ChannelObserver(mpsc::UnboundedSender<(&'static str, OperationId, Option<String>)>);
+
+#[apply(async_trait_maybe_send!)]
+impl OperationObserver for ChannelObserver {
+    async fn on_operation_created(
+        &self,
+        operation_id: OperationId,
+        entry: &OperationLogEntry,
+    ) -> anyhow::Result<()> {
+        self.0
+            .send(("created", operation_id, entry.outcome::<String>()))?;
+        anyhow::bail!("observer failure")
+    }
+
+    async fn on_operation_updated(
+        &self,
+        operation_id: OperationId,
+        entry: &OperationLogEntry,
+    ) -> anyhow::Result<()> {
+        self.0
+            .send(("updated", operation_id, entry.outcome::<String>()))?;
+        anyhow::bail!("observer failure")
+    }
+}
+
+#[tokio::test]
+async fn test_operation_observer() {
+    let op_id = OperationId([0x32; 32]);
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let db = MemDatabase::new().into_database();
+    let task_group = TaskGroup::new();
+    let op_log = OperationLog::new(db.clone())
+        .with_observer(Some(Arc::new(ChannelObserver(tx))), &task_group);
+
+    // Nothing is observed if the write is not committed
+    let mut dbtx = db.begin_transaction().await;
+    op_log
+        .add_operation_log_entry_dbtx(&mut dbtx.to_ref_nc(), op_id, "foo", "bar")
+        .await;
+    dbtx.ignore_uncommitted();
+    drop(dbtx);
+
+    let mut dbtx = db.begin_transaction().await;
+    op_log
+        .add_operation_log_entry_dbtx(&mut dbtx.to_ref_nc(), op_id, "foo", "bar")
+        .await;
+    dbtx.commit_tx().await;
+
+    assert_eq!(rx.recv().await, Some(("created", op_id, None)));
+
+    let op = op_log.get_operation(op_id).await.expect("op exists");
+    let update_stream = IOperationLog::outcome_or_updates(
+        &op_log,
+        &db,
+        op_id,
+        op,
+        Box::new(|| futures::stream::iter(vec![serde_json::json!("baz")]).boxed()),
+    );
+    assert_eq!(
+        update_stream.into_stream().collect::<Vec<_>>().await.len(),
+        1
+    );
+
+    assert_eq!(
+        rx.recv().await,
+        Some(("updated", op_id, Some("baz".to_string())))
+    );
+
+    let op = op_log.get_operation(op_id).await.expect("op exists");
+    assert_eq!(op.outcome::<String>(), Some("baz".to_string()));
+
+    // Writes are observed in the order they were committed
+    let op_ids = (0..10).map(|i| OperationId([i; 32])).collect::<Vec<_>>();
+    for op_id in &op_ids {
+        let mut dbtx = db.begin_transaction().await;
+        op_log
+            .add_operation_log_entry_dbtx(&mut dbtx.to_ref_nc(), *op_id, "foo", "bar")
+            .await;
+        dbtx.commit_tx().await;
+    }
+
+    for op_id in op_ids {
+        assert_eq!(rx.recv().await, Some(("created", op_id, None)));
+    }
+
+    task_group.shutdown_join_all(None).await.unwrap();
+}
+
 #[tokio::test]
 async fn test_pagination() {
     fn assert_page_entries(
@@ -168,6 +268,70 @@ fn assert_page_entries(
     assert_page_entries(page, 9);
 }
 
+#[tokio::test]
+async fn test_pagination_filtered() {
+    let db = Database::new(MemDatabase::new(), ModuleRegistry::default());
+    let op_log = OperationLog::new(db.clone());
+
+    for operation_idx in 0u8..30 {
+        let mut dbtx = db.begin_transaction().await;
+        op_log
+            .add_operation_log_entry_dbtx(
+                &mut dbtx.to_ref_nc(),
+                OperationId([operation_idx; 32]),
+                if operation_idx % 2 == 0 { "foo" } else { "bar" },
+                operation_idx,
+            )
+            .await;
+        dbtx.commit_tx().await;
+    }
+
+    // Only the "foo" operations still have running state machines
+    let active_operations = (0u8..30)
+        .step_by(2)
+        .map(|operation_idx| OperationId([operation_idx; 32]))
+        .collect::<HashSet<_>>();
+
+    let filter = OperationFilter {
+        module_kind: Some("foo".to_string()),
+        status: Some(OperationStatus::Active),
+        ..OperationFilter::default()
+    };
+
+    let (page, next) = op_log
+        .paginate_operations_rev_filtered(&filter, &active_operations, 0, None)
+        .await;
+    assert!(page.is_empty());
+    assert!(next.is_none());
+
+    let (page, next) = op_log
+        .paginate_operations_rev_filtered(&filter, &active_operations, 10, None)
+        .await;
+    assert_eq!(page.len(), 10);
+    assert!(next.is_some());
+    assert!(page.iter().all(|summary| summary.module_kind == "foo"));
+    assert_eq!(page[0].operation_id, OperationId([28; 32]));
+    assert_eq!(page[0].description, "foo operation");
+
+    let (page, next) = op_log
+        .paginate_operations_rev_filtered(&filter, &active_operations, 10, next)
+        .await;
+    assert_eq!(page.len(), 5);
+    assert!(next.is_none());
+    assert_eq!(page[4].operation_id, OperationId([0; 32]));
+
+    let filter = OperationFilter {
+        status: Some(OperationStatus::Completed),
+        ..OperationFilter::default()
+    };
+    let (page, next) = op_log
+        .paginate_operations_rev_filtered(&filter, &active_operations, 20, None)
+        .await;
+    assert_eq!(page.len(), 15);
+    assert!(next.is_none());
+    assert!(page.iter().all(|summary| summary.module_kind == "bar"));
+}
+
 #[tokio::test]
 async fn test_pagination_empty() {
     let db = Database::new(MemDatabase::new(), ModuleRegistry::default());
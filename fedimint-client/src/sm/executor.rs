@@ -4,7 +4,8 @@
 use std::io::{Error, Write};
 use std::mem;
 use std::sync::Arc;
-use std::time::Duration;
+use std::task::Poll;
+use std::time::{Duration, SystemTime};
 
 use anyhow::anyhow;
 use fedimint_client_module::sm::executor::{
@@ -24,28 +25,35 @@
 use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::task::TaskGroup;
 use fedimint_core::util::{BoxFuture, FmtCompactAnyhow as _};
-use fedimint_core::{apply, async_trait_maybe_send};
+use fedimint_core::{apply, async_trait_maybe_send, impl_db_record, maybe_add_send_sync};
 use fedimint_eventlog::{DBTransactionEventLogExt as _, Event, EventKind, EventPersistence};
 use fedimint_logging::LOG_CLIENT_REACTOR;
 use futures::future::{self, select_all};
 use futures::stream::{FuturesUnordered, StreamExt};
 use serde::{Deserialize, Serialize};
 use tokio::select;
-use tokio::sync::{mpsc, oneshot, watch};
+use tokio::sync::{Notify, mpsc, oneshot, watch};
 use tracing::{Instrument, debug, error, info, trace, warn};
 
+use self::watch_only::WatchOnlyGlobalContext;
 use crate::sm::notifier::Notifier;
 use crate::{AddStateMachinesError, AddStateMachinesResult, DynGlobalClientContext};
 
 /// After how many attempts a DB transaction is aborted with an error
 const MAX_DB_ATTEMPTS: Option<usize> = Some(100);
 
+/// How many transitions can wait for the [`StateTransitionHook`] before
+/// further ones are dropped
+const STATE_TRANSITION_HOOK_QUEUE_LEN: usize = 1024;
+
 /// Prefixes for executor DB entries
 pub(crate) enum ExecutorDbPrefixes {
     /// See [`ActiveStateKey`]
     ActiveStates = 0xa1,
     /// See [`InactiveStateKey`]
     InactiveStates = 0xa2,
+    /// See [`CompactedOperationKey`]
+    CompactedOperations = 0xa3,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -62,6 +70,15 @@ impl Event for StateMachineUpdated {
     const PERSISTENCE: EventPersistence = EventPersistence::Trimable;
 }
 
+/// Called by the [`Executor`] after every committed state transition with the
+/// operation id, the old and the new state
+///
+/// Runs on a dedicated task, one transition at a time, so a slow hook doesn't
+/// hold up state machines. Transitions are dropped with a
+/// warning while the hook falls too far behind.
+pub type StateTransitionHook =
+    Arc<maybe_add_send_sync!(dyn Fn(OperationId, &DynState, &DynState) + 'static)>;
+
 /// Executor that drives forward state machines under its management.
 ///
 /// Each state transition is atomic and supposed to be idempotent such that a
@@ -83,8 +100,16 @@ struct ExecutorInner {
     /// Any time executor should notice state machine update (e.g. because it
     /// was created), it's must be sent through this channel for it to notice.
     sm_update_tx: mpsc::UnboundedSender<DynState>,
+    /// Number of state machines sent through `sm_update_tx` or executing a
+    /// transition, so not just waiting for one of their triggers
+    busy_states: Arc<watch::Sender<usize>>,
     client_task_group: TaskGroup,
     log_ordering_wakeup_tx: watch::Sender<()>,
+    /// Queue of the task calling the [`StateTransitionHook`], if one is set
+    state_transition_hook_tx: Option<mpsc::Sender<(OperationId, DynState, DynState)>>,
+    /// Park state machines instead of letting them submit transactions, see
+    /// [`ExecutorBuilder::with_watch_only`]
+    watch_only: bool,
 }
 
 enum ExecutorState {
@@ -165,10 +190,21 @@ fn gen_context(&self, state: &DynState) -> Option<DynGlobalClientContext> {
 
 /// Builder to which module clients can be attached and used to build an
 /// [`Executor`] supporting these.
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct ExecutorBuilder {
     module_contexts: BTreeMap<ModuleInstanceId, DynContext>,
     valid_module_ids: BTreeSet<ModuleInstanceId>,
+    state_transition_hook: Option<StateTransitionHook>,
+    watch_only: bool,
+}
+
+impl Debug for ExecutorBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecutorBuilder")
+            .field("module_contexts", &self.module_contexts)
+            .field("valid_module_ids", &self.valid_module_ids)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Executor {
@@ -181,6 +217,90 @@ pub async fn get_active_states(&self) -> Vec<(DynState, ActiveStateMeta)> {
         self.inner.get_active_states().await
     }
 
+    /// Returns the operations without any active state whose inactive states
+    /// all exited before `exited_before`
+    pub async fn operations_inactive_since(
+        &self,
+        exited_before: SystemTime,
+    ) -> BTreeSet<OperationId> {
+        let mut dbtx = self.inner.db.begin_transaction_nc().await;
+        let active_operations = dbtx
+            .find_by_prefix(&ActiveStateKeyPrefix)
+            .await
+            .map(|(state, _)| state.0.state.operation_id())
+            .collect::<BTreeSet<_>>()
+            .await;
+
+        // Latest exit of each operation, without holding all states in memory
+        let last_exits = dbtx
+            .find_by_prefix(&InactiveStateKeyPrefix)
+            .await
+            .fold(
+                BTreeMap::<OperationId, SystemTime>::new(),
+                |mut last_exits, (state, meta)| async move {
+                    let last_exit = last_exits
+                        .entry(state.0.state.operation_id())
+                        .or_insert(meta.exited_at);
+                    *last_exit = (*last_exit).max(meta.exited_at);
+                    last_exits
+                },
+            )
+            .await;
+
+        last_exits
+            .into_iter()
+            .filter(|(operation_id, last_exit)| {
+                *last_exit < exited_before && !active_operations.contains(operation_id)
+            })
+            .map(|(operation_id, _)| operation_id)
+            .collect()
+    }
+
+    /// Removes all inactive states of an operation, returning how many were
+    /// removed
+    ///
+    /// The operation is marked as compacted, so that
+    /// [`Self::add_state_machines_dbtx`] refuses to start any of its states
+    /// again, which would otherwise no longer be detected as duplicates.
+    pub async fn remove_inactive_operation_states(&self, operation_id: OperationId) -> usize {
+        let mut dbtx = self.inner.db.begin_transaction().await;
+        let states = dbtx
+            .find_by_prefix(&InactiveOperationStateKeyPrefix { operation_id })
+            .await
+            .map(|(state, _)| state)
+            .collect::<Vec<_>>()
+            .await;
+
+        for state in &states {
+            dbtx.remove_entry(state).await;
+        }
+        dbtx.insert_entry(&CompactedOperationKey { operation_id }, &())
+            .await;
+        dbtx.commit_tx().await;
+
+        states.len()
+    }
+
+    /// Returns `true` if no state machine is waiting to be picked up or
+    /// executing a transition
+    ///
+    /// Active state machines that are only waiting for one of their triggers
+    /// (e.g. a response from the federation) don't prevent the executor from
+    /// being idle.
+    pub fn is_idle(&self) -> bool {
+        *self.inner.busy_states.borrow() == 0
+    }
+
+    /// Wait until the executor is idle, see [`Self::is_idle`]
+    pub async fn wait_idle(&self) {
+        self.inner
+            .busy_states
+            .subscribe()
+            .wait_for(|busy_states| *busy_states == 0)
+            .await
+            .expect("Sender is owned by the executor");
+    }
+
     /// Adds a number of state machines to the executor atomically. They will be
     /// driven to completion automatically in the background.
     ///
@@ -240,7 +360,14 @@ pub async fn add_state_machines_dbtx(
                 .await
                 .is_some();
 
-            if is_active_state || is_inactive_state {
+            let is_compacted_operation = dbtx
+                .get_value(&CompactedOperationKey {
+                    operation_id: state.operation_id(),
+                })
+                .await
+                .is_some();
+
+            if is_active_state || is_inactive_state || is_compacted_operation {
                 return Err(AddStateMachinesError::StateAlreadyExists);
             }
 
@@ -248,22 +375,24 @@ pub async fn add_state_machines_dbtx(
             // so we can't check if the state is terminal. However the
             // [`Self::get_transitions_for`] function will double check and
             // deactivate any terminal states that would slip past this check.
+            let mut sm_context = None;
             if let Some(module_context) =
                 self.inner.module_contexts.get(&state.module_instance_id())
             {
-                match self
+                let global_context = self
                     .inner
                     .state
                     .read()
                     .expect("locking failed")
-                    .gen_context(&state)
-                {
+                    .gen_context(&state);
+                match global_context {
                     Some(context) => {
                         if state.is_terminal(module_context, &context) {
                             return Err(AddStateMachinesError::Other(anyhow!(
                                 "State is already terminal, adding it to the executor doesn't make sense."
                             )));
                         }
+                        sm_context = Some((module_context, context));
                     }
                     _ => {
                         warn!(target: LOG_CLIENT_REACTOR, "Executor should be running at this point");
@@ -277,6 +406,21 @@ pub async fn add_state_machines_dbtx(
             )
             .await;
 
+            // Without the module being initialized (recovery) there is no context to
+            // call the hook with, so it's skipped for these states.
+            if let Some((module_context, global_context)) = sm_context {
+                state
+                    .on_enter(
+                        module_context,
+                        &global_context,
+                        &mut ClientSMDatabaseTransaction::new(
+                            &mut dbtx.to_ref(),
+                            state.module_instance_id(),
+                        ),
+                    )
+                    .await;
+            }
+
             let operation_id = state.operation_id();
             self.inner
                 .log_event_dbtx(
@@ -292,9 +436,13 @@ pub async fn add_state_machines_dbtx(
 
             let notify_sender = self.inner.notifier.sender();
             let sm_updates_tx = self.inner.sm_update_tx.clone();
+            let busy_states = self.inner.busy_states.clone();
             dbtx.on_commit(move || {
                 notify_sender.notify(state.clone());
-                let _ = sm_updates_tx.send(state);
+                busy_states.send_modify(|busy_states| *busy_states += 1);
+                if sm_updates_tx.send(state).is_err() {
+                    busy_states.send_modify(|busy_states| *busy_states -= 1);
+                }
             });
         }
 
@@ -467,6 +615,9 @@ struct TransitionForActiveState {
     state: DynState,
     meta: ActiveStateMeta,
     transition_fn: StateTransitionFunction<DynState>,
+    /// Set if the transition runs in watch-only mode, fires once it tried to
+    /// submit a transaction
+    spend_attempted: Option<Arc<Notify>>,
 }
 
 impl ExecutorInner {
@@ -499,14 +650,22 @@ async fn get_transition_for(
             .module_contexts
             .get(&module_instance)
             .expect("Unknown module");
-        let transitions = state
-            .transitions(
-                context,
-                &global_context_gen(module_instance, state.operation_id()),
+        let global_context = global_context_gen(module_instance, state.operation_id());
+        let (global_context, spend_attempted) = if self.watch_only {
+            let spend_attempted = Arc::new(Notify::new());
+            (
+                WatchOnlyGlobalContext::new(global_context, spend_attempted.clone()).into(),
+                Some(spend_attempted),
             )
+        } else {
+            (global_context, None)
+        };
+        let transitions = state
+            .transitions(context, &global_context)
             .into_iter()
             .map(|transition| {
                 let state = state.clone();
+                let spend_attempted = spend_attempted.clone();
                 let f: BoxFuture<TransitionForActiveState> = Box::pin(async move {
                     let StateTransition {
                         trigger,
@@ -517,6 +676,7 @@ async fn get_transition_for(
                         state,
                         transition_fn: transition,
                         meta,
+                        spend_attempted,
                     }
                 });
                 f
@@ -565,10 +725,14 @@ enum ExecutorLoopEvent {
             /// usually added to the list of pending futures.
             New { state: DynState },
             /// One of trigger futures of a state machine finished and
-            /// returned transition function to run
+            /// returned transition function to run, the state machine is
+            /// already counted as busy
             Triggered(TransitionForActiveState),
             /// The state machine did not need to run, so it was canceled
             Invalid { state: DynState },
+            /// The transition tried to submit a transaction in watch-only mode
+            /// and was abandoned, leaving the state machine active
+            Parked { state: DynState },
             /// Transition function and all the accounting around it are done
             Completed {
                 state: DynState,
@@ -599,6 +763,8 @@ enum ExecutorLoopEvent {
                     "Active state machine has been running for over a week, possibly stuck",
                 );
             }
+            self.busy_states
+                .send_modify(|busy_states| *busy_states += 1);
             self.sm_update_tx
                 .send(state)
                 .expect("Must be able to send state machine to own opened channel");
@@ -638,6 +804,7 @@ enum ExecutorLoopEvent {
                 ExecutorLoopEvent::New { state } => {
                     if currently_running_sms.contains(&state) {
                         warn!(target: LOG_CLIENT_REACTOR, operation_id = %state.operation_id().fmt_short(), "Received a state machine that is already running. Ignoring");
+                        self.finish_busy_state();
                         continue;
                     }
                     currently_running_sms.insert(state.clone());
@@ -647,6 +814,7 @@ enum ExecutorLoopEvent {
                     futures.push(Box::pin(async move {
                         let Some(meta) = self.get_active_state(&state).await else {
                             warn!(target: LOG_CLIENT_REACTOR, operation_id = %state.operation_id().fmt_short(), "Couldn't look up received state machine. Ignoring.");
+                            self.finish_busy_state();
                             return ExecutorLoopEvent::Invalid { state: state.clone() };
                         };
 
@@ -655,14 +823,30 @@ enum ExecutorLoopEvent {
                             .await;
                         if transitions.is_empty() {
                             warn!(target: LOG_CLIENT_REACTOR, operation_id = %state.operation_id().fmt_short(), "Received an active state that doesn't produce any transitions. Ignoring.");
+                            self.finish_busy_state();
                             return ExecutorLoopEvent::Invalid { state: state.clone() };
                         }
                         let transitions_num = transitions.len();
 
-                        debug!(target: LOG_CLIENT_REACTOR, operation_id = %state.operation_id().fmt_short(), total = futures_len + 1, transitions_num, "New active state machine.");
+                        debug!(target: LOG_CLIENT_REACTOR, operation_id = %state.operation_id().fmt_short(), state = state.state_name(), total = futures_len + 1, transitions_num, "New active state machine.");
 
-                        let (first_completed_result, _index, _unused_transitions) =
-                            select_all(transitions).await;
+                        // A trigger that is ready right away transitions the state machine without
+                        // waiting on anything, so it stays busy until the transition is done.
+                        // Otherwise it is only waiting for its triggers until one of them fires.
+                        let mut triggers = select_all(transitions);
+                        let first_completed_result = match futures::poll!(&mut triggers) {
+                            Poll::Ready((first_completed_result, _index, _unused_transitions)) => {
+                                first_completed_result
+                            }
+                            Poll::Pending => {
+                                self.finish_busy_state();
+                                let (first_completed_result, _index, _unused_transitions) =
+                                    triggers.await;
+                                self.busy_states
+                                    .send_modify(|busy_states| *busy_states += 1);
+                                first_completed_result
+                            }
+                        };
                         ExecutorLoopEvent::Triggered(first_completed_result)
                     }));
                 }
@@ -671,10 +855,12 @@ enum ExecutorLoopEvent {
                     state,
                     meta,
                     transition_fn,
+                    spend_attempted,
                 }) => {
                     debug!(
                         target: LOG_CLIENT_REACTOR,
                         operation_id = %state.operation_id().fmt_short(),
+                        state = state.state_name(),
                         "Triggered state transition",
                     );
                     let span = tracing::debug_span!(
@@ -709,7 +895,7 @@ enum ExecutorLoopEvent {
                                 let module_contexts = &module_contexts;
                                 let global_context_gen = &global_context_gen;
 
-                                let outcome = db
+                                let transition = db
                                     .autocommit::<'_, '_, _, _, Infallible>(
                                         |dbtx, _| {
                                             let state = state.clone();
@@ -757,6 +943,17 @@ enum ExecutorLoopEvent {
                                                     }
                                                 ).await;
 
+                                                new_state
+                                                    .on_enter(
+                                                        context,
+                                                        &global_context,
+                                                        &mut ClientSMDatabaseTransaction::new(
+                                                            &mut dbtx.to_ref(),
+                                                            state_module_instance_id,
+                                                        ),
+                                                    )
+                                                    .await;
+
                                                 if is_terminal {
                                                     let k = InactiveStateKey::from_state(
                                                         new_state.clone(),
@@ -780,9 +977,26 @@ enum ExecutorLoopEvent {
                                             })
                                         },
                                         None,
-                                    )
-                                    .await
-                                    .expect("autocommit should keep trying to commit (max_attempt: None) and body doesn't return errors");
+                                    );
+                                // Dropping the transition rolls back everything it wrote so far
+                                let parked = async {
+                                    match &spend_attempted {
+                                        Some(spend_attempted) => spend_attempted.notified().await,
+                                        None => future::pending().await,
+                                    }
+                                };
+                                let outcome = select! {
+                                    outcome = transition => outcome
+                                        .expect("autocommit should keep trying to commit (max_attempt: None) and body doesn't return errors"),
+                                    () = parked => {
+                                        info!(
+                                            target: LOG_CLIENT_REACTOR,
+                                            state = state.state_name(),
+                                            "Parked state machine trying to submit a transaction in watch-only mode",
+                                        );
+                                        return ExecutorLoopEvent::Parked { state };
+                                    }
+                                };
 
                                 debug!(
                                     target: LOG_CLIENT_REACTOR,
@@ -791,8 +1005,25 @@ enum ExecutorLoopEvent {
                                     "State transition complete",
                                 );
 
+                                if let Some(hook_tx) = &self.state_transition_hook_tx
+                                    && hook_tx
+                                        .try_send((
+                                            state.operation_id(),
+                                            state.clone(),
+                                            outcome.state().clone(),
+                                        ))
+                                        .is_err()
+                                {
+                                    warn!(
+                                        target: LOG_CLIENT_REACTOR,
+                                        "State transition hook is falling behind, dropping transition",
+                                    );
+                                }
+
                                 match &outcome {
                                     ActiveOrInactiveState::Active { dyn_state, meta: _ } => {
+                                        self.busy_states
+                                            .send_modify(|busy_states| *busy_states += 1);
                                         sm_update_tx
                                             .send(dyn_state.clone())
                                             .expect("can't fail: we are the receiving end");
@@ -820,11 +1051,19 @@ enum ExecutorLoopEvent {
                     );
                 }
 
+                ExecutorLoopEvent::Parked { state } => {
+                    assert!(
+                        currently_running_sms.remove(&state),
+                        "State must have been recorded"
+                    );
+                    self.finish_busy_state();
+                }
                 ExecutorLoopEvent::Completed { state, outcome } => {
                     assert!(
                         currently_running_sms.remove(&state),
                         "State must have been recorded"
                     );
+                    self.finish_busy_state();
                     debug!(
                         target: LOG_CLIENT_REACTOR,
                         operation_id = %state.operation_id().fmt_short(),
@@ -849,6 +1088,13 @@ enum ExecutorLoopEvent {
         Ok(())
     }
 
+    /// Record that a state machine counted in `busy_states` is done being
+    /// processed
+    fn finish_busy_state(&self) {
+        self.busy_states
+            .send_modify(|busy_states| *busy_states = busy_states.saturating_sub(1));
+    }
+
     async fn get_active_states(&self) -> Vec<(DynState, ActiveStateMeta)> {
         self.db
             .begin_transaction_nc()
@@ -956,6 +1202,21 @@ pub fn with_valid_module_id(&mut self, module_id: ModuleInstanceId) {
         self.valid_module_ids.insert(module_id);
     }
 
+    /// Call `hook` after every committed state transition
+    pub fn with_state_transition_hook(&mut self, hook: StateTransitionHook) {
+        self.state_transition_hook = Some(hook);
+    }
+
+    /// Don't let state machines submit transactions
+    ///
+    /// A transition that tries to claim inputs or fund outputs is abandoned
+    /// before it commits and its state machine is parked: it stays active in
+    /// the database, but is not driven any further until the executor is
+    /// started again without this option.
+    pub fn with_watch_only(&mut self) {
+        self.watch_only = true;
+    }
+
     /// Build [`Executor`] and spawn background task in `tasks` executing active
     /// state machines. The supplied database `db` must support isolation, so
     /// cannot be an isolated DB instance itself.
@@ -968,6 +1229,17 @@ pub fn build(
     ) -> Executor {
         let (sm_update_tx, sm_update_rx) = tokio::sync::mpsc::unbounded_channel();
 
+        let state_transition_hook_tx = self.state_transition_hook.map(|hook| {
+            let (hook_tx, mut hook_rx) =
+                mpsc::channel::<(OperationId, DynState, DynState)>(STATE_TRANSITION_HOOK_QUEUE_LEN);
+            client_task_group.spawn_cancellable("sm-transition-hook", async move {
+                while let Some((operation_id, old_state, new_state)) = hook_rx.recv().await {
+                    hook(operation_id, &old_state, &new_state);
+                }
+            });
+            hook_tx
+        });
+
         let inner = Arc::new(ExecutorInner {
             db,
             log_ordering_wakeup_tx,
@@ -976,7 +1248,10 @@ pub fn build(
             valid_module_ids: self.valid_module_ids,
             notifier,
             sm_update_tx,
+            busy_states: Arc::new(watch::channel(0).0),
             client_task_group,
+            state_transition_hook_tx,
+            watch_only: self.watch_only,
         });
 
         debug!(
@@ -1100,6 +1375,19 @@ fn consensus_decode_partial<R: std::io::Read>(
     }
 }
 
+/// Marks an operation whose inactive states were removed by
+/// [`Executor::remove_inactive_operation_states`]
+#[derive(Debug, Encodable, Decodable)]
+pub(crate) struct CompactedOperationKey {
+    pub operation_id: OperationId,
+}
+
+impl_db_record!(
+    key = CompactedOperationKey,
+    value = (),
+    db_prefix = ExecutorDbPrefixes::CompactedOperations,
+);
+
 #[derive(Debug)]
 pub struct InactiveOperationStateKeyPrefix {
     pub operation_id: OperationId,
@@ -1219,6 +1507,13 @@ enum ActiveOrInactiveState {
 }
 
 impl ActiveOrInactiveState {
+    fn state(&self) -> &DynState {
+        match self {
+            ActiveOrInactiveState::Active { dyn_state, .. }
+            | ActiveOrInactiveState::Inactive { dyn_state } => dyn_state,
+        }
+    }
+
     fn is_active(&self) -> bool {
         match self {
             ActiveOrInactiveState::Active { .. } => true,
@@ -1242,5 +1537,7 @@ async fn add_state_machines_dbtx(
     }
 }
 
+mod watch_only;
+
 #[cfg(test)]
 mod tests;
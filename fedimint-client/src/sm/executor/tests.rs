@@ -1,21 +1,30 @@
-use std::fmt::Debug;
-use std::sync::Arc;
-use std::time::Duration;
+use std::any::Any;
+use std::collections::BTreeSet;
+use std::fmt::{self, Debug};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
 
-use fedimint_client_module::sm::{Context, DynContext, DynState, State, StateTransition};
-use fedimint_core::core::{Decoder, IntoDynInstance, ModuleInstanceId, ModuleKind, OperationId};
+use fedimint_client_module::sm::{
+    ClientSMDatabaseTransaction, Context, DynContext, DynState, State, StateTransition,
+};
+use fedimint_client_module::transaction::ClientOutputBundle;
+use fedimint_core::core::{
+    Decoder, DynOutput, IOutput, IntoDynInstance, ModuleInstanceId, ModuleKind, OperationId,
+};
 use fedimint_core::db::Database;
 use fedimint_core::db::mem_impl::MemDatabase;
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::runtime;
 use fedimint_core::task::TaskGroup;
+use fedimint_core::util::BoxFuture;
 use fedimint_logging::LOG_CLIENT_REACTOR;
+use futures::future;
 use tokio::sync::broadcast::Sender;
 use tokio::sync::watch;
 use tracing::{info, trace};
 
-use super::Executor;
+use super::{Executor, ExecutorBuilder, StateTransitionHook};
 use crate::DynGlobalClientContext;
 use crate::sm::notifier::Notifier;
 
@@ -23,16 +32,52 @@
 enum MockStateMachine {
     Start,
     ReceivedNonNull(u64),
+    /// Transitions right away, counting down to [`MockStateMachine::Final`]
+    Immediate(u64),
+    /// Transitions right away by submitting a transaction
+    Spend,
     Final,
 }
 
+/// Output type for the transaction [`MockStateMachine::Spend`] submits
+#[derive(Debug, Encodable)]
+struct MockOutput(u64);
+
+impl fmt::Display for MockOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MockOutput({})", self.0)
+    }
+}
+
+impl IOutput for MockOutput {
+    fn as_any(&self) -> &(dyn Any + Send + Sync) {
+        self
+    }
+
+    fn module_kind(&self) -> Option<ModuleKind> {
+        None
+    }
+
+    fn clone(&self, _instance_id: ModuleInstanceId) -> DynOutput {
+        unimplemented!("never part of a finalized transaction")
+    }
+
+    fn dyn_hash(&self) -> u64 {
+        self.0
+    }
+
+    fn erased_eq_no_instance_id(&self, _other: &DynOutput) -> bool {
+        false
+    }
+}
+
 impl State for MockStateMachine {
     type ModuleContext = MockContext;
 
     fn transitions(
         &self,
         context: &Self::ModuleContext,
-        _global_context: &DynGlobalClientContext,
+        global_context: &DynGlobalClientContext,
     ) -> Vec<StateTransition<Self>> {
         match self {
             MockStateMachine::Start => {
@@ -83,6 +128,38 @@ fn transitions(
                     |_dbtx, (), _state| Box::pin(async { MockStateMachine::Final }),
                 )]
             }
+            MockStateMachine::Immediate(remaining) => {
+                let next = match remaining.checked_sub(1) {
+                    Some(remaining) => MockStateMachine::Immediate(remaining),
+                    None => MockStateMachine::Final,
+                };
+                vec![StateTransition::new(
+                    future::ready(()),
+                    move |_dbtx, (), _state| {
+                        let next = next.clone();
+                        Box::pin(async move { next })
+                    },
+                )]
+            }
+            MockStateMachine::Spend => {
+                let global_context = global_context.clone();
+                vec![StateTransition::new(
+                    future::ready(()),
+                    move |dbtx, (), _state| {
+                        let global_context = global_context.clone();
+                        Box::pin(async move {
+                            global_context
+                                .fund_output(
+                                    dbtx,
+                                    ClientOutputBundle::<MockOutput>::new_no_sm(vec![]),
+                                )
+                                .await
+                                .expect("Funding the output failed");
+                            MockStateMachine::Final
+                        })
+                    },
+                )]
+            }
             MockStateMachine::Final => {
                 vec![]
             }
@@ -92,6 +169,21 @@ fn transitions(
     fn operation_id(&self) -> OperationId {
         OperationId([0u8; 32])
     }
+
+    fn on_enter<'a>(
+        &'a self,
+        context: &'a Self::ModuleContext,
+        _global_context: &'a DynGlobalClientContext,
+        _dbtx: &'a mut ClientSMDatabaseTransaction<'_, '_>,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            context
+                .entered
+                .lock()
+                .expect("Locking failed")
+                .push(self.clone());
+        })
+    }
 }
 
 impl IntoDynInstance for MockStateMachine {
@@ -105,6 +197,8 @@ fn into_dyn(self, instance_id: ModuleInstanceId) -> Self::DynType {
 #[derive(Debug, Clone)]
 struct MockContext {
     broadcast: tokio::sync::broadcast::Sender<u64>,
+    /// States [`State::on_enter`] was called for, in order
+    entered: Arc<Mutex<Vec<MockStateMachine>>>,
 }
 
 impl IntoDynInstance for MockContext {
@@ -120,6 +214,15 @@ impl Context for MockContext {
 }
 
 fn get_executor() -> (Executor, Sender<u64>, Database) {
+    let (executor, context, db) = get_executor_with(|_| {});
+    (executor, context.broadcast, db)
+}
+
+/// Builds and starts an executor running [`MockStateMachine`]s, allowing
+/// `configure` to customize the [`ExecutorBuilder`] first
+fn get_executor_with(
+    configure: impl FnOnce(&mut ExecutorBuilder),
+) -> (Executor, MockContext, Database) {
     let (broadcast, _) = tokio::sync::broadcast::channel(10);
 
     let mut decoder_builder = Decoder::builder();
@@ -130,13 +233,14 @@ fn get_executor() -> (Executor, Sender<u64>, Database) {
         ModuleDecoderRegistry::new(vec![(42, ModuleKind::from_static_str("test"), decoder)]);
     let db = Database::new(MemDatabase::new(), decoders);
 
+    let context = MockContext {
+        broadcast,
+        entered: Arc::default(),
+    };
+
     let mut executor_builder = Executor::builder();
-    executor_builder.with_module(
-        42,
-        MockContext {
-            broadcast: broadcast.clone(),
-        },
-    );
+    executor_builder.with_module(42, context.clone());
+    configure(&mut executor_builder);
     let (log_ordering_wakeup_tx, _log_ordering_wakeup_rx) = watch::channel(());
     let executor = executor_builder.build(
         db.clone(),
@@ -153,7 +257,7 @@ fn get_executor() -> (Executor, Sender<u64>, Database) {
         target: LOG_CLIENT_REACTOR,
         "Initialized test executor"
     );
-    (executor, broadcast, db)
+    (executor, context, db)
 }
 
 #[tokio::test]
@@ -207,3 +311,220 @@ async fn test_executor() {
         "State was written to DB and waits for broadcast"
     );
 }
+
+#[tokio::test]
+async fn on_enter_is_called_once_per_persisted_state() {
+    const MOCK_INSTANCE: ModuleInstanceId = 42;
+
+    let (executor, context, _db) = get_executor_with(|_| {});
+    executor
+        .add_state_machines(vec![DynState::from_typed(
+            MOCK_INSTANCE,
+            MockStateMachine::Start,
+        )])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        *context.entered.lock().expect("Locking failed"),
+        vec![MockStateMachine::Start]
+    );
+
+    // Wait for the state machine to subscribe to the broadcast
+    executor.wait_idle().await;
+    context.broadcast.send(5).unwrap();
+    executor
+        .await_active_state(DynState::from_typed(
+            MOCK_INSTANCE,
+            MockStateMachine::ReceivedNonNull(5),
+        ))
+        .await;
+    executor.wait_idle().await;
+    context.broadcast.send(5).unwrap();
+    executor
+        .await_inactive_state(DynState::from_typed(MOCK_INSTANCE, MockStateMachine::Final))
+        .await;
+
+    assert_eq!(
+        *context.entered.lock().expect("Locking failed"),
+        vec![
+            MockStateMachine::Start,
+            MockStateMachine::ReceivedNonNull(5),
+            MockStateMachine::Final,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn executor_is_busy_during_chained_immediate_transitions() {
+    const MOCK_INSTANCE: ModuleInstanceId = 42;
+
+    let (executor, context, _db) = get_executor_with(|_| {});
+    executor
+        .add_state_machines(vec![DynState::from_typed(
+            MOCK_INSTANCE,
+            MockStateMachine::Immediate(5),
+        )])
+        .await
+        .unwrap();
+
+    executor.wait_idle().await;
+
+    assert!(
+        executor
+            .contains_inactive_state(MOCK_INSTANCE, MockStateMachine::Final)
+            .await,
+        "Executor must not be idle before the chain of transitions is done"
+    );
+    assert_eq!(
+        context.entered.lock().expect("Locking failed").len(),
+        7,
+        "Every state of the chain was entered"
+    );
+}
+
+#[tokio::test]
+async fn compacted_operation_can_not_be_restarted() {
+    const MOCK_INSTANCE: ModuleInstanceId = 42;
+    let operation_id = MockStateMachine::Start.operation_id();
+
+    let (executor, _context, _db) = get_executor_with(|_| {});
+    executor
+        .add_state_machines(vec![DynState::from_typed(
+            MOCK_INSTANCE,
+            MockStateMachine::Immediate(0),
+        )])
+        .await
+        .unwrap();
+    executor
+        .await_inactive_state(DynState::from_typed(MOCK_INSTANCE, MockStateMachine::Final))
+        .await;
+
+    assert!(
+        executor
+            .operations_inactive_since(UNIX_EPOCH)
+            .await
+            .is_empty(),
+        "Operation exited after the cutoff"
+    );
+
+    let cutoff = fedimint_core::time::now() + Duration::from_secs(1);
+    assert_eq!(
+        executor.operations_inactive_since(cutoff).await,
+        BTreeSet::from([operation_id])
+    );
+    assert_eq!(
+        executor
+            .remove_inactive_operation_states(operation_id)
+            .await,
+        2
+    );
+    assert!(executor.operations_inactive_since(cutoff).await.is_empty());
+
+    assert!(
+        executor
+            .add_state_machines(vec![DynState::from_typed(
+                MOCK_INSTANCE,
+                MockStateMachine::Immediate(0),
+            )])
+            .await
+            .is_err(),
+        "States of a compacted operation must not run again"
+    );
+}
+
+#[tokio::test]
+async fn operations_with_active_states_are_not_inactive() {
+    const MOCK_INSTANCE: ModuleInstanceId = 42;
+
+    let (executor, _context, _db) = get_executor_with(|_| {});
+    executor
+        .add_state_machines(vec![DynState::from_typed(
+            MOCK_INSTANCE,
+            MockStateMachine::Start,
+        )])
+        .await
+        .unwrap();
+
+    let cutoff = fedimint_core::time::now() + Duration::from_secs(1);
+    assert!(executor.operations_inactive_since(cutoff).await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn state_transition_hook_does_not_block_transitions() {
+    const MOCK_INSTANCE: ModuleInstanceId = 42;
+
+    // The hook blocks until `release_tx` is dropped
+    let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+    let release_rx = Arc::new(Mutex::new(release_rx));
+    let (transitions_tx, mut transitions_rx) = tokio::sync::mpsc::unbounded_channel();
+    let hook: StateTransitionHook = Arc::new(move |_operation_id, old_state, new_state| {
+        let _ = release_rx.lock().expect("Locking failed").recv();
+        let typed = |state: &DynState| {
+            state
+                .as_any()
+                .downcast_ref::<MockStateMachine>()
+                .expect("Unexpected state type")
+                .clone()
+        };
+        transitions_tx
+            .send((typed(old_state), typed(new_state)))
+            .expect("Receiver is alive");
+    });
+
+    let (executor, _context, _db) =
+        get_executor_with(|builder| builder.with_state_transition_hook(hook));
+    executor
+        .add_state_machines(vec![DynState::from_typed(
+            MOCK_INSTANCE,
+            MockStateMachine::Immediate(1),
+        )])
+        .await
+        .unwrap();
+    executor
+        .await_inactive_state(DynState::from_typed(MOCK_INSTANCE, MockStateMachine::Final))
+        .await;
+    drop(release_tx);
+
+    let mut transitions = vec![];
+    for _ in 0..2 {
+        transitions.push(transitions_rx.recv().await.expect("Hook was called"));
+    }
+    assert_eq!(
+        transitions,
+        vec![
+            (
+                MockStateMachine::Immediate(1),
+                MockStateMachine::Immediate(0)
+            ),
+            (MockStateMachine::Immediate(0), MockStateMachine::Final),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn watch_only_executor_parks_spending_state_machines() {
+    const MOCK_INSTANCE: ModuleInstanceId = 42;
+
+    let (executor, context, _db) = get_executor_with(ExecutorBuilder::with_watch_only);
+    executor
+        .add_state_machines(vec![DynState::from_typed(
+            MOCK_INSTANCE,
+            MockStateMachine::Spend,
+        )])
+        .await
+        .unwrap();
+
+    executor.wait_idle().await;
+
+    assert!(
+        executor
+            .contains_active_state(MOCK_INSTANCE, MockStateMachine::Spend)
+            .await,
+        "Parked state machine must stay active"
+    );
+    assert_eq!(
+        *context.entered.lock().expect("Locking failed"),
+        vec![MockStateMachine::Spend]
+    );
+}
@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use fedimint_api_client::api::{DynGlobalApi, DynModuleApi};
+use fedimint_client_module::module::OutPointRange;
+use fedimint_client_module::sm::{ClientSMDatabaseTransaction, IState};
+use fedimint_client_module::transaction::TxSubmissionStatesSM;
+use fedimint_client_module::{
+    AddStateMachinesResult, DynGlobalClientContext, IGlobalClientContext,
+    InstancelessDynClientInputBundle, InstancelessDynClientOutputBundle,
+};
+use fedimint_core::config::ClientConfig;
+use fedimint_core::core::{ModuleInstanceId, ModuleKind};
+use fedimint_core::module::ApiVersion;
+use fedimint_core::module::registry::ModuleDecoderRegistry;
+use fedimint_core::util::BoxStream;
+use fedimint_core::{apply, async_trait_maybe_send, maybe_add_send_sync};
+use fedimint_eventlog::{EventKind, EventPersistence};
+use tokio::sync::Notify;
+
+/// Global context handed to state machines by a watch-only
+/// [`Executor`](super::Executor)
+///
+/// Instead of submitting a transaction it signals `spend_attempted` and never
+/// returns, so the executor can abandon the transition before it commits.
+#[derive(Debug)]
+pub(super) struct WatchOnlyGlobalContext {
+    inner: DynGlobalClientContext,
+    spend_attempted: Arc<Notify>,
+}
+
+impl WatchOnlyGlobalContext {
+    pub(super) fn new(inner: DynGlobalClientContext, spend_attempted: Arc<Notify>) -> Self {
+        Self {
+            inner,
+            spend_attempted,
+        }
+    }
+
+    async fn park(&self) -> anyhow::Result<OutPointRange> {
+        self.spend_attempted.notify_one();
+        std::future::pending().await
+    }
+}
+
+#[apply(async_trait_maybe_send!)]
+impl IGlobalClientContext for WatchOnlyGlobalContext {
+    fn module_api(&self) -> DynModuleApi {
+        self.inner.module_api()
+    }
+
+    async fn client_config(&self) -> ClientConfig {
+        self.inner.client_config().await
+    }
+
+    fn api(&self) -> &DynGlobalApi {
+        self.inner.api()
+    }
+
+    fn decoders(&self) -> &ModuleDecoderRegistry {
+        self.inner.decoders()
+    }
+
+    async fn claim_inputs_dyn(
+        &self,
+        _dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
+        _inputs: InstancelessDynClientInputBundle,
+    ) -> anyhow::Result<OutPointRange> {
+        self.park().await
+    }
+
+    async fn fund_output_dyn(
+        &self,
+        _dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
+        _outputs: InstancelessDynClientOutputBundle,
+    ) -> anyhow::Result<OutPointRange> {
+        self.park().await
+    }
+
+    async fn add_state_machine_dyn(
+        &self,
+        dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
+        sm: Box<maybe_add_send_sync!(dyn IState)>,
+    ) -> AddStateMachinesResult {
+        self.inner.add_state_machine_dyn(dbtx, sm).await
+    }
+
+    async fn log_event_json(
+        &self,
+        dbtx: &mut ClientSMDatabaseTransaction<'_, '_>,
+        kind: EventKind,
+        module: Option<(ModuleKind, ModuleInstanceId)>,
+        payload: serde_json::Value,
+        persist: EventPersistence,
+    ) {
+        self.inner
+            .log_event_json(dbtx, kind, module, payload, persist)
+            .await;
+    }
+
+    async fn transaction_update_stream(&self) -> BoxStream<TxSubmissionStatesSM> {
+        self.inner.transaction_update_stream().await
+    }
+
+    async fn core_api_version(&self) -> ApiVersion {
+        self.inner.core_api_version().await
+    }
+
+    async fn await_operation_cancelled(&self) {
+        self.inner.await_operation_cancelled().await;
+    }
+}
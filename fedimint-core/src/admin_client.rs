@@ -57,6 +57,14 @@ pub struct SetLocalParamsRequest {
     pub federation_size: Option<u32>,
 }
 
+/// The maximum number of concurrent connections a guardian's API accepts and
+/// how many of them are currently in use
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ApiConnectionStatus {
+    pub max_connections: u64,
+    pub active_connections: u64,
+}
+
 /// Archive of all the guardian config files that can be used to recover a lost
 /// guardian node.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -3469,22 +3469,38 @@ pub async fn verify_module_db_integrity_dbtx(
     module_kind: ModuleKind,
     prefixes: &BTreeSet<u8>,
 ) {
+    try_verify_module_db_integrity_dbtx(dbtx, module_id, module_kind, prefixes)
+        .await
+        .unwrap_or_else(|err| panic!("{err}"));
+}
+
+/// Like [`verify_module_db_integrity_dbtx`], but returns an error describing
+/// the first record outside of `prefixes` instead of panicking
+///
+/// The error only names the key and its prefix, values are left out since
+/// they might hold secrets.
+pub async fn try_verify_module_db_integrity_dbtx(
+    dbtx: &mut DatabaseTransaction<'_>,
+    module_id: ModuleInstanceId,
+    module_kind: ModuleKind,
+    prefixes: &BTreeSet<u8>,
+) -> anyhow::Result<()> {
     let module_db_prefix = module_instance_id_to_byte_prefix(module_id);
     if module_id < 250 {
         assert_eq!(module_db_prefix.len(), 2);
     }
-    let mut records = dbtx
-        .raw_find_by_prefix(&module_db_prefix)
-        .await
-        .expect("DB fail");
-    while let Some((k, v)) = records.next().await {
-        assert!(
-            prefixes.contains(&k[module_db_prefix.len()]),
-            "Unexpected module {module_kind} {module_id} db record found: {}: {}",
-            k.as_hex(),
-            v.as_hex()
-        );
+    let mut records = dbtx.raw_find_by_prefix(&module_db_prefix).await?;
+    while let Some((k, _)) = records.next().await {
+        let prefix = k[module_db_prefix.len()];
+        if !prefixes.contains(&prefix) {
+            anyhow::bail!(
+                "Unexpected module {module_kind} {module_id} db record found with prefix {prefix:#04x}: {}",
+                k.as_hex()
+            );
+        }
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -42,3 +42,6 @@
 pub const FEDIMINTD_VERSION_ENDPOINT: &str = "fedimintd_version";
 pub const CHANGE_PASSWORD_ENDPOINT: &str = "change_password";
 pub const CHAIN_ID_ENDPOINT: &str = "chain_id";
+pub const SET_DB_CHECKPOINT_RETENTION_ENDPOINT: &str = "set_db_checkpoint_retention";
+pub const API_CONNECTIONS_ENDPOINT: &str = "api_connections";
+pub const SET_MAX_API_CONNECTIONS_ENDPOINT: &str = "set_max_api_connections";
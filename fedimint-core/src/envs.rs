@@ -145,6 +145,11 @@ macro_rules! fedimint_build_code_version_env {
 pub const FM_IROH_DHT_ENABLE_ENV: &str = "FM_IROH_DHT_ENABLE";
 
 /// Env var to disable default n0 discovery
+///
+/// Since the DHT ([`FM_IROH_DHT_ENABLE_ENV`]) is off by default, disabling n0
+/// discovery turns off all discovery via public infrastructure for private
+/// deployments. Pkarr discovery via the server set in [`FM_IROH_DNS_ENV`] is
+/// not affected.
 pub const FM_IROH_N0_DISCOVERY_ENABLE_ENV: &str = "FM_IROH_N0_DISCOVERY_ENABLE";
 
 /// Env var to disable default pkarr resolver
@@ -170,6 +175,12 @@ macro_rules! fedimint_build_code_version_env {
 /// Comma separated key-value list (`peer_id=url,peer_id=url`)
 pub const FM_WS_API_CONNECT_OVERRIDES_ENV: &str = "FM_WS_API_CONNECT_OVERRIDES";
 
+/// Comma separated permutation of the default peer ids (e.g. `2,0,3,1`)
+/// assigned during config generation. The guardian that would get the peer id
+/// at position `i` by default gets peer id `i` instead. Has to be set to the
+/// same value on all guardians.
+pub const FM_PEER_ORDER_ENV: &str = "FM_PEER_ORDER";
+
 pub const FM_IROH_API_SECRET_KEY_OVERRIDE_ENV: &str = "FM_IROH_API_SECRET_KEY_OVERRIDE";
 pub const FM_IROH_P2P_SECRET_KEY_OVERRIDE_ENV: &str = "FM_IROH_P2P_SECRET_KEY_OVERRIDE";
 
@@ -393,6 +393,27 @@ pub async fn cancel_on_shutdown<F: Future>(
             Either::Right((value, _)) => Ok(value),
         }
     }
+
+    /// Run the future or cancel it once `drain_timeout` has passed since the
+    /// [`TaskGroup`] started shutting down.
+    ///
+    /// Unlike [`Self::cancel_on_shutdown`] this lets in-flight work finish
+    /// during shutdown, but bounds how long it can delay it.
+    pub async fn cancel_after_shutdown_drain<F: Future>(
+        &self,
+        drain_timeout: Duration,
+        fut: F,
+    ) -> Result<F::Output, ShuttingDownError> {
+        let rx = self.make_shutdown_rx();
+        let drain_deadline = async move {
+            rx.await;
+            runtime::sleep(drain_timeout).await;
+        };
+        match future::select(pin!(drain_deadline), pin!(fut)).await {
+            Either::Left(((), _)) => Err(ShuttingDownError {}),
+            Either::Right((value, _)) => Ok(value),
+        }
+    }
 }
 
 pub struct TaskShutdownToken(Pin<Box<dyn Future<Output = ()> + Send>>);
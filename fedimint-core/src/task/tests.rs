@@ -1,4 +1,4 @@
-use super::{Duration, TaskGroup, sleep};
+use super::{Duration, TaskGroup, sleep, timeout};
 
 #[test_log::test(tokio::test)]
 async fn shutdown_task_group_after() -> anyhow::Result<()> {
@@ -45,3 +45,49 @@ async fn shutdown_task_subgroup_before() -> anyhow::Result<()> {
     tg.shutdown_join_all(None).await?;
     Ok(())
 }
+
+#[test_log::test(tokio::test)]
+async fn cancel_after_shutdown_drain_lets_future_finish() -> anyhow::Result<()> {
+    let tg = TaskGroup::new();
+    let handle = tg.make_handle();
+    tg.shutdown();
+
+    let result = handle
+        .cancel_after_shutdown_drain(Duration::from_secs(60), async {
+            sleep(Duration::from_millis(10)).await;
+            5
+        })
+        .await;
+    assert_eq!(result.ok(), Some(5));
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn cancel_after_shutdown_drain_cancels_after_timeout() -> anyhow::Result<()> {
+    let tg = TaskGroup::new();
+    let handle = tg.make_handle();
+    tg.shutdown();
+
+    let result = handle
+        .cancel_after_shutdown_drain(Duration::from_millis(10), std::future::pending::<()>())
+        .await;
+    assert!(result.is_err());
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn cancel_after_shutdown_drain_waits_for_shutdown() -> anyhow::Result<()> {
+    let tg = TaskGroup::new();
+    let handle = tg.make_handle();
+
+    let result = timeout(
+        Duration::from_millis(50),
+        handle.cancel_after_shutdown_drain(Duration::ZERO, std::future::pending::<()>()),
+    )
+    .await;
+    assert!(
+        result.is_err(),
+        "Future must not be cancelled before shutdown"
+    );
+    Ok(())
+}
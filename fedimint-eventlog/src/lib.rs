@@ -17,7 +17,7 @@
 use std::time::Duration;
 use std::{fmt, ops};
 
-use fedimint_core::core::{ModuleInstanceId, ModuleKind};
+use fedimint_core::core::{ModuleInstanceId, ModuleKind, OperationId};
 use fedimint_core::db::{
     Database, DatabaseTransaction, IDatabaseTransactionOpsCoreTyped, NonCommittable,
 };
@@ -263,6 +263,19 @@ pub fn to_event<E>(&self) -> Option<E>
     {
         serde_json::from_slice(&self.payload).ok()
     }
+
+    /// Get the operation the event belongs to, if its payload has an
+    /// `operation_id` field
+    pub fn operation_id(&self) -> Option<OperationId> {
+        #[derive(Deserialize)]
+        struct OperationPayload {
+            operation_id: OperationId,
+        }
+
+        serde_json::from_slice::<OperationPayload>(&self.payload)
+            .ok()
+            .map(|payload| payload.operation_id)
+    }
 }
 
 /// An `EventLogEntry` that was already persisted (so has an id)
@@ -649,14 +662,44 @@ async fn get_event_log_trimable(
 
 /// Trims old entries from the trimable event log
 async fn trim_trimable_log(db: &Database, current_time_usecs: u64) {
-    let mut dbtx = db.begin_transaction().await;
-
-    let current_trimable_id = dbtx.get_next_event_log_trimable_id().await;
+    let current_trimable_id = db
+        .begin_transaction_nc()
+        .await
+        .get_next_event_log_trimable_id()
+        .await;
     let min_id_threshold = current_trimable_id
         .0
         .saturating_sub(TRIMABLE_EVENTLOG_MIN_ID_AGE);
     let min_ts_threshold = current_time_usecs.saturating_sub(TRIMABLE_EVENTLOG_MIN_TS_AGE);
 
+    trim_trimable_log_entries(db, min_id_threshold, min_ts_threshold).await;
+}
+
+/// Trims entries of the trimable event log that are older than `retention`,
+/// regardless of how many newer entries there are. Returns the number of
+/// removed entries.
+pub async fn trim_trimable_log_older_than(db: &Database, retention: Duration) -> usize {
+    let current_time_usecs =
+        u64::try_from(fedimint_core::time::duration_since_epoch().as_micros()).unwrap_or(u64::MAX);
+    let retention_usecs = u64::try_from(retention.as_micros()).unwrap_or(u64::MAX);
+
+    trim_trimable_log_entries(
+        db,
+        EventLogId(u64::MAX),
+        current_time_usecs.saturating_sub(retention_usecs),
+    )
+    .await
+}
+
+/// Removes the oldest entries of the trimable event log as long as both their
+/// id and timestamp are at or below the thresholds
+async fn trim_trimable_log_entries(
+    db: &Database,
+    min_id_threshold: EventLogId,
+    min_ts_threshold: u64,
+) -> usize {
+    let mut dbtx = db.begin_transaction().await;
+
     let entries_to_delete: Vec<_> = dbtx
         .find_by_prefix(&EventLogTrimableIdPrefixAll)
         .await
@@ -677,6 +720,8 @@ async fn trim_trimable_log(db: &Database, current_time_usecs: u64) {
     }
 
     dbtx.commit_tx().await;
+
+    entries_to_delete.len()
 }
 
 /// The code that handles new unordered events and rewriters them fully ordered
@@ -17,7 +17,7 @@
 };
 use fedimint_core::envs::{
     FM_DISABLE_BASE_FEES_ENV, FM_IROH_API_SECRET_KEY_OVERRIDE_ENV,
-    FM_IROH_P2P_SECRET_KEY_OVERRIDE_ENV, is_env_var_set,
+    FM_IROH_P2P_SECRET_KEY_OVERRIDE_ENV, FM_PEER_ORDER_ENV, is_env_var_set,
 };
 use fedimint_core::module::{
     ApiAuth, ApiEndpoint, ApiEndpointContext, ApiError, ApiRequestErased, ApiVersion, api_endpoint,
@@ -427,8 +427,13 @@ async fn start_dkg(&self) -> anyhow::Result<()> {
             .find_map(|info| info.enabled_modules.clone())
             .unwrap_or_else(|| self.settings.default_modules.clone());
 
-        let our_id = state
-            .setup_codes
+        let setup_codes = match std::env::var(FM_PEER_ORDER_ENV) {
+            Ok(order) => apply_peer_order(state.setup_codes.iter().cloned().collect(), &order)
+                .with_context(|| format!("Invalid {FM_PEER_ORDER_ENV}"))?,
+            Err(_) => state.setup_codes.iter().cloned().collect(),
+        };
+
+        let our_id = setup_codes
             .iter()
             .position(|info| info == &our_setup_code)
             .expect("We inserted the key above.");
@@ -441,7 +446,7 @@ async fn start_dkg(&self) -> anyhow::Result<()> {
             api_auth: local_params.auth,
             peers: (0..)
                 .map(|i| PeerId::from(i as u16))
-                .zip(state.setup_codes.clone())
+                .zip(setup_codes)
                 .collect(),
             meta: BTreeMap::from_iter(vec![(
                 META_FEDERATION_NAME_KEY.to_string(),
@@ -526,6 +531,40 @@ async fn context(
     }
 }
 
+/// Reorders `peers`, given in default peer id order, according to a comma
+/// separated permutation of their default peer ids
+fn apply_peer_order<T>(peers: Vec<T>, order: &str) -> anyhow::Result<Vec<T>> {
+    let order = order
+        .split(',')
+        .map(|peer| {
+            peer.trim()
+                .parse::<usize>()
+                .with_context(|| format!("Invalid peer id {peer}"))
+        })
+        .collect::<anyhow::Result<Vec<usize>>>()?;
+
+    ensure!(
+        order.len() == peers.len(),
+        "Expected {} peer ids but got {}",
+        peers.len(),
+        order.len()
+    );
+
+    ensure!(
+        order.iter().copied().collect::<BTreeSet<usize>>()
+            == (0..peers.len()).collect::<BTreeSet<usize>>(),
+        "Peer ids have to be a permutation of 0 to {}",
+        peers.len() - 1
+    );
+
+    let mut peers = peers.into_iter().map(Some).collect::<Vec<Option<T>>>();
+
+    Ok(order
+        .into_iter()
+        .map(|peer| peers[peer].take().expect("Peer ids are unique"))
+        .collect())
+}
+
 pub fn server_endpoints() -> Vec<ApiEndpoint<SetupApi>> {
     vec![
         api_endpoint! {
@@ -674,4 +713,16 @@ async fn rejects_peer_setup_code_with_different_network() {
                 .contains("Guardian uses Bitcoin network signet but we use regtest")
         );
     }
+
+    #[test]
+    fn applies_peer_order() {
+        assert_eq!(
+            apply_peer_order(vec!["a", "b", "c", "d"], "2,0, 3,1").expect("valid order"),
+            vec!["c", "a", "d", "b"]
+        );
+        assert!(apply_peer_order(vec!["a", "b", "c", "d"], "0,1,2").is_err());
+        assert!(apply_peer_order(vec!["a", "b", "c", "d"], "0,1,2,2").is_err());
+        assert!(apply_peer_order(vec!["a", "b", "c", "d"], "0,1,2,4").is_err());
+        assert!(apply_peer_order(vec!["a", "b", "c", "d"], "0,1,2,x").is_err());
+    }
 }
@@ -1,3 +1,10 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use fedimint_core::admin_client::ApiConnectionStatus;
+
 /// Configuration for connection and request limits
 #[derive(Debug, Clone, Copy)]
 pub struct ConnectionLimits {
@@ -5,6 +12,9 @@ pub struct ConnectionLimits {
     pub max_connections: usize,
     /// Maximum number of parallel requests per connection
     pub max_requests_per_connection: usize,
+    /// Maximum number of requests per second per client IP address, unlimited
+    /// if `None`
+    pub max_requests_per_second: Option<u32>,
 }
 
 impl ConnectionLimits {
@@ -13,6 +23,198 @@ pub fn new(max_connections: usize, max_requests_per_connection: usize) -> Self {
         Self {
             max_connections,
             max_requests_per_connection,
+            max_requests_per_second: None,
+        }
+    }
+
+    /// Limit the number of requests per second per client IP address
+    pub fn with_max_requests_per_second(mut self, max_requests_per_second: Option<u32>) -> Self {
+        self.max_requests_per_second = max_requests_per_second;
+        self
+    }
+}
+
+/// Limits the number of concurrent API connections to a maximum that can be
+/// changed at runtime. Lowering the maximum rejects new connections until
+/// enough existing ones have been closed, without dropping any of them.
+#[derive(Debug)]
+pub struct ConnectionLimiter {
+    max_connections: AtomicUsize,
+    active_connections: AtomicUsize,
+}
+
+impl ConnectionLimiter {
+    pub fn new(max_connections: usize) -> Arc<Self> {
+        Arc::new(Self {
+            max_connections: AtomicUsize::new(max_connections),
+            active_connections: AtomicUsize::new(0),
+        })
+    }
+
+    /// Returns a permit for a new connection or `None` if the limit has been
+    /// reached
+    pub fn try_acquire(self: &Arc<Self>) -> Option<ConnectionPermit> {
+        self.active_connections
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+                (active < self.max_connections.load(Ordering::SeqCst)).then_some(active + 1)
+            })
+            .ok()
+            .map(|_| ConnectionPermit(self.clone()))
+    }
+
+    pub fn set_max_connections(&self, max_connections: usize) {
+        self.max_connections
+            .store(max_connections, Ordering::SeqCst);
+    }
+
+    pub fn status(&self) -> ApiConnectionStatus {
+        ApiConnectionStatus {
+            max_connections: self.max_connections.load(Ordering::SeqCst) as u64,
+            active_connections: self.active_connections.load(Ordering::SeqCst) as u64,
+        }
+    }
+}
+
+/// Counts as an active connection of a [`ConnectionLimiter`] until dropped
+#[derive(Debug)]
+pub struct ConnectionPermit(Arc<ConnectionLimiter>);
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.0.active_connections.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// How often buckets that have been refilled completely are dropped
+const RATE_LIMITER_PRUNE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Limits the rate of API requests per remote peer using a token bucket that
+/// allows bursts of up to one second worth of requests
+#[derive(Debug)]
+pub struct RequestRateLimiter<K> {
+    max_requests_per_second: u32,
+    state: Mutex<RateLimiterState<K>>,
+    rejected_requests: AtomicU64,
+}
+
+#[derive(Debug)]
+struct RateLimiterState<K> {
+    buckets: BTreeMap<K, TokenBucket>,
+    last_pruned: Instant,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Status of a [`RequestRateLimiter`], useful to tune its limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestRateLimiterStatus {
+    pub max_requests_per_second: u32,
+    /// Number of peers that recently sent requests
+    pub tracked_peers: usize,
+    /// Number of requests rejected since startup
+    pub rejected_requests: u64,
+}
+
+impl<K: Ord> RequestRateLimiter<K> {
+    pub fn new(max_requests_per_second: u32) -> Arc<Self> {
+        Arc::new(Self {
+            max_requests_per_second,
+            state: Mutex::new(RateLimiterState {
+                buckets: BTreeMap::new(),
+                last_pruned: Instant::now(),
+            }),
+            rejected_requests: AtomicU64::new(0),
+        })
+    }
+
+    /// Returns `true` if `peer` may send another request right now
+    pub fn try_acquire(&self, peer: K) -> bool {
+        self.try_acquire_at(peer, Instant::now())
+    }
+
+    fn try_acquire_at(&self, peer: K, now: Instant) -> bool {
+        let capacity = f64::from(self.max_requests_per_second);
+        let mut state = self.state.lock().expect("lock poisoned");
+
+        if RATE_LIMITER_PRUNE_INTERVAL <= now.saturating_duration_since(state.last_pruned) {
+            state.buckets.retain(|_, bucket| {
+                now.saturating_duration_since(bucket.last_refill)
+                    .as_secs_f64()
+                    * capacity
+                    + bucket.tokens
+                    < capacity
+            });
+            state.last_pruned = now;
         }
+
+        let bucket = state.buckets.entry(peer).or_insert(TokenBucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * capacity).min(capacity);
+        bucket.last_refill = now;
+
+        if 1.0 <= bucket.tokens {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            self.rejected_requests.fetch_add(1, Ordering::Relaxed);
+            false
+        }
+    }
+
+    pub fn status(&self) -> RequestRateLimiterStatus {
+        RequestRateLimiterStatus {
+            max_requests_per_second: self.max_requests_per_second,
+            tracked_peers: self.state.lock().expect("lock poisoned").buckets.len(),
+            rejected_requests: self.rejected_requests.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{ConnectionLimiter, RequestRateLimiter};
+
+    #[test]
+    fn lowering_the_limit_keeps_existing_connections() {
+        let limiter = ConnectionLimiter::new(2);
+        let first = limiter.try_acquire().expect("below limit");
+        let second = limiter.try_acquire().expect("below limit");
+        assert!(limiter.try_acquire().is_none());
+
+        limiter.set_max_connections(1);
+        assert_eq!(limiter.status().active_connections, 2);
+
+        drop(first);
+        assert!(limiter.try_acquire().is_none());
+
+        drop(second);
+        let _third = limiter.try_acquire().expect("below new limit");
+        assert_eq!(limiter.status().active_connections, 1);
+    }
+
+    #[test]
+    fn rate_limiter_refills_per_peer() {
+        let limiter = RequestRateLimiter::new(2);
+        let start = Instant::now();
+
+        assert!(limiter.try_acquire_at(0, start));
+        assert!(limiter.try_acquire_at(0, start));
+        assert!(!limiter.try_acquire_at(0, start));
+        assert!(limiter.try_acquire_at(1, start));
+        assert_eq!(limiter.status().rejected_requests, 1);
+
+        assert!(limiter.try_acquire_at(0, start + Duration::from_millis(500)));
+        assert!(!limiter.try_acquire_at(0, start + Duration::from_millis(500)));
+        assert_eq!(limiter.status().tracked_peers, 2);
     }
 }
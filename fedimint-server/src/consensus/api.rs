@@ -11,7 +11,9 @@
 use fedimint_api_client::api::{
     LegacyFederationStatus, LegacyP2PConnectionStatus, LegacyPeerStatus, StatusResponse,
 };
-use fedimint_core::admin_client::{GuardianConfigBackup, ServerStatusLegacy, SetupStatus};
+use fedimint_core::admin_client::{
+    ApiConnectionStatus, GuardianConfigBackup, ServerStatusLegacy, SetupStatus,
+};
 use fedimint_core::backup::{
     BackupStatistics, ClientBackupKey, ClientBackupKeyPrefix, ClientBackupSnapshot,
 };
@@ -24,17 +26,19 @@
 #[allow(deprecated)]
 use fedimint_core::endpoint_constants::AWAIT_OUTPUT_OUTCOME_ENDPOINT;
 use fedimint_core::endpoint_constants::{
-    API_ANNOUNCEMENTS_ENDPOINT, AUDIT_ENDPOINT, AUTH_ENDPOINT, AWAIT_OUTPUTS_OUTCOMES_ENDPOINT,
-    AWAIT_SESSION_OUTCOME_ENDPOINT, AWAIT_SIGNED_SESSION_OUTCOME_ENDPOINT,
-    AWAIT_TRANSACTION_ENDPOINT, BACKUP_ENDPOINT, BACKUP_STATISTICS_ENDPOINT, CHAIN_ID_ENDPOINT,
-    CHANGE_PASSWORD_ENDPOINT, CLIENT_CONFIG_ENDPOINT, CLIENT_CONFIG_JSON_ENDPOINT,
-    CONSENSUS_ORD_LATENCY_ENDPOINT, FEDERATION_ID_ENDPOINT, FEDIMINTD_VERSION_ENDPOINT,
-    GUARDIAN_CONFIG_BACKUP_ENDPOINT, GUARDIAN_METADATA_ENDPOINT, INVITE_CODE_ENDPOINT,
-    P2P_CONNECTION_STATUS_ENDPOINT, RECOVER_ENDPOINT, SERVER_CONFIG_CONSENSUS_HASH_ENDPOINT,
-    SESSION_COUNT_ENDPOINT, SESSION_STATUS_ENDPOINT, SESSION_STATUS_V2_ENDPOINT,
-    SETUP_STATUS_ENDPOINT, SHUTDOWN_ENDPOINT, SIGN_API_ANNOUNCEMENT_ENDPOINT,
-    SIGN_GUARDIAN_METADATA_ENDPOINT, STATUS_ENDPOINT, SUBMIT_API_ANNOUNCEMENT_ENDPOINT,
-    SUBMIT_GUARDIAN_METADATA_ENDPOINT, SUBMIT_TRANSACTION_ENDPOINT, VERSION_ENDPOINT,
+    API_ANNOUNCEMENTS_ENDPOINT, API_CONNECTIONS_ENDPOINT, AUDIT_ENDPOINT, AUTH_ENDPOINT,
+    AWAIT_OUTPUTS_OUTCOMES_ENDPOINT, AWAIT_SESSION_OUTCOME_ENDPOINT,
+    AWAIT_SIGNED_SESSION_OUTCOME_ENDPOINT, AWAIT_TRANSACTION_ENDPOINT, BACKUP_ENDPOINT,
+    BACKUP_STATISTICS_ENDPOINT, CHAIN_ID_ENDPOINT, CHANGE_PASSWORD_ENDPOINT,
+    CLIENT_CONFIG_ENDPOINT, CLIENT_CONFIG_JSON_ENDPOINT, CONSENSUS_ORD_LATENCY_ENDPOINT,
+    FEDERATION_ID_ENDPOINT, FEDIMINTD_VERSION_ENDPOINT, GUARDIAN_CONFIG_BACKUP_ENDPOINT,
+    GUARDIAN_METADATA_ENDPOINT, INVITE_CODE_ENDPOINT, P2P_CONNECTION_STATUS_ENDPOINT,
+    RECOVER_ENDPOINT, SERVER_CONFIG_CONSENSUS_HASH_ENDPOINT, SESSION_COUNT_ENDPOINT,
+    SESSION_STATUS_ENDPOINT, SESSION_STATUS_V2_ENDPOINT, SET_DB_CHECKPOINT_RETENTION_ENDPOINT,
+    SET_MAX_API_CONNECTIONS_ENDPOINT, SETUP_STATUS_ENDPOINT, SHUTDOWN_ENDPOINT,
+    SIGN_API_ANNOUNCEMENT_ENDPOINT, SIGN_GUARDIAN_METADATA_ENDPOINT, STATUS_ENDPOINT,
+    SUBMIT_API_ANNOUNCEMENT_ENDPOINT, SUBMIT_GUARDIAN_METADATA_ENDPOINT,
+    SUBMIT_TRANSACTION_ENDPOINT, VERSION_ENDPOINT,
 };
 use fedimint_core::epoch::ConsensusItem;
 use fedimint_core::invite_code::InviteCode;
@@ -72,6 +76,7 @@
     reencrypt_private_config,
 };
 use crate::config::{ServerConfig, legacy_consensus_config_hash};
+use crate::connection_limits::ConnectionLimiter;
 use crate::consensus::db::{AcceptedItemPrefix, AcceptedTransactionKey, SignedSessionOutcomeKey};
 use crate::consensus::engine::get_finished_session_count_static;
 use crate::consensus::transaction::{TxProcessingMode, process_transaction_with_dbtx};
@@ -104,6 +109,10 @@ pub struct ConsensusApi {
     pub supported_api_versions: SupportedApiVersionsSummary,
     pub code_version_str: String,
     pub task_group: TaskGroup,
+    /// Number of database checkpoints the consensus engine retains
+    pub db_checkpoint_retention: watch::Sender<u64>,
+    /// Limits the number of concurrent iroh API connections
+    pub iroh_api_connection_limiter: Arc<ConnectionLimiter>,
 }
 
 impl ConsensusApi {
@@ -310,6 +319,7 @@ pub async fn get_federation_status(&self) -> ApiResult<LegacyFederationStatus> {
             peers_offline,
             peers_flagged,
             scheduled_shutdown,
+            db_checkpoint_retention: Some(*self.db_checkpoint_retention.borrow()),
         })
     }
 
@@ -317,6 +327,11 @@ fn shutdown(&self, index: Option<u64>) {
         self.shutdown_sender.send_replace(index);
     }
 
+    fn set_db_checkpoint_retention(&self, retention: u64) {
+        let previous = self.db_checkpoint_retention.send_replace(retention);
+        info!(target: LOG_NET_API, %previous, %retention, "Changed database checkpoint retention");
+    }
+
     async fn get_federation_audit(&self) -> ApiResult<AuditSummary> {
         let mut dbtx = self.db.begin_transaction_nc().await;
         // Writes are related to compacting audit keys, which we can safely ignore
@@ -1130,6 +1145,40 @@ pub fn server_endpoints() -> Vec<ApiEndpoint<ConsensusApi>> {
                     .map_err(|e| ApiError::server_error(e.to_string()))
             }
         },
+        api_endpoint! {
+            SET_DB_CHECKPOINT_RETENTION_ENDPOINT,
+            ApiVersion::new(0, 9),
+            async |fedimint: &ConsensusApi, context, retention: u64| -> () {
+                check_auth(context)?;
+                fedimint.set_db_checkpoint_retention(retention);
+                Ok(())
+            }
+        },
+        api_endpoint! {
+            API_CONNECTIONS_ENDPOINT,
+            ApiVersion::new(0, 9),
+            async |fedimint: &ConsensusApi, context, _v: ()| -> ApiConnectionStatus {
+                check_auth(context)?;
+                Ok(fedimint.iroh_api_connection_limiter.status())
+            }
+        },
+        api_endpoint! {
+            SET_MAX_API_CONNECTIONS_ENDPOINT,
+            ApiVersion::new(0, 9),
+            async |fedimint: &ConsensusApi, context, max_connections: u64| -> () {
+                check_auth(context)?;
+                if max_connections == 0 {
+                    return Err(ApiError::bad_request(
+                        "The connection limit must be at least 1".to_string(),
+                    ));
+                }
+                let max_connections = usize::try_from(max_connections)
+                    .map_err(|e| ApiError::bad_request(e.to_string()))?;
+                fedimint.iroh_api_connection_limiter.set_max_connections(max_connections);
+                info!(target: LOG_NET_API, %max_connections, "Changed API connection limit");
+                Ok(())
+            }
+        },
     ]
 }
 
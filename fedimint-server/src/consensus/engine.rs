@@ -71,7 +71,9 @@ pub struct ConsensusEngine {
     pub ord_latency_sender: watch::Sender<Option<Duration>>,
     pub task_group: TaskGroup,
     pub data_dir: PathBuf,
-    pub db_checkpoint_retention: u64,
+    /// Number of database checkpoints to retain, can be changed at runtime via
+    /// the admin API
+    pub db_checkpoint_retention: watch::Receiver<u64>,
 }
 
 impl ConsensusEngine {
@@ -83,6 +85,10 @@ fn identity(&self) -> PeerId {
         self.cfg.local.identity
     }
 
+    fn db_checkpoint_retention(&self) -> u64 {
+        *self.db_checkpoint_retention.borrow()
+    }
+
     #[instrument(target = LOG_CONSENSUS, name = "run", skip_all, fields(id=%self.cfg.local.identity))]
     pub async fn run(self) -> anyhow::Result<()> {
         if self.num_peers().total() == 1 {
@@ -781,6 +787,7 @@ fn db_checkpoints_dir(&self) -> PathBuf {
     /// `checkpoint_retention`.
     fn initialize_checkpoint_directory(&self, current_session: u64) -> anyhow::Result<()> {
         let checkpoint_dir = self.db_checkpoints_dir();
+        let db_checkpoint_retention = self.db_checkpoint_retention();
 
         if checkpoint_dir.exists() {
             debug!(
@@ -793,8 +800,8 @@ fn initialize_checkpoint_directory(&self, current_session: u64) -> anyhow::Resul
                 // Validate that the directory is a session index
                 if let Ok(file_name) = checkpoint.file_name().into_string()
                     && let Ok(session) = file_name.parse::<u64>()
-                    && current_session >= self.db_checkpoint_retention
-                    && session < current_session - self.db_checkpoint_retention
+                    && current_session >= db_checkpoint_retention
+                    && session < current_session - db_checkpoint_retention
                 {
                     fs::remove_dir_all(checkpoint.path())?;
                 }
@@ -812,7 +819,8 @@ fn initialize_checkpoint_directory(&self, current_session: u64) -> anyhow::Resul
     fn checkpoint_database(&self, session_index: u64) {
         // If `checkpoint_retention` has been turned off, don't checkpoint the database
         // at all.
-        if self.db_checkpoint_retention == 0 {
+        let db_checkpoint_retention = self.db_checkpoint_retention();
+        if db_checkpoint_retention == 0 {
             return;
         }
 
@@ -834,27 +842,36 @@ fn checkpoint_database(&self, session_index: u64) {
         {
             // Check if any old checkpoint need to be cleaned up
             let _timing /* logs on drop */ = timing::TimeReporter::new("remove-database-checkpoint").level(Level::TRACE);
-            if let Err(err) = self.delete_old_database_checkpoint(session_index, &checkpoint_dir) {
+            if let Err(err) = Self::delete_old_database_checkpoints(
+                session_index,
+                db_checkpoint_retention,
+                &checkpoint_dir,
+            ) {
                 warn!(target: LOG_CONSENSUS, err = %err.fmt_compact_anyhow(), "Could not delete old checkpoints");
             }
         }
     }
 
-    /// Deletes the database checkpoint directory equal to `session_index` -
-    /// `checkpoint_retention`
-    fn delete_old_database_checkpoint(
-        &self,
+    /// Deletes all database checkpoint directories up to and including
+    /// `session_index` - `checkpoint_retention`. Deleting all of them rather
+    /// than only the one that just fell out of the window lets a reduced
+    /// retention take effect immediately.
+    fn delete_old_database_checkpoints(
         session_index: u64,
+        db_checkpoint_retention: u64,
         checkpoint_dir: &Path,
     ) -> anyhow::Result<()> {
-        if self.db_checkpoint_retention > session_index {
+        let Some(delete_session_index) = session_index.checked_sub(db_checkpoint_retention) else {
             return Ok(());
-        }
+        };
 
-        let delete_session_index = session_index - self.db_checkpoint_retention;
-        let checkpoint_to_delete = checkpoint_dir.join(delete_session_index.to_string());
-        if checkpoint_to_delete.exists() {
-            fs::remove_dir_all(checkpoint_to_delete)?;
+        for checkpoint in fs::read_dir(checkpoint_dir)?.flatten() {
+            if let Ok(file_name) = checkpoint.file_name().into_string()
+                && let Ok(session) = file_name.parse::<u64>()
+                && session <= delete_session_index
+            {
+                fs::remove_dir_all(checkpoint.path())?;
+            }
         }
 
         Ok(())
@@ -1113,3 +1130,91 @@ pub async fn get_finished_session_count_static(dbtx: &mut DatabaseTransaction<'_
         .await
         .map_or(0, |entry| (entry.0.0) + 1)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use super::ConsensusEngine;
+
+    fn create_checkpoints(checkpoint_dir: &Path, sessions: impl IntoIterator<Item = u64>) {
+        for session in sessions {
+            fs::create_dir(checkpoint_dir.join(session.to_string()))
+                .expect("Failed to create checkpoint");
+        }
+    }
+
+    fn remaining_checkpoints(checkpoint_dir: &Path) -> Vec<String> {
+        let mut checkpoints = fs::read_dir(checkpoint_dir)
+            .expect("Failed to read checkpoint dir")
+            .map(|entry| {
+                entry
+                    .expect("Failed to read entry")
+                    .file_name()
+                    .into_string()
+                    .expect("Valid utf8")
+            })
+            .collect::<Vec<_>>();
+        checkpoints.sort_by_key(|name| name.parse::<u64>().ok());
+        checkpoints
+    }
+
+    fn checkpoint_dir() -> tempfile::TempDir {
+        tempfile::Builder::new()
+            .prefix("db-checkpoints")
+            .tempdir()
+            .expect("Failed to create temp dir")
+    }
+
+    #[test]
+    fn deletes_all_checkpoints_outside_of_retention() {
+        let dir = checkpoint_dir();
+        create_checkpoints(dir.path(), 0..10);
+
+        ConsensusEngine::delete_old_database_checkpoints(9, 3, dir.path())
+            .expect("Failed to delete checkpoints");
+
+        assert_eq!(remaining_checkpoints(dir.path()), vec!["7", "8", "9"]);
+    }
+
+    #[test]
+    fn reduced_retention_takes_effect_immediately() {
+        let dir = checkpoint_dir();
+        create_checkpoints(dir.path(), 0..10);
+
+        ConsensusEngine::delete_old_database_checkpoints(9, 5, dir.path())
+            .expect("Failed to delete checkpoints");
+        assert_eq!(
+            remaining_checkpoints(dir.path()),
+            vec!["5", "6", "7", "8", "9"]
+        );
+
+        ConsensusEngine::delete_old_database_checkpoints(9, 1, dir.path())
+            .expect("Failed to delete checkpoints");
+        assert_eq!(remaining_checkpoints(dir.path()), vec!["9"]);
+    }
+
+    #[test]
+    fn keeps_checkpoints_while_retention_exceeds_session_index() {
+        let dir = checkpoint_dir();
+        create_checkpoints(dir.path(), 0..3);
+
+        ConsensusEngine::delete_old_database_checkpoints(2, 5, dir.path())
+            .expect("Failed to delete checkpoints");
+
+        assert_eq!(remaining_checkpoints(dir.path()), vec!["0", "1", "2"]);
+    }
+
+    #[test]
+    fn ignores_entries_that_are_not_checkpoints() {
+        let dir = checkpoint_dir();
+        create_checkpoints(dir.path(), 0..3);
+        fs::create_dir(dir.path().join("backup")).expect("Failed to create dir");
+
+        ConsensusEngine::delete_old_database_checkpoints(2, 0, dir.path())
+            .expect("Failed to delete checkpoints");
+
+        assert_eq!(remaining_checkpoints(dir.path()), vec!["backup"]);
+    }
+}
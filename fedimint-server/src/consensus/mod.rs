@@ -6,7 +6,7 @@
 pub mod transaction;
 
 use std::collections::BTreeMap;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
@@ -16,7 +16,6 @@
 use db::{ServerDbMigrationContext, get_global_database_migrations};
 use fedimint_api_client::api::DynGlobalApi;
 use fedimint_connectors::ConnectorRegistry;
-use fedimint_core::NumPeers;
 use fedimint_core::config::P2PMessage;
 use fedimint_core::core::{ModuleInstanceId, ModuleKind};
 use fedimint_core::db::{Database, apply_migrations_dbtx, verify_module_db_integrity_dbtx};
@@ -28,29 +27,34 @@
 use fedimint_core::net::peers::DynP2PConnections;
 use fedimint_core::task::{TaskGroup, sleep};
 use fedimint_core::util::{FmtCompactAnyhow as _, SafeUrl};
+use fedimint_core::{NumPeers, runtime};
 use fedimint_logging::{LOG_CONSENSUS, LOG_CORE, LOG_NET_API};
 use fedimint_server_core::bitcoin_rpc::{DynServerBitcoinRpc, ServerBitcoinRpcMonitor};
 use fedimint_server_core::dashboard_ui::IDashboardApi;
 use fedimint_server_core::migration::apply_migrations_server_dbtx;
 use fedimint_server_core::{DynServerModule, ServerModuleInitRegistry};
 use futures::FutureExt;
-use iroh::Endpoint;
-use iroh::endpoint::{Incoming, RecvStream, SendStream};
+use iroh::endpoint::{ConnectionType, Incoming, RecvStream, SendStream};
+use iroh::{Endpoint, NodeId};
 use jsonrpsee::RpcModule;
 use jsonrpsee::server::ServerHandle;
 use serde_json::Value;
 use tokio::net::TcpListener;
 use tokio::sync::{Semaphore, watch};
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
+use crate::config::io::DB_FILE;
 use crate::config::{ServerConfig, ServerConfigLocal};
-use crate::connection_limits::ConnectionLimits;
+use crate::connection_limits::{
+    ConnectionLimiter, ConnectionLimits, ConnectionPermit, RequestRateLimiter,
+};
 use crate::consensus::api::{ConsensusApi, server_endpoints};
 use crate::consensus::engine::ConsensusEngine;
 use crate::db::verify_server_db_integrity_dbtx;
 use crate::metrics::{
     IROH_API_CONNECTION_DURATION_SECONDS, IROH_API_CONNECTIONS_ACTIVE,
-    IROH_API_REQUEST_DURATION_SECONDS,
+    IROH_API_REQUEST_DURATION_SECONDS, IROH_API_REQUESTS_RATE_LIMITED_TOTAL,
+    spawn_db_size_update_task,
 };
 use crate::net::api::announcement::get_api_urls;
 use crate::net::api::{ApiSecrets, HasApiContext};
@@ -80,6 +84,7 @@ pub async fn run(
     dashboard_ui_router: DashboardUiRouter,
     db_checkpoint_retention: u64,
     iroh_api_limits: ConnectionLimits,
+    shutdown_drain_timeout: Duration,
 ) -> anyhow::Result<()> {
     cfg.validate_config(&cfg.local.identity, &module_init_registry)?;
 
@@ -176,6 +181,8 @@ pub async fn run(
     let (submission_sender, submission_receiver) = async_channel::bounded(TRANSACTION_BUFFER);
     let (shutdown_sender, shutdown_receiver) = watch::channel(None);
     let (ord_latency_sender, ord_latency_receiver) = watch::channel(None);
+    let (db_checkpoint_retention_sender, db_checkpoint_retention_receiver) =
+        watch::channel(db_checkpoint_retention);
 
     let mut ci_status_senders = BTreeMap::new();
     let mut ci_status_receivers = BTreeMap::new();
@@ -207,6 +214,8 @@ pub async fn run(
         force_api_secret: force_api_secrets.get_active(),
         code_version_str,
         task_group: task_group.clone(),
+        db_checkpoint_retention: db_checkpoint_retention_sender,
+        iroh_api_connection_limiter: ConnectionLimiter::new(iroh_api_limits.max_connections),
     };
 
     info!(target: LOG_CONSENSUS, "Starting Consensus Api...");
@@ -219,6 +228,20 @@ pub async fn run(
     )
     .await;
 
+    // Stop the ws API on shutdown, giving in-flight requests up to the drain
+    // timeout to finish
+    let ws_api_handler = api_handler.clone();
+    task_group.spawn("drain-consensus-api", move |handle| async move {
+        handle.make_shutdown_rx().await;
+        if ws_api_handler.stop().is_ok()
+            && runtime::timeout(shutdown_drain_timeout, ws_api_handler.stopped())
+                .await
+                .is_err()
+        {
+            warn!(target: LOG_NET_API, "Consensus api did not drain before the shutdown drain timeout");
+        }
+    });
+
     if let Some(iroh_api_sk) = cfg.private.iroh_api_sk.clone()
         && let Err(e) = Box::pin(start_iroh_api(
             iroh_api_sk,
@@ -228,6 +251,7 @@ pub async fn run(
             consensus_api.clone(),
             task_group,
             iroh_api_limits,
+            shutdown_drain_timeout,
         ))
         .await
     {
@@ -286,6 +310,8 @@ pub async fn run(
         sleep(Duration::from_secs(1)).await;
     }
 
+    spawn_db_size_update_task(task_group, data_dir.join(DB_FILE));
+
     info!(target: LOG_CONSENSUS, "Starting Consensus Engine...");
 
     let api_urls = get_api_urls(&db, &cfg.consensus).await;
@@ -308,7 +334,7 @@ pub async fn run(
         modules: module_registry,
         task_group: task_group.clone(),
         data_dir,
-        db_checkpoint_retention,
+        db_checkpoint_retention: db_checkpoint_retention_receiver,
     }
     .run()
     .await?;
@@ -420,6 +446,7 @@ async fn start_iroh_api(
     consensus_api: ConsensusApi,
     task_group: &TaskGroup,
     iroh_api_limits: ConnectionLimits,
+    shutdown_drain_timeout: Duration,
 ) -> anyhow::Result<()> {
     let endpoint = build_iroh_endpoint(
         secret_key,
@@ -431,7 +458,13 @@ async fn start_iroh_api(
     .await?;
     task_group.spawn_cancellable(
         "iroh-api",
-        run_iroh_api(consensus_api, endpoint, task_group.clone(), iroh_api_limits),
+        run_iroh_api(
+            consensus_api,
+            endpoint,
+            task_group.clone(),
+            iroh_api_limits,
+            shutdown_drain_timeout,
+        ),
     );
 
     Ok(())
@@ -442,6 +475,7 @@ async fn run_iroh_api(
     endpoint: Endpoint,
     task_group: TaskGroup,
     iroh_api_limits: ConnectionLimits,
+    shutdown_drain_timeout: Duration,
 ) {
     let core_api = server_endpoints()
         .into_iter()
@@ -462,26 +496,26 @@ async fn run_iroh_api(
         })
         .collect::<BTreeMap<ModuleInstanceId, BTreeMap<String, ApiEndpoint<DynServerModule>>>>();
 
+    let connection_limiter = consensus_api.iroh_api_connection_limiter.clone();
+    let rate_limiter = iroh_api_limits
+        .max_requests_per_second
+        .map(RequestRateLimiter::new);
     let consensus_api = Arc::new(consensus_api);
     let core_api = Arc::new(core_api);
     let module_api = Arc::new(module_api);
-    let parallel_connections_limit = Arc::new(Semaphore::new(iroh_api_limits.max_connections));
 
     loop {
         match endpoint.accept().await {
             Some(incoming) => {
-                if parallel_connections_limit.available_permits() == 0 {
+                let Some(permit) = connection_limiter.try_acquire() else {
                     warn!(
                         target: LOG_NET_API,
-                        limit = iroh_api_limits.max_connections,
-                        "Iroh API connection limit reached, blocking new connections"
+                        limit = connection_limiter.status().max_connections,
+                        "Iroh API connection limit reached, rejecting new connection"
                     );
-                }
-                let permit = parallel_connections_limit
-                    .clone()
-                    .acquire_owned()
-                    .await
-                    .expect("semaphore should not be closed");
+                    incoming.refuse();
+                    continue;
+                };
                 task_group.spawn_cancellable_silent(
                     "handle-iroh-connection",
                     handle_incoming(
@@ -489,9 +523,12 @@ async fn run_iroh_api(
                         core_api.clone(),
                         module_api.clone(),
                         task_group.clone(),
+                        endpoint.clone(),
                         incoming,
                         permit,
                         iroh_api_limits.max_requests_per_connection,
+                        rate_limiter.clone(),
+                        shutdown_drain_timeout,
                     )
                     .then(|result| async {
                         if let Err(err) = result {
@@ -510,11 +547,15 @@ async fn handle_incoming(
     core_api: Arc<BTreeMap<String, ApiEndpoint<ConsensusApi>>>,
     module_api: Arc<BTreeMap<ModuleInstanceId, BTreeMap<String, ApiEndpoint<DynServerModule>>>>,
     task_group: TaskGroup,
+    endpoint: Endpoint,
     incoming: Incoming,
-    _connection_permit: tokio::sync::OwnedSemaphorePermit,
+    _connection_permit: ConnectionPermit,
     iroh_api_max_requests_per_connection: usize,
+    rate_limiter: Option<Arc<RequestRateLimiter<RateLimitKey>>>,
+    shutdown_drain_timeout: Duration,
 ) -> anyhow::Result<()> {
     let connection = incoming.accept()?.await?;
+    let remote_node_id = connection.remote_node_id()?;
     let parallel_requests_limit = Arc::new(Semaphore::new(iroh_api_max_requests_per_connection));
 
     IROH_API_CONNECTIONS_ACTIVE.inc();
@@ -539,22 +580,55 @@ async fn handle_incoming(
             .acquire_owned()
             .await
             .expect("semaphore should not be closed");
-        task_group.spawn_cancellable_silent(
-            "handle-iroh-request",
-            handle_request(
-                consensus_api.clone(),
-                core_api.clone(),
-                module_api.clone(),
-                send_stream,
-                recv_stream,
-                permit,
-            )
-            .then(|result| async {
-                if let Err(err) = result {
+
+        if let Some(rate_limiter) = &rate_limiter {
+            let key = rate_limit_key(&endpoint, remote_node_id);
+            if !rate_limiter.try_acquire(key) {
+                IROH_API_REQUESTS_RATE_LIMITED_TOTAL.inc();
+                debug!(
+                    target: LOG_NET_API,
+                    ?key,
+                    status = ?rate_limiter.status(),
+                    "Iroh API rate limit exceeded, rejecting request"
+                );
+                // The rejection holds on to the request permit, so a client
+                // that keeps exceeding the limit can't pile up rejections
+                task_group.spawn_silent("reject-iroh-request", |_| {
+                    reject_request(send_stream, permit).then(|result| async {
+                        if let Err(err) = result {
+                            warn!(target: LOG_NET_API, err = %err.fmt_compact_anyhow(), "Failed to reject iroh request");
+                        }
+                    })
+                });
+                continue;
+            }
+        }
+
+        // Requests are not cancelled right away on shutdown so they can finish
+        // within the shutdown drain timeout, while cancelling this task stops
+        // accepting new ones
+        let request = handle_request(
+            consensus_api.clone(),
+            core_api.clone(),
+            module_api.clone(),
+            send_stream,
+            recv_stream,
+            permit,
+        );
+        task_group.spawn_silent("handle-iroh-request", move |handle| async move {
+            match handle
+                .cancel_after_shutdown_drain(shutdown_drain_timeout, request)
+                .await
+            {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => {
                     warn!(target: LOG_NET_API, err = %err.fmt_compact_anyhow(), "Failed to handle iroh request");
                 }
-            }),
-        );
+                Err(_) => {
+                    debug!(target: LOG_NET_API, "Cancelled iroh request after the shutdown drain timeout");
+                }
+            }
+        });
     }
 }
 
@@ -588,6 +662,49 @@ async fn handle_request(
     Ok(())
 }
 
+/// Remote an iroh API request is rate limited by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum RateLimitKey {
+    /// IP address of a client connected directly
+    Ip(IpAddr),
+    /// Node id of a client only reachable through a relay, whose IP address is
+    /// not known yet
+    Relayed(NodeId),
+}
+
+fn rate_limit_key(endpoint: &Endpoint, node_id: NodeId) -> RateLimitKey {
+    match endpoint
+        .conn_type(node_id)
+        .ok()
+        .and_then(|conn_type| conn_type.get().ok())
+    {
+        Some(ConnectionType::Direct(addr) | ConnectionType::Mixed(addr, _)) => {
+            RateLimitKey::Ip(addr.ip())
+        }
+        Some(ConnectionType::Relay(_) | ConnectionType::None) | None => {
+            RateLimitKey::Relayed(node_id)
+        }
+    }
+}
+
+async fn reject_request(
+    mut send_stream: SendStream,
+    _request_permit: tokio::sync::OwnedSemaphorePermit,
+) -> anyhow::Result<()> {
+    let response: Result<Value, ApiError> = Err(ApiError::new(
+        429,
+        "Rate limit exceeded, retry later".to_string(),
+    ));
+
+    send_stream
+        .write_all(&serde_json::to_vec(&response)?)
+        .await?;
+
+    send_stream.finish()?;
+
+    Ok(())
+}
+
 async fn await_response(
     consensus_api: Arc<ConsensusApi>,
     core_api: Arc<BTreeMap<String, ApiEndpoint<ConsensusApi>>>,
@@ -31,7 +31,7 @@
 use anyhow::Context;
 use config::ServerConfig;
 use config::io::{PLAINTEXT_PASSWORD, read_server_config};
-pub use connection_limits::ConnectionLimits;
+pub use connection_limits::{ConnectionLimits, RequestRateLimiterStatus};
 use fedimint_aead::random_salt;
 use fedimint_connectors::ConnectorRegistry;
 use fedimint_core::config::P2PMessage;
@@ -99,6 +99,7 @@ pub async fn run(
     dashboard_ui_router: DashboardUiRouter,
     db_checkpoint_retention: u64,
     iroh_api_limits: ConnectionLimits,
+    shutdown_drain_timeout: Duration,
 ) -> anyhow::Result<()> {
     let (cfg, connections, p2p_status_receivers) = match get_config(&data_dir)? {
         Some(cfg) => {
@@ -194,6 +195,7 @@ pub async fn run(
         dashboard_ui_router,
         db_checkpoint_retention,
         iroh_api_limits,
+        shutdown_drain_timeout,
     ))
     .await?;
 
@@ -3,6 +3,7 @@
 
 pub(crate) mod jsonrpsee;
 
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 use std::time::Duration;
 
@@ -10,7 +11,8 @@
 use fedimint_core::db::{Database, IDatabaseTransactionOpsCoreTyped};
 use fedimint_core::task::{TaskGroup, sleep};
 use fedimint_metrics::prometheus::{
-    HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, register_histogram_vec_with_registry,
+    HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+    register_histogram_vec_with_registry, register_int_counter_with_registry,
     register_int_gauge_vec_with_registry, register_int_gauge_with_registry,
 };
 use fedimint_metrics::{
@@ -24,6 +26,8 @@
 
 const BACKUP_STATS_REFRESH_INTERVAL: Duration = Duration::from_mins(1);
 
+const DB_SIZE_REFRESH_INTERVAL: Duration = Duration::from_mins(1);
+
 pub static TX_ELEMS_BUCKETS: LazyLock<Vec<f64>> = LazyLock::new(|| {
     vec![
         1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0,
@@ -120,6 +124,18 @@
     .unwrap()
 });
 
+pub(crate) static IROH_API_REQUESTS_RATE_LIMITED_TOTAL: LazyLock<IntCounter> =
+    LazyLock::new(|| {
+        register_int_counter_with_registry!(
+            opts!(
+                "iroh_api_requests_rate_limited_total",
+                "Number of iroh API requests rejected by the per-client rate limit",
+            ),
+            REGISTRY
+        )
+        .unwrap()
+    });
+
 pub(crate) static IROH_API_REQUEST_DURATION_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
     register_histogram_vec_with_registry!(
         histogram_opts!(
@@ -250,6 +266,24 @@
     )
     .unwrap()
 });
+pub(crate) static PEER_CONNECTED: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec_with_registry!(
+        opts!(
+            "peer_connected",
+            "Whether we are currently connected to the peer (1) or not (0)",
+        ),
+        &["self_id", "peer_id"],
+        REGISTRY
+    )
+    .unwrap()
+});
+pub(crate) static DB_SIZE_BYTES: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge_with_registry!(
+        opts!("db_size_bytes", "Size of the database on disk",),
+        REGISTRY
+    )
+    .unwrap()
+});
 
 /// Initialize gauges or other metrics that need eager initialization on start,
 /// e.g. because they are triggered infrequently.
@@ -315,3 +349,32 @@ pub(crate) async fn initialize_gauge_metrics(tg: &TaskGroup, db: &Database) {
         })
         .await;
 }
+
+/// Regularly updates [`DB_SIZE_BYTES`] with the size of the database directory
+/// at `db_path`, if it exists. In-memory databases are not reported.
+pub(crate) fn spawn_db_size_update_task(tg: &TaskGroup, db_path: PathBuf) {
+    tg.spawn_cancellable("prometheus_db_size", async move {
+        loop {
+            let db_path = db_path.clone();
+            if let Ok(Ok(size)) = tokio::task::spawn_blocking(move || dir_size(&db_path)).await {
+                DB_SIZE_BYTES.set(size.try_into().expect("u64 to i64 overflow"));
+            }
+
+            sleep(DB_SIZE_REFRESH_INTERVAL).await;
+        }
+    });
+}
+
+fn dir_size(path: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
@@ -21,7 +21,9 @@
 use tokio::sync::watch;
 use tracing::{Instrument, debug, info, info_span, warn};
 
-use crate::metrics::{PEER_CONNECT_COUNT, PEER_DISCONNECT_COUNT, PEER_MESSAGES_COUNT};
+use crate::metrics::{
+    PEER_CONNECT_COUNT, PEER_CONNECTED, PEER_DISCONNECT_COUNT, PEER_MESSAGES_COUNT,
+};
 use crate::net::p2p_connection::DynP2PConnection;
 use crate::net::p2p_connector::DynP2PConnector;
 
@@ -241,6 +243,9 @@ async fn state_transition(mut self) -> Option<Self> {
         match self.state {
             P2PConnectionSMState::Disconnected(backoff) => {
                 self.common.status_sender.send_replace(None);
+                PEER_CONNECTED
+                    .with_label_values(&[&self.common.our_id_str, &self.common.peer_id_str])
+                    .set(0);
 
                 self.common.transition_disconnected(backoff).await
             }
@@ -251,6 +256,9 @@ async fn state_transition(mut self) -> Option<Self> {
                 };
 
                 self.common.status_sender.send_replace(Some(status));
+                PEER_CONNECTED
+                    .with_label_values(&[&self.common.our_id_str, &self.common.peer_id_str])
+                    .set(1);
 
                 self.common.transition_connected(connection).await
             }
@@ -4,7 +4,7 @@
 
 use fedimint_api_client::api::{DynGlobalApi, FederationApiExt};
 use fedimint_client::module_init::ClientModuleInitRegistry;
-use fedimint_client::{Client, ClientHandleArc, RootSecret};
+use fedimint_client::{Client, ClientHandleArc, RootSecret, RootSecretDeriver};
 use fedimint_client_module::AdminCreds;
 use fedimint_client_module::secret::{PlainRootSecretStrategy, RootSecretStrategy};
 use fedimint_connectors::ConnectorRegistry;
@@ -140,6 +140,17 @@ pub async fn join_client_with_db(
         db: Database,
         root_secret: RootSecret,
     ) -> ClientHandleArc {
+        self.try_join_client_with_db(db, root_secret)
+            .await
+            .expect("Failed to join client")
+    }
+
+    /// Like [`Self::join_client_with_db`], but returns joining errors
+    pub async fn try_join_client_with_db(
+        &self,
+        db: Database,
+        root_secret: RootSecret,
+    ) -> anyhow::Result<ClientHandleArc> {
         let client_config = self.configs[&PeerId::from(0)]
             .consensus
             .to_client_config(&self.server_init)
@@ -155,7 +166,32 @@ pub async fn join_client_with_db(
             .join(db, root_secret)
             .await
             .map(Arc::new)
-            .expect("Failed to join client")
+    }
+
+    /// Like [`Self::try_join_client_with_db`], but derives the client's root
+    /// secret with `deriver`
+    pub async fn try_join_client_with_deriver(
+        &self,
+        db: Database,
+        root_secret: RootSecret,
+        deriver: Arc<dyn RootSecretDeriver>,
+    ) -> anyhow::Result<ClientHandleArc> {
+        let client_config = self.configs[&PeerId::from(0)]
+            .consensus
+            .to_client_config(&self.server_init)
+            .unwrap();
+
+        info!(target: LOG_TEST, "Joining client with root secret deriver");
+        let mut client_builder = Client::builder().await.expect("Failed to build client");
+        client_builder.with_module_inits(self.client_init.clone());
+        client_builder
+            .with_root_secret_deriver(deriver)
+            .preview_with_existing_config(self.connectors.clone(), client_config, None)
+            .await
+            .expect("Preview failed")
+            .join(db, root_secret)
+            .await
+            .map(Arc::new)
     }
 
     /// Create a recovering client with an existing database and root secret.
@@ -200,6 +236,40 @@ pub async fn open_client_with_db(
             .expect("Failed to open client")
     }
 
+    /// Like [`Self::open_client_with_db`], but derives the client's root
+    /// secret with `deriver` and returns opening errors
+    pub async fn try_open_client_with_deriver(
+        &self,
+        db: Database,
+        root_secret: RootSecret,
+        deriver: Arc<dyn RootSecretDeriver>,
+    ) -> anyhow::Result<ClientHandleArc> {
+        info!(target: LOG_TEST, "Opening client with root secret deriver");
+        let mut client_builder = Client::builder().await.expect("Failed to build client");
+        client_builder.with_module_inits(self.client_init.clone());
+        client_builder
+            .with_root_secret_deriver(deriver)
+            .open(self.connectors.clone(), db, root_secret)
+            .await
+            .map(Arc::new)
+    }
+
+    /// Open an existing client database in watch-only mode
+    pub async fn open_watch_only_client_with_db(
+        &self,
+        db: Database,
+        root_secret: RootSecret,
+    ) -> ClientHandleArc {
+        info!(target: LOG_TEST, "Opening watch-only client with existing db");
+        let mut client_builder = Client::builder().await.expect("Failed to build client");
+        client_builder.with_module_inits(self.client_init.clone());
+        client_builder
+            .open_watch_only(self.connectors.clone(), db, root_secret)
+            .await
+            .map(Arc::new)
+            .expect("Failed to open watch-only client")
+    }
+
     /// Return first invite code for gateways
     pub fn invite_code(&self) -> InviteCode {
         let peer_id = PeerId::from(0);
@@ -228,6 +298,7 @@ pub async fn connect_gateway(&self, gw: &Gateway) {
             invite_code: self.invite_code().to_string(),
             use_tor: Some(false),
             recover: Some(false),
+            modules: None,
         })
         .await
         .expect("Failed to connect federation");
@@ -399,7 +470,9 @@ pub async fn build(self) -> FederationTest {
                     ConnectionLimits {
                         max_connections: 1000,
                         max_requests_per_connection: 100,
+                        max_requests_per_second: None,
                     },
+                    Duration::from_secs(10),
                 ))
                 .await
                 .expect("Could not initialise consensus");
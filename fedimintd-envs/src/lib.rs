@@ -32,8 +32,12 @@
 
 pub const FM_DB_CHECKPOINT_RETENTION_ENV: &str = "FM_DB_CHECKPOINT_RETENTION";
 
+pub const FM_SHUTDOWN_DRAIN_TIMEOUT_ENV: &str = "FM_SHUTDOWN_DRAIN_TIMEOUT";
+
 pub const FM_IROH_API_MAX_CONNECTIONS_ENV: &str = "FM_IROH_API_MAX_CONNECTIONS";
 
+pub const FM_MAX_CLIENT_RPS_ENV: &str = "FM_MAX_CLIENT_RPS";
+
 pub const FM_BITCOIND_USERNAME_ENV: &str = "FM_BITCOIND_USERNAME";
 
 pub const FM_BITCOIND_PASSWORD_ENV: &str = "FM_BITCOIND_PASSWORD";
@@ -51,7 +51,8 @@
     FM_BITCOIND_URL_ENV, FM_BITCOIND_URL_PASSWORD_FILE_ENV, FM_BITCOIND_USERNAME_ENV,
     FM_DATA_DIR_ENV, FM_DB_CHECKPOINT_RETENTION_ENV, FM_DISABLE_META_MODULE_ENV,
     FM_ENABLE_IROH_ENV, FM_ESPLORA_URL_ENV, FM_FORCE_API_SECRETS_ENV,
-    FM_IROH_API_MAX_CONNECTIONS_ENV, FM_IROH_API_MAX_REQUESTS_PER_CONNECTION_ENV, FM_P2P_URL_ENV,
+    FM_IROH_API_MAX_CONNECTIONS_ENV, FM_IROH_API_MAX_REQUESTS_PER_CONNECTION_ENV,
+    FM_MAX_CLIENT_RPS_ENV, FM_P2P_URL_ENV, FM_SHUTDOWN_DRAIN_TIMEOUT_ENV,
 };
 use futures::FutureExt as _;
 #[cfg(all(
@@ -63,9 +64,6 @@
 
 use crate::metrics::APP_START_TS;
 
-/// Time we will wait before forcefully shutting down tasks
-const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
-
 #[derive(Parser)]
 #[command(version)]
 #[command(
@@ -177,6 +175,11 @@ struct ServerOpts {
     #[arg(long, env = FM_DB_CHECKPOINT_RETENTION_ENV, default_value = "1")]
     db_checkpoint_retention: u64,
 
+    /// Seconds to wait on shutdown for in-flight API requests and other tasks
+    /// to finish before forcefully aborting them
+    #[arg(long, env = FM_SHUTDOWN_DRAIN_TIMEOUT_ENV, default_value = "10")]
+    shutdown_drain_timeout: u64,
+
     /// Enable tokio console logging
     #[arg(long, env = FM_BIND_TOKIO_CONSOLE_ENV)]
     bind_tokio_console: Option<SocketAddr>,
@@ -213,6 +216,15 @@ struct ServerOpts {
     /// Maximum number of parallel requests per Iroh API connection
     #[arg(long = "iroh-api-max-requests-per-connection", env = FM_IROH_API_MAX_REQUESTS_PER_CONNECTION_ENV, default_value = "50")]
     iroh_api_max_requests_per_connection: usize,
+
+    /// Maximum number of Iroh API requests per second per client IP address,
+    /// requests above the limit are rejected with error code 429
+    ///
+    /// Only applies to the Iroh API, not the websocket API. Clients that are
+    /// connected through a relay are limited by their node id until a direct
+    /// connection to them is established.
+    #[arg(long = "max-client-rps", env = FM_MAX_CLIENT_RPS_ENV)]
+    max_client_rps: Option<u32>,
 }
 
 impl ServerOpts {
@@ -409,6 +421,7 @@ pub async fn run(
 
     install_crypto_provider().await;
 
+    let shutdown_drain_timeout = Duration::from_secs(server_opts.shutdown_drain_timeout);
     let task_group = root_task_group.clone();
     root_task_group.spawn_cancellable("main", async move {
         fedimint_server::run(
@@ -426,7 +439,9 @@ pub async fn run(
             fedimint_server::ConnectionLimits::new(
                 server_opts.iroh_api_max_connections,
                 server_opts.iroh_api_max_requests_per_connection,
-            ),
+            )
+            .with_max_requests_per_second(server_opts.max_client_rps),
+            shutdown_drain_timeout,
         )
         .await
         .unwrap_or_else(|err| panic!("Main task returned error: {}", err.fmt_compact_anyhow()));
@@ -443,7 +458,7 @@ pub async fn run(
 
     debug!(target: LOG_CORE, "Terminating main task");
 
-    if let Err(err) = root_task_group.join_all(Some(SHUTDOWN_TIMEOUT)).await {
+    if let Err(err) = root_task_group.join_all(Some(shutdown_drain_timeout)).await {
         error!(target: LOG_CORE, err = %err.fmt_compact_anyhow(), "Error while shutting down task group");
     }
 
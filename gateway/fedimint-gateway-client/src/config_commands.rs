@@ -2,14 +2,21 @@
 use fedimint_core::Amount;
 use fedimint_core::config::FederationId;
 use fedimint_core::util::SafeUrl;
-use fedimint_gateway_client::{get_config, get_info, set_fees, set_mnemonic};
-use fedimint_gateway_common::{ConfigPayload, SetFeesPayload, SetMnemonicPayload};
+use fedimint_gateway_client::{
+    get_config, get_fees, get_info, get_limits, get_routing_policy, set_fees, set_limits,
+    set_mnemonic, set_routing_policy,
+};
+use fedimint_gateway_common::{
+    ConfigPayload, GetFeesPayload, GetLimitsPayload, MAX_FEE_PARTS_PER_MILLION, RouteHintMode,
+    SetFeesPayload, SetLimitsPayload, SetMnemonicPayload, SetRoutingPolicyPayload,
+};
 use fedimint_ln_common::client::GatewayApi;
 
 use crate::{CliOutput, CliOutputResult};
 
 /// Management commands for changing or displaying configuration, including
-/// setting fees per federation.
+/// setting fees and payment limits per federation and the lightning routing
+/// policy.
 #[derive(Subcommand)]
 pub enum ConfigCommands {
     /// Gets each connected federation's JSON client config
@@ -22,7 +29,13 @@ pub enum ConfigCommands {
         #[clap(long)]
         federation_id: Option<FederationId>,
     },
-    /// Set the gateway's lightning or transaction fees
+    /// Gets the gateway's lightning and transaction fees for each federation
+    GetFees {
+        #[clap(long)]
+        federation_id: Option<FederationId>,
+    },
+    /// Set the gateway's lightning or transaction fees and print the resulting
+    /// fee schedule
     SetFees {
         #[clap(long)]
         federation_id: Option<FederationId>,
@@ -30,15 +43,52 @@ pub enum ConfigCommands {
         #[clap(long)]
         ln_base: Option<Amount>,
 
-        #[clap(long)]
+        #[clap(long, value_parser = clap::value_parser!(u64).range(..=MAX_FEE_PARTS_PER_MILLION))]
         ln_ppm: Option<u64>,
 
         #[clap(long)]
         tx_base: Option<Amount>,
 
-        #[clap(long)]
+        #[clap(long, value_parser = clap::value_parser!(u64).range(..=MAX_FEE_PARTS_PER_MILLION))]
         tx_ppm: Option<u64>,
     },
+    /// Gets the gateway's lightning routing policy
+    GetRoutingPolicy,
+    /// Set the gateway's lightning routing policy without restarting it and
+    /// print the resulting policy
+    SetRoutingPolicy {
+        /// CLTV expiry delta in blocks the gateway requests for LNv2 payments
+        #[clap(long)]
+        cltv_delta: Option<u64>,
+
+        /// Whether to include route hints for private channels in invoices
+        #[clap(long, value_enum)]
+        route_hint_mode: Option<RouteHintMode>,
+    },
+    /// Gets a federation's send and receive limits and the amounts used within
+    /// the current window
+    GetLimits {
+        #[clap(long)]
+        federation_id: FederationId,
+    },
+    /// Limit how much a federation's users can send or receive through the
+    /// gateway within a sliding window. Omitting both limits removes them.
+    SetLimits {
+        #[clap(long)]
+        federation_id: FederationId,
+
+        /// Maximum amount in msat the federation's users can send per window
+        #[clap(long)]
+        max_send_msat: Option<u64>,
+
+        /// Maximum amount in msat the federation's users can receive per window
+        #[clap(long)]
+        max_receive_msat: Option<u64>,
+
+        /// Length of the sliding window in seconds
+        #[clap(long, default_value_t = 86400)]
+        window_secs: u64,
+    },
     /// Instructs the gateway to create a new mnemonic or set it to the provided
     /// mnemonic
     SetMnemonic {
@@ -69,6 +119,10 @@ pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> CliOutputR
                     .collect::<Vec<_>>();
                 Ok(CliOutput::FederationConfigs(federations))
             }
+            Self::GetFees { federation_id } => {
+                let fees = get_fees(client, base_url, GetFeesPayload { federation_id }).await?;
+                Ok(CliOutput::Fees(fees))
+            }
             Self::SetFees {
                 federation_id,
                 ln_base,
@@ -88,7 +142,52 @@ pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> CliOutputR
                     },
                 )
                 .await?;
-                Ok(CliOutput::Empty)
+
+                let fees = get_fees(client, base_url, GetFeesPayload { federation_id }).await?;
+                Ok(CliOutput::Fees(fees))
+            }
+            Self::GetRoutingPolicy => {
+                let routing_policy = get_routing_policy(client, base_url).await?;
+                Ok(CliOutput::RoutingPolicy(routing_policy))
+            }
+            Self::SetRoutingPolicy {
+                cltv_delta,
+                route_hint_mode,
+            } => {
+                let routing_policy = set_routing_policy(
+                    client,
+                    base_url,
+                    SetRoutingPolicyPayload {
+                        cltv_delta,
+                        route_hint_mode,
+                    },
+                )
+                .await?;
+                Ok(CliOutput::RoutingPolicy(routing_policy))
+            }
+            Self::GetLimits { federation_id } => {
+                let limits =
+                    get_limits(client, base_url, GetLimitsPayload { federation_id }).await?;
+                Ok(CliOutput::Limits(limits))
+            }
+            Self::SetLimits {
+                federation_id,
+                max_send_msat,
+                max_receive_msat,
+                window_secs,
+            } => {
+                let limits = set_limits(
+                    client,
+                    base_url,
+                    SetLimitsPayload {
+                        federation_id,
+                        max_send_msat,
+                        max_receive_msat,
+                        window_secs,
+                    },
+                )
+                .await?;
+                Ok(CliOutput::Limits(limits))
             }
             Self::SetMnemonic { words } => {
                 set_mnemonic(client, base_url, SetMnemonicPayload { words }).await?;
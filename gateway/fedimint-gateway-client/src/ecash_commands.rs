@@ -5,12 +5,13 @@
 use fedimint_core::util::SafeUrl;
 use fedimint_core::{Amount, BitcoinAmountOrAll};
 use fedimint_gateway_client::{
-    backup, get_deposit_address, pegin_from_onchain, receive_ecash, recheck_address, spend_ecash,
-    withdraw, withdraw_to_onchain,
+    backup, consolidate_notes, get_deposit_address, notes, pegin_from_onchain, rebalance,
+    receive_ecash, recheck_address, spend_ecash, withdraw, withdraw_to_onchain,
 };
 use fedimint_gateway_common::{
-    BackupPayload, DepositAddressPayload, DepositAddressRecheckPayload, PeginFromOnchainPayload,
-    ReceiveEcashPayload, SpendEcashPayload, WithdrawPayload, WithdrawToOnchainPayload,
+    BackupPayload, ConsolidateNotesPayload, DepositAddressPayload, DepositAddressRecheckPayload,
+    NotesPayload, PeginFromOnchainPayload, RebalancePayload, ReceiveEcashPayload,
+    SpendEcashPayload, WithdrawPayload, WithdrawToOnchainPayload,
 };
 use fedimint_ln_common::client::GatewayApi;
 
@@ -69,6 +70,19 @@ pub enum EcashCommands {
         #[clap(long)]
         amount: BitcoinAmountOrAll,
     },
+    /// Move funds from one federation to another on-chain by pegging out of
+    /// `from_federation` directly into a deposit address of `to_federation`.
+    /// The funds are available in `to_federation` once the peg-out confirms.
+    Rebalance {
+        #[clap(long)]
+        from_federation: FederationId,
+        #[clap(long)]
+        to_federation: FederationId,
+        /// The amount to move, rounded down to whole sats. The peg-out fees
+        /// are paid by `from_federation` on top of it.
+        #[clap(long)]
+        amount_msat: u64,
+    },
     /// Send e-cash out of band
     Send {
         #[clap(long)]
@@ -83,6 +97,16 @@ pub enum EcashCommands {
         #[arg(long = "no-wait", action = clap::ArgAction::SetFalse)]
         wait: bool,
     },
+    /// Show the number of e-cash notes per denomination held in a federation
+    Notes {
+        #[clap(long)]
+        federation_id: FederationId,
+    },
+    /// Reissue excess small denomination notes into fewer, larger notes
+    Consolidate {
+        #[clap(long)]
+        federation_id: FederationId,
+    },
 }
 
 impl EcashCommands {
@@ -167,6 +191,24 @@ pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> CliOutputR
 
                 Ok(CliOutput::Withdraw(response))
             }
+            Self::Rebalance {
+                from_federation,
+                to_federation,
+                amount_msat,
+            } => {
+                let response = rebalance(
+                    client,
+                    base_url,
+                    RebalancePayload {
+                        from_federation_id: from_federation,
+                        to_federation_id: to_federation,
+                        amount: Amount::from_msats(amount_msat),
+                    },
+                )
+                .await?;
+
+                Ok(CliOutput::Rebalance(response))
+            }
             Self::Send {
                 federation_id,
                 amount,
@@ -188,6 +230,16 @@ pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> CliOutputR
                     receive_ecash(client, base_url, ReceiveEcashPayload { notes, wait }).await?;
                 Ok(CliOutput::ReceiveEcash(response))
             }
+            Self::Notes { federation_id } => {
+                let response = notes(client, base_url, NotesPayload { federation_id }).await?;
+                Ok(CliOutput::NoteCounts(response))
+            }
+            Self::Consolidate { federation_id } => {
+                let response =
+                    consolidate_notes(client, base_url, ConsolidateNotesPayload { federation_id })
+                        .await?;
+                Ok(CliOutput::ConsolidateNotes(response))
+            }
         }
     }
 }
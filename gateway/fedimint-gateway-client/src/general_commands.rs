@@ -1,23 +1,225 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use std::time::{Duration, UNIX_EPOCH};
 
-use clap::Subcommand;
+use clap::{Subcommand, ValueEnum};
 use fedimint_connectors::error::ServerError;
 use fedimint_core::config::FederationId;
-use fedimint_core::fedimint_build_code_version_env;
+use fedimint_core::core::ModuleKind;
 use fedimint_core::time::now;
 use fedimint_core::util::SafeUrl;
-use fedimint_eventlog::{EventKind, EventLogId};
+use fedimint_core::{Amount, fedimint_build_code_version_env};
+use fedimint_eventlog::{EventKind, EventLogId, PersistedLogEntry};
 use fedimint_gateway_client::{
-    connect_federation, get_balances, get_info, get_invite_codes, get_mnemonic, leave_federation,
-    payment_log, payment_summary, stop,
+    connect_federation, export_state, federation_ready, get_balances, get_info, get_invite_codes,
+    get_mnemonic, health_check, import_state, in_flight_payments, leave_federation,
+    list_federations, payment_log, payment_log_tail, payment_summary, preview_federation,
+    reregister, self_test, stop,
 };
 use fedimint_gateway_common::{
-    ConnectFedPayload, LeaveFedPayload, PaymentLogPayload, PaymentSummaryPayload,
+    ConnectFedPayload, FederationReadiness, FederationReadyPayload, GatewayStateBackup,
+    LeaveFedPayload, LightningInfo, PaymentLogPayload, PaymentLogTailPayload,
+    PaymentSummaryGroupBy, PaymentSummaryPayload, PreviewFedPayload, ReregisterPayload,
+    SelfTestPayload,
 };
 use fedimint_ln_common::client::GatewayApi;
+use serde::Serialize;
 
 use crate::{CliOutput, CliOutputResult};
 
+/// How long to wait for a federation to become ready if no timeout is given
+const DEFAULT_WAIT_READY_TIMEOUT_SECS: u64 = 300;
+
+/// How often to ask the gateway whether a federation is ready
+const WAIT_READY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the gateway holds a `PaymentTail` request open waiting for new
+/// events
+const PAYMENT_TAIL_WAIT_SECS: u64 = 30;
+
+/// Time left for the gateway to answer a `PaymentTail` request after its
+/// long-poll ended, when `--timeout` limits the request
+const PAYMENT_TAIL_RESPONSE_MARGIN_SECS: u64 = 5;
+
+/// File format of [`GeneralCommands::PaymentLogExport`]
+#[derive(Clone, Copy, ValueEnum)]
+pub enum PaymentLogExportFormat {
+    Csv,
+    Jsonl,
+}
+
+/// One exported payment log event
+#[derive(Serialize)]
+struct PaymentLogExportRow {
+    position: EventLogId,
+    ts_usecs: u64,
+    kind: EventKind,
+    /// Amount in msats, if the event carries one
+    amount_msats: Option<u64>,
+    federation_id: FederationId,
+}
+
+impl PaymentLogExportRow {
+    /// Payload fields holding the amount of an event, in order of preference
+    const AMOUNT_FIELDS: [&str; 4] = [
+        "amount",
+        "spent_amount",
+        "invoice_amount",
+        "min_contract_amount",
+    ];
+
+    fn new(entry: &PersistedLogEntry, federation_id: FederationId) -> Self {
+        let payload = serde_json::from_slice::<serde_json::Value>(&entry.payload).ok();
+        let amount_msats = payload.as_ref().and_then(|payload| {
+            Self::AMOUNT_FIELDS
+                .iter()
+                .find_map(|field| payload.get(field)?.as_u64())
+        });
+
+        Self {
+            position: entry.id(),
+            ts_usecs: entry.ts_usecs,
+            kind: entry.kind.clone(),
+            amount_msats,
+            federation_id,
+        }
+    }
+
+    fn write(&self, writer: &mut impl Write, format: PaymentLogExportFormat) -> anyhow::Result<()> {
+        match format {
+            PaymentLogExportFormat::Csv => writeln!(
+                writer,
+                "{},{},{},{},{}",
+                self.position,
+                self.ts_usecs,
+                self.kind,
+                self.amount_msats
+                    .map_or_else(String::new, |amount| amount.to_string()),
+                self.federation_id
+            )?,
+            PaymentLogExportFormat::Jsonl => {
+                serde_json::to_writer(&mut *writer, self)?;
+                writeln!(writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_state_backup(backup: &GatewayStateBackup, out: &Path) -> anyhow::Result<()> {
+    let mut writer = BufWriter::new(File::create(out)?);
+    serde_json::to_writer_pretty(&mut writer, backup)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn read_state_backup(path: &Path) -> anyhow::Result<GatewayStateBackup> {
+    let file = File::open(path)?;
+    Ok(serde_json::from_reader(BufReader::new(file))?)
+}
+
+/// Reads a password from `path`, or from stdin if `path` is `-`. A single
+/// trailing newline is stripped.
+fn read_password_file(path: &Path) -> anyhow::Result<String> {
+    let mut password = String::new();
+    if path == Path::new("-") {
+        std::io::stdin().read_to_string(&mut password)?;
+    } else {
+        File::open(path)?.read_to_string(&mut password)?;
+    }
+
+    let password = password
+        .strip_suffix('\n')
+        .map(|password| password.strip_suffix('\r').unwrap_or(password))
+        .unwrap_or(&password);
+    anyhow::ensure!(!password.is_empty(), "Password file is empty");
+    Ok(password.to_string())
+}
+
+/// Writes the whole payment log of a federation to `out`, newest events
+/// first, fetching `pagination_size` events at a time. Returns the number of
+/// exported events.
+async fn export_payment_log(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    federation_id: FederationId,
+    pagination_size: usize,
+    format: PaymentLogExportFormat,
+    out: &Path,
+) -> anyhow::Result<usize> {
+    let mut writer = BufWriter::new(File::create(out)?);
+    if matches!(format, PaymentLogExportFormat::Csv) {
+        writeln!(writer, "position,ts_usecs,kind,amount_msats,federation_id")?;
+    }
+
+    let mut end_position = None;
+    let mut num_events = 0;
+    loop {
+        let page = payment_log(
+            client,
+            base_url,
+            PaymentLogPayload {
+                end_position,
+                pagination_size,
+                federation_id,
+                event_kinds: vec![],
+                start_millis: None,
+                end_millis: None,
+            },
+        )
+        .await?;
+
+        for entry in &page.0 {
+            PaymentLogExportRow::new(entry, federation_id).write(&mut writer, format)?;
+        }
+        num_events += page.0.len();
+
+        match page.0.last().and_then(|oldest| oldest.id().checked_sub(1)) {
+            Some(next_end_position) if page.0.len() == pagination_size => {
+                end_position = Some(next_end_position);
+            }
+            _ => break,
+        }
+    }
+
+    writer.flush()?;
+    Ok(num_events)
+}
+
+/// Asks the operator to confirm that leaving the federation abandons the
+/// `stranded` ecash
+fn confirm_leave(federation_id: FederationId, stranded: Amount) -> anyhow::Result<bool> {
+    eprint!(
+        "The gateway still holds {stranded} of ecash in federation {federation_id}, which will be stranded. Type \"yes\" to leave anyway: "
+    );
+    std::io::stderr().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim() == "yes")
+}
+
+/// Polls the gateway until the federation is ready to serve payments or the
+/// timeout elapses and returns the last reported readiness
+async fn wait_ready(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    federation_id: FederationId,
+    timeout: Duration,
+) -> Result<FederationReadiness, ServerError> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let readiness =
+            federation_ready(client, base_url, FederationReadyPayload { federation_id }).await?;
+        if readiness.is_ready() || tokio::time::Instant::now() >= deadline {
+            return Ok(readiness);
+        }
+
+        tokio::time::sleep(WAIT_READY_POLL_INTERVAL).await;
+    }
+}
+
 /// General federation management commands including info, connecting, or
 /// leaving a federation.
 #[derive(Subcommand)]
@@ -26,6 +228,9 @@ pub enum GeneralCommands {
     VersionHash,
     /// Display high-level information about the gateway.
     Info,
+    /// Display the identity of the gateway: the node id of its lightning
+    /// node, the public keys it registers with federations and its network.
+    Identity,
     /// Get the total on-chain, lightning, and eCash balances of the gateway.
     GetBalances,
     /// Register the gateway with a federation.
@@ -39,14 +244,90 @@ pub enum GeneralCommands {
         /// Indicates if the client should be recovered from a mnemonic
         #[clap(long)]
         recover: Option<bool>,
+        /// Comma separated module kinds to mount in the federation client,
+        /// e.g. `mint,wallet,lnv2`. The mint and the gateway's lightning
+        /// modules are always mounted. All supported modules are mounted if
+        /// omitted.
+        #[clap(long, value_delimiter = ',')]
+        modules: Vec<String>,
+        /// Block until the federation is ready to serve payments. Exits with a
+        /// non-zero code if it is not ready within `--wait-timeout`.
+        #[clap(long)]
+        wait: bool,
+        /// Seconds to wait for the federation to become ready
+        #[clap(long, default_value_t = DEFAULT_WAIT_READY_TIMEOUT_SECS, requires = "wait")]
+        wait_timeout: u64,
+    },
+    /// Block until the gateway reports the federation as ready to serve
+    /// payments. Exits with a non-zero code if the timeout elapses first.
+    WaitReady {
+        #[clap(long)]
+        federation_id: FederationId,
+        /// Seconds to wait for the federation to become ready
+        #[clap(long, default_value_t = DEFAULT_WAIT_READY_TIMEOUT_SECS)]
+        timeout: u64,
     },
+    /// Register the gateway with a federation, or all connected federations,
+    /// right away instead of waiting for the periodic registration.
+    Reregister {
+        /// Federation to register with, all connected federations if omitted
+        #[clap(long)]
+        federation_id: Option<FederationId>,
+    },
+    /// Show the id, name, network and modules of a federation without
+    /// registering the gateway with it.
+    PreviewFed {
+        /// Invite code of the federation
+        invite_code: String,
+    },
+    /// List all connected federations with the gateway's ecash balance in
+    /// each of them.
+    ListFeds,
     /// Leave a federation.
     LeaveFed {
         #[clap(long)]
         federation_id: FederationId,
+        /// Leave even if the gateway still holds ecash in the federation
+        #[clap(long)]
+        force: bool,
+        /// Do not ask for confirmation before abandoning the ecash
+        #[clap(long, requires = "force")]
+        yes: bool,
+    },
+    /// Check the lightning node, its on-chain wallet and each federation's
+    /// API, printing one status line per subsystem. Exits with a non-zero
+    /// code if any of them is unhealthy.
+    HealthCheck,
+    /// Spend and reissue a small amount of the gateway's ecash, then pay it to
+    /// itself over LNv2 and print the outcome and duration of each leg. Exits
+    /// with a non-zero code if any leg failed.
+    SelfTest {
+        #[clap(long)]
+        federation_id: FederationId,
+        /// Amount to move, 100 sats if omitted
+        #[clap(long)]
+        amount: Option<Amount>,
     },
+    /// List the lightning payments the gateway is currently mediating and how
+    /// long each of them has been pending
+    InFlight,
     /// Prints the seed phrase for the gateway
     Seed,
+    /// Write the gateway's persistent configuration (connected federations,
+    /// fees, routing policy and lightning node) to a file
+    Backup {
+        /// File to write the backup to
+        #[clap(long)]
+        out: PathBuf,
+    },
+    /// Reconnect to every federation in a backup created with `backup`,
+    /// recovering the ecash from the seed, and restore the fees and routing
+    /// policy
+    Restore {
+        /// File to read the backup from
+        #[clap(long)]
+        r#in: PathBuf,
+    },
     /// Safely stop the gateway
     Stop,
     /// List the fedimint transactions that the gateway has processed
@@ -62,10 +343,51 @@ pub enum GeneralCommands {
 
         #[clap(long)]
         event_kinds: Vec<EventKind>,
+
+        /// Only list events at or after this time (unix millis)
+        #[clap(long)]
+        start_time: Option<u64>,
+
+        /// Only list events at or before this time (unix millis)
+        #[clap(long)]
+        end_time: Option<u64>,
+    },
+    /// Follow the payment log of a federation, printing new events as JSON
+    /// lines as they occur until interrupted
+    PaymentTail {
+        #[clap(long)]
+        federation_id: FederationId,
+
+        #[clap(long)]
+        event_kinds: Vec<EventKind>,
+    },
+    /// Export the whole payment log of a federation to a file, one event per
+    /// row
+    PaymentLogExport {
+        #[clap(long)]
+        federation_id: FederationId,
+
+        #[clap(long, value_enum, default_value = "csv")]
+        format: PaymentLogExportFormat,
+
+        /// File to write the payment log to
+        #[clap(long)]
+        out: PathBuf,
+
+        /// Number of events to fetch from the gateway per request
+        #[clap(long, default_value_t = 100)]
+        pagination_size: usize,
     },
     /// Create a bcrypt hash of a password, for use in gateway deployment
     CreatePasswordHash {
-        password: String,
+        /// The password to hash. Prefer `--password-file` to keep it out of
+        /// shell history and process listings.
+        #[clap(required_unless_present = "password_file")]
+        password: Option<String>,
+
+        /// Read the password from a file instead, or from stdin if `-`
+        #[clap(long, conflicts_with = "password")]
+        password_file: Option<PathBuf>,
 
         /// The bcrypt cost factor to use when hashing the password
         #[clap(long)]
@@ -78,6 +400,10 @@ pub enum GeneralCommands {
 
         #[clap(long)]
         end: Option<u64>,
+
+        /// Break the summary down by federation or by UTC day
+        #[clap(long, value_enum)]
+        group_by: Option<PaymentSummaryGroupBy>,
     },
     /// List all invite codes of each federation the gateway has joined
     InviteCodes,
@@ -87,15 +413,35 @@ impl GeneralCommands {
     #[allow(clippy::too_many_lines)]
     pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> CliOutputResult {
         match self {
-            Self::VersionHash => {
-                // Keep version-hash as raw string output for backward compatibility
-                println!("{}", fedimint_build_code_version_env!());
-                Ok(CliOutput::Empty)
-            }
+            Self::VersionHash => Ok(CliOutput::VersionHash(
+                fedimint_build_code_version_env!().to_string(),
+            )),
             Self::Info => {
                 let response = get_info(client, base_url).await?;
                 Ok(CliOutput::Info(response))
             }
+            Self::Identity => {
+                let info = get_info(client, base_url).await?;
+                let (node_id, network) = match info.lightning_info {
+                    LightningInfo::Connected {
+                        public_key,
+                        network,
+                        ..
+                    } => (Some(public_key), Some(network)),
+                    LightningInfo::NotConnected => (None, None),
+                };
+                let gateway_pubkeys = info
+                    .registrations
+                    .into_iter()
+                    .map(|(protocol, (_url, pubkey))| (protocol, pubkey))
+                    .collect();
+
+                Ok(CliOutput::Identity {
+                    node_id,
+                    gateway_pubkeys,
+                    network,
+                })
+            }
             Self::GetBalances => {
                 let response = get_balances(client, base_url).await?;
                 Ok(CliOutput::Balances(response))
@@ -105,6 +451,9 @@ pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> CliOutputR
                 #[cfg(feature = "tor")]
                 use_tor,
                 recover,
+                modules,
+                wait,
+                wait_timeout,
             } => {
                 let response = connect_federation(
                     client,
@@ -116,30 +465,186 @@ pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> CliOutputR
                         #[cfg(not(feature = "tor"))]
                         use_tor: None,
                         recover,
+                        modules: (!modules.is_empty()).then(|| {
+                            modules
+                                .iter()
+                                .map(|kind| ModuleKind::clone_from_str(kind))
+                                .collect()
+                        }),
                     },
                 )
                 .await?;
 
+                if wait {
+                    let readiness = wait_ready(
+                        client,
+                        base_url,
+                        response.federation_id,
+                        Duration::from_secs(wait_timeout),
+                    )
+                    .await?;
+                    if !readiness.is_ready() {
+                        return Ok(CliOutput::FederationReadiness(readiness));
+                    }
+                }
+
                 Ok(CliOutput::Federation(response))
             }
-            Self::LeaveFed { federation_id } => {
+            Self::WaitReady {
+                federation_id,
+                timeout,
+            } => {
+                let readiness = wait_ready(
+                    client,
+                    base_url,
+                    federation_id,
+                    Duration::from_secs(timeout),
+                )
+                .await?;
+                Ok(CliOutput::FederationReadiness(readiness))
+            }
+            Self::Reregister { federation_id } => {
+                let response =
+                    reregister(client, base_url, ReregisterPayload { federation_id }).await?;
+                Ok(CliOutput::Registrations(response))
+            }
+            Self::PreviewFed { invite_code } => {
                 let response =
-                    leave_federation(client, base_url, LeaveFedPayload { federation_id }).await?;
+                    preview_federation(client, base_url, PreviewFedPayload { invite_code }).await?;
+                Ok(CliOutput::FederationPreview(response))
+            }
+            Self::HealthCheck => {
+                let response = health_check(client, base_url).await?;
+                Ok(CliOutput::HealthCheck(response))
+            }
+            Self::SelfTest {
+                federation_id,
+                amount,
+            } => {
+                let response = self_test(
+                    client,
+                    base_url,
+                    SelfTestPayload {
+                        federation_id,
+                        amount,
+                    },
+                )
+                .await?;
+                Ok(CliOutput::SelfTest(response))
+            }
+            Self::InFlight => {
+                let response = in_flight_payments(client, base_url).await?;
+                Ok(CliOutput::InFlightPayments(response))
+            }
+            Self::ListFeds => {
+                let response = list_federations(client, base_url).await?;
+                Ok(CliOutput::Federations(response))
+            }
+            Self::LeaveFed {
+                federation_id,
+                force,
+                yes,
+            } => {
+                if force && !yes {
+                    let stranded = get_balances(client, base_url)
+                        .await?
+                        .ecash_balances
+                        .into_iter()
+                        .find(|balance| balance.federation_id == federation_id)
+                        .map_or(Amount::ZERO, |balance| balance.ecash_balance_msats);
+
+                    if stranded != Amount::ZERO
+                        && !confirm_leave(federation_id, stranded)
+                            .map_err(ServerError::InternalClientError)?
+                    {
+                        return Err(ServerError::InternalClientError(anyhow::anyhow!(
+                            "Did not leave federation {federation_id}"
+                        )));
+                    }
+                }
+
+                let response = leave_federation(
+                    client,
+                    base_url,
+                    LeaveFedPayload {
+                        federation_id,
+                        force,
+                    },
+                )
+                .await?;
                 Ok(CliOutput::Federation(response))
             }
             Self::Seed => {
                 let response = get_mnemonic(client, base_url).await?;
                 Ok(CliOutput::Mnemonic(response))
             }
+            Self::Backup { out } => {
+                let backup = export_state(client, base_url).await?;
+                let num_federations = backup.federations.len();
+                write_state_backup(&backup, &out).map_err(ServerError::InternalClientError)?;
+                Ok(CliOutput::StateBackup {
+                    path: out,
+                    num_federations,
+                })
+            }
+            Self::Restore { r#in } => {
+                let backup = read_state_backup(&r#in).map_err(ServerError::InternalClientError)?;
+                let response = import_state(client, base_url, backup).await?;
+                Ok(CliOutput::RestoredFederations(response))
+            }
             Self::Stop => {
                 stop(client, base_url).await?;
                 Ok(CliOutput::Empty)
             }
+            Self::PaymentTail {
+                federation_id,
+                event_kinds,
+            } => {
+                // The long-poll has to end before the request times out
+                let wait_secs = match client.timeout() {
+                    Some(timeout) => {
+                        let wait_secs = timeout
+                            .as_secs()
+                            .saturating_sub(PAYMENT_TAIL_RESPONSE_MARGIN_SECS);
+                        if wait_secs == 0 {
+                            return Err(ServerError::InternalClientError(anyhow::anyhow!(
+                                "payment-tail needs a --timeout of more than {PAYMENT_TAIL_RESPONSE_MARGIN_SECS}s"
+                            )));
+                        }
+                        wait_secs.min(PAYMENT_TAIL_WAIT_SECS)
+                    }
+                    None => PAYMENT_TAIL_WAIT_SECS,
+                };
+                let mut start_position = None;
+                loop {
+                    let response = payment_log_tail(
+                        client,
+                        base_url,
+                        PaymentLogTailPayload {
+                            federation_id,
+                            event_kinds: event_kinds.clone(),
+                            start_position,
+                            wait_secs,
+                        },
+                    )
+                    .await?;
+
+                    for entry in response.entries {
+                        println!(
+                            "{}",
+                            serde_json::to_string(&entry).expect("Cannot serialize")
+                        );
+                    }
+                    start_position = Some(response.next_position);
+                }
+            }
             Self::PaymentLog {
                 end_position,
                 pagination_size,
                 federation_id,
                 event_kinds,
+                start_time,
+                end_time,
             } => {
                 let payment_log = payment_log(
                     client,
@@ -149,17 +654,55 @@ pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> CliOutputR
                         pagination_size,
                         federation_id,
                         event_kinds,
+                        start_millis: start_time,
+                        end_millis: end_time,
                     },
                 )
                 .await?;
                 Ok(CliOutput::PaymentLog(payment_log))
             }
-            Self::CreatePasswordHash { password, cost } => {
+            Self::PaymentLogExport {
+                federation_id,
+                format,
+                out,
+                pagination_size,
+            } => {
+                let num_events = export_payment_log(
+                    client,
+                    base_url,
+                    federation_id,
+                    pagination_size,
+                    format,
+                    &out,
+                )
+                .await
+                .map_err(ServerError::InternalClientError)?;
+                Ok(CliOutput::PaymentLogExport {
+                    path: out,
+                    num_events,
+                })
+            }
+            Self::CreatePasswordHash {
+                password,
+                password_file,
+                cost,
+            } => {
+                let password = match (password, password_file) {
+                    (Some(password), _) => password,
+                    (None, Some(path)) => {
+                        read_password_file(&path).map_err(ServerError::InternalClientError)?
+                    }
+                    (None, None) => unreachable!("clap requires a password or a password file"),
+                };
                 let hash = bcrypt::hash(password, cost.unwrap_or(bcrypt::DEFAULT_COST))
                     .expect("Unable to create bcrypt hash");
                 Ok(CliOutput::PasswordHash(hash))
             }
-            Self::PaymentSummary { start, end } => {
+            Self::PaymentSummary {
+                start,
+                end,
+                group_by,
+            } => {
                 let now = now();
                 let now_millis: u64 = now
                     .duration_since(UNIX_EPOCH)
@@ -184,6 +727,7 @@ pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> CliOutputR
                     PaymentSummaryPayload {
                         start_millis,
                         end_millis,
+                        group_by,
                     },
                 )
                 .await?;
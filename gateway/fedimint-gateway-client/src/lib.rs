@@ -10,24 +10,37 @@
 use fedimint_gateway_common::{
     ADDRESS_ENDPOINT, ADDRESS_RECHECK_ENDPOINT, BACKUP_ENDPOINT, BackupPayload,
     CLOSE_CHANNELS_WITH_PEER_ENDPOINT, CONFIGURATION_ENDPOINT, CONNECT_FED_ENDPOINT,
-    CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT, CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT,
-    ChannelInfo, CloseChannelsWithPeerRequest, CloseChannelsWithPeerResponse, ConfigPayload,
-    ConnectFedPayload, CreateInvoiceForOperatorPayload, CreateOfferPayload, CreateOfferResponse,
-    DepositAddressPayload, DepositAddressRecheckPayload, FederationInfo, GATEWAY_INFO_ENDPOINT,
-    GET_BALANCES_ENDPOINT, GET_INVOICE_ENDPOINT, GET_LN_ONCHAIN_ADDRESS_ENDPOINT, GatewayBalances,
-    GatewayFedConfig, GatewayInfo, GetInvoiceRequest, GetInvoiceResponse, INVITE_CODES_ENDPOINT,
-    LEAVE_FED_ENDPOINT, LIST_CHANNELS_ENDPOINT, LIST_TRANSACTIONS_ENDPOINT, LeaveFedPayload,
-    ListTransactionsPayload, ListTransactionsResponse, MNEMONIC_ENDPOINT, MnemonicResponse,
-    OPEN_CHANNEL_ENDPOINT, OPEN_CHANNEL_WITH_PUSH_ENDPOINT, OpenChannelRequest,
-    PAY_INVOICE_FOR_OPERATOR_ENDPOINT, PAY_OFFER_FOR_OPERATOR_ENDPOINT, PAYMENT_LOG_ENDPOINT,
-    PAYMENT_SUMMARY_ENDPOINT, PEGIN_FROM_ONCHAIN_ENDPOINT, PayInvoiceForOperatorPayload,
+    CONSOLIDATE_NOTES_ENDPOINT, CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT,
+    CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT, ChannelInfo, CloseChannelsWithPeerRequest,
+    CloseChannelsWithPeerResponse, ConfigPayload, ConnectFedPayload, ConsolidateNotesPayload,
+    ConsolidateNotesResponse, CreateInvoiceForOperatorPayload, CreateOfferPayload,
+    CreateOfferResponse, DepositAddressPayload, DepositAddressRecheckPayload,
+    ESTIMATE_FEE_ENDPOINT, EXPORT_STATE_ENDPOINT, EstimateFeePayload, FEDERATION_READY_ENDPOINT,
+    FederationFees, FederationInfo, FederationLimitsResponse, FederationPreview,
+    FederationReadiness, FederationReadyPayload, FederationRegistration, FeeEstimate,
+    GATEWAY_INFO_ENDPOINT, GET_BALANCES_ENDPOINT, GET_FEES_ENDPOINT, GET_INVOICE_ENDPOINT,
+    GET_LIMITS_ENDPOINT, GET_LN_ONCHAIN_ADDRESS_ENDPOINT, GET_ROUTING_POLICY_ENDPOINT,
+    GatewayBalances, GatewayFedConfig, GatewayInfo, GatewayStateBackup, GetFeesPayload,
+    GetInvoiceRequest, GetInvoiceResponse, GetLimitsPayload, HEALTH_CHECK_ENDPOINT,
+    HealthCheckResponse, IMPORT_STATE_ENDPOINT, IN_FLIGHT_PAYMENTS_ENDPOINT, INVITE_CODES_ENDPOINT,
+    InFlightPayment, LEAVE_FED_ENDPOINT, LIST_CHANNELS_ENDPOINT, LIST_FEDERATIONS_ENDPOINT,
+    LIST_TRANSACTIONS_ENDPOINT, LeaveFedPayload, ListFederationsResponse, ListTransactionsPayload,
+    ListTransactionsResponse, MNEMONIC_ENDPOINT, MnemonicResponse, NOTES_ENDPOINT,
+    NoteCountsResponse, NotesPayload, OPEN_CHANNEL_ENDPOINT, OPEN_CHANNEL_WITH_PUSH_ENDPOINT,
+    OpenChannelRequest, PAY_INVOICE_FOR_OPERATOR_ENDPOINT, PAY_OFFER_FOR_OPERATOR_ENDPOINT,
+    PAYMENT_LOG_ENDPOINT, PAYMENT_LOG_TAIL_ENDPOINT, PAYMENT_SUMMARY_ENDPOINT,
+    PEGIN_FROM_ONCHAIN_ENDPOINT, PREVIEW_FED_ENDPOINT, PayInvoiceForOperatorPayload,
     PayOfferPayload, PayOfferResponse, PaymentLogPayload, PaymentLogResponse,
-    PaymentSummaryPayload, PaymentSummaryResponse, PeginFromOnchainPayload, RECEIVE_ECASH_ENDPOINT,
-    ReceiveEcashPayload, ReceiveEcashResponse, SEND_ONCHAIN_ENDPOINT, SET_CHANNEL_FEES_ENDPOINT,
-    SET_FEES_ENDPOINT, SPEND_ECASH_ENDPOINT, STOP_ENDPOINT, SendOnchainRequest,
-    SetChannelFeesRequest, SetFeesPayload, SetMnemonicPayload, SpendEcashPayload,
-    SpendEcashResponse, WITHDRAW_ENDPOINT, WITHDRAW_TO_ONCHAIN_ENDPOINT, WithdrawPayload,
-    WithdrawResponse, WithdrawToOnchainPayload,
+    PaymentLogTailPayload, PaymentLogTailResponse, PaymentSummaryPayload, PaymentSummaryResponse,
+    PeginFromOnchainPayload, PreviewFedPayload, REBALANCE_ENDPOINT, RECEIVE_ECASH_ENDPOINT,
+    REREGISTER_ENDPOINT, RebalancePayload, RebalanceResponse, ReceiveEcashPayload,
+    ReceiveEcashResponse, ReregisterPayload, RoutingPolicy, SELF_TEST_ENDPOINT,
+    SEND_ONCHAIN_ENDPOINT, SET_CHANNEL_FEES_ENDPOINT, SET_FEES_ENDPOINT, SET_LIMITS_ENDPOINT,
+    SET_ROUTING_POLICY_ENDPOINT, SPEND_ECASH_ENDPOINT, STOP_ENDPOINT, SelfTestPayload,
+    SelfTestResponse, SendOnchainRequest, SetChannelFeesRequest, SetFeesPayload, SetLimitsPayload,
+    SetMnemonicPayload, SetRoutingPolicyPayload, SpendEcashPayload, SpendEcashResponse,
+    WITHDRAW_ENDPOINT, WITHDRAW_TO_ONCHAIN_ENDPOINT, WithdrawPayload, WithdrawResponse,
+    WithdrawToOnchainPayload,
 };
 use fedimint_ln_common::Method;
 use fedimint_ln_common::client::GatewayApi;
@@ -79,6 +92,16 @@ pub async fn pegin_from_onchain(
         .await
 }
 
+pub async fn rebalance(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: RebalancePayload,
+) -> ServerResult<RebalanceResponse> {
+    client
+        .request(base_url, Method::POST, REBALANCE_ENDPOINT, Some(payload))
+        .await
+}
+
 pub async fn withdraw(
     client: &GatewayApi,
     base_url: &SafeUrl,
@@ -114,6 +137,16 @@ pub async fn connect_federation(
         .await
 }
 
+pub async fn preview_federation(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: PreviewFedPayload,
+) -> ServerResult<FederationPreview> {
+    client
+        .request(base_url, Method::POST, PREVIEW_FED_ENDPOINT, Some(payload))
+        .await
+}
+
 pub async fn leave_federation(
     client: &GatewayApi,
     base_url: &SafeUrl,
@@ -134,6 +167,31 @@ pub async fn backup(
         .await
 }
 
+pub async fn notes(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: NotesPayload,
+) -> ServerResult<NoteCountsResponse> {
+    client
+        .request(base_url, Method::POST, NOTES_ENDPOINT, Some(payload))
+        .await
+}
+
+pub async fn consolidate_notes(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: ConsolidateNotesPayload,
+) -> ServerResult<ConsolidateNotesResponse> {
+    client
+        .request(
+            base_url,
+            Method::POST,
+            CONSOLIDATE_NOTES_ENDPOINT,
+            Some(payload),
+        )
+        .await
+}
+
 pub async fn set_fees(
     client: &GatewayApi,
     base_url: &SafeUrl,
@@ -144,6 +202,85 @@ pub async fn set_fees(
         .await
 }
 
+pub async fn get_fees(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: GetFeesPayload,
+) -> ServerResult<Vec<FederationFees>> {
+    client
+        .request(base_url, Method::POST, GET_FEES_ENDPOINT, Some(payload))
+        .await
+}
+
+pub async fn federation_ready(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: FederationReadyPayload,
+) -> ServerResult<FederationReadiness> {
+    client
+        .request(
+            base_url,
+            Method::POST,
+            FEDERATION_READY_ENDPOINT,
+            Some(payload),
+        )
+        .await
+}
+
+pub async fn reregister(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: ReregisterPayload,
+) -> ServerResult<Vec<FederationRegistration>> {
+    client
+        .request(base_url, Method::POST, REREGISTER_ENDPOINT, Some(payload))
+        .await
+}
+
+pub async fn get_routing_policy(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+) -> ServerResult<RoutingPolicy> {
+    client
+        .request::<(), RoutingPolicy>(base_url, Method::GET, GET_ROUTING_POLICY_ENDPOINT, None)
+        .await
+}
+
+pub async fn set_routing_policy(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: SetRoutingPolicyPayload,
+) -> ServerResult<RoutingPolicy> {
+    client
+        .request(
+            base_url,
+            Method::POST,
+            SET_ROUTING_POLICY_ENDPOINT,
+            Some(payload),
+        )
+        .await
+}
+
+pub async fn get_limits(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: GetLimitsPayload,
+) -> ServerResult<FederationLimitsResponse> {
+    client
+        .request(base_url, Method::POST, GET_LIMITS_ENDPOINT, Some(payload))
+        .await
+}
+
+pub async fn set_limits(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: SetLimitsPayload,
+) -> ServerResult<FederationLimitsResponse> {
+    client
+        .request(base_url, Method::POST, SET_LIMITS_ENDPOINT, Some(payload))
+        .await
+}
+
 pub async fn create_invoice_for_self(
     client: &GatewayApi,
     base_url: &SafeUrl,
@@ -159,6 +296,16 @@ pub async fn create_invoice_for_self(
         .await
 }
 
+pub async fn estimate_fee(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: EstimateFeePayload,
+) -> ServerResult<FeeEstimate> {
+    client
+        .request(base_url, Method::POST, ESTIMATE_FEE_ENDPOINT, Some(payload))
+        .await
+}
+
 pub async fn pay_invoice(
     client: &GatewayApi,
     base_url: &SafeUrl,
@@ -336,6 +483,21 @@ pub async fn payment_log(
         .await
 }
 
+pub async fn payment_log_tail(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: PaymentLogTailPayload,
+) -> ServerResult<PaymentLogTailResponse> {
+    client
+        .request(
+            base_url,
+            Method::POST,
+            PAYMENT_LOG_TAIL_ENDPOINT,
+            Some(payload),
+        )
+        .await
+}
+
 pub async fn payment_summary(
     client: &GatewayApi,
     base_url: &SafeUrl,
@@ -429,3 +591,69 @@ pub async fn get_invite_codes(
         )
         .await
 }
+
+pub async fn list_federations(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+) -> ServerResult<ListFederationsResponse> {
+    client
+        .request::<(), ListFederationsResponse>(
+            base_url,
+            Method::GET,
+            LIST_FEDERATIONS_ENDPOINT,
+            None,
+        )
+        .await
+}
+
+pub async fn health_check(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+) -> ServerResult<HealthCheckResponse> {
+    client
+        .request::<(), HealthCheckResponse>(base_url, Method::GET, HEALTH_CHECK_ENDPOINT, None)
+        .await
+}
+
+pub async fn self_test(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: SelfTestPayload,
+) -> ServerResult<SelfTestResponse> {
+    client
+        .request(base_url, Method::POST, SELF_TEST_ENDPOINT, Some(payload))
+        .await
+}
+
+pub async fn export_state(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+) -> ServerResult<GatewayStateBackup> {
+    client
+        .request::<(), GatewayStateBackup>(base_url, Method::GET, EXPORT_STATE_ENDPOINT, None)
+        .await
+}
+
+pub async fn import_state(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+    payload: GatewayStateBackup,
+) -> ServerResult<Vec<FederationInfo>> {
+    client
+        .request(base_url, Method::POST, IMPORT_STATE_ENDPOINT, Some(payload))
+        .await
+}
+
+pub async fn in_flight_payments(
+    client: &GatewayApi,
+    base_url: &SafeUrl,
+) -> ServerResult<Vec<InFlightPayment>> {
+    client
+        .request::<(), Vec<InFlightPayment>>(
+            base_url,
+            Method::GET,
+            IN_FLIGHT_PAYMENTS_ENDPOINT,
+            None,
+        )
+        .await
+}
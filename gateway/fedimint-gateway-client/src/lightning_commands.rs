@@ -3,15 +3,16 @@
 use clap::Subcommand;
 use fedimint_connectors::error::ServerError;
 use fedimint_core::Amount;
+use fedimint_core::config::FederationId;
 use fedimint_gateway_client::{
-    close_channels_with_peer, create_invoice_for_self, create_offer, get_invoice, list_channels,
-    list_transactions, open_channel, open_channel_with_push, pay_invoice, pay_offer,
+    close_channels_with_peer, create_invoice_for_self, create_offer, estimate_fee, get_invoice,
+    list_channels, list_transactions, open_channel, open_channel_with_push, pay_invoice, pay_offer,
     set_channel_fees,
 };
 use fedimint_gateway_common::{
     CloseChannelsWithPeerRequest, CreateInvoiceForOperatorPayload, CreateOfferPayload,
-    GetInvoiceRequest, ListTransactionsPayload, OpenChannelRequest, PayInvoiceForOperatorPayload,
-    PayOfferPayload, SetChannelFeesRequest,
+    EstimateFeePayload, GetInvoiceRequest, ListTransactionsPayload, OpenChannelRequest,
+    PayInvoiceForOperatorPayload, PayOfferPayload, SetChannelFeesRequest,
 };
 use fedimint_ln_common::client::GatewayApi;
 use lightning_invoice::Bolt11Invoice;
@@ -34,6 +35,14 @@ pub enum LightningCommands {
     },
     /// Pay a lightning invoice as the gateway (i.e. no e-cash exchange).
     PayInvoice { invoice: Bolt11Invoice },
+    /// Compute the fee the gateway charges a client of a federation for
+    /// paying an invoice, without paying it.
+    EstimateFee {
+        #[clap(long)]
+        federation_id: FederationId,
+
+        invoice: Bolt11Invoice,
+    },
     /// Open a channel with another lightning node.
     OpenChannel {
         /// The public key of the node to open a channel with
@@ -176,6 +185,21 @@ pub async fn handle(self, client: &GatewayApi, base_url: &SafeUrl) -> CliOutputR
                     pay_invoice(client, base_url, PayInvoiceForOperatorPayload { invoice }).await?;
                 Ok(CliOutput::Preimage { preimage })
             }
+            Self::EstimateFee {
+                federation_id,
+                invoice,
+            } => {
+                let estimate = estimate_fee(
+                    client,
+                    base_url,
+                    EstimateFeePayload {
+                        federation_id,
+                        invoice,
+                    },
+                )
+                .await?;
+                Ok(CliOutput::FeeEstimate(estimate))
+            }
             Self::OpenChannel {
                 pubkey,
                 host,
@@ -19,11 +19,15 @@
 use fedimint_core::config::FederationId;
 use fedimint_core::invite_code::InviteCode;
 use fedimint_core::util::SafeUrl;
+use fedimint_gateway_common::envs::FM_GATEWAY_CLI_JSON_ENV;
 use fedimint_gateway_common::{
-    ChannelInfo, CloseChannelsWithPeerResponse, CreateOfferResponse, FederationConfig,
-    FederationInfo, GatewayBalances, GatewayFedConfig, GatewayInfo, GetInvoiceResponse,
-    ListTransactionsResponse, MnemonicResponse, PayOfferResponse, PaymentLogResponse,
-    PaymentSummaryResponse, ReceiveEcashResponse, SpendEcashResponse, WithdrawResponse,
+    ChannelInfo, CloseChannelsWithPeerResponse, ConsolidateNotesResponse, CreateOfferResponse,
+    FederationConfig, FederationFees, FederationInfo, FederationLimitsResponse, FederationPreview,
+    FederationReadiness, FederationRegistration, FeeEstimate, GatewayBalances, GatewayFedConfig,
+    GatewayInfo, GetInvoiceResponse, HealthCheckResponse, InFlightPayment, ListFederationsResponse,
+    ListTransactionsResponse, MnemonicResponse, NoteCountsResponse, PayOfferResponse,
+    PaymentLogResponse, PaymentSummaryResponse, RebalanceResponse, ReceiveEcashResponse,
+    RegisteredProtocol, RoutingPolicy, SelfTestResponse, SpendEcashResponse, WithdrawResponse,
 };
 use fedimint_ln_common::client::GatewayApi;
 use fedimint_logging::TracingSetup;
@@ -42,13 +46,39 @@
 pub enum CliOutput {
     // General commands
     Info(GatewayInfo),
+    Identity {
+        /// Public key of the lightning node, if it is connected
+        node_id: Option<bitcoin::secp256k1::PublicKey>,
+        /// Public keys the gateway registers with federations, per protocol
+        gateway_pubkeys: BTreeMap<RegisteredProtocol, bitcoin::secp256k1::PublicKey>,
+        network: Option<bitcoin::Network>,
+    },
     Balances(GatewayBalances),
     Federation(FederationInfo),
+    FederationPreview(FederationPreview),
+    FederationReadiness(FederationReadiness),
+    Registrations(Vec<FederationRegistration>),
+    Federations(ListFederationsResponse),
+    HealthCheck(HealthCheckResponse),
+    SelfTest(SelfTestResponse),
+    InFlightPayments(Vec<InFlightPayment>),
     Mnemonic(MnemonicResponse),
     PaymentLog(PaymentLogResponse),
+    PaymentLogExport {
+        path: std::path::PathBuf,
+        num_events: usize,
+    },
     PaymentSummary(PaymentSummaryResponse),
+    StateBackup {
+        path: std::path::PathBuf,
+        num_federations: usize,
+    },
+    RestoredFederations(Vec<FederationInfo>),
     InviteCodes(BTreeMap<FederationId, BTreeMap<PeerId, (String, InviteCode)>>),
     PasswordHash(String),
+    /// Printed as a raw string unless `--json` is used, for backward
+    /// compatibility
+    VersionHash(String),
 
     // Lightning commands
     Invoice {
@@ -57,6 +87,7 @@ pub enum CliOutput {
     Preimage {
         preimage: String,
     },
+    FeeEstimate(FeeEstimate),
     FundingTxid {
         funding_txid: Txid,
     },
@@ -76,8 +107,11 @@ pub enum CliOutput {
         txid: Txid,
     },
     Withdraw(WithdrawResponse),
+    Rebalance(RebalanceResponse),
     SpendEcash(SpendEcashResponse),
     ReceiveEcash(ReceiveEcashResponse),
+    NoteCounts(NoteCountsResponse),
+    ConsolidateNotes(ConsolidateNotesResponse),
 
     // Onchain commands
     OnchainAddress {
@@ -90,6 +124,9 @@ pub enum CliOutput {
     // Config commands
     Config(GatewayFedConfig),
     FederationConfigs(Vec<FederationConfig>),
+    Fees(Vec<FederationFees>),
+    RoutingPolicy(RoutingPolicy),
+    Limits(FederationLimitsResponse),
 
     // No output (for commands that succeed silently)
     #[serde(skip)]
@@ -139,6 +176,7 @@ pub enum ExitCode {
     InvalidInput = 4,
     NotFound = 5,
     Timeout = 6,
+    Unhealthy = 7,
 }
 
 impl From<ErrorCode> for ExitCode {
@@ -228,6 +266,19 @@ struct Cli {
     /// Password for authenticated requests to the gateway
     #[clap(long)]
     rpcpassword: Option<String>,
+
+    /// Print compact, single-line JSON (including errors) instead of
+    /// pretty-printed output, for scripting
+    #[clap(long, global = true, env = FM_GATEWAY_CLI_JSON_ENV)]
+    json: bool,
+
+    /// Seconds to wait for the gateway to answer a request before failing
+    #[clap(long, global = true)]
+    timeout: Option<u64>,
+
+    /// How often to retry requests that failed to reach the gateway
+    #[clap(long, global = true, default_value_t = 0)]
+    retries: usize,
 }
 
 #[derive(Subcommand)]
@@ -249,33 +300,50 @@ enum Commands {
 
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+    let json = cli.json;
+
     if let Err(err) = TracingSetup::default().init() {
         let cli_err = CliError {
             error: format!("Failed to initialize logging: {err}"),
             code: ErrorCode::Internal,
             cause: None,
         };
-        print_response(&cli_err);
+        print_response(&cli_err, json);
         std::process::exit(ExitCode::GeneralError as i32);
     }
 
-    if let Err(err) = run().await {
-        let cli_err = CliError::from_server_error(&err);
-        let exit_code = ExitCode::from(cli_err.code);
-        print_response(&cli_err);
-        std::process::exit(exit_code as i32);
+    match run(cli).await {
+        Ok(CliOutput::HealthCheck(health)) if !health.is_healthy() => {
+            std::process::exit(ExitCode::Unhealthy as i32);
+        }
+        Ok(CliOutput::SelfTest(self_test)) if !self_test.is_success() => {
+            std::process::exit(ExitCode::Unhealthy as i32);
+        }
+        Ok(CliOutput::FederationReadiness(readiness)) if !readiness.is_ready() => {
+            std::process::exit(ExitCode::Timeout as i32);
+        }
+        Ok(_) => {}
+        Err(err) => {
+            let cli_err = CliError::from_server_error(&err);
+            let exit_code = ExitCode::from(cli_err.code);
+            print_response(&cli_err, json);
+            std::process::exit(exit_code as i32);
+        }
     }
 }
 
-async fn run() -> CliOutputResult {
-    let cli = Cli::parse();
+async fn run(cli: Cli) -> CliOutputResult {
     let connector_registry = ConnectorRegistry::build_from_client_defaults()
         .with_env_var_overrides()
         .map_err(ServerError::InternalClientError)?
         .bind()
         .await
         .map_err(ServerError::InternalClientError)?;
-    let client = GatewayApi::new(cli.rpcpassword, connector_registry);
+    let mut client = GatewayApi::new(cli.rpcpassword, connector_registry).with_retries(cli.retries);
+    if let Some(timeout) = cli.timeout {
+        client = client.with_timeout(std::time::Duration::from_secs(timeout));
+    }
 
     let output = match cli.command {
         Commands::General(general_command) => general_command.handle(&client, &cli.address).await?,
@@ -296,17 +364,36 @@ async fn run() -> CliOutputResult {
         }
     };
 
-    // Only print output for non-empty results
-    if !matches!(output, CliOutput::Empty) {
-        print_response(&output);
+    match &output {
+        // Only print output for non-empty results
+        CliOutput::Empty => {}
+        CliOutput::VersionHash(version_hash) if !cli.json => println!("{version_hash}"),
+        CliOutput::HealthCheck(health) if !cli.json => {
+            for subsystem in &health.subsystems {
+                let status = if subsystem.healthy { "ok" } else { "FAIL" };
+                println!("{}: {status} ({})", subsystem.subsystem, subsystem.detail);
+            }
+        }
+        CliOutput::SelfTest(self_test) if !cli.json => {
+            for leg in &self_test.legs {
+                let status = if leg.success { "ok" } else { "FAIL" };
+                println!(
+                    "{}: {status} in {}ms ({})",
+                    leg.leg, leg.duration_ms, leg.detail
+                );
+            }
+        }
+        output => print_response(output, cli.json),
     }
 
     Ok(output)
 }
 
-fn print_response<T: Serialize>(val: T) {
-    println!(
-        "{}",
-        serde_json::to_string_pretty(&val).expect("Cannot serialize")
-    );
+fn print_response<T: Serialize>(val: T, json: bool) {
+    let output = if json {
+        serde_json::to_string(&val)
+    } else {
+        serde_json::to_string_pretty(&val)
+    };
+    println!("{}", output.expect("Cannot serialize"));
 }
@@ -24,3 +24,7 @@
 /// `SendPaymentRequest`. Must parse as an f64 in the range [-1.0, 1.0], where
 /// -1 optimizes for fees and 1 optimizes for reliability.
 pub const FM_LND_TIME_PREF_ENV: &str = "FM_LND_TIME_PREF";
+
+/// Environment variable that makes `gateway-cli` print compact, single-line
+/// JSON instead of pretty-printed output
+pub const FM_GATEWAY_CLI_JSON_ENV: &str = "FM_GATEWAY_CLI_JSON";
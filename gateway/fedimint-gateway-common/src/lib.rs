@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::time::{Duration, SystemTime};
 
@@ -12,6 +12,7 @@
     FM_LND_TLS_CERT_ENV, FM_PORT_LDK,
 };
 use fedimint_core::config::{FederationId, JsonClientConfig};
+use fedimint_core::core::{ModuleInstanceId, ModuleKind, OperationId};
 use fedimint_core::encoding::{Decodable, Encodable};
 use fedimint_core::invite_code::InviteCode;
 use fedimint_core::util::{SafeUrl, get_average, get_median};
@@ -31,28 +32,47 @@
 pub const BACKUP_ENDPOINT: &str = "/backup";
 pub const CONFIGURATION_ENDPOINT: &str = "/config";
 pub const CONNECT_FED_ENDPOINT: &str = "/connect_fed";
+pub const CONSOLIDATE_NOTES_ENDPOINT: &str = "/consolidate_notes";
 pub const CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT: &str = "/create_bolt11_invoice_for_operator";
 pub const CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT: &str = "/create_bolt12_offer_for_operator";
+pub const ESTIMATE_FEE_ENDPOINT: &str = "/estimate_fee";
+pub const EXPORT_STATE_ENDPOINT: &str = "/export_state";
+pub const FEDERATION_READY_ENDPOINT: &str = "/federation_ready";
 pub const GATEWAY_INFO_ENDPOINT: &str = "/info";
 pub const INVITE_CODES_ENDPOINT: &str = "/invite_codes";
 pub const GET_BALANCES_ENDPOINT: &str = "/balances";
+pub const GET_FEES_ENDPOINT: &str = "/get_fees";
 pub const GET_INVOICE_ENDPOINT: &str = "/get_invoice";
+pub const GET_LIMITS_ENDPOINT: &str = "/get_limits";
 pub const GET_LN_ONCHAIN_ADDRESS_ENDPOINT: &str = "/get_ln_onchain_address";
+pub const GET_ROUTING_POLICY_ENDPOINT: &str = "/get_routing_policy";
+pub const HEALTH_CHECK_ENDPOINT: &str = "/health_check";
+pub const IMPORT_STATE_ENDPOINT: &str = "/import_state";
+pub const IN_FLIGHT_PAYMENTS_ENDPOINT: &str = "/in_flight_payments";
 pub const LEAVE_FED_ENDPOINT: &str = "/leave_fed";
 pub const LIST_CHANNELS_ENDPOINT: &str = "/list_channels";
+pub const LIST_FEDERATIONS_ENDPOINT: &str = "/list_federations";
 pub const LIST_TRANSACTIONS_ENDPOINT: &str = "/list_transactions";
 pub const MNEMONIC_ENDPOINT: &str = "/mnemonic";
+pub const NOTES_ENDPOINT: &str = "/notes";
 pub const OPEN_CHANNEL_ENDPOINT: &str = "/open_channel";
 pub const OPEN_CHANNEL_WITH_PUSH_ENDPOINT: &str = "/open_channel_with_push";
 pub const CLOSE_CHANNELS_WITH_PEER_ENDPOINT: &str = "/close_channels_with_peer";
 pub const PAY_INVOICE_FOR_OPERATOR_ENDPOINT: &str = "/pay_invoice_for_operator";
 pub const PAY_OFFER_FOR_OPERATOR_ENDPOINT: &str = "/pay_offer_for_operator";
 pub const PAYMENT_LOG_ENDPOINT: &str = "/payment_log";
+pub const PAYMENT_LOG_TAIL_ENDPOINT: &str = "/payment_log_tail";
 pub const PAYMENT_SUMMARY_ENDPOINT: &str = "/payment_summary";
 pub const PEGIN_FROM_ONCHAIN_ENDPOINT: &str = "/pegin_from_onchain";
+pub const PREVIEW_FED_ENDPOINT: &str = "/preview_fed";
+pub const REBALANCE_ENDPOINT: &str = "/rebalance";
 pub const RECEIVE_ECASH_ENDPOINT: &str = "/receive_ecash";
+pub const REREGISTER_ENDPOINT: &str = "/reregister";
+pub const SELF_TEST_ENDPOINT: &str = "/self_test";
 pub const SET_CHANNEL_FEES_ENDPOINT: &str = "/set_channel_fees";
 pub const SET_FEES_ENDPOINT: &str = "/set_fees";
+pub const SET_LIMITS_ENDPOINT: &str = "/set_limits";
+pub const SET_ROUTING_POLICY_ENDPOINT: &str = "/set_routing_policy";
 pub const STOP_ENDPOINT: &str = "/stop";
 pub const SEND_ONCHAIN_ENDPOINT: &str = "/send_onchain";
 pub const SPEND_ECASH_ENDPOINT: &str = "/spend_ecash";
@@ -64,11 +84,73 @@ pub struct ConnectFedPayload {
     pub invite_code: String,
     pub use_tor: Option<bool>,
     pub recover: Option<bool>,
+    /// Module kinds the gateway client should mount for this federation, in
+    /// addition to the mint and lightning modules that are always mounted. All
+    /// modules supported by the gateway are mounted if unset.
+    #[serde(default)]
+    pub modules: Option<BTreeSet<ModuleKind>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PreviewFedPayload {
+    pub invite_code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FederationReadyPayload {
+    pub federation_id: FederationId,
+}
+
+/// Whether the gateway's client for a federation is able to serve payments
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FederationReadiness {
+    pub federation_id: FederationId,
+    /// The client finished negotiating API versions and was added to the
+    /// gateway
+    pub connected: bool,
+    /// At least one of the client's modules is still recovering
+    pub recovering: bool,
+}
+
+impl FederationReadiness {
+    /// Returns true if the gateway can serve payments for the federation
+    pub fn is_ready(&self) -> bool {
+        self.connected && !self.recovering
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReregisterPayload {
+    /// Federation to register with, all connected federations if `None`
+    pub federation_id: Option<FederationId>,
+}
+
+/// Outcome of registering the gateway with a federation, see
+/// [`ReregisterPayload`]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FederationRegistration {
+    pub federation_id: FederationId,
+    /// Why the registration failed, `None` if it succeeded
+    pub error: Option<String>,
+}
+
+/// Information about a federation resolved from its invite code, without
+/// joining it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FederationPreview {
+    pub federation_id: FederationId,
+    pub federation_name: Option<String>,
+    /// Bitcoin network of the federation's wallet module, if it has one
+    pub network: Option<Network>,
+    pub modules: BTreeMap<ModuleInstanceId, ModuleKind>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LeaveFedPayload {
     pub federation_id: FederationId,
+    /// Leave even if the gateway still holds ecash in the federation
+    #[serde(default)]
+    pub force: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -125,6 +207,41 @@ pub struct WithdrawResponse {
     pub fees: PegOutFees,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RebalancePayload {
+    pub from_federation_id: FederationId,
+    pub to_federation_id: FederationId,
+    /// Amount to move, rounded down to whole sats
+    pub amount: Amount,
+}
+
+/// How the gateway moved funds between federations
+///
+/// There is no lightning route: the gateway's federation clients only run the
+/// gateway side of the lightning modules, so it cannot originate a payment.
+/// Even if it could, a direct swap it serves for itself funds the incoming
+/// contract in the destination federation and claims the outgoing contract
+/// in the source federation, moving its own ecash in a circle.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RebalanceRoute {
+    /// Pegged out of the source federation directly into a deposit address of
+    /// the destination federation
+    Onchain,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RebalanceResponse {
+    pub route: RebalanceRoute,
+    pub amount: bitcoin::Amount,
+    /// Peg-out transaction paying into the destination federation
+    pub txid: bitcoin::Txid,
+    /// Fees paid for the peg-out
+    pub fees: PegOutFees,
+    /// Amount deducted from the source federation, including fees
+    pub total_cost: Amount,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct WithdrawPreviewPayload {
     pub federation_id: FederationId,
@@ -166,6 +283,26 @@ pub struct FederationConfig {
     pub _connector: ConnectorType,
 }
 
+impl FederationConfig {
+    /// Fee the gateway charges by default for paying an invoice on behalf of a
+    /// client of this federation
+    pub fn send_fee_default(&self) -> PaymentFee {
+        self.lightning_fee + self.transaction_fee
+    }
+
+    /// Lowest fee the gateway accepts for paying an invoice on behalf of a
+    /// client of this federation
+    pub fn send_fee_minimum(&self) -> PaymentFee {
+        self.transaction_fee
+    }
+
+    /// Fee the gateway charges for receiving a payment on behalf of a client
+    /// of this federation
+    pub fn receive_fee(&self) -> PaymentFee {
+        self.transaction_fee
+    }
+}
+
 /// Information about one of the feds we are connected to
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FederationInfo {
@@ -188,6 +325,22 @@ pub struct GatewayInfo {
     pub lightning_info: LightningInfo,
     pub lightning_mode: LightningMode,
     pub registrations: BTreeMap<RegisteredProtocol, (SafeUrl, secp256k1::PublicKey)>,
+    /// Whether the API of each connected federation was reachable when the
+    /// gateway last probed it in the background
+    #[serde(default)]
+    pub federation_connectivity: Vec<FederationConnectivity>,
+}
+
+/// Reachability of the API of a federation the gateway is connected to
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FederationConnectivity {
+    pub federation_id: FederationId,
+    pub reachable: bool,
+    /// Last time the federation's API answered the gateway, if ever since the
+    /// gateway started
+    pub last_ok: Option<SystemTime>,
+    /// Round trip time of the query, `None` if the federation is unreachable
+    pub api_latency_ms: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -195,6 +348,23 @@ pub struct GatewayFedConfig {
     pub federations: BTreeMap<FederationId, JsonClientConfig>,
 }
 
+/// Upper bound for the proportional part of a fee, 100%
+pub const MAX_FEE_PARTS_PER_MILLION: u64 = 1_000_000;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetFeesPayload {
+    /// Federation to return the fees of, all federations if `None`
+    pub federation_id: Option<FederationId>,
+}
+
+/// Fee schedule the gateway charges in a federation
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FederationFees {
+    pub federation_id: FederationId,
+    pub lightning_fee: PaymentFee,
+    pub transaction_fee: PaymentFee,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SetFeesPayload {
     pub federation_id: Option<FederationId>,
@@ -204,6 +374,108 @@ pub struct SetFeesPayload {
     pub transaction_parts_per_million: Option<u64>,
 }
 
+/// The CLTV expiry delta the gateway requests for LNv2 payments unless an
+/// operator configured a different [`RoutingPolicy`]
+pub const DEFAULT_CLTV_DELTA: u64 = 1440;
+
+/// Whether the gateway includes route hints for its private channels in the
+/// invoices it creates on behalf of federation users
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Encodable, Decodable, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteHintMode {
+    /// Do not include any route hints
+    None,
+    /// Include route hints for the gateway's private channels
+    Private,
+}
+
+/// Lightning routing policy of the gateway that can be changed at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encodable, Decodable, Serialize, Deserialize)]
+pub struct RoutingPolicy {
+    pub cltv_delta: u64,
+    pub route_hint_mode: RouteHintMode,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SetRoutingPolicyPayload {
+    pub cltv_delta: Option<u64>,
+    pub route_hint_mode: Option<RouteHintMode>,
+}
+
+/// Limits on the amount a federation's users can send or receive through the
+/// gateway within a sliding time window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encodable, Decodable, Serialize, Deserialize)]
+pub struct FederationLimits {
+    pub max_send: Option<Amount>,
+    pub max_receive: Option<Amount>,
+    pub window_secs: u64,
+}
+
+impl FederationLimits {
+    /// Returns the configured limit for payments in `direction`, if any
+    pub fn max(&self, direction: LimitDirection) -> Option<Amount> {
+        match direction {
+            LimitDirection::Send => self.max_send,
+            LimitDirection::Receive => self.max_receive,
+        }
+    }
+}
+
+/// The direction of a payment relative to the federation's users
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Encodable, Decodable)]
+pub enum LimitDirection {
+    /// A federation user pays a lightning invoice through the gateway
+    Send,
+    /// A federation user receives a lightning payment through the gateway
+    Receive,
+}
+
+impl fmt::Display for LimitDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitDirection::Send => write!(f, "send"),
+            LimitDirection::Receive => write!(f, "receive"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SetLimitsPayload {
+    pub federation_id: FederationId,
+    pub max_send_msat: Option<u64>,
+    pub max_receive_msat: Option<u64>,
+    pub window_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetLimitsPayload {
+    pub federation_id: FederationId,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FederationLimitsResponse {
+    pub federation_id: FederationId,
+    pub limits: Option<FederationLimits>,
+    pub sent_in_window: Amount,
+    pub received_in_window: Amount,
+}
+
+/// The persistent configuration of a gateway, used to migrate it to new
+/// hardware. The ecash itself is not part of it and is recovered from the
+/// mnemonic when the federations are reconnected.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GatewayStateBackup {
+    pub federations: Vec<FederationConfig>,
+    /// Module kinds the client of a federation is restricted to, for the
+    /// federations that were connected with `--modules`
+    #[serde(default)]
+    pub enabled_modules: BTreeMap<FederationId, BTreeSet<ModuleKind>>,
+    pub routing_policy: RoutingPolicy,
+    pub lightning_mode: LightningMode,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CreateInvoiceForOperatorPayload {
     pub amount_msats: u64,
@@ -216,6 +488,31 @@ pub struct PayInvoiceForOperatorPayload {
     pub invoice: Bolt11Invoice,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EstimateFeePayload {
+    pub federation_id: FederationId,
+    pub invoice: Bolt11Invoice,
+}
+
+/// Fee the gateway charges for paying an invoice on behalf of a client of a
+/// federation
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeeEstimate {
+    pub federation_id: FederationId,
+    /// Amount of the invoice
+    pub amount: Amount,
+    /// Base part of the gateway's lightning and transaction fee
+    pub base_fee: Amount,
+    /// Proportional part of the gateway's lightning and transaction fee
+    pub proportional_fee: Amount,
+    /// Part of the fee the gateway may spend on routing the payment over the
+    /// lightning network, set by its configured lightning fee. Already
+    /// included in the base and proportional fee.
+    pub routing_budget: Amount,
+    /// Amount the client pays in total, including all fees
+    pub total: Amount,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SpendEcashPayload {
     /// Federation id of the e-cash to spend
@@ -230,6 +527,32 @@ pub struct SpendEcashResponse {
     pub notes: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotesPayload {
+    pub federation_id: FederationId,
+}
+
+/// The e-cash notes the gateway holds in a federation
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NoteCountsResponse {
+    pub federation_id: FederationId,
+    /// Number of notes per denomination
+    pub notes: BTreeMap<Amount, usize>,
+    pub total_notes: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConsolidateNotesPayload {
+    pub federation_id: FederationId,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConsolidateNotesResponse {
+    /// Operation reissuing the excess notes, `None` if no denomination held
+    /// enough notes to be consolidated
+    pub operation_id: Option<OperationId>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ReceiveEcashPayload {
     /// Can be OOBNotes (v1) or ECash (v2)
@@ -257,6 +580,102 @@ pub struct FederationBalanceInfo {
     pub ecash_balance_msats: Amount,
 }
 
+/// Health of a single gateway subsystem, see [`HealthCheckResponse`]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubsystemHealth {
+    pub subsystem: String,
+    pub healthy: bool,
+    pub detail: String,
+}
+
+impl SubsystemHealth {
+    pub fn healthy(subsystem: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            subsystem: subsystem.into(),
+            healthy: true,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn unhealthy(subsystem: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            subsystem: subsystem.into(),
+            healthy: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Health of the lightning node, its on-chain wallet and the API of each
+/// connected federation
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HealthCheckResponse {
+    pub subsystems: Vec<SubsystemHealth>,
+}
+
+impl HealthCheckResponse {
+    /// Returns true if all subsystems are healthy
+    pub fn is_healthy(&self) -> bool {
+        self.subsystems.iter().all(|subsystem| subsystem.healthy)
+    }
+}
+
+/// Amount of ecash the gateway moves during a self test unless another amount
+/// is requested
+pub const DEFAULT_SELF_TEST_AMOUNT: Amount = Amount::from_sats(100);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SelfTestPayload {
+    pub federation_id: FederationId,
+    /// Defaults to [`DEFAULT_SELF_TEST_AMOUNT`]
+    pub amount: Option<Amount>,
+}
+
+/// Outcome of a single step of a self test
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SelfTestLeg {
+    pub leg: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    pub detail: String,
+}
+
+/// Result of a self test, see [`SelfTestPayload`]. The test stops at the
+/// first leg that failed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SelfTestResponse {
+    pub federation_id: FederationId,
+    pub amount: Amount,
+    pub legs: Vec<SelfTestLeg>,
+}
+
+impl SelfTestResponse {
+    /// Returns true if all legs succeeded
+    pub fn is_success(&self) -> bool {
+        self.legs.iter().all(|leg| leg.success)
+    }
+}
+
+/// Overview of one of the feds we are connected to, see
+/// [`ListFederationsResponse`]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FederationSummary {
+    pub federation_id: FederationId,
+    pub federation_name: Option<String>,
+    pub ecash_balance_msats: Amount,
+    /// Number of active lightning channels that can route this federation's
+    /// payments, `None` if the lightning node could not be reached
+    pub active_channels: Option<usize>,
+    /// Number of route hints the gateway registers with this federation,
+    /// `None` if the lightning node could not be reached
+    pub route_hints: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ListFederationsResponse {
+    pub federations: Vec<FederationSummary>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MnemonicResponse {
     pub mnemonic: Vec<String>,
@@ -286,15 +705,67 @@ pub struct PaymentLogPayload {
     /// events (e.g. `tx-created`, `NoteCreated`) share the same ID space but
     /// are filtered out.
     pub event_kinds: Vec<EventKind>,
+
+    /// Only return events that happened at or after this time (unix millis)
+    #[serde(default)]
+    pub start_millis: Option<u64>,
+
+    /// Only return events that happened at or before this time (unix millis)
+    #[serde(default)]
+    pub end_millis: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PaymentLogResponse(pub Vec<PersistedLogEntry>);
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaymentLogTailPayload {
+    pub federation_id: FederationId,
+
+    /// Filter to only return events of these kinds. If empty, defaults to
+    /// gateway payment-related events, like [`PaymentLogPayload`].
+    pub event_kinds: Vec<EventKind>,
+
+    /// The position in the log to start returning events from (inclusive). If
+    /// `None`, only events added after the request are returned.
+    pub start_position: Option<EventLogId>,
+
+    /// How long to wait for new events before returning an empty response
+    pub wait_secs: u64,
+}
+
+/// New events of a [`PaymentLogTailPayload`] request, ordered from oldest to
+/// newest
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaymentLogTailResponse {
+    pub entries: Vec<PersistedLogEntry>,
+    /// The `start_position` to request the following events with
+    pub next_position: EventLogId,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PaymentSummaryResponse {
     pub outgoing: PaymentStats,
     pub incoming: PaymentStats,
+    /// Breakdown of the summary, keyed by federation id or by UTC date
+    /// (`YYYY-MM-DD`) depending on [`PaymentSummaryPayload::group_by`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groups: Option<BTreeMap<String, PaymentSummaryGroup>>,
+}
+
+/// Payment statistics of one group of a [`PaymentSummaryResponse`]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PaymentSummaryGroup {
+    pub outgoing: PaymentStats,
+    pub incoming: PaymentStats,
+}
+
+/// How to break down a [`PaymentSummaryResponse`]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentSummaryGroupBy {
+    Federation,
+    Day,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -323,6 +794,8 @@ pub fn compute(events: &StructuredPaymentEvents) -> Self {
 pub struct PaymentSummaryPayload {
     pub start_millis: u64,
     pub end_millis: u64,
+    #[serde(default)]
+    pub group_by: Option<PaymentSummaryGroupBy>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -504,6 +977,20 @@ pub enum PaymentDirection {
     Inbound,
 }
 
+/// A lightning payment the gateway is currently mediating, i.e. one whose
+/// state machines have not yet reached a final state
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InFlightPayment {
+    pub operation_id: OperationId,
+    pub federation_id: FederationId,
+    /// `None` if the incoming contract has already been funded and the gateway
+    /// is only waiting to settle the HTLC with the lightning node
+    pub amount: Option<Amount>,
+    pub direction: PaymentDirection,
+    /// Seconds since the first state machine of the payment was created
+    pub pending_secs: u64,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CreateOfferPayload {
     pub amount: Option<Amount>,
@@ -1,9 +1,10 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::str::FromStr;
 use std::time::SystemTime;
 
 use bitcoin::hashes::{Hash, sha256};
 use fedimint_core::config::FederationId;
+use fedimint_core::core::ModuleKind;
 use fedimint_core::db::{
     Database, DatabaseTransaction, DatabaseVersion, GeneralDbMigrationFn,
     GeneralDbMigrationFnContext, IDatabaseTransactionOpsCore, IDatabaseTransactionOpsCoreTyped,
@@ -14,7 +15,10 @@
 use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_core::{Amount, impl_db_lookup, impl_db_record, push_db_pair_items, secp256k1};
 use fedimint_gateway_common::envs::FM_GATEWAY_IROH_SECRET_KEY_OVERRIDE_ENV;
-use fedimint_gateway_common::{ConnectorType, FederationConfig, RegisteredProtocol};
+use fedimint_gateway_common::{
+    ConnectorType, FederationConfig, FederationLimits, LimitDirection, RegisteredProtocol,
+    RoutingPolicy,
+};
 use fedimint_ln_common::serde_routing_fees;
 use fedimint_lnv2_common::contracts::{IncomingContract, PaymentImage};
 use fedimint_lnv2_common::gateway_api::PaymentFee;
@@ -106,6 +110,70 @@ async fn save_federation_backup_record(
         federation_id: FederationId,
         backup_time: Option<SystemTime>,
     );
+
+    /// Returns the routing policy configured by the operator, if any
+    async fn load_routing_policy(&mut self) -> Option<RoutingPolicy>;
+
+    /// Saves the routing policy, replacing any previously configured one
+    async fn save_routing_policy(&mut self, routing_policy: &RoutingPolicy);
+
+    /// Returns the module kinds the client of a federation is restricted to,
+    /// if the operator chose any when connecting
+    async fn load_enabled_modules(
+        &mut self,
+        federation_id: FederationId,
+    ) -> Option<BTreeSet<ModuleKind>>;
+
+    /// Saves the module kinds the client of a federation is restricted to
+    async fn save_enabled_modules(
+        &mut self,
+        federation_id: FederationId,
+        modules: &BTreeSet<ModuleKind>,
+    );
+
+    async fn remove_enabled_modules(&mut self, federation_id: FederationId);
+
+    /// Returns the send and receive limits configured for a federation, if
+    /// any
+    async fn load_federation_limits(
+        &mut self,
+        federation_id: FederationId,
+    ) -> Option<FederationLimits>;
+
+    /// Saves the send and receive limits of a federation, replacing any
+    /// previously configured ones
+    async fn save_federation_limits(
+        &mut self,
+        federation_id: FederationId,
+        limits: &FederationLimits,
+    );
+
+    async fn remove_federation_limits(&mut self, federation_id: FederationId);
+
+    /// Returns all amounts reserved against the payment limits in `direction`
+    async fn load_payment_limit_reservations(
+        &mut self,
+        direction: LimitDirection,
+    ) -> Vec<(sha256::Hash, PaymentLimitReservation)>;
+
+    async fn load_payment_limit_reservation(
+        &mut self,
+        direction: LimitDirection,
+        payment_id: sha256::Hash,
+    ) -> Option<PaymentLimitReservation>;
+
+    async fn save_payment_limit_reservation(
+        &mut self,
+        direction: LimitDirection,
+        payment_id: sha256::Hash,
+        reservation: &PaymentLimitReservation,
+    );
+
+    async fn remove_payment_limit_reservation(
+        &mut self,
+        direction: LimitDirection,
+        payment_id: sha256::Hash,
+    );
 }
 
 impl<Cap: Send> GatewayDbtxNcExt for DatabaseTransaction<'_, Cap> {
@@ -281,6 +349,111 @@ async fn save_federation_backup_record(
         self.insert_entry(&FederationBackupKey { federation_id }, &backup_time)
             .await;
     }
+
+    async fn load_routing_policy(&mut self) -> Option<RoutingPolicy> {
+        self.get_value(&RoutingPolicyKey).await
+    }
+
+    async fn save_routing_policy(&mut self, routing_policy: &RoutingPolicy) {
+        self.insert_entry(&RoutingPolicyKey, routing_policy).await;
+    }
+
+    async fn load_enabled_modules(
+        &mut self,
+        federation_id: FederationId,
+    ) -> Option<BTreeSet<ModuleKind>> {
+        self.get_value(&EnabledModulesKey { federation_id }).await
+    }
+
+    async fn save_enabled_modules(
+        &mut self,
+        federation_id: FederationId,
+        modules: &BTreeSet<ModuleKind>,
+    ) {
+        self.insert_entry(&EnabledModulesKey { federation_id }, modules)
+            .await;
+    }
+
+    async fn remove_enabled_modules(&mut self, federation_id: FederationId) {
+        self.remove_entry(&EnabledModulesKey { federation_id })
+            .await;
+    }
+
+    async fn load_federation_limits(
+        &mut self,
+        federation_id: FederationId,
+    ) -> Option<FederationLimits> {
+        self.get_value(&FederationLimitsKey { federation_id }).await
+    }
+
+    async fn save_federation_limits(
+        &mut self,
+        federation_id: FederationId,
+        limits: &FederationLimits,
+    ) {
+        self.insert_entry(&FederationLimitsKey { federation_id }, limits)
+            .await;
+    }
+
+    async fn remove_federation_limits(&mut self, federation_id: FederationId) {
+        self.remove_entry(&FederationLimitsKey { federation_id })
+            .await;
+    }
+
+    async fn load_payment_limit_reservations(
+        &mut self,
+        direction: LimitDirection,
+    ) -> Vec<(sha256::Hash, PaymentLimitReservation)> {
+        self.find_by_prefix(&PaymentLimitReservationPrefix { direction })
+            .await
+            .map(
+                |(key, reservation): (PaymentLimitReservationKey, PaymentLimitReservation)| {
+                    (key.payment_id, reservation)
+                },
+            )
+            .collect::<Vec<_>>()
+            .await
+    }
+
+    async fn load_payment_limit_reservation(
+        &mut self,
+        direction: LimitDirection,
+        payment_id: sha256::Hash,
+    ) -> Option<PaymentLimitReservation> {
+        self.get_value(&PaymentLimitReservationKey {
+            direction,
+            payment_id,
+        })
+        .await
+    }
+
+    async fn save_payment_limit_reservation(
+        &mut self,
+        direction: LimitDirection,
+        payment_id: sha256::Hash,
+        reservation: &PaymentLimitReservation,
+    ) {
+        self.insert_entry(
+            &PaymentLimitReservationKey {
+                direction,
+                payment_id,
+            },
+            reservation,
+        )
+        .await;
+    }
+
+    async fn remove_payment_limit_reservation(
+        &mut self,
+        direction: LimitDirection,
+        payment_id: sha256::Hash,
+    ) {
+        self.remove_entry(&PaymentLimitReservationKey {
+            direction,
+            payment_id,
+        })
+        .await;
+    }
 }
 
 #[repr(u8)]
@@ -294,6 +467,10 @@ enum DbKeyPrefix {
     ClientDatabase = 0x10,
     Iroh = 0x11,
     FederationBackup = 0x12,
+    RoutingPolicy = 0x13,
+    EnabledModules = 0x14,
+    FederationLimits = 0x15,
+    PaymentLimitReservation = 0x16,
 }
 
 impl std::fmt::Display for DbKeyPrefix {
@@ -508,6 +685,68 @@ pub struct FederationBackupKey {
     query_prefix = FederationBackupPrefix,
 );
 
+#[derive(Debug, Encodable, Decodable)]
+struct RoutingPolicyKey;
+
+impl_db_record!(
+    key = RoutingPolicyKey,
+    value = RoutingPolicy,
+    db_prefix = DbKeyPrefix::RoutingPolicy,
+);
+
+#[derive(Debug, Encodable, Decodable)]
+struct EnabledModulesKey {
+    federation_id: FederationId,
+}
+
+impl_db_record!(
+    key = EnabledModulesKey,
+    value = BTreeSet<ModuleKind>,
+    db_prefix = DbKeyPrefix::EnabledModules,
+);
+
+#[derive(Debug, Encodable, Decodable)]
+struct FederationLimitsKey {
+    federation_id: FederationId,
+}
+
+impl_db_record!(
+    key = FederationLimitsKey,
+    value = FederationLimits,
+    db_prefix = DbKeyPrefix::FederationLimits,
+);
+
+/// An amount counted against a federation's payment limit, keyed by the
+/// payment it was reserved for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encodable, Decodable)]
+pub struct PaymentLimitReservation {
+    pub federation_id: FederationId,
+    pub amount: Amount,
+    pub created_at: SystemTime,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+struct PaymentLimitReservationKey {
+    direction: LimitDirection,
+    payment_id: sha256::Hash,
+}
+
+#[derive(Debug, Encodable, Decodable)]
+struct PaymentLimitReservationPrefix {
+    direction: LimitDirection,
+}
+
+impl_db_record!(
+    key = PaymentLimitReservationKey,
+    value = PaymentLimitReservation,
+    db_prefix = DbKeyPrefix::PaymentLimitReservation,
+);
+
+impl_db_lookup!(
+    key = PaymentLimitReservationKey,
+    query_prefix = PaymentLimitReservationPrefix,
+);
+
 pub fn get_gatewayd_database_migrations() -> BTreeMap<DatabaseVersion, GeneralDbMigrationFn> {
     let mut migrations: BTreeMap<DatabaseVersion, GeneralDbMigrationFn> = BTreeMap::new();
     migrations.insert(
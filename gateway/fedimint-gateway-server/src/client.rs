@@ -3,14 +3,18 @@
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use fedimint_api_client::download_from_invite_code;
 use fedimint_bip39::{Bip39RootSecretStrategy, Mnemonic};
 use fedimint_client::db::ClientConfigKey;
 use fedimint_client::module_init::ClientModuleInitRegistry;
 use fedimint_client::{Client, ClientBuilder, RootSecret};
 use fedimint_client_module::secret::{PlainRootSecretStrategy, RootSecretStrategy};
 use fedimint_connectors::ConnectorRegistry;
-use fedimint_core::config::FederationId;
+use fedimint_core::config::{ClientConfig, FederationId};
+use fedimint_core::core::ModuleKind;
 use fedimint_core::db::{Database, IDatabaseTransactionOpsCoreTyped};
+use fedimint_core::invite_code::InviteCode;
+use fedimint_core::module::CommonModuleInit as _;
 use fedimint_core::module::registry::ModuleDecoderRegistry;
 use fedimint_derive_secret::DerivableSecret;
 use fedimint_gateway_common::FederationConfig;
@@ -48,6 +52,21 @@ pub fn data_dir(&self) -> PathBuf {
         self.work_dir.clone()
     }
 
+    /// Module kinds the gateway can mount in the client of a federation. The
+    /// gateway's lightning modules and the mint modules are always mounted.
+    pub fn supported_module_kinds(&self) -> BTreeSet<ModuleKind> {
+        let mut kinds = self.registry.kinds();
+        kinds.insert(fedimint_ln_common::LightningCommonInit::KIND);
+        kinds.insert(fedimint_lnv2_common::LightningCommonInit::KIND);
+        kinds
+    }
+
+    /// Whether a module of `kind` is mounted regardless of the selection of the
+    /// operator, since the gateway cannot hold ecash without it
+    fn is_always_mounted(kind: &ModuleKind) -> bool {
+        *kind == fedimint_mint_client::KIND || *kind == fedimint_mintv2_client::common::KIND
+    }
+
     /// Reads a plain root secret from a database to construct a database.
     /// Only used for "legacy" federations before v0.5.0
     async fn client_plainrootsecret(&self, db: &Database) -> AdminResult<DerivableSecret> {
@@ -58,17 +77,28 @@ async fn client_plainrootsecret(&self, db: &Database) -> AdminResult<DerivableSe
     }
 
     /// Constructs the client builder with the modules, database, and connector
-    /// used to create clients for connected federations.
+    /// used to create clients for connected federations. If `enabled_modules`
+    /// is set only those modules are mounted, in addition to the ones that are
+    /// always mounted.
     async fn create_client_builder(
         &self,
         federation_config: &FederationConfig,
         gateway: Arc<Gateway>,
+        enabled_modules: Option<&BTreeSet<ModuleKind>>,
     ) -> AdminResult<ClientBuilder> {
         let FederationConfig {
             federation_index, ..
         } = federation_config.to_owned();
 
-        let mut registry = self.registry.clone();
+        let mut registry = match enabled_modules {
+            Some(enabled_modules) => self
+                .registry
+                .iter()
+                .filter(|(kind, _)| enabled_modules.contains(kind) || Self::is_always_mounted(kind))
+                .map(|(_, module_init)| module_init.clone())
+                .collect(),
+            None => self.registry.clone(),
+        };
 
         registry.attach(GatewayClientInit {
             federation_index,
@@ -88,6 +118,26 @@ async fn create_client_builder(
         Ok(client_builder)
     }
 
+    /// Downloads the client config of the federation behind `invite_code`
+    /// without creating a client for it. Configs of modules the gateway
+    /// supports are decoded.
+    pub async fn preview_config(&self, invite_code: &InviteCode) -> AdminResult<ClientConfig> {
+        let (config, _api) = download_from_invite_code(&self.connectors, invite_code)
+            .await
+            .map_err(AdminGatewayError::ClientCreationError)?;
+        let decoders =
+            self.registry
+                .available_decoders(config.modules.iter().map(
+                    |(module_instance_id, module_config)| {
+                        (*module_instance_id, &module_config.kind)
+                    },
+                ))
+                .map_err(AdminGatewayError::ClientCreationError)?;
+        config
+            .redecode_raw(&decoders)
+            .map_err(|e| AdminGatewayError::ClientCreationError(e.into()))
+    }
+
     /// Recovers a client with the provided mnemonic. This function will wait
     /// for the recoveries to finish, but a new client must be created
     /// afterwards and waited on until the state machines have finished
@@ -97,10 +147,13 @@ pub async fn recover(
         config: FederationConfig,
         gateway: Arc<Gateway>,
         mnemonic: &Mnemonic,
+        enabled_modules: Option<&BTreeSet<ModuleKind>>,
     ) -> AdminResult<()> {
         let federation_id = config.invite_code.federation_id();
         let db = gateway.gateway_db.get_client_database(&federation_id);
-        let client_builder = self.create_client_builder(&config, gateway.clone()).await?;
+        let client_builder = self
+            .create_client_builder(&config, gateway.clone(), enabled_modules)
+            .await?;
         let root_secret = RootSecret::StandardDoubleDerive(
             Bip39RootSecretStrategy::<12>::to_root_secret(mnemonic),
         );
@@ -119,12 +172,14 @@ pub async fn recover(
     }
 
     /// Builds a new client with the provided `FederationConfig` and `Mnemonic`.
-    /// Only used for newly joined federations.
+    /// Only used for newly joined federations. See
+    /// [`Self::create_client_builder`] for `enabled_modules`.
     pub async fn build(
         &self,
         config: FederationConfig,
         gateway: Arc<Gateway>,
         mnemonic: &Mnemonic,
+        enabled_modules: Option<&BTreeSet<ModuleKind>>,
     ) -> AdminResult<fedimint_client::ClientHandleArc> {
         let invite_code = config.invite_code.clone();
         let federation_id = invite_code.federation_id();
@@ -159,7 +214,9 @@ pub async fn build(
 
         Self::verify_client_config(&db, federation_id).await?;
 
-        let client_builder = self.create_client_builder(&config, gateway).await?;
+        let client_builder = self
+            .create_client_builder(&config, gateway, enabled_modules)
+            .await?;
 
         if Client::is_initialized(&db).await {
             client_builder
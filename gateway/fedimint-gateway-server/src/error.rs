@@ -4,10 +4,11 @@
 use axum::body::Body;
 use axum::response::{IntoResponse, Response};
 use fedimint_core::config::{FederationId, FederationIdPrefix};
-use fedimint_core::crit;
 use fedimint_core::envs::is_env_var_set;
 use fedimint_core::fmt_utils::OptStacktrace;
 use fedimint_core::util::FmtCompactAnyhow;
+use fedimint_core::{Amount, crit};
+use fedimint_gateway_common::LimitDirection;
 use fedimint_gw_client::pay::OutgoingPaymentError;
 use fedimint_lightning::LightningRpcError;
 use fedimint_logging::LOG_GATEWAY;
@@ -52,6 +53,14 @@ pub enum PublicGatewayError {
     FederationNotConnected(#[from] FederationNotConnected),
     #[error("Failed to receive ecash: {failure_reason}")]
     ReceiveEcashError { failure_reason: String },
+    #[error(
+        "Payment exceeds the {direction} limit of {limit} within the configured window for federation {federation_id}"
+    )]
+    PaymentLimitExceeded {
+        federation_id: FederationId,
+        direction: LimitDirection,
+        limit: Amount,
+    },
     #[error("Unexpected Error: {}", OptStacktrace(.0))]
     Unexpected(#[from] anyhow::Error),
 }
@@ -70,6 +79,9 @@ fn into_response(self) -> Response {
                 "Failed to receive ecash".to_string(),
                 StatusCode::INTERNAL_SERVER_ERROR,
             ),
+            PublicGatewayError::PaymentLimitExceeded { .. } => {
+                (self.to_string(), StatusCode::FORBIDDEN)
+            }
             PublicGatewayError::Lightning(_) => (
                 "Lightning Network operation failed".to_string(),
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -120,6 +132,13 @@ pub enum AdminGatewayError {
     RegistrationError { federation_id: FederationId },
     #[error("Error withdrawing funds onchain: {failure_reason}")]
     WithdrawError { failure_reason: String },
+    #[error(
+        "Gateway still holds {balance_msat} of ecash in federation {federation_id}, leave with force to abandon it"
+    )]
+    OutstandingBalance {
+        federation_id: FederationId,
+        balance_msat: Amount,
+    },
 }
 
 impl IntoResponse for AdminGatewayError {
@@ -1,14 +1,17 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use fedimint_client::ClientHandle;
 use fedimint_eventlog::{
     DBTransactionEventLogExt, Event, EventKind, EventLogId, PersistedLogEntry,
+    StructuredPaymentEvents,
 };
+use fedimint_gw_client::events::compute_lnv1_stats;
 use fedimint_gwv2_client::events::{
     CompleteLightningPaymentSucceeded, IncomingPaymentFailed, IncomingPaymentStarted,
     IncomingPaymentSucceeded, OutgoingPaymentFailed, OutgoingPaymentStarted,
-    OutgoingPaymentSucceeded,
+    OutgoingPaymentSucceeded, compute_lnv2_stats,
 };
 use fedimint_mint_client::events::{OOBNotesReissued, OOBNotesSpent};
 use fedimint_wallet_client::events::{DepositConfirmed, WithdrawRequest};
@@ -33,6 +36,11 @@
     DepositConfirmed::KIND,
 ];
 
+/// The event kinds that mark the start of an LNv1 or LNv2 payment. Both
+/// modules use the same kind names.
+const PAYMENT_START_EVENTS: [EventKind; 2] =
+    [OutgoingPaymentStarted::KIND, IncomingPaymentStarted::KIND];
+
 /// Searches through the event log for all events that occurred within the
 /// specified time bounds.
 ///
@@ -108,3 +116,59 @@ pub async fn get_events_for_duration(
         batch_start = batch_start.saturating_add(BATCH_SIZE);
     }
 }
+
+/// Computes the outgoing and incoming payment events of both LNv1 and LNv2
+/// from `all_events`.
+pub fn compute_payment_events(
+    all_events: &[PersistedLogEntry],
+) -> (StructuredPaymentEvents, StructuredPaymentEvents) {
+    let (mut outgoing, mut incoming) = compute_lnv1_stats(all_events);
+    let (mut lnv2_outgoing, mut lnv2_incoming) = compute_lnv2_stats(all_events);
+    outgoing.combine(&mut lnv2_outgoing);
+    incoming.combine(&mut lnv2_incoming);
+    (outgoing, incoming)
+}
+
+/// Formats the UTC date (`YYYY-MM-DD`) of a timestamp given in microseconds
+/// since the unix epoch.
+pub fn utc_date_from_usecs(ts_usecs: u64) -> String {
+    i64::try_from(ts_usecs)
+        .ok()
+        .and_then(chrono::DateTime::from_timestamp_micros)
+        .map_or_else(
+            || "invalid-date".to_string(),
+            |ts| ts.date_naive().to_string(),
+        )
+}
+
+/// Groups `all_events` by the UTC date (`YYYY-MM-DD`) on which each payment
+/// started.
+///
+/// Start events are bucketed by their own date. All other events are added to
+/// every bucket on or before their own date, so that
+/// [`compute_payment_events`] still joins a payment that completed after
+/// midnight with its start event.
+pub fn group_payment_events_by_start_day(
+    all_events: Vec<PersistedLogEntry>,
+) -> BTreeMap<String, Vec<PersistedLogEntry>> {
+    let (start_events, other_events): (Vec<_>, Vec<_>) = all_events
+        .into_iter()
+        .partition(|event| PAYMENT_START_EVENTS.contains(&event.as_raw().kind));
+
+    let mut grouped_events = BTreeMap::<String, Vec<PersistedLogEntry>>::new();
+    for event in start_events {
+        grouped_events
+            .entry(utc_date_from_usecs(event.as_raw().ts_usecs))
+            .or_default()
+            .push(event);
+    }
+
+    for event in other_events {
+        let day = utc_date_from_usecs(event.as_raw().ts_usecs);
+        for (_, events) in grouped_events.range_mut(..=day) {
+            events.push(event.clone());
+        }
+    }
+
+    grouped_events
+}
@@ -1,19 +1,24 @@
 use std::collections::BTreeMap;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::SystemTime;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 use bitcoin::secp256k1::Keypair;
+use fedimint_api_client::api::{DynGlobalApi, IGlobalFederationApi as _};
 use fedimint_client::ClientHandleArc;
 use fedimint_core::config::{FederationId, FederationIdPrefix, JsonClientConfig};
+use fedimint_core::core::OperationId;
 use fedimint_core::db::{Committable, DatabaseTransaction, NonCommittable};
 use fedimint_core::invite_code::InviteCode;
 use fedimint_core::util::{FmtCompactAnyhow as _, Spanned};
-use fedimint_core::{PeerId, TieredCounts};
-use fedimint_gateway_common::FederationInfo;
+use fedimint_core::{Amount, PeerId, TieredCounts};
+use fedimint_gateway_common::{
+    FederationConnectivity, FederationInfo, FederationSummary, InFlightPayment, PaymentDirection,
+    SubsystemHealth,
+};
 use fedimint_gateway_server_db::GatewayDbtxNcExt as _;
 use fedimint_gw_client::GatewayClientModule;
-use fedimint_gwv2_client::GatewayClientModuleV2;
+use fedimint_gwv2_client::{GatewayClientModuleV2, GatewayClientStateMachinesV2};
 use fedimint_logging::LOG_GATEWAY;
 use fedimint_mint_client::MintClientModule;
 use tracing::{info, warn};
@@ -42,6 +47,10 @@ pub struct FederationManager {
     /// federation, this value is incremented and assigned to the federation
     /// as the `federation_index`
     next_index: AtomicU64,
+
+    /// Connectivity of the API of each federation as of the last time the
+    /// gateway probed it
+    api_connectivity: Arc<Mutex<BTreeMap<FederationId, FederationConnectivity>>>,
 }
 
 impl FederationManager {
@@ -50,6 +59,7 @@ pub fn new() -> Self {
             clients: BTreeMap::new(),
             index_to_federation: BTreeMap::new(),
             next_index: AtomicU64::new(INITIAL_INDEX),
+            api_connectivity: Arc::new(Mutex::new(BTreeMap::new())),
         }
     }
 
@@ -62,11 +72,20 @@ pub fn add_client(&mut self, index: u64, client: Spanned<fedimint_client::Client
     pub async fn leave_federation(
         &mut self,
         federation_id: FederationId,
+        force: bool,
         dbtx: &mut DatabaseTransaction<'_, NonCommittable>,
         registrations: Vec<&Registration>,
     ) -> AdminResult<FederationInfo> {
         let federation_info = self.federation_info(federation_id, dbtx).await?;
 
+        // Leaving would strand the ecash, so only do it if explicitly requested
+        if !force && federation_info.balance_msat != Amount::ZERO {
+            return Err(AdminGatewayError::OutstandingBalance {
+                federation_id,
+                balance_msat: federation_info.balance_msat,
+            });
+        }
+
         for registration in registrations {
             self.unannounce_from_federation(federation_id, registration.keypair)
                 .await;
@@ -88,6 +107,10 @@ async fn remove_client(&mut self, federation_id: FederationId) -> AdminResult<()
 
         self.index_to_federation
             .retain(|_, fid| *fid != federation_id);
+        self.api_connectivity
+            .lock()
+            .expect("lock poisoned")
+            .remove(&federation_id);
 
         match Arc::into_inner(client) {
             Some(client) => {
@@ -291,6 +314,120 @@ pub async fn federation_info_all_federations(
         federation_infos
     }
 
+    /// Returns a [`FederationSummary`] for each connected federation, skipping
+    /// federations whose primary module isn't available yet.
+    ///
+    /// All federations are served by the same lightning node, so they share
+    /// `active_channels` and `route_hints`.
+    pub async fn federation_summary_all_federations(
+        &self,
+        active_channels: Option<usize>,
+        route_hints: Option<usize>,
+    ) -> Vec<FederationSummary> {
+        let mut summaries = Vec::new();
+        for (federation_id, client) in &self.clients {
+            let ecash_balance_msats = match client.value().get_balance_for_btc().await {
+                Ok(balance) => balance,
+                Err(err) => {
+                    warn!(
+                        target: LOG_GATEWAY,
+                        err = %err.fmt_compact_anyhow(),
+                        %federation_id,
+                        "Skipped Federation due to lack of primary module"
+                    );
+                    continue;
+                }
+            };
+
+            summaries.push(FederationSummary {
+                federation_id: *federation_id,
+                federation_name: self.federation_name(client.value()).await,
+                ecash_balance_msats,
+                active_channels,
+                route_hints,
+            });
+        }
+        summaries
+    }
+
+    /// Returns a prober for the APIs of the connected federations that does
+    /// not borrow the `FederationManager` or its clients, so that slow
+    /// federations can be probed without holding its lock.
+    pub fn api_prober(&self) -> FederationApiProber {
+        FederationApiProber {
+            apis: self
+                .clients
+                .iter()
+                .map(|(federation_id, client)| (*federation_id, client.value().api_clone()))
+                .collect(),
+            api_connectivity: self.api_connectivity.clone(),
+        }
+    }
+
+    /// Returns the connectivity of the connected federations as of their last
+    /// probe, without querying them. Federations that were not probed yet are
+    /// omitted.
+    pub fn api_connectivity(&self) -> Vec<FederationConnectivity> {
+        let api_connectivity = self.api_connectivity.lock().expect("lock poisoned");
+        self.clients
+            .keys()
+            .filter_map(|federation_id| api_connectivity.get(federation_id).cloned())
+            .collect()
+    }
+
+    /// Lists the LNv2 payments of all federations whose state machines are
+    /// still active. LNv1 state machines are not included since they do not
+    /// carry the amount of the payment.
+    pub async fn in_flight_payments_all_federations(&self) -> Vec<InFlightPayment> {
+        let now = fedimint_core::time::now();
+        let mut in_flight = Vec::new();
+        for (federation_id, client) in &self.clients {
+            let Ok(lnv2) = client.value().get_first_module::<GatewayClientModuleV2>() else {
+                continue;
+            };
+
+            let mut payments =
+                BTreeMap::<OperationId, (Option<Amount>, PaymentDirection, SystemTime)>::new();
+            for (state, meta) in lnv2.client_ctx.get_own_active_states().await {
+                let (operation_id, amount, direction) = match state {
+                    GatewayClientStateMachinesV2::Send(send) => (
+                        send.common.operation_id,
+                        Some(send.common.contract.amount),
+                        PaymentDirection::Outbound,
+                    ),
+                    GatewayClientStateMachinesV2::Receive(receive) => (
+                        receive.common.operation_id,
+                        Some(receive.common.contract.commitment.amount),
+                        PaymentDirection::Inbound,
+                    ),
+                    GatewayClientStateMachinesV2::Complete(complete) => (
+                        complete.common.operation_id,
+                        None,
+                        PaymentDirection::Inbound,
+                    ),
+                };
+
+                let entry =
+                    payments
+                        .entry(operation_id)
+                        .or_insert((amount, direction, meta.created_at));
+                entry.0 = entry.0.or(amount);
+                entry.2 = entry.2.min(meta.created_at);
+            }
+
+            in_flight.extend(payments.into_iter().map(
+                |(operation_id, (amount, direction, created_at))| InFlightPayment {
+                    operation_id,
+                    federation_id: *federation_id,
+                    amount,
+                    direction,
+                    pending_secs: now.duration_since(created_at).unwrap_or_default().as_secs(),
+                },
+            ));
+        }
+        in_flight
+    }
+
     pub async fn get_federation_config(
         &self,
         federation_id: FederationId,
@@ -399,3 +536,82 @@ pub fn pop_next_index(&self) -> AdminResult<u64> {
         Ok(next_index)
     }
 }
+
+/// Probes the APIs of the federations that were connected when it was created
+/// by [`FederationManager::api_prober`], recording the results for
+/// [`FederationManager::api_connectivity`].
+pub struct FederationApiProber {
+    apis: Vec<(FederationId, DynGlobalApi)>,
+    api_connectivity: Arc<Mutex<BTreeMap<FederationId, FederationConnectivity>>>,
+}
+
+impl FederationApiProber {
+    /// Checks concurrently that the API of each federation returns the
+    /// session count within `timeout`.
+    pub async fn api_health(&self, timeout: Duration) -> Vec<SubsystemHealth> {
+        futures::future::join_all(self.apis.iter().map(|(federation_id, api)| async move {
+            let subsystem = format!("federation {federation_id}");
+            match self.query_session_count(*federation_id, api, timeout).await {
+                Ok((session_count, _)) => {
+                    SubsystemHealth::healthy(subsystem, format!("session count {session_count}"))
+                }
+                Err(err) => SubsystemHealth::unhealthy(subsystem, err),
+            }
+        }))
+        .await
+    }
+
+    /// Probes the API of each federation concurrently, only to refresh
+    /// [`FederationManager::api_connectivity`].
+    pub async fn refresh_connectivity(&self, timeout: Duration) {
+        futures::future::join_all(
+            self.apis
+                .iter()
+                .map(|(federation_id, api)| self.query_session_count(*federation_id, api, timeout)),
+        )
+        .await;
+    }
+
+    /// Queries the session count of a federation, returning it together with
+    /// the round trip time. Records the outcome as the federation's
+    /// connectivity.
+    async fn query_session_count(
+        &self,
+        federation_id: FederationId,
+        api: &DynGlobalApi,
+        timeout: Duration,
+    ) -> Result<(u64, Duration), String> {
+        let start = fedimint_core::time::now();
+        let session_count = fedimint_core::runtime::timeout(timeout, api.session_count()).await;
+        let now = fedimint_core::time::now();
+        let result = match session_count {
+            Ok(Ok(session_count)) => {
+                Ok((session_count, now.duration_since(start).unwrap_or_default()))
+            }
+            Ok(Err(err)) => Err(err.to_string()),
+            Err(_) => Err(format!("no response within {}s", timeout.as_secs())),
+        };
+
+        let mut api_connectivity = self.api_connectivity.lock().expect("lock poisoned");
+        let last_ok = match result {
+            Ok(_) => Some(now),
+            Err(_) => api_connectivity
+                .get(&federation_id)
+                .and_then(|connectivity| connectivity.last_ok),
+        };
+        api_connectivity.insert(
+            federation_id,
+            FederationConnectivity {
+                federation_id,
+                reachable: result.is_ok(),
+                last_ok,
+                api_latency_ms: result
+                    .as_ref()
+                    .ok()
+                    .map(|(_, latency)| latency.as_millis().try_into().unwrap_or(u64::MAX)),
+            },
+        );
+
+        result
+    }
+}
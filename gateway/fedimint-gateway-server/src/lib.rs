@@ -22,6 +22,7 @@
 mod federation_manager;
 mod iroh_server;
 mod metrics;
+mod payment_limits;
 pub mod rpc_server;
 mod types;
 
@@ -35,7 +36,7 @@
 
 use anyhow::{Context, anyhow, ensure};
 use async_trait::async_trait;
-use bitcoin::hashes::sha256;
+use bitcoin::hashes::{Hash as _, sha256};
 use bitcoin::{Address, Network, Txid, secp256k1};
 use clap::Parser;
 use client::GatewayClientBuilder;
@@ -70,30 +71,39 @@
     Amount, BitcoinAmountOrAll, PeerId, TieredCounts, crit, fedimint_build_code_version_env,
     get_network_for_address,
 };
-use fedimint_eventlog::{DBTransactionEventLogExt, EventLogId, StructuredPaymentEvents};
+use fedimint_eventlog::{
+    DBTransactionEventLogExt, EventLogId, PersistedLogEntry, StructuredPaymentEvents,
+};
 use fedimint_gateway_common::{
     BackupPayload, ChainSource, CloseChannelsWithPeerRequest, CloseChannelsWithPeerResponse,
-    ConnectFedPayload, ConnectorType, CreateInvoiceForOperatorPayload, CreateOfferPayload,
-    CreateOfferResponse, DepositAddressPayload, DepositAddressRecheckPayload,
-    FederationBalanceInfo, FederationConfig, FederationInfo, GatewayBalances, GatewayFedConfig,
-    GatewayInfo, GetInvoiceRequest, GetInvoiceResponse, LeaveFedPayload, LightningInfo,
-    LightningMode, ListTransactionsPayload, ListTransactionsResponse, MnemonicResponse,
-    OpenChannelRequest, PayInvoiceForOperatorPayload, PayOfferPayload, PayOfferResponse,
-    PaymentLogPayload, PaymentLogResponse, PaymentStats, PaymentSummaryPayload,
-    PaymentSummaryResponse, PeginFromOnchainPayload, ReceiveEcashPayload, ReceiveEcashResponse,
-    RegisteredProtocol, SendOnchainRequest, SetChannelFeesRequest, SetFeesPayload,
-    SetMnemonicPayload, SpendEcashPayload, SpendEcashResponse, V1_API_ENDPOINT, WithdrawPayload,
-    WithdrawPreviewPayload, WithdrawPreviewResponse, WithdrawResponse, WithdrawToOnchainPayload,
+    ConnectFedPayload, ConnectorType, ConsolidateNotesPayload, ConsolidateNotesResponse,
+    CreateInvoiceForOperatorPayload, CreateOfferPayload, CreateOfferResponse, DEFAULT_CLTV_DELTA,
+    DEFAULT_SELF_TEST_AMOUNT, DepositAddressPayload, DepositAddressRecheckPayload,
+    EstimateFeePayload, FederationBalanceInfo, FederationConfig, FederationFees, FederationInfo,
+    FederationLimits, FederationLimitsResponse, FederationPreview, FederationReadiness,
+    FederationReadyPayload, FederationRegistration, FeeEstimate, GatewayBalances, GatewayFedConfig,
+    GatewayInfo, GatewayStateBackup, GetFeesPayload, GetInvoiceRequest, GetInvoiceResponse,
+    GetLimitsPayload, HealthCheckResponse, InFlightPayment, LeaveFedPayload, LightningInfo,
+    LightningMode, LimitDirection, ListFederationsResponse, ListTransactionsPayload,
+    ListTransactionsResponse, MAX_FEE_PARTS_PER_MILLION, MnemonicResponse, NoteCountsResponse,
+    NotesPayload, OpenChannelRequest, PayInvoiceForOperatorPayload, PayOfferPayload,
+    PayOfferResponse, PaymentLogPayload, PaymentLogResponse, PaymentLogTailPayload,
+    PaymentLogTailResponse, PaymentStats, PaymentSummaryGroup, PaymentSummaryGroupBy,
+    PaymentSummaryPayload, PaymentSummaryResponse, PeginFromOnchainPayload, PreviewFedPayload,
+    RebalancePayload, RebalanceResponse, RebalanceRoute, ReceiveEcashPayload, ReceiveEcashResponse,
+    RegisteredProtocol, ReregisterPayload, RouteHintMode, RoutingPolicy, SelfTestLeg,
+    SelfTestPayload, SelfTestResponse, SendOnchainRequest, SetChannelFeesRequest, SetFeesPayload,
+    SetLimitsPayload, SetMnemonicPayload, SetRoutingPolicyPayload, SpendEcashPayload,
+    SpendEcashResponse, SubsystemHealth, V1_API_ENDPOINT, WithdrawPayload, WithdrawPreviewPayload,
+    WithdrawPreviewResponse, WithdrawResponse, WithdrawToOnchainPayload,
 };
 use fedimint_gateway_server_db::{GatewayDbtxNcExt as _, get_gatewayd_database_migrations};
 pub use fedimint_gateway_ui::IAdminGateway;
-use fedimint_gw_client::events::compute_lnv1_stats;
 use fedimint_gw_client::pay::{OutgoingPaymentError, OutgoingPaymentErrorType};
 use fedimint_gw_client::{
     GatewayClientModule, GatewayExtPayStates, GatewayExtReceiveStates, IGatewayClientV1,
     SwapParameters,
 };
-use fedimint_gwv2_client::events::compute_lnv2_stats;
 use fedimint_gwv2_client::{
     EXPIRATION_DELTA_MINIMUM_V2, FinalReceiveState, GatewayClientModuleV2, IGatewayClientV2,
 };
@@ -108,6 +118,7 @@
 use fedimint_ln_common::config::LightningClientConfig;
 use fedimint_ln_common::contracts::outgoing::OutgoingContractAccount;
 use fedimint_ln_common::contracts::{IdentifiableContract, Preimage};
+use fedimint_ln_common::route_hints::RouteHint;
 use fedimint_lnurl::VerifyResponse;
 use fedimint_lnv2_common::Bolt11InvoiceDescription;
 use fedimint_lnv2_common::contracts::{IncomingContract, PaymentImage};
@@ -119,7 +130,9 @@
 use fedimint_mintv2_client::{
     MintClientInit as MintV2ClientInit, MintClientModule as MintV2ClientModule,
 };
+use fedimint_wallet_client::config::WalletClientConfig;
 use fedimint_wallet_client::{PegOutFees, WalletClientInit, WalletClientModule, WithdrawState};
+use fedimint_walletv2_client::common::config::WalletClientConfig as WalletV2ClientConfig;
 use futures::stream::StreamExt;
 use lightning_invoice::{Bolt11Invoice, RoutingFees};
 use rand::rngs::OsRng;
@@ -128,13 +141,19 @@
 
 use crate::envs::FM_GATEWAY_MNEMONIC_ENV;
 use crate::error::{AdminGatewayError, LNv1Error, LNv2Error, PublicGatewayError};
-use crate::events::get_events_for_duration;
+use crate::events::{
+    compute_payment_events, get_events_for_duration, group_payment_events_by_start_day,
+};
+use crate::payment_limits::{PaymentLimiter, receive_payment_id, send_payment_id};
 use crate::rpc_server::run_webserver;
 use crate::types::PrettyInterceptPaymentRequest;
 
 /// How long a gateway announcement stays valid
 const GW_ANNOUNCEMENT_TTL: Duration = Duration::from_mins(10);
 
+/// How long the health check waits for a federation's API to respond
+const HEALTH_CHECK_FEDERATION_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// The default number of route hints that the legacy gateway provides for
 /// invoice creation.
 const DEFAULT_NUM_ROUTE_HINTS: u32 = 1;
@@ -364,6 +383,10 @@ pub struct Gateway {
     /// A map of the network protocols the gateway supports to the data needed
     /// for registering with a federation.
     registrations: BTreeMap<RegisteredProtocol, Registration>,
+
+    /// Tracks the amounts sent and received per federation to enforce the
+    /// operator configured limits.
+    payment_limiter: Arc<PaymentLimiter>,
 }
 
 impl std::fmt::Debug for Gateway {
@@ -722,6 +745,7 @@ async fn new(
             iroh_relays: gateway_parameters.iroh_relays,
             iroh_listen: gateway_parameters.iroh_listen,
             registrations,
+            payment_limiter: Arc::new(PaymentLimiter::new(gateway_db.clone())),
         })
     }
 
@@ -777,6 +801,7 @@ pub async fn run(
         self.load_clients().await?;
         self.start_gateway(runtime, mnemonic_receiver.resubscribe());
         self.spawn_backup_task();
+        self.spawn_connectivity_task();
         // start metrics server
         fedimint_metrics::spawn_api_server(self.metrics_listen, self.task_group.clone()).await?;
         // start webserver last to avoid handling requests before fully initialized
@@ -806,6 +831,25 @@ fn spawn_backup_task(&self) {
             });
     }
 
+    /// Spawns a background task that probes the API of every connected
+    /// federation each `CONNECTIVITY_REFRESH_INTERVAL`, so that the info
+    /// endpoint can report their connectivity without querying them.
+    fn spawn_connectivity_task(&self) {
+        let self_copy = self.clone();
+        self.task_group
+            .spawn_cancellable_silent("federation connectivity", async move {
+                const CONNECTIVITY_REFRESH_INTERVAL: Duration = Duration::from_mins(1);
+                let mut interval = tokio::time::interval(CONNECTIVITY_REFRESH_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let api_prober = self_copy.federation_manager.read().await.api_prober();
+                    api_prober
+                        .refresh_connectivity(HEALTH_CHECK_FEDERATION_TIMEOUT)
+                        .await;
+                }
+            });
+    }
+
     /// Loops through all federations and checks their last save backup time. If
     /// the last saved backup time is past the threshold time, backup the
     /// federation.
@@ -1172,6 +1216,40 @@ async fn try_handle_lightning_payment_lnv2(
             )
             .await?;
 
+        let payment_id = receive_payment_id(
+            htlc_request.payment_hash,
+            htlc_request.incoming_chan_id,
+            htlc_request.htlc_id,
+        );
+
+        let reserved = match self
+            .reserve_payment_limit(
+                client.federation_id(),
+                LimitDirection::Receive,
+                payment_id,
+                Amount::from_msats(htlc_request.amount_msat),
+            )
+            .await
+        {
+            Ok(reserved) => reserved,
+            Err(err) => {
+                warn!(target: LOG_GATEWAY, err = %err, "Rejecting incoming lightning payment");
+
+                let outcome = InterceptPaymentResponse {
+                    action: PaymentAction::Cancel,
+                    payment_hash: htlc_request.payment_hash,
+                    incoming_chan_id: htlc_request.incoming_chan_id,
+                    htlc_id: htlc_request.htlc_id,
+                };
+
+                if let Err(err) = lightning_context.lnrpc.complete_htlc(outcome).await {
+                    warn!(target: LOG_GATEWAY, err = %err.fmt_compact(), "Error sending HTLC response to lightning node");
+                }
+
+                return Ok(());
+            }
+        };
+
         if let Err(err) = client
             .get_first_module::<GatewayClientModuleV2>()
             .expect("Must have client module")
@@ -1186,6 +1264,12 @@ async fn try_handle_lightning_payment_lnv2(
         {
             warn!(target: LOG_GATEWAY, err = %err.fmt_compact_anyhow(), "Error relaying incoming lightning payment");
 
+            if reserved {
+                self.payment_limiter
+                    .release(LimitDirection::Receive, payment_id)
+                    .await;
+            }
+
             let outcome = InterceptPaymentResponse {
                 action: PaymentAction::Cancel,
                 payment_hash: htlc_request.payment_hash,
@@ -1223,7 +1307,21 @@ async fn try_handle_lightning_payment_ln_legacy(
             return Err(PublicGatewayError::LNv1(LNv1Error::IncomingPayment("Incoming payment has a last hop short channel id that does not map to a known federation".to_string())));
         };
 
-        client
+        let payment_id = receive_payment_id(
+            htlc_request.payment_hash,
+            htlc_request.incoming_chan_id,
+            htlc_request.htlc_id,
+        );
+        let reserved = self
+            .reserve_payment_limit(
+                client.value().federation_id(),
+                LimitDirection::Receive,
+                payment_id,
+                Amount::from_msats(htlc_request.amount_msat),
+            )
+            .await?;
+
+        let result = client
             .borrow()
             .with(|client| async {
                 let htlc = htlc_request.clone().try_into();
@@ -1249,7 +1347,15 @@ async fn try_handle_lightning_payment_ln_legacy(
                     ))),
                 }
             })
-            .await
+            .await;
+
+        if reserved && result.is_err() {
+            self.payment_limiter
+                .release(LimitDirection::Receive, payment_id)
+                .await;
+        }
+
+        result
     }
 
     /// Cancels (fails back) a lightning payment whose last-hop scid maps to a
@@ -1384,11 +1490,41 @@ async fn handle_pay_invoice_msg(
             .get_first_module::<GatewayClientModule>()
             .map_err(LNv1Error::OutgoingPayment)
             .map_err(PublicGatewayError::LNv1)?;
-        let operation_id = gateway_module
-            .gateway_pay_bolt11_invoice(payload)
+
+        // Retries of an already started payment have been counted before, and
+        // concurrent first attempts share the reservation of the payment
+        let operation_id = OperationId(contract_id.to_byte_array());
+        let payment_id = send_payment_id(operation_id);
+        let is_new_payment = client
+            .value()
+            .operation_log()
+            .get_operation(operation_id)
             .await
-            .map_err(LNv1Error::OutgoingPayment)
-            .map_err(PublicGatewayError::LNv1)?;
+            .is_none();
+        let reserved = if is_new_payment {
+            self.reserve_payment_limit(
+                payload.federation_id,
+                LimitDirection::Send,
+                payment_id,
+                payload.payment_data.amount().unwrap_or(Amount::ZERO),
+            )
+            .await?
+        } else {
+            false
+        };
+
+        let operation_id = match gateway_module.gateway_pay_bolt11_invoice(payload).await {
+            Ok(operation_id) => operation_id,
+            Err(err) => {
+                if reserved {
+                    self.payment_limiter
+                        .release(LimitDirection::Send, payment_id)
+                        .await;
+                }
+
+                return Err(PublicGatewayError::LNv1(LNv1Error::OutgoingPayment(err)));
+            }
+        };
         let mut updates = gateway_module
             .gateway_subscribe_ln_pay(operation_id)
             .await
@@ -1405,6 +1541,12 @@ async fn handle_pay_invoice_msg(
                     error,
                     error_message,
                 } => {
+                    if reserved {
+                        self.payment_limiter
+                            .release(LimitDirection::Send, payment_id)
+                            .await;
+                    }
+
                     return Err(PublicGatewayError::LNv1(LNv1Error::OutgoingContract {
                         error: Box::new(error),
                         message: format!(
@@ -1413,6 +1555,12 @@ async fn handle_pay_invoice_msg(
                     }));
                 }
                 GatewayExtPayStates::Canceled { error } => {
+                    if reserved {
+                        self.payment_limiter
+                            .release(LimitDirection::Send, payment_id)
+                            .await;
+                    }
+
                     return Err(PublicGatewayError::LNv1(LNv1Error::OutgoingContract {
                         error: Box::new(error.clone()),
                         message: format!(
@@ -1457,6 +1605,70 @@ pub async fn handle_backup_msg(
         Ok(())
     }
 
+    /// Returns the number of e-cash notes per denomination the gateway holds
+    /// in a federation
+    pub async fn handle_notes_msg(
+        &self,
+        NotesPayload { federation_id }: NotesPayload,
+    ) -> AdminResult<NoteCountsResponse> {
+        let client = self.select_client(federation_id).await?;
+
+        let notes: BTreeMap<Amount, usize> = if client
+            .value()
+            .get_first_module::<MintClientModule>()
+            .is_ok()
+        {
+            self.federation_manager
+                .read()
+                .await
+                .get_note_summary(&federation_id)
+                .await?
+                .iter()
+                .collect()
+        } else if let Ok(mint) = client.value().get_first_module::<MintV2ClientModule>() {
+            mint.get_count_by_denomination()
+                .await
+                .into_iter()
+                .map(|(denomination, count)| {
+                    (
+                        denomination.amount(),
+                        usize::try_from(count).expect("Note count fits into usize"),
+                    )
+                })
+                .collect()
+        } else {
+            return Err(AdminGatewayError::Unexpected(anyhow::anyhow!(
+                "No mint module available"
+            )));
+        };
+
+        Ok(NoteCountsResponse {
+            federation_id,
+            total_notes: notes.values().sum(),
+            notes,
+        })
+    }
+
+    /// Reissues the gateway's excess small denomination notes in a federation
+    /// into fewer, larger notes
+    pub async fn handle_consolidate_notes_msg(
+        &self,
+        ConsolidateNotesPayload { federation_id }: ConsolidateNotesPayload,
+    ) -> AdminResult<ConsolidateNotesResponse> {
+        let client = self.select_client(federation_id).await?;
+
+        let Ok(mint) = client.value().get_first_module::<MintClientModule>() else {
+            return Err(AdminGatewayError::Unexpected(anyhow::anyhow!(
+                "Note consolidation requires the mint v1 module"
+            )));
+        };
+
+        let operation_id = mint.reissue_excess_notes().await?;
+        info!(target: LOG_GATEWAY, %federation_id, ?operation_id, "Consolidating notes");
+
+        Ok(ConsolidateNotesResponse { operation_id })
+    }
+
     /// Trigger rechecking for deposits on an address
     pub async fn handle_recheck_address_msg(
         &self,
@@ -1464,168 +1676,1112 @@ pub async fn handle_recheck_address_msg(
     ) -> AdminResult<()> {
         let client = self.select_client(payload.federation_id).await?;
 
-        if let Ok(wallet_module) = client.value().get_first_module::<WalletClientModule>() {
-            wallet_module
-                .recheck_pegin_address_by_address(payload.address)
-                .await?;
-            Ok(())
-        } else if client
-            .value()
-            .get_first_module::<fedimint_walletv2_client::WalletClientModule>()
-            .is_ok()
-        {
-            // Walletv2 auto-claims deposits, so this is a no-op
-            Ok(())
-        } else {
-            Err(AdminGatewayError::Unexpected(anyhow!(
-                "No wallet module found"
-            )))
+        if let Ok(wallet_module) = client.value().get_first_module::<WalletClientModule>() {
+            wallet_module
+                .recheck_pegin_address_by_address(payload.address)
+                .await?;
+            Ok(())
+        } else if client
+            .value()
+            .get_first_module::<fedimint_walletv2_client::WalletClientModule>()
+            .is_ok()
+        {
+            // Walletv2 auto-claims deposits, so this is a no-op
+            Ok(())
+        } else {
+            Err(AdminGatewayError::Unexpected(anyhow!(
+                "No wallet module found"
+            )))
+        }
+    }
+
+    /// Handles a request to receive ecash into the gateway.
+    pub async fn handle_receive_ecash_msg(
+        &self,
+        payload: ReceiveEcashPayload,
+    ) -> Result<ReceiveEcashResponse> {
+        // Extract federation_id_prefix from either format
+        let federation_id_prefix = base32::decode_prefixed::<fedimint_mintv2_client::ECash>(
+            FEDIMINT_PREFIX,
+            &payload.notes,
+        )
+        .ok()
+        .and_then(|e| e.mint())
+        .map(|id| id.to_prefix())
+        .or_else(|| {
+            OOBNotes::from_str(&payload.notes)
+                .ok()
+                .map(|n| n.federation_id_prefix())
+        })
+        .ok_or_else(|| PublicGatewayError::ReceiveEcashError {
+            failure_reason: "Invalid ecash format: could not parse as ECash or OOBNotes"
+                .to_string(),
+        })?;
+
+        let client = self
+            .federation_manager
+            .read()
+            .await
+            .get_client_for_federation_id_prefix(federation_id_prefix)
+            .ok_or(FederationNotConnected {
+                federation_id_prefix,
+            })?;
+
+        // Check which module is present and parse accordingly
+        if let Ok(mint) = client.value().get_first_module::<MintClientModule>() {
+            let notes = OOBNotes::from_str(&payload.notes).map_err(|e| {
+                PublicGatewayError::ReceiveEcashError {
+                    failure_reason: format!("Expected OOBNotes for MintV1 federation: {e}"),
+                }
+            })?;
+            let amount = notes.total_amount();
+
+            let operation_id = mint.reissue_external_notes(notes, ()).await.map_err(|e| {
+                PublicGatewayError::ReceiveEcashError {
+                    failure_reason: e.to_string(),
+                }
+            })?;
+            if payload.wait {
+                let mut updates = mint
+                    .subscribe_reissue_external_notes(operation_id)
+                    .await
+                    .unwrap()
+                    .into_stream();
+
+                while let Some(update) = updates.next().await {
+                    if let fedimint_mint_client::ReissueExternalNotesState::Failed(e) = update {
+                        return Err(PublicGatewayError::ReceiveEcashError {
+                            failure_reason: e.clone(),
+                        });
+                    }
+                }
+            }
+
+            Ok(ReceiveEcashResponse { amount })
+        } else if let Ok(mint) = client.value().get_first_module::<MintV2ClientModule>() {
+            let ecash: fedimint_mintv2_client::ECash =
+                base32::decode_prefixed(FEDIMINT_PREFIX, &payload.notes).map_err(|e| {
+                    PublicGatewayError::ReceiveEcashError {
+                        failure_reason: format!("Expected ECash for MintV2 federation: {e}"),
+                    }
+                })?;
+            let amount = ecash.amount();
+
+            let operation_id = mint
+                .receive(ecash, serde_json::Value::Null)
+                .await
+                .map_err(|e| PublicGatewayError::ReceiveEcashError {
+                    failure_reason: e.to_string(),
+                })?;
+
+            if payload.wait {
+                match mint.await_final_receive_operation_state(operation_id).await {
+                    fedimint_mintv2_client::FinalReceiveOperationState::Success => {}
+                    fedimint_mintv2_client::FinalReceiveOperationState::Rejected => {
+                        return Err(PublicGatewayError::ReceiveEcashError {
+                            failure_reason: "ECash receive was rejected".to_string(),
+                        });
+                    }
+                }
+            }
+
+            Ok(ReceiveEcashResponse { amount })
+        } else {
+            Err(PublicGatewayError::ReceiveEcashError {
+                failure_reason: "No mint module found".to_string(),
+            })
+        }
+    }
+
+    /// Retrieves an invoice by the payment hash if it exists, otherwise returns
+    /// `None`.
+    pub async fn handle_get_invoice_msg(
+        &self,
+        payload: GetInvoiceRequest,
+    ) -> AdminResult<Option<GetInvoiceResponse>> {
+        let lightning_context = self.get_lightning_context().await?;
+        let invoice = lightning_context.lnrpc.get_invoice(payload).await?;
+        Ok(invoice)
+    }
+
+    /// Withdraws ecash from a federation and pegs-out to the Lightning node's
+    /// onchain wallet
+    pub async fn handle_withdraw_to_onchain_msg(
+        &self,
+        payload: WithdrawToOnchainPayload,
+    ) -> AdminResult<WithdrawResponse> {
+        let address = self.handle_get_ln_onchain_address_msg().await?;
+        let withdraw = WithdrawPayload {
+            address: address.into_unchecked(),
+            federation_id: payload.federation_id,
+            amount: payload.amount,
+            quoted_fees: None,
+        };
+        self.handle_withdraw_msg(withdraw).await
+    }
+
+    /// Deposits the specified amount from the gateway's onchain wallet into the
+    /// Federation's ecash wallet
+    pub async fn handle_pegin_from_onchain_msg(
+        &self,
+        payload: PeginFromOnchainPayload,
+    ) -> AdminResult<Txid> {
+        let deposit = DepositAddressPayload {
+            federation_id: payload.federation_id,
+        };
+        let address = self.handle_address_msg(deposit).await?;
+        let send_onchain = SendOnchainRequest {
+            address: address.into_unchecked(),
+            amount: payload.amount,
+            fee_rate_sats_per_vbyte: payload.fee_rate_sats_per_vbyte,
+        };
+        let txid = self.handle_send_onchain_msg(send_onchain).await?;
+
+        Ok(txid)
+    }
+
+    /// Moves ecash from one federation into another by pegging out of the
+    /// source federation directly into a deposit address of the destination
+    /// federation. The gateway's on-chain wallet is not involved; the deposit
+    /// is claimed by the destination federation's wallet client once the
+    /// peg-out confirms.
+    ///
+    /// Fails without moving any funds if the source federation's balance does
+    /// not cover the amount plus the peg-out fees.
+    pub async fn handle_rebalance_msg(
+        &self,
+        payload: RebalancePayload,
+    ) -> AdminResult<RebalanceResponse> {
+        if payload.from_federation_id == payload.to_federation_id {
+            return Err(AdminGatewayError::Unexpected(anyhow!(
+                "Cannot rebalance a federation into itself"
+            )));
+        }
+
+        let balance = self
+            .select_client(payload.from_federation_id)
+            .await?
+            .value()
+            .get_balance_for_btc()
+            .await
+            .map_err(|err| {
+                AdminGatewayError::Unexpected(anyhow!(
+                    "Balance not available: {}",
+                    err.fmt_compact_anyhow()
+                ))
+            })?;
+        let insufficient_balance = |required: Amount| AdminGatewayError::WithdrawError {
+            failure_reason: format!(
+                "Insufficient balance in federation {}: {balance} available, {required} required",
+                payload.from_federation_id
+            ),
+        };
+        if balance < payload.amount {
+            return Err(insufficient_balance(payload.amount));
+        }
+
+        let amount = bitcoin::Amount::from_sat(payload.amount.sats_round_down());
+        let deposit_address = self
+            .handle_address_msg(DepositAddressPayload {
+                federation_id: payload.to_federation_id,
+            })
+            .await?
+            .into_unchecked();
+
+        let preview = self
+            .handle_withdraw_preview_msg(WithdrawPreviewPayload {
+                federation_id: payload.from_federation_id,
+                amount: BitcoinAmountOrAll::Amount(amount),
+                address: deposit_address.clone(),
+            })
+            .await?;
+        if balance < preview.total_cost {
+            return Err(insufficient_balance(preview.total_cost));
+        }
+
+        let pegout = self
+            .handle_withdraw_msg(WithdrawPayload {
+                address: deposit_address,
+                federation_id: payload.from_federation_id,
+                amount: BitcoinAmountOrAll::Amount(amount),
+                quoted_fees: Some(preview.peg_out_fees),
+            })
+            .await?;
+
+        info!(
+            target: LOG_GATEWAY,
+            from_federation_id = %payload.from_federation_id,
+            to_federation_id = %payload.to_federation_id,
+            %amount,
+            txid = %pegout.txid,
+            "Rebalanced federations via onchain"
+        );
+
+        Ok(RebalanceResponse {
+            route: RebalanceRoute::Onchain,
+            amount,
+            txid: pegout.txid,
+            fees: pegout.fees,
+            total_cost: preview.total_cost,
+        })
+    }
+
+    /// Exercises the payment paths of the gateway for a federation without a
+    /// second party and reports the outcome and duration of each leg:
+    ///
+    /// 1. `spend_ecash`: spends `amount` of the gateway's ecash
+    /// 2. `receive_ecash`: reissues the spent notes, which also returns the
+    ///    funds to the gateway's balance
+    /// 3. `create_invoice`: creates an LNv2 invoice for `amount` that pays into
+    ///    an incoming contract the gateway can claim itself
+    /// 4. `pay_invoice`: funds an outgoing contract for the invoice and sends
+    ///    the payment through the gateway's LNv2 send path, which completes it
+    ///    as a direct swap by funding the incoming contract
+    /// 5. `claim_payment`: claims the incoming contract, which returns the
+    ///    funds to the gateway's balance
+    ///
+    /// The lightning legs are skipped if the federation does not support LNv2.
+    /// The test stops at the first failed leg. An outgoing contract that could
+    /// not be paid is cancelled, notes that could not be reissued are
+    /// reclaimed by the mint client once they expire.
+    pub async fn handle_self_test_msg(
+        &self,
+        SelfTestPayload {
+            federation_id,
+            amount,
+        }: SelfTestPayload,
+    ) -> AdminResult<SelfTestResponse> {
+        const SELF_TEST_INVOICE_EXPIRY_SECS: u32 = 60;
+
+        let client = self.select_client(federation_id).await?.into_value();
+
+        let amount = amount.unwrap_or(DEFAULT_SELF_TEST_AMOUNT);
+        let mut response = SelfTestResponse {
+            federation_id,
+            amount,
+            legs: Vec::new(),
+        };
+
+        let Some(notes) = Self::run_self_test_leg(&mut response, "spend_ecash", async {
+            self.handle_spend_ecash_msg(SpendEcashPayload {
+                federation_id,
+                amount,
+            })
+            .await
+            .map(|spent| (format!("spent {amount}"), spent.notes))
+            .map_err(|err| err.to_string())
+        })
+        .await
+        else {
+            return Ok(response);
+        };
+
+        let Some(()) = Self::run_self_test_leg(&mut response, "receive_ecash", async {
+            Self::handle_receive_ecash_msg(self, ReceiveEcashPayload { notes, wait: true })
+                .await
+                .map(|received| (format!("reissued {}", received.amount), ()))
+                .map_err(|err| err.to_string())
+        })
+        .await
+        else {
+            return Ok(response);
+        };
+
+        let Ok(lnv2) = client.get_first_module::<GatewayClientModuleV2>() else {
+            return Ok(response);
+        };
+
+        let Some((routing_info, incoming_contract, agg_decryption_key, invoice)) =
+            Self::run_self_test_leg(&mut response, "create_invoice", async {
+                let routing_info = self
+                    .routing_info_v2(&federation_id)
+                    .await
+                    .map_err(|err| err.to_string())?
+                    .ok_or("The gateway has no LNv2 key for the federation".to_string())?;
+
+                let (incoming_contract, agg_decryption_key) = lnv2.create_self_incoming_contract(
+                    routing_info.receive_fee.subtract_from(amount.msats),
+                    duration_since_epoch().as_secs() + u64::from(SELF_TEST_INVOICE_EXPIRY_SECS),
+                );
+
+                let invoice = self
+                    .create_bolt11_invoice_v2(CreateBolt11InvoicePayload {
+                        federation_id,
+                        contract: incoming_contract.clone(),
+                        amount,
+                        description: Bolt11InvoiceDescription::Direct(format!(
+                            "Gateway self test for federation {federation_id}"
+                        )),
+                        expiry_secs: SELF_TEST_INVOICE_EXPIRY_SECS,
+                    })
+                    .await
+                    .map_err(|err| err.to_string())?;
+
+                Ok::<_, String>((
+                    format!("payment hash {}", invoice.payment_hash()),
+                    (routing_info, incoming_contract, agg_decryption_key, invoice),
+                ))
+            })
+            .await
+        else {
+            return Ok(response);
+        };
+
+        let Some(()) = Self::run_self_test_leg(&mut response, "pay_invoice", async {
+            let (send_fee, expiration_delta) = routing_info.send_parameters(&invoice);
+
+            let payload = lnv2
+                .fund_self_outgoing_contract(
+                    invoice,
+                    send_fee.add_to(amount.msats),
+                    expiration_delta,
+                )
+                .await
+                .map_err(|err| err.to_string())?;
+            let outpoint = payload.outpoint;
+            let outgoing_contract = payload.contract.clone();
+
+            let error = match self.send_payment_v2(payload).await {
+                Ok(Ok(_)) => return Ok(("paid invoice as a direct swap".to_string(), ())),
+                Ok(Err(_)) => "The payment was cancelled".to_string(),
+                Err(err) => err.to_string(),
+            };
+
+            // The gateway did not claim the outgoing contract, so it can take
+            // the funds back right away
+            match lnv2
+                .cancel_self_outgoing_contract(outpoint, &outgoing_contract)
+                .await
+            {
+                Ok(()) => Err(error),
+                Err(err) => Err(format!(
+                    "{error}, cancelling the outgoing contract failed: {err}"
+                )),
+            }
+        })
+        .await
+        else {
+            return Ok(response);
+        };
+
+        Self::run_self_test_leg(&mut response, "claim_payment", async {
+            lnv2.claim_incoming_contract(&incoming_contract, agg_decryption_key)
+                .await
+                .map(|()| {
+                    (
+                        format!("claimed {}", incoming_contract.commitment.amount),
+                        (),
+                    )
+                })
+                .map_err(|err| err.to_string())
+        })
+        .await;
+
+        Ok(response)
+    }
+
+    /// Runs one leg of a self test, recording its outcome and duration in
+    /// `response`. Returns the leg's output if it succeeded.
+    async fn run_self_test_leg<T>(
+        response: &mut SelfTestResponse,
+        leg: &str,
+        future: impl Future<Output = std::result::Result<(String, T), String>>,
+    ) -> Option<T> {
+        let start = fedimint_core::time::now();
+        let result = future.await;
+        let duration_ms = fedimint_core::time::now()
+            .duration_since(start)
+            .unwrap_or_default()
+            .as_millis()
+            .try_into()
+            .unwrap_or(u64::MAX);
+
+        let (success, detail, output) = match result {
+            Ok((detail, output)) => (true, detail, Some(output)),
+            Err(err) => (false, err, None),
+        };
+
+        response.legs.push(SelfTestLeg {
+            leg: leg.to_string(),
+            success,
+            duration_ms,
+            detail,
+        });
+
+        output
+    }
+
+    /// Lists all connected federations together with the gateway's ecash
+    /// balance in each of them and the active channels and route hints that
+    /// route their payments.
+    pub async fn handle_list_federations_msg(&self) -> AdminResult<ListFederationsResponse> {
+        let (active_channels, route_hints) = match self.get_lightning_context().await {
+            Ok(lightning_context) => {
+                let active_channels = match lightning_context.lnrpc.list_channels().await {
+                    Ok(response) => Some(
+                        response
+                            .channels
+                            .iter()
+                            .filter(|channel| channel.is_active)
+                            .count(),
+                    ),
+                    Err(err) => {
+                        warn!(target: LOG_GATEWAY, err = %err.fmt_compact(), "Failed to list lightning channels");
+                        None
+                    }
+                };
+                let route_hints = self
+                    .registration_route_hints(&lightning_context)
+                    .await
+                    .len();
+                (active_channels, Some(route_hints))
+            }
+            Err(err) => {
+                warn!(target: LOG_GATEWAY, err = %err.fmt_compact(), "Lightning node is not available");
+                (None, None)
+            }
+        };
+
+        let federations = self
+            .federation_manager
+            .read()
+            .await
+            .federation_summary_all_federations(active_channels, route_hints)
+            .await;
+        Ok(ListFederationsResponse { federations })
+    }
+
+    /// Reports whether the client for the federation has been connected and
+    /// finished recovering, so that it can serve payments.
+    pub async fn handle_federation_ready_msg(
+        &self,
+        FederationReadyPayload { federation_id }: FederationReadyPayload,
+    ) -> AdminResult<FederationReadiness> {
+        let federation_manager = self.federation_manager.read().await;
+        let client = federation_manager.client(&federation_id);
+        Ok(FederationReadiness {
+            federation_id,
+            connected: client.is_some(),
+            recovering: client.is_some_and(|client| client.value().has_pending_recoveries()),
+        })
+    }
+
+    /// Registers the gateway with the given federation, or all connected
+    /// federations, right away instead of waiting for the periodic
+    /// registration. Only gateways using LND register with federations.
+    pub async fn handle_reregister_msg(
+        &self,
+        ReregisterPayload { federation_id }: ReregisterPayload,
+    ) -> AdminResult<Vec<FederationRegistration>> {
+        if !matches!(self.lightning_mode, LightningMode::Lnd { .. }) {
+            return Err(AdminGatewayError::GatewayConfigurationError(
+                "Only gateways using LND register with federations".to_string(),
+            ));
+        }
+
+        let lightning_context = self.get_lightning_context().await?;
+        let route_hints = self.registration_route_hints(&lightning_context).await;
+
+        let mut federations = self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_federation_configs()
+            .await;
+        if let Some(federation_id) = federation_id {
+            let federation_config =
+                federations
+                    .remove(&federation_id)
+                    .ok_or(FederationNotConnected {
+                        federation_id_prefix: federation_id.to_prefix(),
+                    })?;
+            federations = BTreeMap::from([(federation_id, federation_config)]);
+        }
+
+        let mut results = Vec::new();
+        for (federation_id, federation_config) in federations {
+            let error = match self.select_client(federation_id).await {
+                Ok(client) => match client.value().get_first_module::<GatewayClientModule>() {
+                    Ok(gateway_client) => {
+                        let mut errors = Vec::new();
+                        for registration in self.registrations.values() {
+                            if let Err(err) = gateway_client
+                                .register_with_federation(
+                                    route_hints.clone(),
+                                    GW_ANNOUNCEMENT_TTL,
+                                    federation_config.lightning_fee.into(),
+                                    lightning_context.clone(),
+                                    registration.endpoint_url.clone(),
+                                    registration.keypair.public_key(),
+                                )
+                                .await
+                            {
+                                errors.push(err.fmt_compact_anyhow().to_string());
+                            }
+                        }
+                        (!errors.is_empty()).then(|| errors.join(", "))
+                    }
+                    Err(err) => Some(err.fmt_compact_anyhow().to_string()),
+                },
+                Err(err) => Some(err.to_string()),
+            };
+
+            results.push(FederationRegistration {
+                federation_id,
+                error,
+            });
+        }
+
+        Ok(results)
+    }
+
+    /// Checks the health of the lightning node, its on-chain wallet and the API
+    /// of each connected federation.
+    pub async fn handle_health_check_msg(&self) -> AdminResult<HealthCheckResponse> {
+        const LIGHTNING_NODE: &str = "lightning node";
+        const ONCHAIN_WALLET: &str = "onchain wallet";
+
+        let mut subsystems = Vec::new();
+        match self.get_lightning_context().await {
+            Ok(context) => {
+                subsystems.push(match context.lnrpc.info().await {
+                    Ok(info) if info.synced_to_chain => SubsystemHealth::healthy(
+                        LIGHTNING_NODE,
+                        format!("{} at block {}", info.alias, info.block_height),
+                    ),
+                    Ok(info) => SubsystemHealth::unhealthy(
+                        LIGHTNING_NODE,
+                        format!("{} is not synced to chain", info.alias),
+                    ),
+                    Err(err) => SubsystemHealth::unhealthy(LIGHTNING_NODE, err.to_string()),
+                });
+                subsystems.push(match context.lnrpc.get_balances().await {
+                    Ok(balances) => SubsystemHealth::healthy(
+                        ONCHAIN_WALLET,
+                        format!("{} sats", balances.onchain_balance_sats),
+                    ),
+                    Err(err) => SubsystemHealth::unhealthy(ONCHAIN_WALLET, err.to_string()),
+                });
+            }
+            Err(_) => {
+                let detail = format!("gateway is {}", self.get_state().await);
+                subsystems.push(SubsystemHealth::unhealthy(LIGHTNING_NODE, detail.clone()));
+                subsystems.push(SubsystemHealth::unhealthy(ONCHAIN_WALLET, detail));
+            }
+        }
+
+        let api_prober = self.federation_manager.read().await.api_prober();
+        subsystems.extend(api_prober.api_health(HEALTH_CHECK_FEDERATION_TIMEOUT).await);
+
+        Ok(HealthCheckResponse { subsystems })
+    }
+
+    /// Lists the lightning payments the gateway is currently mediating
+    pub async fn handle_in_flight_payments_msg(&self) -> AdminResult<Vec<InFlightPayment>> {
+        Ok(self
+            .federation_manager
+            .read()
+            .await
+            .in_flight_payments_all_federations()
+            .await)
+    }
+
+    /// Computes the fee the gateway charges a client of the federation for
+    /// paying the invoice, without paying it. Mirrors the default send fee the
+    /// gateway quotes to LNv2 clients.
+    pub async fn handle_estimate_fee_msg(
+        &self,
+        EstimateFeePayload {
+            federation_id,
+            invoice,
+        }: EstimateFeePayload,
+    ) -> AdminResult<FeeEstimate> {
+        let amount_msats = invoice
+            .amount_milli_satoshis()
+            .ok_or_else(|| anyhow!("Amountless invoice not supported"))?;
+
+        let config = self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_federation_config(federation_id)
+            .await
+            .ok_or(FederationNotConnected {
+                federation_id_prefix: federation_id.to_prefix(),
+            })?;
+
+        let send_fee = config.send_fee_default();
+        let proportional_fee = PaymentFee {
+            base: Amount::ZERO,
+            parts_per_million: send_fee.parts_per_million,
+        }
+        .fee(amount_msats);
+
+        Ok(FeeEstimate {
+            federation_id,
+            amount: Amount::from_msats(amount_msats),
+            base_fee: send_fee.base,
+            proportional_fee,
+            routing_budget: config.lightning_fee.fee(amount_msats),
+            total: send_fee.add_to(amount_msats),
+        })
+    }
+
+    /// Returns the payment events added to the log of a federation since
+    /// `start_position`, oldest first. If there are none yet, waits up to
+    /// `wait_secs` for new ones, so clients can follow the log by repeatedly
+    /// requesting the returned `next_position`.
+    pub async fn handle_payment_log_tail_msg(
+        &self,
+        PaymentLogTailPayload {
+            federation_id,
+            event_kinds,
+            start_position,
+            wait_secs,
+        }: PaymentLogTailPayload,
+    ) -> AdminResult<PaymentLogTailResponse> {
+        const MAX_ENTRIES: usize = 100;
+        const MAX_WAIT: Duration = Duration::from_secs(60);
+
+        // Don't hold the lock on the federation manager while waiting
+        let client = self
+            .federation_manager
+            .read()
+            .await
+            .client(&federation_id)
+            .ok_or(FederationNotConnected {
+                federation_id_prefix: federation_id.to_prefix(),
+            })?
+            .value()
+            .clone();
+
+        let event_kinds = if event_kinds.is_empty() {
+            ALL_GATEWAY_EVENTS.to_vec()
+        } else {
+            event_kinds
+        };
+
+        let mut log_event_added_rx = client.log_event_added_rx();
+        let mut start_position = match start_position {
+            Some(position) => position,
+            None => {
+                client
+                    .db()
+                    .begin_transaction_nc()
+                    .await
+                    .get_next_event_log_id()
+                    .await
+            }
+        };
+
+        let deadline = fedimint_core::time::now() + Duration::from_secs(wait_secs).min(MAX_WAIT);
+        loop {
+            log_event_added_rx.borrow_and_update();
+
+            let end_position = client
+                .db()
+                .begin_transaction_nc()
+                .await
+                .get_next_event_log_id()
+                .await;
+            let entries = client
+                .query_event_log(Some(start_position), MAX_ENTRIES, &event_kinds)
+                .await;
+
+            let next_position = match entries.last() {
+                Some(last) if entries.len() == MAX_ENTRIES => last.id().next(),
+                Some(last) => end_position.max(last.id().next()),
+                None => end_position.max(start_position),
+            };
+
+            let remaining = deadline
+                .duration_since(fedimint_core::time::now())
+                .unwrap_or_default();
+            if !entries.is_empty() || remaining.is_zero() {
+                return Ok(PaymentLogTailResponse {
+                    entries,
+                    next_position,
+                });
+            }
+
+            start_position = next_position;
+            if !matches!(
+                fedimint_core::runtime::timeout(remaining, log_event_added_rx.changed()).await,
+                Ok(Ok(()))
+            ) {
+                return Ok(PaymentLogTailResponse {
+                    entries,
+                    next_position,
+                });
+            }
+        }
+    }
+
+    /// Returns the lightning and transaction fees the gateway charges in the
+    /// federation specified by the `FederationId`, or in all federations.
+    pub async fn handle_get_fees_msg(
+        &self,
+        GetFeesPayload { federation_id }: GetFeesPayload,
+    ) -> AdminResult<Vec<FederationFees>> {
+        let mut dbtx = self.gateway_db.begin_transaction_nc().await;
+        let fed_configs = if let Some(federation_id) = federation_id {
+            let config =
+                dbtx.load_federation_config(federation_id)
+                    .await
+                    .ok_or(FederationNotConnected {
+                        federation_id_prefix: federation_id.to_prefix(),
+                    })?;
+            BTreeMap::from([(federation_id, config)])
+        } else {
+            dbtx.load_federation_configs().await
+        };
+
+        Ok(fed_configs
+            .into_iter()
+            .map(|(federation_id, config)| FederationFees {
+                federation_id,
+                lightning_fee: config.lightning_fee,
+                transaction_fee: config.transaction_fee,
+            })
+            .collect())
+    }
+
+    /// Returns the routing policy configured by the operator, falling back to
+    /// the policy derived from the startup configuration.
+    async fn routing_policy(&self) -> RoutingPolicy {
+        self.gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_routing_policy()
+            .await
+            .unwrap_or(RoutingPolicy {
+                cltv_delta: DEFAULT_CLTV_DELTA,
+                route_hint_mode: if self.num_route_hints == 0 {
+                    RouteHintMode::None
+                } else {
+                    RouteHintMode::Private
+                },
+            })
+    }
+
+    /// Exports the persistent configuration of the gateway: the connected
+    /// federations with their fees, the routing policy and the lightning node
+    /// the gateway is linked to.
+    pub async fn handle_export_state_msg(&self) -> AdminResult<GatewayStateBackup> {
+        let mut dbtx = self.gateway_db.begin_transaction_nc().await;
+        let federations = dbtx
+            .load_federation_configs()
+            .await
+            .into_values()
+            .collect::<Vec<_>>();
+        let mut enabled_modules = BTreeMap::new();
+        for config in &federations {
+            let federation_id = config.invite_code.federation_id();
+            if let Some(modules) = dbtx.load_enabled_modules(federation_id).await {
+                enabled_modules.insert(federation_id, modules);
+            }
+        }
+
+        Ok(GatewayStateBackup {
+            federations,
+            enabled_modules,
+            routing_policy: self.routing_policy().await,
+            lightning_mode: self.lightning_mode.clone(),
+        })
+    }
+
+    /// Restores a backup created by `handle_export_state_msg`. Every federation
+    /// the gateway is not yet connected to is joined with recovery enabled,
+    /// so the ecash is restored from the mnemonic, and its fees are set to the
+    /// backed up values. The lightning node cannot be changed at runtime and
+    /// is only compared against the one the gateway was started with.
+    pub async fn handle_import_state_msg(
+        &self,
+        backup: GatewayStateBackup,
+    ) -> AdminResult<Vec<FederationInfo>> {
+        if backup.lightning_mode != self.lightning_mode {
+            warn!(
+                target: LOG_GATEWAY,
+                "Lightning node of the backup differs from the one the gateway was started with"
+            );
+        }
+
+        let mut restored = Vec::new();
+        for config in backup.federations {
+            let federation_id = config.invite_code.federation_id();
+            if self
+                .federation_manager
+                .read()
+                .await
+                .has_federation(federation_id)
+            {
+                info!(
+                    target: LOG_GATEWAY,
+                    %federation_id,
+                    "Federation is already connected, skipping"
+                );
+                continue;
+            }
+
+            let mut federation_info = self
+                .handle_connect_federation(ConnectFedPayload {
+                    invite_code: config.invite_code.to_string(),
+                    use_tor: None,
+                    recover: Some(true),
+                    modules: backup.enabled_modules.get(&federation_id).cloned(),
+                })
+                .await?;
+
+            self.handle_set_fees_msg(SetFeesPayload {
+                federation_id: Some(federation_id),
+                lightning_base: Some(config.lightning_fee.base),
+                lightning_parts_per_million: Some(config.lightning_fee.parts_per_million),
+                transaction_base: Some(config.transaction_fee.base),
+                transaction_parts_per_million: Some(config.transaction_fee.parts_per_million),
+            })
+            .await?;
+
+            federation_info.config.lightning_fee = config.lightning_fee;
+            federation_info.config.transaction_fee = config.transaction_fee;
+            restored.push(federation_info);
+        }
+
+        self.handle_set_routing_policy_msg(SetRoutingPolicyPayload {
+            cltv_delta: Some(backup.routing_policy.cltv_delta),
+            route_hint_mode: Some(backup.routing_policy.route_hint_mode),
+        })
+        .await?;
+
+        Ok(restored)
+    }
+
+    /// Returns the lightning routing policy currently used by the gateway.
+    pub async fn handle_get_routing_policy_msg(&self) -> AdminResult<RoutingPolicy> {
+        Ok(self.routing_policy().await)
+    }
+
+    /// Updates the lightning routing policy of the gateway and re-registers
+    /// with all connected federations so that the new route hints take effect
+    /// without a restart.
+    pub async fn handle_set_routing_policy_msg(
+        &self,
+        SetRoutingPolicyPayload {
+            cltv_delta,
+            route_hint_mode,
+        }: SetRoutingPolicyPayload,
+    ) -> AdminResult<RoutingPolicy> {
+        let mut routing_policy = self.routing_policy().await;
+        if let Some(cltv_delta) = cltv_delta {
+            if cltv_delta < EXPIRATION_DELTA_MINIMUM_V2 {
+                return Err(AdminGatewayError::GatewayConfigurationError(format!(
+                    "CLTV delta must be at least {EXPIRATION_DELTA_MINIMUM_V2}"
+                )));
+            }
+
+            routing_policy.cltv_delta = cltv_delta;
+        }
+
+        if let Some(route_hint_mode) = route_hint_mode {
+            routing_policy.route_hint_mode = route_hint_mode;
+        }
+
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+        dbtx.save_routing_policy(&routing_policy).await;
+        let fed_configs = dbtx.load_federation_configs().await;
+        dbtx.commit_tx().await;
+
+        if matches!(self.lightning_mode, LightningMode::Lnd { .. }) {
+            let register_task_group = TaskGroup::new();
+
+            self.register_federations(&fed_configs, &register_task_group)
+                .await;
         }
+
+        Ok(routing_policy)
     }
 
-    /// Handles a request to receive ecash into the gateway.
-    pub async fn handle_receive_ecash_msg(
+    /// Returns the send and receive limits of a federation together with the
+    /// amounts used within the current window.
+    pub async fn handle_get_limits_msg(
         &self,
-        payload: ReceiveEcashPayload,
-    ) -> Result<ReceiveEcashResponse> {
-        // Extract federation_id_prefix from either format
-        let federation_id_prefix = base32::decode_prefixed::<fedimint_mintv2_client::ECash>(
-            FEDIMINT_PREFIX,
-            &payload.notes,
-        )
-        .ok()
-        .and_then(|e| e.mint())
-        .map(|id| id.to_prefix())
-        .or_else(|| {
-            OOBNotes::from_str(&payload.notes)
-                .ok()
-                .map(|n| n.federation_id_prefix())
-        })
-        .ok_or_else(|| PublicGatewayError::ReceiveEcashError {
-            failure_reason: "Invalid ecash format: could not parse as ECash or OOBNotes"
-                .to_string(),
-        })?;
+        GetLimitsPayload { federation_id }: GetLimitsPayload,
+    ) -> AdminResult<FederationLimitsResponse> {
+        self.select_client(federation_id).await?;
 
-        let client = self
-            .federation_manager
-            .read()
+        let limits = self
+            .gateway_db
+            .begin_transaction_nc()
             .await
-            .get_client_for_federation_id_prefix(federation_id_prefix)
-            .ok_or(FederationNotConnected {
-                federation_id_prefix,
-            })?;
+            .load_federation_limits(federation_id)
+            .await;
 
-        // Check which module is present and parse accordingly
-        if let Ok(mint) = client.value().get_first_module::<MintClientModule>() {
-            let notes = OOBNotes::from_str(&payload.notes).map_err(|e| {
-                PublicGatewayError::ReceiveEcashError {
-                    failure_reason: format!("Expected OOBNotes for MintV1 federation: {e}"),
-                }
-            })?;
-            let amount = notes.total_amount();
+        let (sent_in_window, received_in_window) = match limits {
+            Some(limits) => (
+                self.payment_limiter
+                    .used(federation_id, LimitDirection::Send, limits.window_secs)
+                    .await,
+                self.payment_limiter
+                    .used(federation_id, LimitDirection::Receive, limits.window_secs)
+                    .await,
+            ),
+            None => (Amount::ZERO, Amount::ZERO),
+        };
 
-            let operation_id = mint.reissue_external_notes(notes, ()).await.map_err(|e| {
-                PublicGatewayError::ReceiveEcashError {
-                    failure_reason: e.to_string(),
-                }
-            })?;
-            if payload.wait {
-                let mut updates = mint
-                    .subscribe_reissue_external_notes(operation_id)
-                    .await
-                    .unwrap()
-                    .into_stream();
+        Ok(FederationLimitsResponse {
+            federation_id,
+            limits,
+            sent_in_window,
+            received_in_window,
+        })
+    }
 
-                while let Some(update) = updates.next().await {
-                    if let fedimint_mint_client::ReissueExternalNotesState::Failed(e) = update {
-                        return Err(PublicGatewayError::ReceiveEcashError {
-                            failure_reason: e.clone(),
-                        });
-                    }
-                }
-            }
+    /// Sets the maximum amounts a federation's users can send and receive
+    /// through the gateway within a sliding window. Removes the limits if
+    /// neither a send nor a receive limit is given.
+    pub async fn handle_set_limits_msg(
+        &self,
+        SetLimitsPayload {
+            federation_id,
+            max_send_msat,
+            max_receive_msat,
+            window_secs,
+        }: SetLimitsPayload,
+    ) -> AdminResult<FederationLimitsResponse> {
+        self.select_client(federation_id).await?;
+
+        if window_secs == 0 {
+            return Err(AdminGatewayError::GatewayConfigurationError(
+                "The limit window must be at least one second".to_string(),
+            ));
+        }
 
-            Ok(ReceiveEcashResponse { amount })
-        } else if let Ok(mint) = client.value().get_first_module::<MintV2ClientModule>() {
-            let ecash: fedimint_mintv2_client::ECash =
-                base32::decode_prefixed(FEDIMINT_PREFIX, &payload.notes).map_err(|e| {
-                    PublicGatewayError::ReceiveEcashError {
-                        failure_reason: format!("Expected ECash for MintV2 federation: {e}"),
-                    }
-                })?;
-            let amount = ecash.amount();
+        let mut dbtx = self.gateway_db.begin_transaction().await;
+        if max_send_msat.is_none() && max_receive_msat.is_none() {
+            dbtx.remove_federation_limits(federation_id).await;
+            self.payment_limiter.clear(federation_id).await;
+        } else {
+            let limits = FederationLimits {
+                max_send: max_send_msat.map(Amount::from_msats),
+                max_receive: max_receive_msat.map(Amount::from_msats),
+                window_secs,
+            };
+            dbtx.save_federation_limits(federation_id, &limits).await;
+        }
+        dbtx.commit_tx().await;
 
-            let operation_id = mint
-                .receive(ecash, serde_json::Value::Null)
-                .await
-                .map_err(|e| PublicGatewayError::ReceiveEcashError {
-                    failure_reason: e.to_string(),
-                })?;
+        self.handle_get_limits_msg(GetLimitsPayload { federation_id })
+            .await
+    }
 
-            if payload.wait {
-                match mint.await_final_receive_operation_state(operation_id).await {
-                    fedimint_mintv2_client::FinalReceiveOperationState::Success => {}
-                    fedimint_mintv2_client::FinalReceiveOperationState::Rejected => {
-                        return Err(PublicGatewayError::ReceiveEcashError {
-                            failure_reason: "ECash receive was rejected".to_string(),
-                        });
-                    }
-                }
-            }
+    /// Records a payment against the limits of its federation, rejecting it if
+    /// the configured limit for the window would be exceeded. Returns whether
+    /// a new reservation was made that has to be released if the payment
+    /// fails.
+    async fn reserve_payment_limit(
+        &self,
+        federation_id: FederationId,
+        direction: LimitDirection,
+        payment_id: sha256::Hash,
+        amount: Amount,
+    ) -> Result<bool> {
+        let Some(limits) = self
+            .gateway_db
+            .begin_transaction_nc()
+            .await
+            .load_federation_limits(federation_id)
+            .await
+        else {
+            return Ok(false);
+        };
 
-            Ok(ReceiveEcashResponse { amount })
-        } else {
-            Err(PublicGatewayError::ReceiveEcashError {
-                failure_reason: "No mint module found".to_string(),
+        self.payment_limiter
+            .try_reserve(federation_id, direction, payment_id, amount, &limits)
+            .await
+            .map_err(|limit| PublicGatewayError::PaymentLimitExceeded {
+                federation_id,
+                direction,
+                limit,
             })
-        }
     }
 
-    /// Retrieves an invoice by the payment hash if it exists, otherwise returns
-    /// `None`.
-    pub async fn handle_get_invoice_msg(
-        &self,
-        payload: GetInvoiceRequest,
-    ) -> AdminResult<Option<GetInvoiceResponse>> {
-        let lightning_context = self.get_lightning_context().await?;
-        let invoice = lightning_context.lnrpc.get_invoice(payload).await?;
-        Ok(invoice)
+    /// Releases the receive limit reservation of an incoming payment whose
+    /// contract failed or was cancelled, as the federation's users never got
+    /// the funds.
+    async fn release_cancelled_receive(&self, htlc_response: &InterceptPaymentResponse) {
+        if matches!(htlc_response.action, PaymentAction::Cancel) {
+            self.payment_limiter
+                .release(
+                    LimitDirection::Receive,
+                    receive_payment_id(
+                        htlc_response.payment_hash,
+                        htlc_response.incoming_chan_id,
+                        htlc_response.htlc_id,
+                    ),
+                )
+                .await;
+        }
     }
 
-    /// Withdraws ecash from a federation and pegs-out to the Lightning node's
-    /// onchain wallet
-    pub async fn handle_withdraw_to_onchain_msg(
+    /// Resolves the invite code and returns the federation's id, name, network
+    /// and modules without creating a client or registering with it.
+    pub async fn handle_preview_federation_msg(
         &self,
-        payload: WithdrawToOnchainPayload,
-    ) -> AdminResult<WithdrawResponse> {
-        let address = self.handle_get_ln_onchain_address_msg().await?;
-        let withdraw = WithdrawPayload {
-            address: address.into_unchecked(),
-            federation_id: payload.federation_id,
-            amount: payload.amount,
-            quoted_fees: None,
-        };
-        self.handle_withdraw_msg(withdraw).await
+        payload: PreviewFedPayload,
+    ) -> AdminResult<FederationPreview> {
+        let invite_code = InviteCode::from_str(&payload.invite_code).map_err(|e| {
+            AdminGatewayError::ClientCreationError(anyhow!(format!(
+                "Invalid federation member string {e:?}"
+            )))
+        })?;
+
+        let config = self.client_builder.preview_config(&invite_code).await?;
+
+        let network = config.modules.values().find_map(|module_config| {
+            let decoded = module_config.config.clone().decoded()?;
+            let any_config = decoded.as_any();
+            any_config
+                .downcast_ref::<WalletClientConfig>()
+                .map(|wallet_config| wallet_config.network.0)
+                .or_else(|| {
+                    any_config
+                        .downcast_ref::<WalletV2ClientConfig>()
+                        .map(|wallet_config| wallet_config.network)
+                })
+        });
+
+        Ok(FederationPreview {
+            federation_id: invite_code.federation_id(),
+            federation_name: config.global.federation_name().map(ToOwned::to_owned),
+            network,
+            modules: config
+                .modules
+                .iter()
+                .map(|(module_instance_id, module_config)| {
+                    (*module_instance_id, module_config.kind.clone())
+                })
+                .collect(),
+        })
     }
 
-    /// Deposits the specified amount from the gateway's onchain wallet into the
-    /// Federation's ecash wallet
-    pub async fn handle_pegin_from_onchain_msg(
+    /// Route hints to include in the registration with federations, according
+    /// to the routing policy
+    async fn registration_route_hints(
         &self,
-        payload: PeginFromOnchainPayload,
-    ) -> AdminResult<Txid> {
-        let deposit = DepositAddressPayload {
-            federation_id: payload.federation_id,
-        };
-        let address = self.handle_address_msg(deposit).await?;
-        let send_onchain = SendOnchainRequest {
-            address: address.into_unchecked(),
-            amount: payload.amount,
-            fee_rate_sats_per_vbyte: payload.fee_rate_sats_per_vbyte,
+        lightning_context: &LightningContext,
+    ) -> Vec<RouteHint> {
+        let num_route_hints = match self.routing_policy().await.route_hint_mode {
+            RouteHintMode::None => 0,
+            RouteHintMode::Private if self.num_route_hints == 0 => DEFAULT_NUM_ROUTE_HINTS,
+            RouteHintMode::Private => self.num_route_hints,
         };
-        let txid = self.handle_send_onchain_msg(send_onchain).await?;
-
-        Ok(txid)
+        let route_hints = lightning_context
+            .lnrpc
+            .parsed_route_hints(num_route_hints)
+            .await;
+        if num_route_hints > 0 && route_hints.is_empty() {
+            warn!(target: LOG_GATEWAY, "Gateway did not retrieve any route hints, may reduce receive success rate.");
+        }
+        route_hints
     }
 
     /// Registers the gateway with each specified federation.
@@ -1635,13 +2791,7 @@ async fn register_federations(
         register_task_group: &TaskGroup,
     ) {
         if let Ok(lightning_context) = self.get_lightning_context().await {
-            let route_hints = lightning_context
-                .lnrpc
-                .parsed_route_hints(self.num_route_hints)
-                .await;
-            if route_hints.is_empty() {
-                warn!(target: LOG_GATEWAY, "Gateway did not retrieve any route hints, may reduce receive success rate.");
-            }
+            let route_hints = self.registration_route_hints(&lightning_context).await;
 
             for (federation_id, federation_config) in federations {
                 let fed_manager = self.federation_manager.read().await;
@@ -1715,9 +2865,16 @@ async fn load_clients(&self) -> AdminResult<()> {
 
         let mut federation_manager = self.federation_manager.write().await;
 
-        let configs = {
+        let (configs, enabled_modules) = {
             let mut dbtx = self.gateway_db.begin_transaction_nc().await;
-            dbtx.load_federation_configs().await
+            let configs = dbtx.load_federation_configs().await;
+            let mut enabled_modules = BTreeMap::new();
+            for federation_id in configs.keys() {
+                if let Some(modules) = dbtx.load_enabled_modules(*federation_id).await {
+                    enabled_modules.insert(*federation_id, modules);
+                }
+            }
+            (configs, enabled_modules)
         };
 
         if let Some(max_federation_index) = configs.values().map(|cfg| cfg.federation_index).max() {
@@ -1732,8 +2889,12 @@ async fn load_clients(&self) -> AdminResult<()> {
             let federation_index = config.federation_index;
             match Box::pin(Spanned::try_new(
                 info_span!(target: LOG_GATEWAY, "client", federation_id  = %federation_id.clone()),
-                self.client_builder
-                    .build(config, Arc::new(self.clone()), &mnemonic),
+                self.client_builder.build(
+                    config,
+                    Arc::new(self.clone()),
+                    &mnemonic,
+                    enabled_modules.get(&federation_id),
+                ),
             ))
             .await
             {
@@ -1941,16 +3102,17 @@ async fn handle_get_info(&self) -> AdminResult<GatewayInfo> {
                     .iter()
                     .map(|(k, v)| (k.clone(), (v.endpoint_url.clone(), v.keypair.public_key())))
                     .collect(),
+                federation_connectivity: vec![],
             });
         };
 
         let dbtx = self.gateway_db.begin_transaction_nc().await;
-        let federations = self
-            .federation_manager
-            .read()
-            .await
+        let federation_manager = self.federation_manager.read().await;
+        let federations = federation_manager
             .federation_info_all_federations(dbtx)
             .await;
+        let federation_connectivity = federation_manager.api_connectivity();
+        drop(federation_manager);
 
         let channels: BTreeMap<u64, FederationId> = federations
             .iter()
@@ -1976,6 +3138,7 @@ async fn handle_get_info(&self) -> AdminResult<GatewayInfo> {
                 .iter()
                 .map(|(k, v)| (k.clone(), (v.endpoint_url.clone(), v.keypair.public_key())))
                 .collect(),
+            federation_connectivity,
         })
     }
 
@@ -1996,6 +3159,7 @@ async fn handle_payment_summary_msg(
         PaymentSummaryPayload {
             start_millis,
             end_millis,
+            group_by,
         }: PaymentSummaryPayload,
     ) -> AdminResult<PaymentSummaryResponse> {
         let federation_manager = self.federation_manager.read().await;
@@ -2010,24 +3174,52 @@ async fn handle_payment_summary_msg(
 
         let mut outgoing = StructuredPaymentEvents::default();
         let mut incoming = StructuredPaymentEvents::default();
+        let mut grouped_events = BTreeMap::<String, Vec<PersistedLogEntry>>::new();
         for fed_id in federation_ids {
             let client = federation_manager
                 .client(fed_id)
                 .expect("No client available")
                 .value();
-            let all_events = &get_events_for_duration(client, start, end).await;
-
-            let (mut lnv1_outgoing, mut lnv1_incoming) = compute_lnv1_stats(all_events);
-            let (mut lnv2_outgoing, mut lnv2_incoming) = compute_lnv2_stats(all_events);
-            outgoing.combine(&mut lnv1_outgoing);
-            incoming.combine(&mut lnv1_incoming);
-            outgoing.combine(&mut lnv2_outgoing);
-            incoming.combine(&mut lnv2_incoming);
+            let all_events = get_events_for_duration(client, start, end).await;
+
+            let (mut fed_outgoing, mut fed_incoming) = compute_payment_events(&all_events);
+            outgoing.combine(&mut fed_outgoing);
+            incoming.combine(&mut fed_incoming);
+
+            match group_by {
+                Some(PaymentSummaryGroupBy::Federation) => {
+                    grouped_events
+                        .entry(fed_id.to_string())
+                        .or_default()
+                        .extend(all_events);
+                }
+                Some(PaymentSummaryGroupBy::Day) => {
+                    for (day, events) in group_payment_events_by_start_day(all_events) {
+                        grouped_events.entry(day).or_default().extend(events);
+                    }
+                }
+                None => {}
+            }
         }
 
+        let groups = group_by.map(|_| {
+            grouped_events
+                .into_iter()
+                .map(|(key, events)| {
+                    let (outgoing, incoming) = compute_payment_events(&events);
+                    let group = PaymentSummaryGroup {
+                        outgoing: PaymentStats::compute(&outgoing),
+                        incoming: PaymentStats::compute(&incoming),
+                    };
+                    (key, group)
+                })
+                .collect()
+        });
+
         Ok(PaymentSummaryResponse {
             outgoing: PaymentStats::compute(&outgoing),
             incoming: PaymentStats::compute(&incoming),
+            groups,
         })
     }
 
@@ -2047,13 +3239,17 @@ async fn handle_leave_federation(
         let federation_info = federation_manager
             .leave_federation(
                 payload.federation_id,
+                payload.force,
                 &mut dbtx.to_ref_nc(),
                 self.registrations.values().collect(),
             )
             .await?;
 
         dbtx.remove_federation_config(payload.federation_id).await;
+        dbtx.remove_enabled_modules(payload.federation_id).await;
+        dbtx.remove_federation_limits(payload.federation_id).await;
         dbtx.commit_tx().await;
+        self.payment_limiter.clear(payload.federation_id).await;
         Ok(federation_info)
     }
 
@@ -2088,6 +3284,20 @@ async fn handle_connect_federation(
             )));
         }
 
+        if let Some(modules) = &payload.modules {
+            let supported = self.client_builder.supported_module_kinds();
+            let unsupported = modules
+                .difference(&supported)
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            if !unsupported.is_empty() {
+                return Err(AdminGatewayError::ClientCreationError(anyhow!(
+                    "Module kinds not supported by the gateway: {}",
+                    unsupported.join(", ")
+                )));
+            }
+        }
+
         // The gateway deterministically assigns a unique identifier (u64) to each
         // federation connected.
         let federation_index = federation_manager.pop_next_index()?;
@@ -2107,13 +3317,23 @@ async fn handle_connect_federation(
         let recover = payload.recover.unwrap_or(false);
         if recover {
             self.client_builder
-                .recover(federation_config.clone(), Arc::new(self.clone()), &mnemonic)
+                .recover(
+                    federation_config.clone(),
+                    Arc::new(self.clone()),
+                    &mnemonic,
+                    payload.modules.as_ref(),
+                )
                 .await?;
         }
 
         let client = self
             .client_builder
-            .build(federation_config.clone(), Arc::new(self.clone()), &mnemonic)
+            .build(
+                federation_config.clone(),
+                Arc::new(self.clone()),
+                &mnemonic,
+                payload.modules.as_ref(),
+            )
             .await?;
 
         if recover {
@@ -2170,6 +3390,10 @@ async fn handle_connect_federation(
         dbtx.save_federation_config(&federation_config).await;
         dbtx.save_federation_backup_record(federation_id, None)
             .await;
+        match &payload.modules {
+            Some(modules) => dbtx.save_enabled_modules(federation_id, modules).await,
+            None => dbtx.remove_enabled_modules(federation_id).await,
+        }
         dbtx.commit_tx().await;
         debug!(
             target: LOG_GATEWAY,
@@ -2254,6 +3478,14 @@ async fn handle_set_fees_msg(
                 )));
             }
 
+            if lightning_fee.parts_per_million > MAX_FEE_PARTS_PER_MILLION
+                || transaction_fee.parts_per_million > MAX_FEE_PARTS_PER_MILLION
+            {
+                return Err(AdminGatewayError::GatewayConfigurationError(format!(
+                    "Fee parts per million exceeded {MAX_FEE_PARTS_PER_MILLION}"
+                )));
+            }
+
             config.lightning_fee = lightning_fee;
             config.transaction_fee = transaction_fee;
             dbtx.save_federation_config(config).await;
@@ -2759,6 +3991,8 @@ async fn handle_payment_log_msg(
             pagination_size,
             federation_id,
             event_kinds,
+            start_millis,
+            end_millis,
         }: PaymentLogPayload,
     ) -> AdminResult<PaymentLogResponse> {
         const BATCH_SIZE: u64 = 10_000;
@@ -2779,6 +4013,12 @@ async fn handle_payment_log_msg(
             event_kinds
         };
 
+        let start_usecs = start_millis.map_or(0, |millis| millis.saturating_mul(1000));
+        let end_usecs = end_millis.map_or(u64::MAX, |millis| millis.saturating_mul(1000));
+        if end_usecs < start_usecs {
+            return Err(AdminGatewayError::Unexpected(anyhow!("Invalid time range")));
+        }
+
         let end_position = if let Some(position) = end_position {
             position
         } else {
@@ -2792,9 +4032,20 @@ async fn handle_payment_log_msg(
 
         while payment_log.len() < pagination_size {
             let batch = client.get_event_log(Some(start_position), BATCH_SIZE).await;
+
+            // The log is ordered by time, so once a batch starts before the time range
+            // there are no older events left to return
+            let reached_start = batch
+                .first()
+                .is_some_and(|e| e.as_raw().ts_usecs < start_usecs);
+
             let mut filtered_batch = batch
                 .into_iter()
-                .filter(|e| e.id() <= end_position && event_kinds.contains(&e.as_raw().kind))
+                .filter(|e| {
+                    e.id() <= end_position
+                        && event_kinds.contains(&e.as_raw().kind)
+                        && (start_usecs..=end_usecs).contains(&e.as_raw().ts_usecs)
+                })
                 .collect::<Vec<_>>();
             filtered_batch.reverse();
             payment_log.extend(filtered_batch);
@@ -2802,7 +4053,7 @@ async fn handle_payment_log_msg(
             // Compute the start position for the next batch query
             start_position = start_position.saturating_sub(BATCH_SIZE);
 
-            if start_position == EventLogId::LOG_START {
+            if start_position == EventLogId::LOG_START || reached_start {
                 break;
             }
         }
@@ -2958,8 +4209,7 @@ pub async fn routing_info_v2(
             }),
         )?;
 
-        let lightning_fee = fed_config.lightning_fee;
-        let transaction_fee = fed_config.transaction_fee;
+        let cltv_delta = self.routing_policy().await.cltv_delta;
 
         Ok(self
             .public_key_v2(federation_id)
@@ -2968,16 +4218,16 @@ pub async fn routing_info_v2(
                 lightning_public_key: context.lightning_public_key,
                 lightning_alias: Some(context.lightning_alias.clone()),
                 module_public_key,
-                send_fee_default: lightning_fee + transaction_fee,
+                send_fee_default: fed_config.send_fee_default(),
                 // The base fee ensures that the gateway does not loose sats sending the payment due
                 // to fees paid on the transaction claiming the outgoing contract or
                 // subsequent transactions spending the newly issued ecash
-                send_fee_minimum: transaction_fee,
-                expiration_delta_default: 1440,
+                send_fee_minimum: fed_config.send_fee_minimum(),
+                expiration_delta_default: cltv_delta,
                 expiration_delta_minimum: EXPIRATION_DELTA_MINIMUM_V2,
                 // The base fee ensures that the gateway does not loose sats receiving the payment
                 // due to fees paid on the transaction funding the incoming contract
-                receive_fee: transaction_fee,
+                receive_fee: fed_config.receive_fee(),
             }))
     }
 
@@ -2987,13 +4237,44 @@ async fn send_payment_v2(
         &self,
         payload: SendPaymentPayload,
     ) -> Result<std::result::Result<[u8; 32], Signature>> {
-        self.select_client(payload.federation_id)
+        let client = self.select_client(payload.federation_id).await?;
+
+        // Retries of an already started payment have been counted before, and
+        // concurrent first attempts share the reservation of the payment
+        let operation_id = OperationId::from_encodable(&payload.contract);
+        let payment_id = send_payment_id(operation_id);
+        let is_new_payment = client
+            .value()
+            .operation_log()
+            .get_operation(operation_id)
+            .await
+            .is_none();
+        let reserved = if is_new_payment {
+            self.reserve_payment_limit(
+                payload.federation_id,
+                LimitDirection::Send,
+                payment_id,
+                payload.contract.amount,
+            )
             .await?
+        } else {
+            false
+        };
+
+        let result = client
             .value()
             .get_first_module::<GatewayClientModuleV2>()
             .expect("Must have client module")
             .send_payment(payload)
-            .await
+            .await;
+
+        if reserved && !matches!(result, Ok(Ok(..))) {
+            self.payment_limiter
+                .release(LimitDirection::Send, payment_id)
+                .await;
+        }
+
+        result
             .map_err(LNv2Error::OutgoingPayment)
             .map_err(PublicGatewayError::LNv2)
     }
@@ -3213,6 +4494,8 @@ pub async fn get_registered_incoming_contract_and_client_v2(
 #[async_trait]
 impl IGatewayClientV2 for Gateway {
     async fn complete_htlc(&self, htlc_response: InterceptPaymentResponse) {
+        self.release_cancelled_receive(&htlc_response).await;
+
         loop {
             match self.get_lightning_context().await {
                 Ok(lightning_context) => {
@@ -3468,6 +4751,8 @@ async fn complete_htlc(
         &self,
         htlc: InterceptPaymentResponse,
     ) -> std::result::Result<(), LightningRpcError> {
+        self.release_cancelled_receive(&htlc).await;
+
         // Wait until the lightning node is online to complete the HTLC.
         let lightning_context = loop {
             match self.get_lightning_context().await {
@@ -0,0 +1,324 @@
+use std::time::{Duration, SystemTime};
+
+use bitcoin::hashes::sha256;
+use fedimint_core::Amount;
+use fedimint_core::config::FederationId;
+use fedimint_core::core::OperationId;
+use fedimint_core::db::{Database, DatabaseTransaction};
+use fedimint_core::encoding::Encodable as _;
+use fedimint_core::time::now;
+use fedimint_gateway_common::{FederationLimits, LimitDirection};
+use fedimint_gateway_server_db::{GatewayDbtxNcExt as _, PaymentLimitReservation};
+use tokio::sync::Mutex;
+
+/// Tracks the amounts each federation has sent and received through the
+/// gateway so that the operator configured [`FederationLimits`] can be
+/// enforced over a sliding window.
+///
+/// Every accepted payment reserves its amount in the gateway database under
+/// an id identifying the payment, so retries are only counted once, a failed
+/// payment releases exactly its own reservation and the window survives
+/// restarts.
+#[derive(Debug)]
+pub struct PaymentLimiter {
+    db: Database,
+    /// Serializes reservations, so concurrent payments can't both fit into
+    /// the remaining budget of a window
+    reserve_lock: Mutex<()>,
+}
+
+impl PaymentLimiter {
+    pub fn new(db: Database) -> Self {
+        Self {
+            db,
+            reserve_lock: Mutex::new(()),
+        }
+    }
+
+    /// Reserves `amount` for the payment `payment_id` if it fits within the
+    /// configured limit of the federation, otherwise returns the limit that
+    /// would have been exceeded.
+    ///
+    /// Returns `true` if a new reservation was made and `false` if no limit
+    /// is configured or the payment already holds a reservation.
+    pub async fn try_reserve(
+        &self,
+        federation_id: FederationId,
+        direction: LimitDirection,
+        payment_id: sha256::Hash,
+        amount: Amount,
+        limits: &FederationLimits,
+    ) -> Result<bool, Amount> {
+        let Some(max) = limits.max(direction) else {
+            return Ok(false);
+        };
+
+        let _guard = self.reserve_lock.lock().await;
+        let mut dbtx = self.db.begin_transaction().await;
+
+        if dbtx
+            .load_payment_limit_reservation(direction, payment_id)
+            .await
+            .is_some()
+        {
+            return Ok(false);
+        }
+
+        let now = now();
+        let used = Self::prune_and_sum(
+            &mut dbtx.to_ref_nc(),
+            federation_id,
+            direction,
+            limits.window_secs,
+            now,
+        )
+        .await;
+
+        // The amount comes from the payment, so it must not be able to
+        // overflow the sum and slip under the limit
+        if used
+            .and_then(|used| used.checked_add(amount))
+            .is_none_or(|total| total > max)
+        {
+            dbtx.commit_tx().await;
+            return Err(max);
+        }
+
+        dbtx.save_payment_limit_reservation(
+            direction,
+            payment_id,
+            &PaymentLimitReservation {
+                federation_id,
+                amount,
+                created_at: now,
+            },
+        )
+        .await;
+        dbtx.commit_tx().await;
+
+        Ok(true)
+    }
+
+    /// Removes the reservation of a payment that failed after it was
+    /// accepted.
+    pub async fn release(&self, direction: LimitDirection, payment_id: sha256::Hash) {
+        let mut dbtx = self.db.begin_transaction().await;
+        dbtx.remove_payment_limit_reservation(direction, payment_id)
+            .await;
+        dbtx.commit_tx().await;
+    }
+
+    /// Returns the amount the federation has used within the window
+    pub async fn used(
+        &self,
+        federation_id: FederationId,
+        direction: LimitDirection,
+        window_secs: u64,
+    ) -> Amount {
+        let _guard = self.reserve_lock.lock().await;
+        let mut dbtx = self.db.begin_transaction().await;
+
+        let used = Self::prune_and_sum(
+            &mut dbtx.to_ref_nc(),
+            federation_id,
+            direction,
+            window_secs,
+            now(),
+        )
+        .await;
+        dbtx.commit_tx().await;
+
+        used.unwrap_or(Amount::from_msats(u64::MAX))
+    }
+
+    /// Forgets the usage of a federation, e.g. after the gateway left it
+    pub async fn clear(&self, federation_id: FederationId) {
+        let _guard = self.reserve_lock.lock().await;
+        let mut dbtx = self.db.begin_transaction().await;
+
+        for direction in [LimitDirection::Send, LimitDirection::Receive] {
+            for (payment_id, reservation) in dbtx.load_payment_limit_reservations(direction).await {
+                if reservation.federation_id == federation_id {
+                    dbtx.remove_payment_limit_reservation(direction, payment_id)
+                        .await;
+                }
+            }
+        }
+
+        dbtx.commit_tx().await;
+    }
+
+    /// Removes the federation's reservations that fell out of the window and
+    /// returns the sum of the remaining ones, or `None` if it overflows
+    async fn prune_and_sum(
+        dbtx: &mut DatabaseTransaction<'_>,
+        federation_id: FederationId,
+        direction: LimitDirection,
+        window_secs: u64,
+        now: SystemTime,
+    ) -> Option<Amount> {
+        let window = Duration::from_secs(window_secs);
+        let mut used = Some(Amount::ZERO);
+
+        for (payment_id, reservation) in dbtx.load_payment_limit_reservations(direction).await {
+            if reservation.federation_id != federation_id {
+                continue;
+            }
+
+            let age = now
+                .duration_since(reservation.created_at)
+                .unwrap_or_default();
+
+            if window <= age {
+                dbtx.remove_payment_limit_reservation(direction, payment_id)
+                    .await;
+            } else {
+                used = used.and_then(|used| used.checked_add(reservation.amount));
+            }
+        }
+
+        used
+    }
+}
+
+/// Identifies an outgoing payment by the operation it is executed in
+pub fn send_payment_id(operation_id: OperationId) -> sha256::Hash {
+    operation_id.consensus_hash_sha256()
+}
+
+/// Identifies an incoming payment by the HTLC it was received with
+pub fn receive_payment_id(
+    payment_hash: sha256::Hash,
+    incoming_chan_id: u64,
+    htlc_id: u64,
+) -> sha256::Hash {
+    (payment_hash, incoming_chan_id, htlc_id).consensus_hash_sha256()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bitcoin::hashes::{Hash as _, sha256};
+    use fedimint_core::config::FederationId;
+    use fedimint_core::db::Database;
+    use fedimint_core::db::mem_impl::MemDatabase;
+    use fedimint_core::module::registry::ModuleDecoderRegistry;
+    use fedimint_core::time::now;
+    use fedimint_core::{Amount, sats};
+    use fedimint_gateway_common::{FederationLimits, LimitDirection};
+    use fedimint_gateway_server_db::{GatewayDbtxNcExt as _, PaymentLimitReservation};
+
+    use super::PaymentLimiter;
+
+    const LIMITS: FederationLimits = FederationLimits {
+        max_send: Some(Amount::from_sats(1000)),
+        max_receive: None,
+        window_secs: 3600,
+    };
+
+    fn db() -> Database {
+        Database::new(MemDatabase::new(), ModuleDecoderRegistry::default())
+    }
+
+    fn payment_id(n: u8) -> sha256::Hash {
+        sha256::Hash::hash(&[n])
+    }
+
+    async fn send(limiter: &PaymentLimiter, n: u8, amount: Amount) -> Result<bool, Amount> {
+        limiter
+            .try_reserve(
+                FederationId::dummy(),
+                LimitDirection::Send,
+                payment_id(n),
+                amount,
+                &LIMITS,
+            )
+            .await
+    }
+
+    async fn used(limiter: &PaymentLimiter, direction: LimitDirection) -> Amount {
+        limiter
+            .used(FederationId::dummy(), direction, LIMITS.window_secs)
+            .await
+    }
+
+    #[tokio::test]
+    async fn reserves_up_to_the_limit() {
+        let limiter = PaymentLimiter::new(db());
+
+        assert_eq!(send(&limiter, 0, sats(600)).await, Ok(true));
+        assert_eq!(send(&limiter, 1, sats(500)).await, Err(sats(1000)));
+        assert_eq!(send(&limiter, 2, sats(400)).await, Ok(true));
+        assert_eq!(used(&limiter, LimitDirection::Send).await, sats(1000));
+
+        // Receiving is not limited and tracked separately
+        let reserved = limiter
+            .try_reserve(
+                FederationId::dummy(),
+                LimitDirection::Receive,
+                payment_id(3),
+                sats(5000),
+                &LIMITS,
+            )
+            .await;
+        assert_eq!(reserved, Ok(false));
+        assert_eq!(used(&limiter, LimitDirection::Receive).await, Amount::ZERO);
+    }
+
+    #[tokio::test]
+    async fn counts_a_payment_once_and_releases_it_by_id() {
+        let limiter = PaymentLimiter::new(db());
+
+        assert_eq!(send(&limiter, 0, sats(300)).await, Ok(true));
+        assert_eq!(send(&limiter, 0, sats(300)).await, Ok(false));
+        assert_eq!(send(&limiter, 1, sats(300)).await, Ok(true));
+        assert_eq!(used(&limiter, LimitDirection::Send).await, sats(600));
+
+        // Releasing a payment doesn't touch another payment of the same amount
+        limiter.release(LimitDirection::Send, payment_id(1)).await;
+        limiter.release(LimitDirection::Send, payment_id(1)).await;
+        assert_eq!(used(&limiter, LimitDirection::Send).await, sats(300));
+    }
+
+    #[tokio::test]
+    async fn rejects_amounts_overflowing_the_usage() {
+        let limiter = PaymentLimiter::new(db());
+
+        assert_eq!(send(&limiter, 0, sats(1)).await, Ok(true));
+        assert_eq!(
+            send(&limiter, 1, Amount::from_msats(u64::MAX)).await,
+            Err(sats(1000))
+        );
+    }
+
+    #[tokio::test]
+    async fn usage_expires_and_survives_restarts() {
+        let db = db();
+
+        let mut dbtx = db.begin_transaction().await;
+        dbtx.save_payment_limit_reservation(
+            LimitDirection::Send,
+            payment_id(0),
+            &PaymentLimitReservation {
+                federation_id: FederationId::dummy(),
+                amount: sats(1000),
+                created_at: now() - Duration::from_secs(LIMITS.window_secs),
+            },
+        )
+        .await;
+        dbtx.commit_tx().await;
+
+        // The expired reservation does not count against the limit
+        assert_eq!(
+            send(&PaymentLimiter::new(db.clone()), 1, sats(1000)).await,
+            Ok(true)
+        );
+
+        let limiter = PaymentLimiter::new(db);
+        assert_eq!(used(&limiter, LimitDirection::Send).await, sats(1000));
+
+        limiter.clear(FederationId::dummy()).await;
+        assert_eq!(used(&limiter, LimitDirection::Send).await, Amount::ZERO);
+    }
+}
@@ -15,21 +15,29 @@
 use fedimint_gateway_common::{
     ADDRESS_ENDPOINT, ADDRESS_RECHECK_ENDPOINT, BACKUP_ENDPOINT, BackupPayload,
     CLOSE_CHANNELS_WITH_PEER_ENDPOINT, CONFIGURATION_ENDPOINT, CONNECT_FED_ENDPOINT,
-    CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT, CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT,
-    CloseChannelsWithPeerRequest, ConfigPayload, ConnectFedPayload,
-    CreateInvoiceForOperatorPayload, CreateOfferPayload, DepositAddressPayload,
-    DepositAddressRecheckPayload, GATEWAY_INFO_ENDPOINT, GET_BALANCES_ENDPOINT,
-    GET_INVOICE_ENDPOINT, GET_LN_ONCHAIN_ADDRESS_ENDPOINT, GetInvoiceRequest,
-    INVITE_CODES_ENDPOINT, LEAVE_FED_ENDPOINT, LIST_CHANNELS_ENDPOINT, LIST_TRANSACTIONS_ENDPOINT,
-    LeaveFedPayload, ListTransactionsPayload, MNEMONIC_ENDPOINT, OPEN_CHANNEL_ENDPOINT,
-    OPEN_CHANNEL_WITH_PUSH_ENDPOINT, OpenChannelRequest, PAY_INVOICE_FOR_OPERATOR_ENDPOINT,
-    PAY_OFFER_FOR_OPERATOR_ENDPOINT, PAYMENT_LOG_ENDPOINT, PAYMENT_SUMMARY_ENDPOINT,
-    PEGIN_FROM_ONCHAIN_ENDPOINT, PayInvoiceForOperatorPayload, PayOfferPayload, PaymentLogPayload,
-    PaymentSummaryPayload, PeginFromOnchainPayload, RECEIVE_ECASH_ENDPOINT, ReceiveEcashPayload,
-    SEND_ONCHAIN_ENDPOINT, SET_CHANNEL_FEES_ENDPOINT, SET_FEES_ENDPOINT, SPEND_ECASH_ENDPOINT,
-    STOP_ENDPOINT, SendOnchainRequest, SetChannelFeesRequest, SetFeesPayload, SetMnemonicPayload,
-    SpendEcashPayload, V1_API_ENDPOINT, WITHDRAW_ENDPOINT, WITHDRAW_TO_ONCHAIN_ENDPOINT,
-    WithdrawPayload, WithdrawToOnchainPayload,
+    CONSOLIDATE_NOTES_ENDPOINT, CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT,
+    CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT, CloseChannelsWithPeerRequest, ConfigPayload,
+    ConnectFedPayload, ConsolidateNotesPayload, CreateInvoiceForOperatorPayload,
+    CreateOfferPayload, DepositAddressPayload, DepositAddressRecheckPayload, ESTIMATE_FEE_ENDPOINT,
+    EXPORT_STATE_ENDPOINT, EstimateFeePayload, FEDERATION_READY_ENDPOINT, FederationReadyPayload,
+    GATEWAY_INFO_ENDPOINT, GET_BALANCES_ENDPOINT, GET_FEES_ENDPOINT, GET_INVOICE_ENDPOINT,
+    GET_LIMITS_ENDPOINT, GET_LN_ONCHAIN_ADDRESS_ENDPOINT, GET_ROUTING_POLICY_ENDPOINT,
+    GatewayStateBackup, GetFeesPayload, GetInvoiceRequest, GetLimitsPayload, HEALTH_CHECK_ENDPOINT,
+    IMPORT_STATE_ENDPOINT, IN_FLIGHT_PAYMENTS_ENDPOINT, INVITE_CODES_ENDPOINT, LEAVE_FED_ENDPOINT,
+    LIST_CHANNELS_ENDPOINT, LIST_FEDERATIONS_ENDPOINT, LIST_TRANSACTIONS_ENDPOINT, LeaveFedPayload,
+    ListTransactionsPayload, MNEMONIC_ENDPOINT, NOTES_ENDPOINT, NotesPayload,
+    OPEN_CHANNEL_ENDPOINT, OPEN_CHANNEL_WITH_PUSH_ENDPOINT, OpenChannelRequest,
+    PAY_INVOICE_FOR_OPERATOR_ENDPOINT, PAY_OFFER_FOR_OPERATOR_ENDPOINT, PAYMENT_LOG_ENDPOINT,
+    PAYMENT_LOG_TAIL_ENDPOINT, PAYMENT_SUMMARY_ENDPOINT, PEGIN_FROM_ONCHAIN_ENDPOINT,
+    PREVIEW_FED_ENDPOINT, PayInvoiceForOperatorPayload, PayOfferPayload, PaymentLogPayload,
+    PaymentLogTailPayload, PaymentSummaryPayload, PeginFromOnchainPayload, PreviewFedPayload,
+    REBALANCE_ENDPOINT, RECEIVE_ECASH_ENDPOINT, REREGISTER_ENDPOINT, RebalancePayload,
+    ReceiveEcashPayload, ReregisterPayload, SELF_TEST_ENDPOINT, SEND_ONCHAIN_ENDPOINT,
+    SET_CHANNEL_FEES_ENDPOINT, SET_FEES_ENDPOINT, SET_LIMITS_ENDPOINT, SET_ROUTING_POLICY_ENDPOINT,
+    SPEND_ECASH_ENDPOINT, STOP_ENDPOINT, SelfTestPayload, SendOnchainRequest,
+    SetChannelFeesRequest, SetFeesPayload, SetLimitsPayload, SetMnemonicPayload,
+    SetRoutingPolicyPayload, SpendEcashPayload, V1_API_ENDPOINT, WITHDRAW_ENDPOINT,
+    WITHDRAW_TO_ONCHAIN_ENDPOINT, WithdrawPayload, WithdrawToOnchainPayload,
 };
 use fedimint_gateway_ui::IAdminGateway;
 use fedimint_ln_common::gateway_endpoint_constants::{
@@ -54,26 +62,37 @@
 
 // Routes that the liquidity manager is allowed to access. Any authenticated
 // route NOT in this list requires the admin password.
-const LIQUIDITY_MANAGER_ROUTES: [&str; 20] = [
+const LIQUIDITY_MANAGER_ROUTES: [&str; 31] = [
     ADDRESS_ENDPOINT,
     ADDRESS_RECHECK_ENDPOINT,
     CLOSE_CHANNELS_WITH_PEER_ENDPOINT,
     CONFIGURATION_ENDPOINT,
     CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT,
     CREATE_BOLT12_OFFER_FOR_OPERATOR_ENDPOINT,
+    ESTIMATE_FEE_ENDPOINT,
+    FEDERATION_READY_ENDPOINT,
     GATEWAY_INFO_ENDPOINT,
     GET_BALANCES_ENDPOINT,
+    GET_FEES_ENDPOINT,
     GET_INVOICE_ENDPOINT,
+    GET_LIMITS_ENDPOINT,
     GET_LN_ONCHAIN_ADDRESS_ENDPOINT,
+    GET_ROUTING_POLICY_ENDPOINT,
+    HEALTH_CHECK_ENDPOINT,
+    IN_FLIGHT_PAYMENTS_ENDPOINT,
     INVITE_CODES_ENDPOINT,
     LIST_CHANNELS_ENDPOINT,
+    LIST_FEDERATIONS_ENDPOINT,
     LIST_TRANSACTIONS_ENDPOINT,
+    NOTES_ENDPOINT,
     OPEN_CHANNEL_ENDPOINT,
     PAYMENT_LOG_ENDPOINT,
+    PAYMENT_LOG_TAIL_ENDPOINT,
     PAYMENT_SUMMARY_ENDPOINT,
     PEGIN_FROM_ONCHAIN_ENDPOINT,
     SET_CHANNEL_FEES_ENDPOINT,
     SET_FEES_ENDPOINT,
+    SET_ROUTING_POLICY_ENDPOINT,
     WITHDRAW_TO_ONCHAIN_ENDPOINT,
 ];
 
@@ -323,6 +342,13 @@ fn routes(gateway: Arc<Gateway>, task_group: TaskGroup, handlers: &mut Handlers)
         is_authenticated,
         authenticated_routes,
     );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        REBALANCE_ENDPOINT,
+        rebalance,
+        is_authenticated,
+        authenticated_routes,
+    );
     let authenticated_routes = register_post_handler(
         handlers,
         CONNECT_FED_ENDPOINT,
@@ -330,6 +356,27 @@ fn routes(gateway: Arc<Gateway>, task_group: TaskGroup, handlers: &mut Handlers)
         is_authenticated,
         authenticated_routes,
     );
+    let authenticated_routes = register_get_handler(
+        handlers,
+        EXPORT_STATE_ENDPOINT,
+        export_state,
+        is_authenticated,
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        IMPORT_STATE_ENDPOINT,
+        import_state,
+        is_authenticated,
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        PREVIEW_FED_ENDPOINT,
+        preview_fed,
+        is_authenticated,
+        authenticated_routes,
+    );
     let authenticated_routes = register_post_handler(
         handlers,
         LEAVE_FED_ENDPOINT,
@@ -344,6 +391,20 @@ fn routes(gateway: Arc<Gateway>, task_group: TaskGroup, handlers: &mut Handlers)
         is_authenticated,
         authenticated_routes,
     );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        NOTES_ENDPOINT,
+        notes,
+        is_authenticated,
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        CONSOLIDATE_NOTES_ENDPOINT,
+        consolidate_notes,
+        is_authenticated,
+        authenticated_routes,
+    );
     let authenticated_routes = register_post_handler(
         handlers,
         CREATE_BOLT11_INVOICE_FOR_OPERATOR_ENDPOINT,
@@ -365,6 +426,13 @@ fn routes(gateway: Arc<Gateway>, task_group: TaskGroup, handlers: &mut Handlers)
         is_authenticated,
         authenticated_routes,
     );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        ESTIMATE_FEE_ENDPOINT,
+        estimate_fee,
+        is_authenticated,
+        authenticated_routes,
+    );
     let authenticated_routes = register_post_handler(
         handlers,
         PAY_OFFER_FOR_OPERATOR_ENDPOINT,
@@ -472,6 +540,13 @@ fn routes(gateway: Arc<Gateway>, task_group: TaskGroup, handlers: &mut Handlers)
         is_authenticated,
         authenticated_routes,
     );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        PAYMENT_LOG_TAIL_ENDPOINT,
+        payment_log_tail,
+        is_authenticated,
+        authenticated_routes,
+    );
     let authenticated_routes = register_post_handler(
         handlers,
         PAYMENT_SUMMARY_ENDPOINT,
@@ -486,6 +561,55 @@ fn routes(gateway: Arc<Gateway>, task_group: TaskGroup, handlers: &mut Handlers)
         is_authenticated,
         authenticated_routes,
     );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        GET_FEES_ENDPOINT,
+        get_fees,
+        is_authenticated,
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        FEDERATION_READY_ENDPOINT,
+        federation_ready,
+        is_authenticated,
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        REREGISTER_ENDPOINT,
+        reregister,
+        is_authenticated,
+        authenticated_routes,
+    );
+    let authenticated_routes = register_get_handler(
+        handlers,
+        GET_ROUTING_POLICY_ENDPOINT,
+        get_routing_policy,
+        is_authenticated,
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        SET_ROUTING_POLICY_ENDPOINT,
+        set_routing_policy,
+        is_authenticated,
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        GET_LIMITS_ENDPOINT,
+        get_limits,
+        is_authenticated,
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        SET_LIMITS_ENDPOINT,
+        set_limits,
+        is_authenticated,
+        authenticated_routes,
+    );
     let authenticated_routes = register_post_handler(
         handlers,
         CONFIGURATION_ENDPOINT,
@@ -514,6 +638,34 @@ fn routes(gateway: Arc<Gateway>, task_group: TaskGroup, handlers: &mut Handlers)
         is_authenticated,
         authenticated_routes,
     );
+    let authenticated_routes = register_get_handler(
+        handlers,
+        LIST_FEDERATIONS_ENDPOINT,
+        list_federations,
+        is_authenticated,
+        authenticated_routes,
+    );
+    let authenticated_routes = register_get_handler(
+        handlers,
+        HEALTH_CHECK_ENDPOINT,
+        health_check,
+        is_authenticated,
+        authenticated_routes,
+    );
+    let authenticated_routes = register_post_handler(
+        handlers,
+        SELF_TEST_ENDPOINT,
+        self_test,
+        is_authenticated,
+        authenticated_routes,
+    );
+    let authenticated_routes = register_get_handler(
+        handlers,
+        IN_FLIGHT_PAYMENTS_ENDPOINT,
+        in_flight_payments,
+        is_authenticated,
+        authenticated_routes,
+    );
     let authenticated_routes = authenticated_routes.layer(middleware::from_fn(auth_middleware));
 
     Router::new()
@@ -566,6 +718,16 @@ async fn pegin_from_onchain(
     Ok(Json(json!(address)))
 }
 
+/// Moves ecash from one gateway federation to another
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn rebalance(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<RebalancePayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let rebalance = gateway.handle_rebalance_msg(payload).await?;
+    Ok(Json(json!(rebalance)))
+}
+
 /// Withdraw from a gateway federation.
 #[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
 async fn withdraw(
@@ -606,6 +768,15 @@ async fn pay_invoice_operator(
     Ok(Json(json!(preimage.0.encode_hex::<String>())))
 }
 
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn estimate_fee(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<EstimateFeePayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let estimate = gateway.handle_estimate_fee_msg(payload).await?;
+    Ok(Json(json!(estimate)))
+}
+
 #[instrument(target = LOG_GATEWAY, skip_all, err)]
 async fn pay_invoice(
     Extension(gateway): Extension<Arc<Gateway>>,
@@ -625,6 +796,36 @@ async fn connect_fed(
     Ok(Json(json!(fed)))
 }
 
+/// Export the persistent configuration of the gateway
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn export_state(
+    Extension(gateway): Extension<Arc<Gateway>>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let backup = gateway.handle_export_state_msg().await?;
+    Ok(Json(json!(backup)))
+}
+
+/// Restore the persistent configuration of the gateway, reconnecting to the
+/// federations it contains
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn import_state(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<GatewayStateBackup>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let federations = gateway.handle_import_state_msg(payload).await?;
+    Ok(Json(json!(federations)))
+}
+
+/// Preview a federation without joining it
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn preview_fed(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<PreviewFedPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let preview = gateway.handle_preview_federation_msg(payload).await?;
+    Ok(Json(json!(preview)))
+}
+
 /// Leave a federation
 #[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
 async fn leave_fed(
@@ -645,6 +846,24 @@ async fn backup(
     Ok(Json(json!(())))
 }
 
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn notes(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<NotesPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let notes = gateway.handle_notes_msg(payload).await?;
+    Ok(Json(json!(notes)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn consolidate_notes(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<ConsolidateNotesPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let response = gateway.handle_consolidate_notes_msg(payload).await?;
+    Ok(Json(json!(response)))
+}
+
 #[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
 async fn set_fees(
     Extension(gateway): Extension<Arc<Gateway>>,
@@ -654,6 +873,68 @@ async fn set_fees(
     Ok(Json(json!(())))
 }
 
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn get_fees(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<GetFeesPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let fees = gateway.handle_get_fees_msg(payload).await?;
+    Ok(Json(json!(fees)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn federation_ready(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<FederationReadyPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let readiness = gateway.handle_federation_ready_msg(payload).await?;
+    Ok(Json(json!(readiness)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn reregister(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<ReregisterPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let registrations = gateway.handle_reregister_msg(payload).await?;
+    Ok(Json(json!(registrations)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn get_routing_policy(
+    Extension(gateway): Extension<Arc<Gateway>>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let routing_policy = gateway.handle_get_routing_policy_msg().await?;
+    Ok(Json(json!(routing_policy)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn set_routing_policy(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<SetRoutingPolicyPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let routing_policy = gateway.handle_set_routing_policy_msg(payload).await?;
+    Ok(Json(json!(routing_policy)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn get_limits(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<GetLimitsPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let limits = gateway.handle_get_limits_msg(payload).await?;
+    Ok(Json(json!(limits)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn set_limits(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<SetLimitsPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let limits = gateway.handle_set_limits_msg(payload).await?;
+    Ok(Json(json!(limits)))
+}
+
 #[instrument(target = LOG_GATEWAY, skip_all, err)]
 async fn get_ln_onchain_address(
     Extension(gateway): Extension<Arc<Gateway>>,
@@ -839,6 +1120,15 @@ async fn payment_log(
     Ok(Json(json!(payment_log)))
 }
 
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn payment_log_tail(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<PaymentLogTailPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let response = gateway.handle_payment_log_tail_msg(payload).await?;
+    Ok(Json(json!(response)))
+}
+
 #[instrument(target = LOG_GATEWAY, skip_all, err)]
 async fn payment_summary(
     Extension(gateway): Extension<Arc<Gateway>>,
@@ -893,3 +1183,36 @@ async fn invite_codes(
     let invite_codes = gateway.handle_export_invite_codes().await;
     Ok(Json(json!(invite_codes)))
 }
+
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn list_federations(
+    Extension(gateway): Extension<Arc<Gateway>>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let federations = gateway.handle_list_federations_msg().await?;
+    Ok(Json(json!(federations)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn health_check(
+    Extension(gateway): Extension<Arc<Gateway>>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let health = gateway.handle_health_check_msg().await?;
+    Ok(Json(json!(health)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err, fields(?payload))]
+async fn self_test(
+    Extension(gateway): Extension<Arc<Gateway>>,
+    Json(payload): Json<SelfTestPayload>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let response = gateway.handle_self_test_msg(payload).await?;
+    Ok(Json(json!(response)))
+}
+
+#[instrument(target = LOG_GATEWAY, skip_all, err)]
+async fn in_flight_payments(
+    Extension(gateway): Extension<Arc<Gateway>>,
+) -> Result<Json<serde_json::Value>, GatewayError> {
+    let payments = gateway.handle_in_flight_payments_msg().await?;
+    Ok(Json(json!(payments)))
+}
@@ -23,7 +23,7 @@
 use fedimint_dummy_client::{DummyClientInit, DummyClientModule};
 use fedimint_dummy_server::DummyInit;
 use fedimint_eventlog::Event;
-use fedimint_gateway_common::{PaymentLogPayload, SetFeesPayload};
+use fedimint_gateway_common::{PaymentLogPayload, RebalancePayload, SetFeesPayload};
 use fedimint_gateway_server::Gateway;
 use fedimint_gateway_ui::IAdminGateway;
 use fedimint_gw_client::pay::{
@@ -920,6 +920,59 @@ async fn test_gateway_executes_swaps_between_connected_federations() -> anyhow::
     .await
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_gateway_rebalance_rejects_same_federation() -> anyhow::Result<()> {
+    multi_federation_test(|gateway, fed1, _, _| async move {
+        let id1 = fed1.invite_code().federation_id();
+        fed1.connect_gateway(&gateway).await;
+        send_msats_to_gateway(&gateway, id1, 10_000).await;
+
+        let err = gateway
+            .handle_rebalance_msg(RebalancePayload {
+                from_federation_id: id1,
+                to_federation_id: id1,
+                amount: msats(5_000),
+            })
+            .await
+            .expect_err("Rebalancing a federation into itself must fail");
+        assert!(err.to_string().contains("into itself"), "{err}");
+        assert_eq!(get_balances(&gateway, vec![id1]).await, vec![10_000]);
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_gateway_rebalance_rejects_insufficient_balance() -> anyhow::Result<()> {
+    multi_federation_test(|gateway, fed1, fed2, _| async move {
+        let id1 = fed1.invite_code().federation_id();
+        let id2 = fed2.invite_code().federation_id();
+        fed1.connect_gateway(&gateway).await;
+        fed2.connect_gateway(&gateway).await;
+        send_msats_to_gateway(&gateway, id1, 10_000).await;
+
+        let err = gateway
+            .handle_rebalance_msg(RebalancePayload {
+                from_federation_id: id1,
+                to_federation_id: id2,
+                amount: msats(20_000),
+            })
+            .await
+            .expect_err("Rebalancing more than the balance must fail");
+        assert!(err.to_string().contains("Insufficient balance"), "{err}");
+
+        let balances = gateway.handle_get_balances_msg().await?;
+        for info in balances.ecash_balances {
+            let expected = if info.federation_id == id1 { 10_000 } else { 0 };
+            assert_eq!(info.ecash_balance_msats.msats, expected);
+        }
+
+        Ok(())
+    })
+    .await
+}
+
 fn routing_fees_in_msats(routing_fees: &PaymentFee, amount: &Amount) -> u64 {
     ((amount.msats * routing_fees.parts_per_million) / 1_000_000) + routing_fees.base.msats
 }
@@ -1232,6 +1285,8 @@ async fn gateway_read_payment_log() -> anyhow::Result<()> {
                     pagination_size: 20,
                     federation_id: fed1.id(),
                     event_kinds: vec![],
+                    start_millis: None,
+                    end_millis: None,
                 })
                 .await?;
             if transactions.0.len() == 20 {
@@ -1253,6 +1308,8 @@ async fn gateway_read_payment_log() -> anyhow::Result<()> {
             pagination_size: 10,
             federation_id: fed1.id(),
             event_kinds: vec![],
+            start_millis: None,
+            end_millis: None,
         })
         .await?;
     assert_eq!(transactions.0.len(), 10);
@@ -1280,6 +1337,8 @@ async fn gateway_read_payment_log() -> anyhow::Result<()> {
             pagination_size: 20,
             federation_id: fed1.id(),
             event_kinds: vec![],
+            start_millis: None,
+            end_millis: None,
         })
         .await?;
     assert_eq!(transactions.0.len(), 10);
@@ -1294,9 +1353,36 @@ async fn gateway_read_payment_log() -> anyhow::Result<()> {
                 IncomingPaymentSucceeded::KIND,
                 CompleteLightningPaymentSucceeded::KIND,
             ],
+            start_millis: None,
+            end_millis: None,
         })
         .await?;
     assert_eq!(transactions.0.len(), 2);
 
+    // Verify filtering by time range works
+    let transactions = gateway
+        .handle_payment_log_msg(PaymentLogPayload {
+            end_position: None,
+            pagination_size: 20,
+            federation_id: fed1.id(),
+            event_kinds: vec![],
+            start_millis: Some(0),
+            end_millis: Some(u64::MAX / 1000),
+        })
+        .await?;
+    assert_eq!(transactions.0.len(), 20);
+
+    let transactions = gateway
+        .handle_payment_log_msg(PaymentLogPayload {
+            end_position: None,
+            pagination_size: 20,
+            federation_id: fed1.id(),
+            event_kinds: vec![],
+            start_millis: None,
+            end_millis: Some(0),
+        })
+        .await?;
+    assert!(transactions.0.is_empty());
+
     Ok(())
 }
@@ -689,7 +689,11 @@ pub async fn leave_federation_handler<E: Display>(
     if let Ok(federation_id) = federation_id {
         match state
             .api
-            .handle_leave_federation(LeaveFedPayload { federation_id })
+            // The user already confirmed leaving with the remaining balance
+            .handle_leave_federation(LeaveFedPayload {
+                federation_id,
+                force: true,
+            })
             .await
         {
             Ok(info) => {
@@ -141,6 +141,7 @@ async fn handle_payment_summary_msg(
         PaymentSummaryPayload {
             start_millis,
             end_millis,
+            group_by,
         }: PaymentSummaryPayload,
     ) -> Result<PaymentSummaryResponse, Self::Error>;
 
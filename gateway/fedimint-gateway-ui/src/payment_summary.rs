@@ -82,6 +82,7 @@ pub async fn render<E>(api: &DynGatewayApi<E>, federations: &[FederationInfo]) -
         .handle_payment_summary_msg(PaymentSummaryPayload {
             start_millis: one_day_ago_millis,
             end_millis: now_millis,
+            group_by: None,
         })
         .await;
 
@@ -403,6 +404,8 @@ pub async fn payment_log_fragment_handler<E>(
             pagination_size,
             federation_id,
             event_kinds: event_kinds.clone(),
+            start_millis: None,
+            end_millis: None,
         })
         .await;
 
@@ -20,7 +20,9 @@
 use fedimint_core::config::FederationId;
 use fedimint_core::time::now;
 use fedimint_core::{Amount, BitcoinAmountOrAll, bitcoin, default_esplora_server};
-use fedimint_gateway_common::{FederationInfo, PaymentDetails, PaymentKind, PaymentStatus};
+use fedimint_gateway_common::{
+    FederationInfo, PaymentDetails, PaymentKind, PaymentStatus, RebalanceRoute,
+};
 use fedimint_logging::LOG_TEST;
 use fedimint_testing_core::node_type::LightningNodeType;
 use itertools::Itertools;
@@ -311,6 +313,33 @@ async fn config_test(gw_type: LightningNodeType) -> anyhow::Result<()> {
                 assert_eq!(first_fed_balance_msat, Amount::ZERO);
                 almost_equal(second_fed_balance_msat.msats, pegin_amount.msats, 10_000).unwrap();
 
+                let gateway_cli_version = util::GatewayCli::version_or_default().await;
+                let second_fed_balance_msat = if gw.gatewayd_version >= *VERSION_0_12_0_ALPHA && gateway_cli_version >= *VERSION_0_12_0_ALPHA {
+                    info!(target: LOG_TEST, "Rebalancing ecash into the first federation...");
+                    let rebalance_amount = Amount::from_msats(5_000_000);
+                    let rebalance = gw.client().rebalance(new_fed_id.clone(), fed_id.clone(), rebalance_amount.msats).await?;
+                    assert_eq!(rebalance.route, RebalanceRoute::Onchain);
+                    assert_eq!(rebalance.amount.to_sat(), rebalance_amount.sats_round_down());
+
+                    bitcoind.poll_get_transaction(rebalance.txid).await?;
+                    bitcoind.mine_blocks(21).await?;
+
+                    let deposit_fees = dev_fed.fed().await?.deposit_fees()?;
+                    poll("rebalanced ecash balance", || async {
+                        let balance = gw.client().ecash_balance(fed_id.clone()).await.map_err(ControlFlow::Continue)?;
+                        almost_equal(balance, (rebalance_amount - deposit_fees).msats, 10_000)
+                            .map_err(|e| ControlFlow::Continue(anyhow::anyhow!(e)))
+                    })
+                    .await?;
+
+                    let balance = Amount::from_msats(gw.client().ecash_balance(new_fed_id.clone()).await?);
+                    assert!(balance <= second_fed_balance_msat - rebalance.total_cost, "Rebalance did not deduct its total cost");
+                    info!(target: LOG_TEST, "Verified rebalancing between federations");
+                    balance
+                } else {
+                    second_fed_balance_msat
+                };
+
                 let fed_id = FederationId::from_str(&fed_id).expect("invalid Federation ID");
                 let fed_info = gw.client().leave_federation(fed_id).await?;
                 assert_eq!(serde_json::from_value::<FederationId>(fed_info["federation_id"].clone())?, fed_id);
@@ -318,13 +347,21 @@ async fn config_test(gw_type: LightningNodeType) -> anyhow::Result<()> {
                 gw.client().leave_federation(fed_id).await.expect_err("Successfully left a federation twice");
 
                 let new_fed_id = FederationId::from_str(&new_fed_id).expect("invalid Federation ID");
-                let fed_info = gw.client().leave_federation(new_fed_id).await?;
-                assert_eq!(serde_json::from_value::<FederationId>(fed_info["federation_id"].clone())?, new_fed_id);
-                assert_eq!(fed_info["config"]["federation_index"].as_u64().expect("Was not u64"), 2);
+                if gw.gatewayd_version >= *VERSION_0_12_0_ALPHA {
+                    // Gateways refuse to leave a federation they still hold ecash in unless forced
+                    gw.client().leave_federation_unforced(new_fed_id).await.expect_err("Left a federation with outstanding ecash without force");
+                }
 
-                // Rejoin new federation, verify that the balance is the same
-                let fed_info = gw.client().connect_fed(new_invite_code).await?;
-                assert_eq!(second_fed_balance_msat, Amount::from_msats(fed_info["balance_msat"].as_u64().expect("Balance should be present")));
+                // Older gateway-cli versions cannot force leaving a federation with outstanding ecash
+                if gw.gatewayd_version < *VERSION_0_12_0_ALPHA || gateway_cli_version >= *VERSION_0_12_0_ALPHA {
+                    let fed_info = gw.client().leave_federation(new_fed_id).await?;
+                    assert_eq!(serde_json::from_value::<FederationId>(fed_info["federation_id"].clone())?, new_fed_id);
+                    assert_eq!(fed_info["config"]["federation_index"].as_u64().expect("Was not u64"), 2);
+
+                    // Rejoin new federation, verify that the balance is the same
+                    let fed_info = gw.client().connect_fed(new_invite_code).await?;
+                    assert_eq!(second_fed_balance_msat, Amount::from_msats(fed_info["balance_msat"].as_u64().expect("Balance should be present")));
+                }
 
                 if gw.gatewayd_version >= *VERSION_0_10_0_ALPHA {
                     // Try to get the info over iroh
@@ -394,23 +394,23 @@ pub async fn try_register_with_federation(
         api: SafeUrl,
         gateway_id: PublicKey,
     ) {
-        let registration_info = self.to_gateway_registration_info(
-            route_hints,
-            time_to_live,
-            fees,
-            lightning_context,
-            api,
-            gateway_id,
-        );
-        let gateway_id = registration_info.info.gateway_id;
-
         let federation_id = self
             .client_ctx
             .get_config()
             .await
             .global
             .calculate_federation_id();
-        match self.module_api.register_gateway(&registration_info).await {
+        match self
+            .register_with_federation(
+                route_hints,
+                time_to_live,
+                fees,
+                lightning_context,
+                api,
+                gateway_id,
+            )
+            .await
+        {
             Err(e) => {
                 warn!(
                     e = %e.fmt_compact(),
@@ -425,6 +425,30 @@ pub async fn try_register_with_federation(
         }
     }
 
+    /// Registers the gateway with the federation like
+    /// [`Self::try_register_with_federation`], but returns an error if the
+    /// registration failed instead of logging it
+    pub async fn register_with_federation(
+        &self,
+        route_hints: Vec<RouteHint>,
+        time_to_live: Duration,
+        fees: RoutingFees,
+        lightning_context: LightningContext,
+        api: SafeUrl,
+        gateway_id: PublicKey,
+    ) -> anyhow::Result<()> {
+        let registration_info = self.to_gateway_registration_info(
+            route_hints,
+            time_to_live,
+            fees,
+            lightning_context,
+            api,
+            gateway_id,
+        );
+        self.module_api.register_gateway(&registration_info).await?;
+        Ok(())
+    }
+
     /// Attempts to remove a gateway's registration from the federation. Since
     /// removing gateway registrations is best effort, this does not return
     /// an error and simply emits a warning when the registration cannot be
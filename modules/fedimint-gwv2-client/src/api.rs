@@ -3,7 +3,10 @@
 use fedimint_core::task::{MaybeSend, MaybeSync};
 use fedimint_core::{OutPoint, apply, async_trait_maybe_send};
 use fedimint_lnv2_common::ContractId;
-use fedimint_lnv2_common::endpoint_constants::OUTGOING_CONTRACT_EXPIRATION_ENDPOINT;
+use fedimint_lnv2_common::endpoint_constants::{
+    AWAIT_INCOMING_CONTRACT_ENDPOINT, CONSENSUS_BLOCK_COUNT_ENDPOINT,
+    OUTGOING_CONTRACT_EXPIRATION_ENDPOINT,
+};
 
 #[apply(async_trait_maybe_send!)]
 pub trait GatewayFederationApi {
@@ -11,6 +14,14 @@ async fn outgoing_contract_expiration(
         &self,
         outpoint: OutPoint,
     ) -> FederationResult<Option<(ContractId, u64)>>;
+
+    async fn consensus_block_count(&self) -> FederationResult<u64>;
+
+    async fn await_incoming_contract(
+        &self,
+        contract_id: &ContractId,
+        expiration: u64,
+    ) -> Option<OutPoint>;
 }
 
 #[apply(async_trait_maybe_send!)]
@@ -28,4 +39,24 @@ async fn outgoing_contract_expiration(
         )
         .await
     }
+
+    async fn consensus_block_count(&self) -> FederationResult<u64> {
+        self.request_current_consensus(
+            CONSENSUS_BLOCK_COUNT_ENDPOINT.to_string(),
+            ApiRequestErased::new(()),
+        )
+        .await
+    }
+
+    async fn await_incoming_contract(
+        &self,
+        contract_id: &ContractId,
+        expiration: u64,
+    ) -> Option<OutPoint> {
+        self.request_current_consensus_retry::<Option<OutPoint>>(
+            AWAIT_INCOMING_CONTRACT_ENDPOINT.to_string(),
+            ApiRequestErased::new((contract_id, expiration)),
+        )
+        .await
+    }
 }
@@ -11,7 +11,7 @@
 
 use anyhow::{anyhow, ensure};
 use async_trait::async_trait;
-use bitcoin::hashes::sha256;
+use bitcoin::hashes::{Hash, sha256};
 use bitcoin::secp256k1::Message;
 use events::{IncomingPaymentStarted, OutgoingPaymentStarted};
 use fedimint_api_client::api::DynModuleApi;
@@ -21,7 +21,8 @@
 use fedimint_client_module::module::{ClientContext, ClientModule, IClientModule, OutPointRange};
 use fedimint_client_module::sm::{Context, DynState, ModuleNotifier, State, StateTransition};
 use fedimint_client_module::transaction::{
-    ClientOutput, ClientOutputBundle, ClientOutputSM, TransactionBuilder,
+    ClientInput, ClientInputBundle, ClientOutput, ClientOutputBundle, ClientOutputSM,
+    TransactionBuilder,
 };
 use fedimint_client_module::{DynGlobalClientContext, sm_enum_variant_translation};
 use fedimint_core::config::FederationId;
@@ -34,13 +35,14 @@
 use fedimint_core::secp256k1::Keypair;
 use fedimint_core::time::now;
 use fedimint_core::util::Spanned;
-use fedimint_core::{Amount, PeerId, apply, async_trait_maybe_send, secp256k1};
+use fedimint_core::{Amount, OutPoint, PeerId, apply, async_trait_maybe_send, secp256k1};
 use fedimint_lightning::{InterceptPaymentResponse, LightningRpcError};
 use fedimint_lnv2_common::config::LightningClientConfig;
-use fedimint_lnv2_common::contracts::{IncomingContract, PaymentImage};
+use fedimint_lnv2_common::contracts::{IncomingContract, OutgoingContract, PaymentImage};
 use fedimint_lnv2_common::gateway_api::SendPaymentPayload;
 use fedimint_lnv2_common::{
-    LightningCommonInit, LightningInvoice, LightningModuleTypes, LightningOutput, LightningOutputV0,
+    LightningCommonInit, LightningInput, LightningInputV0, LightningInvoice, LightningModuleTypes,
+    LightningOutput, LightningOutputV0, OutgoingWitness, tweak,
 };
 use futures::StreamExt;
 use lightning_invoice::Bolt11Invoice;
@@ -48,7 +50,7 @@
 use secp256k1::schnorr::Signature;
 use send_sm::{SendSMState, SendStateMachine};
 use serde::{Deserialize, Serialize};
-use tpe::{AggregatePublicKey, PublicKeyShare};
+use tpe::{AggregateDecryptionKey, AggregatePublicKey, PublicKeyShare, derive_agg_dk};
 use tracing::{info, warn};
 
 use crate::api::GatewayFederationApi;
@@ -59,6 +61,10 @@
 /// LNv2 CLTV Delta in blocks
 pub const EXPIRATION_DELTA_MINIMUM_V2: u64 = 144;
 
+/// Blocks added to the expiration of outgoing contracts the gateway funds
+/// itself, to account for the time it takes the contract to be confirmed
+const CONTRACT_CONFIRMATION_BUFFER: u64 = 12;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GatewayOperationMetaV2;
 
@@ -603,6 +609,181 @@ pub async fn await_completion(&self, operation_id: OperationId) {
             }
         }
     }
+
+    /// Creates an incoming contract that this gateway can claim itself, so it
+    /// can receive a payment without a second party, e.g. during a self test.
+    /// Returns the contract together with the key that decrypts its preimage,
+    /// which is needed to claim it with [`Self::claim_incoming_contract`].
+    pub fn create_self_incoming_contract(
+        &self,
+        amount: Amount,
+        expiration: u64,
+    ) -> (IncomingContract, AggregateDecryptionKey) {
+        let (ephemeral_tweak, ephemeral_pk) = tweak::generate(self.keypair.public_key());
+
+        let encryption_seed = ephemeral_tweak
+            .consensus_hash::<sha256::Hash>()
+            .to_byte_array();
+
+        let preimage = encryption_seed
+            .consensus_hash::<sha256::Hash>()
+            .to_byte_array();
+
+        let contract = IncomingContract::new(
+            self.cfg.tpe_agg_pk,
+            encryption_seed,
+            preimage,
+            PaymentImage::Hash(preimage.consensus_hash()),
+            amount,
+            expiration,
+            self.keypair.public_key(),
+            self.keypair.public_key(),
+            ephemeral_pk,
+        );
+
+        let agg_decryption_key = derive_agg_dk(&self.cfg.tpe_agg_pk, &encryption_seed);
+
+        (contract, agg_decryption_key)
+    }
+
+    /// Claims a funded incoming contract created by
+    /// [`Self::create_self_incoming_contract`]
+    pub async fn claim_incoming_contract(
+        &self,
+        contract: &IncomingContract,
+        agg_decryption_key: AggregateDecryptionKey,
+    ) -> anyhow::Result<()> {
+        let outpoint = self
+            .module_api
+            .await_incoming_contract(&contract.contract_id(), contract.commitment.expiration)
+            .await
+            .ok_or(anyhow!(
+                "The incoming contract expired before it was funded"
+            ))?;
+
+        self.claim_input(ClientInput::<LightningInput> {
+            input: LightningInput::V0(LightningInputV0::Incoming(outpoint, agg_decryption_key)),
+            amounts: Amounts::new_bitcoin(contract.commitment.amount),
+            keys: vec![self.keypair],
+        })
+        .await
+    }
+
+    /// Funds an outgoing contract for `invoice` that is keyed to this gateway
+    /// on both ends, so it can send a payment without a second party, e.g.
+    /// during a self test. Returns the payload to request the payment with.
+    pub async fn fund_self_outgoing_contract(
+        &self,
+        invoice: Bolt11Invoice,
+        amount: Amount,
+        expiration_delta: u64,
+    ) -> anyhow::Result<SendPaymentPayload> {
+        let consensus_block_count = self
+            .module_api
+            .consensus_block_count()
+            .await
+            .map_err(|_| anyhow!("The gateway can not reach the federation"))?;
+
+        let contract = OutgoingContract {
+            payment_image: PaymentImage::Hash(*invoice.payment_hash()),
+            amount,
+            expiration: consensus_block_count + expiration_delta + CONTRACT_CONFIRMATION_BUFFER,
+            claim_pk: self.keypair.public_key(),
+            refund_pk: self.keypair.public_key(),
+            ephemeral_pk: self.keypair.public_key(),
+        };
+
+        let client_output = ClientOutput::<LightningOutput> {
+            output: LightningOutput::V0(LightningOutputV0::Outgoing(contract.clone())),
+            amounts: Amounts::new_bitcoin(contract.amount),
+        };
+
+        let transaction = TransactionBuilder::new().with_outputs(
+            self.client_ctx
+                .make_client_outputs(ClientOutputBundle::new_no_sm(vec![client_output])),
+        );
+
+        let operation_id = OperationId::new_random();
+
+        let change_range = self
+            .client_ctx
+            .finalize_and_submit_transaction(
+                operation_id,
+                LightningCommonInit::KIND.as_str(),
+                |_| GatewayOperationMetaV2,
+                transaction,
+            )
+            .await?;
+
+        self.client_ctx
+            .transaction_updates(operation_id)
+            .await
+            .await_tx_accepted(change_range.txid())
+            .await
+            .map_err(|e| anyhow!("The outgoing contract was rejected: {e}"))?;
+
+        let invoice = LightningInvoice::Bolt11(invoice);
+
+        let auth = self.keypair.sign_schnorr(Message::from_digest(
+            *invoice.consensus_hash::<sha256::Hash>().as_ref(),
+        ));
+
+        Ok(SendPaymentPayload {
+            federation_id: self.federation_id,
+            // The change is appended after the outputs of the transaction builder
+            outpoint: OutPoint {
+                txid: change_range.txid(),
+                out_idx: 0,
+            },
+            contract,
+            invoice,
+            auth,
+        })
+    }
+
+    /// Cancels an outgoing contract funded by
+    /// [`Self::fund_self_outgoing_contract`] that has not been claimed and
+    /// returns its funds to the gateway's balance
+    pub async fn cancel_self_outgoing_contract(
+        &self,
+        outpoint: OutPoint,
+        contract: &OutgoingContract,
+    ) -> anyhow::Result<()> {
+        let forfeit_signature = self.keypair.sign_schnorr(contract.forfeit_message());
+
+        self.claim_input(ClientInput::<LightningInput> {
+            input: LightningInput::V0(LightningInputV0::Outgoing(
+                outpoint,
+                OutgoingWitness::Cancel(forfeit_signature),
+            )),
+            amounts: Amounts::new_bitcoin(contract.amount),
+            keys: vec![self.keypair],
+        })
+        .await
+    }
+
+    async fn claim_input(&self, client_input: ClientInput<LightningInput>) -> anyhow::Result<()> {
+        let transaction = TransactionBuilder::new().with_inputs(
+            self.client_ctx
+                .make_client_inputs(ClientInputBundle::new_no_sm(vec![client_input])),
+        );
+
+        let operation_id = OperationId::new_random();
+
+        let change_range = self
+            .client_ctx
+            .finalize_and_submit_transaction(
+                operation_id,
+                LightningCommonInit::KIND.as_str(),
+                |_| GatewayOperationMetaV2,
+                transaction,
+            )
+            .await?;
+
+        self.client_ctx
+            .await_primary_module_outputs(operation_id, change_range.into_iter().collect())
+            .await
+    }
 }
 
 /// An interface between module implementation and the general `Gateway`
@@ -1,21 +1,28 @@
 use std::collections::BTreeSet;
 use std::fmt::Debug;
+use std::time::Duration;
 
 use anyhow::Context;
 use fedimint_connectors::error::ServerError;
 use fedimint_connectors::{
     ConnectionPool, ConnectorRegistry, DynGatewayConnection, IGatewayConnection, ServerResult,
 };
+use fedimint_core::runtime;
 use fedimint_core::util::SafeUrl;
 use reqwest::Method;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 use tokio::sync::watch;
 
+/// Delay between retries of a failed request, see [`GatewayApi::with_retries`]
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
 #[derive(Clone, Debug)]
 pub struct GatewayApi {
     password: Option<String>,
     connection_pool: ConnectionPool<dyn IGatewayConnection>,
+    timeout: Option<Duration>,
+    retries: usize,
 }
 
 impl GatewayApi {
@@ -23,9 +30,32 @@ pub fn new(password: Option<String>, connectors: ConnectorRegistry) -> Self {
         Self {
             password,
             connection_pool: ConnectionPool::new(connectors),
+            timeout: None,
+            retries: 0,
         }
     }
 
+    /// Fail requests the gateway did not answer within `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout for requests set with [`Self::with_timeout`], if any
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Retry failed requests up to `retries` times
+    ///
+    /// Failures to connect are retried for every request. Transport errors
+    /// and timeouts are only retried for `GET` requests, as other requests
+    /// might already have been executed by the gateway.
+    pub fn with_retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
     async fn get_or_create_connection(&self, url: &SafeUrl) -> ServerResult<DynGatewayConnection> {
         self.connection_pool
             .get_or_create_connection(url, None, |url, _api_secret, connectors| async move {
@@ -45,21 +75,55 @@ pub async fn request<P: Serialize, T: DeserializeOwned>(
         route: &str,
         payload: Option<P>,
     ) -> ServerResult<T> {
-        let conn = self
-            .get_or_create_connection(base_url)
-            .await
-            .context("Failed to connect to gateway")
-            .map_err(ServerError::Connection)?;
         let payload = payload.map(|p| serde_json::to_value(p).expect("Could not serialize"));
-        let res = conn
-            .request(self.password.clone(), method, route, payload)
-            .await?;
+        let mut attempt = 0;
+        let res = loop {
+            let res = self
+                .request_once(base_url, method.clone(), route, payload.clone())
+                .await;
+            let retryable = match &res {
+                Ok(_) => false,
+                Err(ServerError::Connection(_)) => true,
+                Err(ServerError::Transport(_)) => method == Method::GET,
+                Err(_) => false,
+            };
+            if !retryable || self.retries <= attempt {
+                break res?;
+            }
+
+            attempt += 1;
+            runtime::sleep(RETRY_DELAY).await;
+        };
         let response = serde_json::from_value::<T>(res).map_err(|e| {
             ServerError::InvalidResponse(anyhow::anyhow!("Received invalid response: {e}"))
         })?;
         Ok(response)
     }
 
+    async fn request_once(
+        &self,
+        base_url: &SafeUrl,
+        method: Method,
+        route: &str,
+        payload: Option<serde_json::Value>,
+    ) -> ServerResult<serde_json::Value> {
+        let conn = self
+            .get_or_create_connection(base_url)
+            .await
+            .context("Failed to connect to gateway")
+            .map_err(ServerError::Connection)?;
+        let request = conn.request(self.password.clone(), method, route, payload);
+        match self.timeout {
+            Some(timeout) => runtime::timeout(timeout, request).await.map_err(|_| {
+                ServerError::Transport(anyhow::anyhow!(
+                    "Gateway did not respond within {}s",
+                    timeout.as_secs_f64()
+                ))
+            })?,
+            None => request.await,
+        }
+    }
+
     /// Get receiver for changes in the active connections
     ///
     /// This allows real-time monitoring of connection status.
@@ -1548,6 +1548,69 @@ pub async fn consolidate_notes(
         self.create_input_from_notes(selected_notes_decoded.into_iter().collect())
     }
 
+    /// Reissues the excess notes of denominations that accumulated more notes
+    /// than needed into fewer, larger notes, see
+    /// [`MintClientModule::consolidate_notes`]
+    ///
+    /// Returns `None` if no denomination holds enough notes to be consolidated.
+    pub async fn reissue_excess_notes(&self) -> anyhow::Result<Option<OperationId>> {
+        self.client_ctx
+            .module_db()
+            .autocommit(
+                |dbtx, _| {
+                    Box::pin(async {
+                        let inputs = self.consolidate_notes(dbtx).await?;
+
+                        if inputs.is_empty() {
+                            return Ok(None);
+                        }
+
+                        let operation_id = OperationId::new_random();
+                        let amount: Amount = inputs
+                            .iter()
+                            .map(|(input, _)| input.amounts.get_bitcoin())
+                            .sum();
+
+                        let tx = TransactionBuilder::new().with_inputs(
+                            self.client_ctx
+                                .make_dyn(create_bundle_for_inputs(inputs, operation_id)),
+                        );
+
+                        self.client_ctx
+                            .finalize_and_submit_transaction_dbtx(
+                                dbtx,
+                                operation_id,
+                                MintCommonInit::KIND.as_str(),
+                                move |change_range: OutPointRange| MintOperationMeta {
+                                    variant: MintOperationMetaVariant::Reissuance {
+                                        legacy_out_point: None,
+                                        txid: Some(change_range.txid()),
+                                        out_point_indices: change_range
+                                            .into_iter()
+                                            .map(|out_point| out_point.out_idx)
+                                            .collect(),
+                                    },
+                                    amount,
+                                    extra_meta: serde_json::Value::Null,
+                                },
+                                tx,
+                            )
+                            .await?;
+
+                        Ok(Some(operation_id))
+                    })
+                },
+                Some(100),
+            )
+            .await
+            .map_err(|e| match e {
+                AutocommitError::ClosureError { error, .. } => error,
+                AutocommitError::CommitFailed { last_error, .. } => {
+                    anyhow!("Commit to DB failed: {last_error}")
+                }
+            })
+    }
+
     /// Create a mint input from external, potentially untrusted notes
     #[allow(clippy::type_complexity)]
     pub fn create_input_from_notes(
@@ -1956,6 +2019,10 @@ pub async fn spend_notes_with_selector<M: Serialize + Send>(
         include_invite: bool,
         extra_meta: M,
     ) -> anyhow::Result<(OperationId, OOBNotes)> {
+        if self.client_ctx.is_watch_only() {
+            bail!("Client was opened in watch-only mode and can not spend notes");
+        }
+
         let federation_id_prefix = self.federation_id.to_prefix();
         let extra_meta = serde_json::to_value(extra_meta)
             .expect("MintClientModule::spend_notes extra_meta is serializable");
@@ -2073,6 +2140,10 @@ pub async fn send_oob_notes<M: Serialize + Send>(
         amount: Amount,
         extra_meta: M,
     ) -> anyhow::Result<OOBNotes> {
+        if self.client_ctx.is_watch_only() {
+            bail!("Client was opened in watch-only mode and can not spend notes");
+        }
+
         let amount = self.cfg.fee_consensus.round_up(amount);
 
         let extra_meta = serde_json::to_value(extra_meta)
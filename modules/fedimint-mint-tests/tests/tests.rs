@@ -1,17 +1,22 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
 use std::time::Duration;
 
 use assert_matches::assert_matches;
 use bls12_381::G1Affine;
-use fedimint_client::ClientHandleArc;
 use fedimint_client::backup::{ClientBackup, Metadata};
+use fedimint_client::secret::{PlainRootSecretStrategy, RootSecretStrategy};
 use fedimint_client::transaction::{ClientInput, ClientInputBundle, TransactionBuilder};
+use fedimint_client::{BuildError, Client, ClientHandleArc, RootSecret, RootSecretDeriver};
 use fedimint_client_module::ClientModule;
 use fedimint_core::core::OperationId;
-use fedimint_core::db::IDatabaseTransactionOpsCoreTyped;
+use fedimint_core::db::mem_impl::MemDatabase;
+use fedimint_core::db::{Database, IDatabaseTransactionOpsCoreTyped};
 use fedimint_core::module::{AmountUnit, Amounts};
 use fedimint_core::task::sleep_in_test;
 use fedimint_core::util::NextOrPending;
 use fedimint_core::{Amount, TieredMulti, sats, secp256k1};
+use fedimint_derive_secret::DerivableSecret;
 use fedimint_dummy_client::{DummyClientInit, DummyClientModule};
 use fedimint_dummy_server::DummyInit;
 use fedimint_logging::LOG_TEST;
@@ -358,6 +363,30 @@ async fn ecash_backup_can_recover_metadata() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[allow(deprecated)]
+#[tokio::test(flavor = "multi_thread")]
+async fn prepared_backup_is_not_uploaded() -> anyhow::Result<()> {
+    let fed = fixtures().new_fed_degraded().await;
+    let client = fed.new_client().await;
+    issue_ecash(&client, sats(1000)).await?;
+
+    let metadata = Metadata::from_json_serialized(BackupTestMetadata {
+        custom_key: "custom_value".into(),
+    });
+
+    let backup = client.prepare_backup(metadata.clone()).await?;
+    assert_eq!(backup.metadata, metadata);
+    assert!(
+        backup
+            .modules
+            .contains_key(&client.get_first_module::<MintClientModule>()?.id)
+    );
+
+    assert!(client.download_backup_from_federation().await?.is_none());
+
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn sends_ecash_out_of_band_cancel() -> anyhow::Result<()> {
     // Give client initial balance
@@ -1161,3 +1190,243 @@ async fn test_send_oob_notes() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Issues e-cash to `client` and spends all of it out-of-band, leaving a zero
+/// balance but an active spend state machine that could still refund
+async fn spend_all_oob(client: &ClientHandleArc) -> anyhow::Result<OperationId> {
+    issue_ecash(client, sats(1000)).await?;
+
+    let balance = client.get_balance_for_btc().await?;
+    let (op, _) = client
+        .get_first_module::<MintClientModule>()?
+        .spend_notes_with_selector(&SelectNotesWithAtleastAmount, balance, TIMEOUT, false, ())
+        .await?;
+
+    assert_eq!(client.get_balance_for_btc().await?, Amount::ZERO);
+
+    Ok(op)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn leave_refuses_with_outstanding_balance() -> anyhow::Result<()> {
+    let fed = fixtures().new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    issue_ecash(&client, sats(1000)).await?;
+
+    let client = Arc::into_inner(client).expect("No other client handles");
+    let err = client
+        .leave(false)
+        .await
+        .expect_err("Must not leave with a balance");
+    assert!(err.to_string().contains("outstanding balances"));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn leave_refuses_with_active_operations() -> anyhow::Result<()> {
+    let fed = fixtures().new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    spend_all_oob(&client).await?;
+
+    let client = Arc::into_inner(client).expect("No other client handles");
+    let err = client
+        .leave(false)
+        .await
+        .expect_err("Must not leave with active state machines");
+    assert!(err.to_string().contains("active operations"));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn forced_leave_reports_abandoned_operations() -> anyhow::Result<()> {
+    let fed = fixtures().new_fed_degraded().await;
+    let client = fed.new_client().await;
+
+    let op = spend_all_oob(&client).await?;
+
+    let client = Arc::into_inner(client).expect("No other client handles");
+    let report = client.leave(true).await?;
+
+    assert!(report.abandoned_balances.is_empty());
+    assert_eq!(report.abandoned_operations, BTreeSet::from([op]));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn watch_only_client_refuses_to_spend_notes() -> anyhow::Result<()> {
+    let fed = fixtures().new_fed_degraded().await;
+    let db: Database = MemDatabase::new().into();
+    let root_secret = RootSecret::StandardDoubleDerive(PlainRootSecretStrategy::to_root_secret(
+        &PlainRootSecretStrategy::random(&mut rand::thread_rng()),
+    ));
+
+    let client = fed
+        .join_client_with_db(db.clone(), root_secret.clone())
+        .await;
+    issue_ecash(&client, sats(1000)).await?;
+    let balance = client.get_balance_for_btc().await?;
+    Arc::into_inner(client)
+        .expect("No other client handles")
+        .shutdown()
+        .await;
+
+    let client = fed.open_watch_only_client_with_db(db, root_secret).await;
+    assert!(client.is_watch_only());
+    assert_eq!(client.get_balance_for_btc().await?, balance);
+
+    let mint = client.get_first_module::<MintClientModule>()?;
+    assert!(
+        mint.spend_notes_with_selector(&SelectNotesWithAtleastAmount, balance, TIMEOUT, false, ())
+            .await
+            .is_err()
+    );
+    assert!(mint.send_oob_notes(balance, ()).await.is_err());
+    assert_eq!(client.get_balance_for_btc().await?, balance);
+
+    Ok(())
+}
+
+#[allow(deprecated)]
+#[tokio::test(flavor = "multi_thread")]
+async fn join_refuses_secret_with_existing_backup() -> anyhow::Result<()> {
+    let fed = fixtures().new_fed_degraded().await;
+    let root_secret = RootSecret::StandardDoubleDerive(PlainRootSecretStrategy::to_root_secret(
+        &PlainRootSecretStrategy::random(&mut rand::thread_rng()),
+    ));
+
+    let client = fed
+        .join_client_with_db(MemDatabase::new().into(), root_secret.clone())
+        .await;
+    issue_ecash(&client, sats(1000)).await?;
+    client.backup_to_federation(Metadata::empty()).await?;
+
+    let err = fed
+        .try_join_client_with_db(MemDatabase::new().into(), root_secret)
+        .await
+        .expect_err("Joining with a secret that has a backup must fail");
+    assert_matches!(
+        err.downcast_ref::<BuildError>(),
+        Some(BuildError::ExistingStateDetected)
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn root_secret_deriver_replaces_pre_root_secret() -> anyhow::Result<()> {
+    fn random_secret() -> DerivableSecret {
+        PlainRootSecretStrategy::to_root_secret(&PlainRootSecretStrategy::random(
+            &mut rand::thread_rng(),
+        ))
+    }
+
+    let fed = fixtures().new_fed_degraded().await;
+    let db: Database = MemDatabase::new().into();
+    let deriver: Arc<dyn RootSecretDeriver> = Arc::new(random_secret());
+
+    // The pre-root secret is only a placeholder when a deriver is set
+    let client = fed
+        .try_join_client_with_deriver(
+            db.clone(),
+            RootSecret::Custom(random_secret()),
+            deriver.clone(),
+        )
+        .await?;
+    issue_ecash(&client, sats(1000)).await?;
+    let balance = client.get_balance_for_btc().await?;
+    Arc::into_inner(client)
+        .expect("No other client handles")
+        .shutdown()
+        .await;
+
+    let placeholder = RootSecret::Custom(random_secret());
+    assert!(
+        Client::builder()
+            .await?
+            .with_root_secret_deriver(deriver.clone())
+            .verify_root_secret(&db, &placeholder)
+            .await?
+    );
+    assert!(
+        !Client::builder()
+            .await?
+            .with_root_secret_deriver(Arc::new(random_secret()))
+            .verify_root_secret(&db, &placeholder)
+            .await?
+    );
+    assert!(
+        fed.try_open_client_with_deriver(
+            db.clone(),
+            placeholder.clone(),
+            Arc::new(random_secret())
+        )
+        .await
+        .is_err()
+    );
+
+    let client = fed
+        .try_open_client_with_deriver(db, placeholder, deriver)
+        .await?;
+    assert_eq!(client.get_balance_for_btc().await?, balance);
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reissue_excess_notes_reduces_note_count() -> anyhow::Result<()> {
+    let fed = fixtures()
+        .new_fed_builder(1)
+        .disable_mint_fees()
+        .build()
+        .await;
+    let client = fed.new_client().await;
+    let client_mint = client.get_first_module::<MintClientModule>()?;
+    let dummy_module = client.get_first_module::<DummyClientModule>()?;
+
+    // Issue way more notes per denomination than the wallet normally keeps
+    let operation_id = OperationId::new_random();
+    let mut dbtx = client_mint.db.begin_transaction().await;
+    let outputs = client_mint
+        .create_output(&mut dbtx.to_ref_nc(), operation_id, 12, sats(10))
+        .await;
+    dbtx.commit_tx().await;
+
+    let outpoint_range = client
+        .finalize_and_submit_transaction(
+            operation_id,
+            "Issue many small notes",
+            |_| (),
+            TransactionBuilder::new()
+                .with_inputs(dummy_module.create_input(sats(10)))
+                .with_outputs(client_mint.client_ctx.make_client_outputs(outputs)),
+        )
+        .await?;
+    client
+        .await_primary_bitcoin_module_outputs(operation_id, outpoint_range.into_iter().collect())
+        .await?;
+
+    let balance = client.get_balance_for_btc().await?;
+    let note_counts_before = client_mint
+        .get_note_counts_by_denomination(&mut client_mint.db.begin_transaction_nc().await)
+        .await;
+    assert!(note_counts_before.iter().any(|(_, count)| 8 < count));
+
+    client_mint
+        .reissue_excess_notes()
+        .await?
+        .expect("Some notes need to be consolidated");
+    client.wait_for_all_active_state_machines().await?;
+
+    let note_counts_after = client_mint
+        .get_note_counts_by_denomination(&mut client_mint.db.begin_transaction_nc().await)
+        .await;
+    assert!(note_counts_after.count_items() < note_counts_before.count_items());
+    assert_eq!(client.get_balance_for_btc().await?, balance);
+
+    Ok(())
+}
@@ -767,6 +767,10 @@ async fn get_count_by_denomination_dbtx(
     /// returned to the regular balance. To cancel a successful ecash send
     /// simply receive it yourself.
     pub async fn send(&self, amount: Amount, custom_meta: Value) -> Result<ECash, SendECashError> {
+        if self.client_ctx.is_watch_only() {
+            return Err(SendECashError::WatchOnly);
+        }
+
         let amount = round_to_multiple(amount, client_denominations().next().unwrap().amount());
 
         if let Some(ecash) = self
@@ -1066,6 +1070,8 @@ pub enum SendECashError {
     InsufficientBalance,
     #[error("A non-recoverable error has occurred")]
     Failure,
+    #[error("The client was opened in watch-only mode")]
+    WatchOnly,
 }
 
 #[derive(Error, Debug, Clone, Eq, PartialEq)]